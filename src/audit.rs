@@ -0,0 +1,85 @@
+use chrono::Local;
+use colored::Colorize;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::dir::get_app_support_dir;
+use crate::theme::Themed;
+
+pub fn get_audit_log_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join("logs")
+        .join("audit.log")
+}
+
+/// Append an action to the audit log: logins, downloads with their hash and deletions.
+/// Lines are `<date>\t<action>\t<detail>` and are never rewritten.
+pub fn audit(action: &str, detail: &str) {
+    let path = get_audit_log_path();
+    let _ = fs::create_dir_all(path.parent().unwrap());
+
+    let line = format!(
+        "{}\t{}\t{}\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        action,
+        detail.replace(['\n', '\t'], " ")
+    );
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        eprintln!("Warning: Failed to write the audit log: {}", e);
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditEntry {
+    pub date: String,
+    pub action: String,
+    pub detail: String,
+}
+
+/// The last `lines` entries of the audit log, only the given action when set
+pub fn read_audit(lines: usize, action: Option<&str>) -> Vec<AuditEntry> {
+    let Ok(content) = fs::read_to_string(get_audit_log_path()) else {
+        return Vec::new();
+    };
+
+    let entries: Vec<AuditEntry> = content
+        .lines()
+        .map(|line| line.splitn(3, '\t').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 3)
+        .filter(|fields| action.is_none_or(|a| fields[1].eq_ignore_ascii_case(a)))
+        .map(|fields| AuditEntry {
+            date: fields[0].to_string(),
+            action: fields[1].to_string(),
+            detail: fields[2].to_string(),
+        })
+        .collect();
+
+    let skip = entries.len().saturating_sub(lines);
+    entries.into_iter().skip(skip).collect()
+}
+
+pub fn print_audit(entries: &[AuditEntry]) {
+    let path = get_audit_log_path();
+    if !path.exists() {
+        println!("Nothing recorded yet.");
+        return;
+    }
+
+    for entry in entries {
+        println!(
+            "{} {:<8} {}",
+            entry.date.muted(),
+            entry.action.bold(),
+            entry.detail
+        );
+    }
+    println!("\nFull log: {}", path.display());
+}
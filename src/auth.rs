@@ -3,10 +3,18 @@ use keyring::Entry;
 use open_launcher::auth::{self, Auth};
 use serde::{Deserialize, Serialize};
 use serde_json::from_str;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock};
 
 use crate::{
-    cmd::{ask_input, ask_no_yes, select_from_multiple_accounts},
+    audit::audit,
+    cmd::{ask_input, ask_no_yes, ask_yes_no_attended, select_from_multiple_accounts},
+    config::load_config,
     dir::get_app_support_dir,
+    net,
+    pin::check_pin,
+    schema::Schema,
+    timings::{exit, timed},
 };
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -14,12 +22,59 @@ pub struct Account {
     pub name: String,
     pub uuid: Option<String>,
     pub offline: bool,
+    /// Unix timestamp after which the stored access token is no longer accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
 }
 
+const KEYRING_SERVICE: &str = "openvoxellauncher";
+const MSA_CLIENT_ID: &str = "fe26d9d5-6a19-45a9-b352-abd3e5db37fc";
+const MSA_TOKEN_URL: &str = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
+/// `offline_access` is what gets a refresh token along with the access token
+const MSA_SCOPE: &str = "XboxLive.signin offline_access";
+/// Hands out the server ids that OpenVoxel services check sessions against
+const SESSION_CHALLENGE_URL: &str = "https://openvoxel.studio/api/session/challenge";
+const SESSION_JOIN_URL: &str = "https://sessionserver.mojang.com/session/minecraft/join";
+
+static ACCOUNT_OVERRIDE: OnceLock<String> = OnceLock::new();
+static DEFAULT_ACCOUNT_WARNING: Once = Once::new();
+
 impl Account {
+    /// Entries are namespaced by account type, UUID and a hash of the name so that the
+    /// same UUID used offline and online, or under a new name, never share a token
+    fn keyring_key(&self) -> Result<String, keyring::Error> {
+        let uuid: &String = self.uuid.as_ref().ok_or_else(|| keyring::Error::NoEntry)?;
+        let kind = if self.offline { "offline" } else { "msa" };
+        let name_hash = sha256::digest(self.name.as_str());
+
+        Ok(format!("{}:{}:{}", kind, uuid, &name_hash[..12]))
+    }
+
     fn get_keyring_entry(&self) -> Result<Entry, keyring::Error> {
+        Entry::new(KEYRING_SERVICE, &self.keyring_key()?)
+    }
+
+    /// The OAuth refresh token is kept next to the access token, to renew it without a login
+    fn get_refresh_keyring_entry(&self) -> Result<Entry, keyring::Error> {
+        Entry::new(KEYRING_SERVICE, &format!("refresh:{}", self.keyring_key()?))
+    }
+
+    /// Entry used before namespacing, keyed by UUID only
+    fn get_legacy_keyring_entry(&self) -> Result<Entry, keyring::Error> {
         let uuid: &String = self.uuid.as_ref().ok_or_else(|| keyring::Error::NoEntry)?;
-        Entry::new("openvoxellauncher", uuid)
+        Entry::new(KEYRING_SERVICE, uuid)
+    }
+
+    /// Move a token stored under the legacy key to the namespaced one
+    fn migrate_legacy_entry(&self) -> Option<String> {
+        let legacy = self.get_legacy_keyring_entry().ok()?;
+        let token = legacy.get_password().ok()?;
+
+        if self.store_access_token(&token).is_ok() {
+            let _ = legacy.delete_credential();
+        }
+
+        Some(token)
     }
 
     pub fn store_access_token(&self, token: &str) -> Result<(), keyring::Error> {
@@ -37,17 +92,41 @@ impl Account {
             .and_then(|entry: Entry| entry.get_password())
         {
             Ok(token) => Some(token),
+            Err(keyring::Error::NoEntry) => self.migrate_legacy_entry(),
             Err(_) => None,
         }
     }
 
+    pub fn store_refresh_token(&self, token: &str) -> Result<(), keyring::Error> {
+        self.get_refresh_keyring_entry()?.set_password(token)
+    }
+
+    pub fn get_refresh_token(&self) -> Option<String> {
+        if self.offline {
+            return None;
+        }
+        self.get_refresh_keyring_entry().ok()?.get_password().ok()
+    }
+
+    /// Forget the access token of the account, and the refresh token it was renewed with
     pub fn delete_access_token(&self) -> Result<(), keyring::Error> {
         if self.offline {
             return Ok(());
         }
 
+        if let Ok(legacy) = self.get_legacy_keyring_entry() {
+            let _ = legacy.delete_credential();
+        }
+        if let Ok(refresh) = self.get_refresh_keyring_entry() {
+            let _ = refresh.delete_credential();
+        }
+
         let entry: Entry = self.get_keyring_entry()?;
-        entry.delete_credential()
+        match entry.delete_credential() {
+            // Only a legacy entry existed
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        }
     }
 }
 
@@ -57,12 +136,90 @@ pub struct Accounts {
     pub accounts: Vec<Account>,
 }
 
+/// Use another account than the selected one for this run (`--account`)
+pub fn use_account(name: &str) -> Result<(), String> {
+    let accounts = fetch_file(false);
+    let account = accounts
+        .accounts
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No account named {}, see `ovl accounts`", name))?;
+    let _ = ACCOUNT_OVERRIDE.set(account.name.clone());
+    Ok(())
+}
+
+/// Name of the account used by this run, `None` before any account is added
+pub fn get_selected_account_name() -> Option<String> {
+    if !get_accounts_path().exists() {
+        return None;
+    }
+    Some(fetch_file(false).selected)
+}
+
+/// Versions of `.accounts`, see [`Schema`]
+const ACCOUNTS_SCHEMA: Schema = Schema {
+    name: "accounts file",
+    migrations: &[
+        // 1: `schema_version` itself, nothing else changed
+        |_| {},
+    ],
+};
+
+fn get_accounts_path() -> PathBuf {
+    get_app_support_dir().unwrap().join(".accounts")
+}
+
+/// Read `.accounts`, upgrading it first when an older ovl wrote it
+fn read_accounts(file: &Path) -> Result<Accounts, String> {
+    let content = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let mut value: serde_json::Value = from_str(&content).map_err(|e| e.to_string())?;
+    ACCOUNTS_SCHEMA.upgrade_file(file, &mut value, |value| {
+        serde_json::to_string(value).map_err(|e| e.to_string())
+    })?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Write `.accounts` with the schema version of this build
+pub fn write_accounts(accounts: &Accounts) -> Result<(), Box<dyn std::error::Error>> {
+    let mut value = serde_json::to_value(accounts)?;
+    ACCOUNTS_SCHEMA.stamp(&mut value);
+    std::fs::write(get_accounts_path(), serde_json::to_string(&value)?)?;
+    Ok(())
+}
+
 pub fn fetch_file(should_add: bool) -> Accounts {
-    let file = get_app_support_dir().unwrap().join(".accounts");
+    let file = get_accounts_path();
 
     if file.exists() {
-        let content = std::fs::read_to_string(file).unwrap();
-        let accounts: Accounts = from_str(&content).unwrap();
+        let mut accounts = match timed("accounts", || read_accounts(&file)) {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        let has_selection = accounts
+            .accounts
+            .iter()
+            .any(|a| a.name == accounts.selected);
+        if let Some(name) = ACCOUNT_OVERRIDE.get() {
+            accounts.selected = name.clone();
+        } else if !has_selection && let Some(name) = load_config().default_account {
+            // Only a fallback, the account picked with `ovl accounts` stays selected
+            match accounts
+                .accounts
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(&name))
+            {
+                Some(account) => accounts.selected = account.name.clone(),
+                None => DEFAULT_ACCOUNT_WARNING.call_once(|| {
+                    eprintln!(
+                        "Warning: Ignoring default_account: No account named {}, see `ovl accounts`",
+                        name
+                    )
+                }),
+            }
+        }
 
         return accounts;
     } else {
@@ -75,7 +232,21 @@ pub fn fetch_file(should_add: bool) -> Accounts {
             };
         } else {
             eprintln!("No accounts file found. Please add an account first.");
-            std::process::exit(1);
+            exit(1);
+        }
+    }
+}
+
+/// Show the account selector before a launch, as `--choose-account` does. There is nothing
+/// to pick with a single account, and `--account` already picked one.
+pub fn choose_account() {
+    if get_selected_account_name().is_none() || ACCOUNT_OVERRIDE.get().is_some() {
+        return;
+    }
+    if fetch_file(false).accounts.len() > 1 {
+        match check_pin("change the account") {
+            Ok(()) => switch_account(),
+            Err(e) => eprintln!("{}, keeping the selected account.", e),
         }
     }
 }
@@ -87,11 +258,7 @@ pub fn switch_account() {
     match account {
         Some(acc) => {
             accounts.selected = acc.name.clone();
-            std::fs::write(
-                get_app_support_dir().unwrap().join(".accounts"),
-                serde_json::to_string(&accounts).unwrap(),
-            )
-            .unwrap();
+            write_accounts(&accounts).unwrap();
         }
         None => println!("No account selected."),
     }
@@ -107,13 +274,14 @@ pub fn add_account() -> Account {
 
         if name.is_empty() {
             eprintln!("Username cannot be empty");
-            std::process::exit(1);
+            exit(1);
         }
 
         account = Account {
             offline: true,
             name: name,
             uuid: None,
+            expires_at: None,
         };
     } else {
         account = match tokio::runtime::Runtime::new()
@@ -123,13 +291,25 @@ pub fn add_account() -> Account {
             Ok(acc) => acc,
             Err(e) => {
                 eprintln!("Failed to authenticate online: {}", e);
-                std::process::exit(1);
+                exit(1);
             }
         };
     }
 
-    let file = get_app_support_dir().unwrap().join(".accounts");
-    if file.exists() {
+    save_account(&account);
+    let kind = if account.offline {
+        "offline"
+    } else {
+        "Microsoft"
+    };
+    audit("login", &format!("{} ({} account)", account.name, kind));
+
+    return account;
+}
+
+/// Add the account to `.accounts` (or replace the one with the same name) and select it
+pub fn save_account(account: &Account) {
+    if get_accounts_path().exists() {
         let mut accounts: Accounts = fetch_file(true);
         accounts.selected = account.name.clone();
         if let Some(existing_account) = accounts
@@ -141,20 +321,213 @@ pub fn add_account() -> Account {
         } else {
             accounts.accounts.push(account.clone());
         }
-        std::fs::write(file, serde_json::to_string(&accounts).unwrap()).unwrap();
+        write_accounts(&accounts).unwrap();
     } else {
-        let content = serde_json::to_string(&Accounts {
+        write_accounts(&Accounts {
             selected: account.name.clone(),
             accounts: vec![account.clone()],
         })
         .unwrap();
-        std::fs::write(file, content).unwrap();
     }
+}
 
-    return account;
+/// Replace the stored details of a Microsoft account after its session was refreshed, without
+/// changing which account is selected. The name follows a rename of the Minecraft profile.
+fn update_account(previous_name: &str, account: &Account) {
+    let Ok(mut accounts) = read_accounts(&get_accounts_path()) else {
+        return;
+    };
+
+    if let Some(existing) = accounts
+        .accounts
+        .iter_mut()
+        .find(|a| a.name == previous_name)
+    {
+        *existing = account.clone();
+    }
+    if accounts.selected == previous_name {
+        accounts.selected = account.name.clone();
+    }
+    let _ = write_accounts(&accounts);
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Ask Minecraft whether the token is still accepted. Network errors count as valid so
+/// that a flaky connection does not force a new login.
+async fn is_token_valid(token: &str) -> bool {
+    match net::send(
+        net::client()
+            .get("https://api.minecraftservices.com/minecraft/profile")
+            .header("Authorization", format!("Bearer {}", token)),
+    )
+    .await
+    {
+        Ok(response) => response.status() != reqwest::StatusCode::UNAUTHORIZED,
+        Err(e) => {
+            eprintln!("Warning: Could not validate the access token: {}", e);
+            true
+        }
+    }
+}
+
+/// Get a new access token with the stored refresh token, without asking anything
+async fn refresh_session(account: &Account) -> Result<Account, Box<dyn std::error::Error>> {
+    let refresh_token = account
+        .get_refresh_token()
+        .ok_or("no refresh token is stored for this account")?;
+
+    let token_data: serde_json::Value = net::send(net::client().post(MSA_TOKEN_URL).form(&[
+        ("grant_type", "refresh_token"),
+        ("client_id", MSA_CLIENT_ID),
+        ("scope", MSA_SCOPE),
+        ("refresh_token", &refresh_token),
+    ]))
+    .await?
+    .json()
+    .await?;
+    if let Some(error) = token_data["error"].as_str() {
+        return Err(format!("OAuth error: {}", error).into());
+    }
+
+    let fresh = minecraft_login(&token_data).await?;
+    if fresh.uuid != account.uuid {
+        return Err("the refreshed session belongs to another Minecraft profile".into());
+    }
+    if fresh.name != account.name {
+        let _ = account.delete_access_token();
+    }
+    update_account(&account.name, &fresh);
+    Ok(fresh)
+}
+
+/// Access token of an online account, renewed with the refresh token when it has expired,
+/// logging in again when that fails too
+async fn get_valid_access_token(account: &Account) -> String {
+    if let Some(token) = account.get_access_token() {
+        let expired = account.expires_at.is_some_and(|at| now() + 60 >= at);
+
+        if !expired && is_token_valid(&token).await {
+            return token;
+        }
+    }
+
+    match refresh_session(account).await {
+        Ok(fresh) => {
+            audit("login", &format!("{} (session refreshed)", fresh.name));
+            if let Some(token) = fresh.get_access_token() {
+                return token;
+            }
+        }
+        Err(e) => eprintln!("Could not refresh the session of {}: {}", account.name, e),
+    }
+
+    println!("\nThe session of {} has expired.", account.name);
+    if !ask_yes_no_attended("Log in again now?") {
+        eprintln!("Cannot launch with an expired session. Run `ovl login` to log in again.");
+        exit(1);
+    }
+
+    match online_auth().await {
+        Ok(fresh) => {
+            save_account(&fresh);
+            audit(
+                "login",
+                &format!("{} (expired session renewed)", fresh.name),
+            );
+            fresh
+                .get_access_token()
+                .expect("Access token was not saved after logging in.")
+        }
+        Err(e) => {
+            eprintln!("Failed to authenticate online: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Proof that the player owns an online account, for OpenVoxel services. OpenVoxel checks it
+/// with Mojang's `hasJoined` like a Minecraft server does, the access token only goes to Mojang.
+#[derive(Debug, Serialize)]
+pub struct SessionProof {
+    pub player: String,
+    /// Single-use id handed out by OpenVoxel and joined with the access token
+    pub server_id: String,
+}
+
+/// Join the session of a one-time OpenVoxel challenge with the access token of an account
+async fn join_challenge(account: &Account, token: &str) -> Result<SessionProof, String> {
+    let uuid = account
+        .uuid
+        .as_deref()
+        .ok_or("The account has no UUID, log out and in again")?;
+    let challenge: serde_json::Value = net::send(net::client().post(SESSION_CHALLENGE_URL))
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to reach OpenVoxel: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid challenge from OpenVoxel: {}", e))?;
+    let server_id = challenge["server_id"]
+        .as_str()
+        .ok_or("Invalid challenge from OpenVoxel")?
+        .to_string();
+
+    net::send(
+        net::client()
+            .post(SESSION_JOIN_URL)
+            .json(&serde_json::json!({
+                "accessToken": token,
+                "selectedProfile": uuid.replace('-', ""),
+                "serverId": server_id,
+            })),
+    )
+    .await
+    .and_then(|r| r.error_for_status())
+    .map_err(|e| format!("Mojang refused the session of {}: {}", account.name, e))?;
+
+    Ok(SessionProof {
+        player: account.name.clone(),
+        server_id,
+    })
+}
+
+/// The selected account and a valid access token
+async fn get_online_session() -> Result<(Account, String), String> {
+    let accounts = fetch_file(false);
+    let account = accounts
+        .accounts
+        .into_iter()
+        .find(|a| a.name == accounts.selected)
+        .ok_or("No account is selected, run `ovl accounts` to pick one")?;
+
+    if account.offline {
+        return Err(format!(
+            "{} is an offline account, log in with a Microsoft account first",
+            account.name
+        ));
+    }
+    // The session acts as the player, like launching does
+    check_pin(&format!("play as {}", account.name))?;
+
+    let token = get_valid_access_token(&account).await;
+    Ok((account, token))
+}
+
+/// The selected account and a proof of its session, for OpenVoxel services that need a
+/// Microsoft account
+pub async fn prove_online_session() -> Result<(Account, SessionProof), String> {
+    let (account, token) = get_online_session().await?;
+    let proof = join_challenge(&account, &token).await?;
+    Ok((account, proof))
 }
 
-pub fn get_auth() -> Auth {
+pub async fn get_auth() -> Auth {
     let mut accounts = fetch_file(true);
     if accounts.accounts.is_empty() {
         if accounts.accounts.len() == 0 {
@@ -175,6 +548,11 @@ pub fn get_auth() -> Auth {
     if selected_account.offline {
         return auth::OfflineAuth::new(&selected_account.name);
     } else {
+        // Offline accounts stay playable without the PIN
+        if let Err(e) = check_pin(&format!("play as {}", selected_account.name)) {
+            eprintln!("{}", e);
+            exit(1);
+        }
         return auth::Auth::new(
             "msa".to_string(),
             "{}".to_string(),
@@ -182,9 +560,7 @@ pub fn get_auth() -> Auth {
             selected_account.uuid.clone().expect(
                 "UUID is not defined for this online account. Please log out and in again.",
             ),
-            selected_account.get_access_token().expect(
-                "Access token is not defined for this online account. Please log out and in again.",
-            ),
+            get_valid_access_token(selected_account).await,
         );
     }
 }
@@ -192,17 +568,15 @@ pub fn get_auth() -> Auth {
 pub async fn online_auth() -> Result<Account, Box<dyn std::error::Error>> {
     println!("Starting Microsoft authentication...");
 
-    let client = reqwest::Client::new();
+    let client = net::client();
 
     // Step 1: Get device code
-    let device_response = client
-        .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode")
-        .form(&[
-            ("client_id", "fe26d9d5-6a19-45a9-b352-abd3e5db37fc"),
-            ("scope", "XboxLive.signin offline_access"),
-        ])
-        .send()
-        .await?;
+    let device_response = net::send(
+        client
+            .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode")
+            .form(&[("client_id", MSA_CLIENT_ID), ("scope", MSA_SCOPE)]),
+    )
+    .await?;
 
     let device_data: serde_json::Value = device_response.json().await?;
     let user_code = device_data["user_code"].as_str().unwrap();
@@ -220,18 +594,15 @@ pub async fn online_auth() -> Result<Account, Box<dyn std::error::Error>> {
     println!("Waiting for authentication...");
 
     // Step 2: Poll for access token
-    let msa_token = loop {
+    let token_data = loop {
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
-        let token_response = client
-            .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
-            .form(&[
-                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
-                ("client_id", "fe26d9d5-6a19-45a9-b352-abd3e5db37fc"),
-                ("device_code", device_code),
-            ])
-            .send()
-            .await?;
+        let token_response = net::send(client.post(MSA_TOKEN_URL).form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ("client_id", MSA_CLIENT_ID),
+            ("device_code", device_code),
+        ]))
+        .await?;
 
         let token_data: serde_json::Value = token_response.json().await?;
 
@@ -243,79 +614,113 @@ pub async fn online_auth() -> Result<Account, Box<dyn std::error::Error>> {
             }
         }
 
-        break token_data["access_token"].as_str().unwrap().to_string();
+        break token_data;
     };
 
+    let fresh_account = minecraft_login(&token_data).await?;
+    println!("Successfully authenticated as: {}", fresh_account.name);
+    Ok(fresh_account)
+}
+
+/// Steps from a Microsoft OAuth token response to a Minecraft session, shared by the login and
+/// the refresh. Both tokens are stored in the keyring.
+async fn minecraft_login(
+    token_data: &serde_json::Value,
+) -> Result<Account, Box<dyn std::error::Error>> {
+    let client = net::client();
+    let msa_token = token_data["access_token"]
+        .as_str()
+        .ok_or("Microsoft did not return an access token")?;
+
     // Step 3: Get Xbox Live token
-    let xbl_response = client
-        .post("https://user.auth.xboxlive.com/user/authenticate")
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "Properties": {
-                "AuthMethod": "RPS",
-                "SiteName": "user.auth.xboxlive.com",
-                "RpsTicket": format!("d={}", msa_token)
-            },
-            "RelyingParty": "http://auth.xboxlive.com",
-            "TokenType": "JWT"
-        }))
-        .send()
-        .await?;
+    let xbl_response = net::send(
+        client
+            .post("https://user.auth.xboxlive.com/user/authenticate")
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({
+                "Properties": {
+                    "AuthMethod": "RPS",
+                    "SiteName": "user.auth.xboxlive.com",
+                    "RpsTicket": format!("d={}", msa_token)
+                },
+                "RelyingParty": "http://auth.xboxlive.com",
+                "TokenType": "JWT"
+            })),
+    )
+    .await?;
 
     let xbl_data: serde_json::Value = xbl_response.json().await?;
-    let xbl_token = xbl_data["Token"].as_str().unwrap();
-    let user_hash = xbl_data["DisplayClaims"]["xui"][0]["uhs"].as_str().unwrap();
+    let xbl_token = xbl_data["Token"]
+        .as_str()
+        .ok_or("Xbox Live did not return a token")?;
+    let user_hash = xbl_data["DisplayClaims"]["xui"][0]["uhs"]
+        .as_str()
+        .ok_or("Xbox Live did not return a user hash")?;
 
     // Step 4: Get XSTS token
-    let xsts_response = client
-        .post("https://xsts.auth.xboxlive.com/xsts/authorize")
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "Properties": {
-                "SandboxId": "RETAIL",
-                "UserTokens": [xbl_token]
-            },
-            "RelyingParty": "rp://api.minecraftservices.com/",
-            "TokenType": "JWT"
-        }))
-        .send()
-        .await?;
+    let xsts_response = net::send(
+        client
+            .post("https://xsts.auth.xboxlive.com/xsts/authorize")
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({
+                "Properties": {
+                    "SandboxId": "RETAIL",
+                    "UserTokens": [xbl_token]
+                },
+                "RelyingParty": "rp://api.minecraftservices.com/",
+                "TokenType": "JWT"
+            })),
+    )
+    .await?;
 
     let xsts_data: serde_json::Value = xsts_response.json().await?;
-    let xsts_token = xsts_data["Token"].as_str().unwrap();
+    let xsts_token = xsts_data["Token"]
+        .as_str()
+        .ok_or("Xbox Live did not authorize Minecraft, does the account own the game?")?;
 
     // Step 5: Get Minecraft access token
-    let mc_response = client
-        .post("https://api.minecraftservices.com/authentication/login_with_xbox")
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "identityToken": format!("XBL3.0 x={};{}", user_hash, xsts_token)
-        }))
-        .send()
-        .await?;
+    let mc_response = net::send(
+        client
+            .post("https://api.minecraftservices.com/authentication/login_with_xbox")
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({
+                "identityToken": format!("XBL3.0 x={};{}", user_hash, xsts_token)
+            })),
+    )
+    .await?;
 
     let mc_data: serde_json::Value = mc_response.json().await?;
-    let mc_access_token = mc_data["access_token"].as_str().unwrap();
+    let mc_access_token = mc_data["access_token"]
+        .as_str()
+        .ok_or("Minecraft did not return an access token")?;
+    let expires_in = mc_data["expires_in"].as_u64().unwrap_or(24 * 60 * 60);
 
     // Step 6: Get Minecraft profile
-    let profile_response = client
-        .get("https://api.minecraftservices.com/minecraft/profile")
-        .header("Authorization", format!("Bearer {}", mc_access_token))
-        .send()
-        .await?;
+    let profile_response = net::send(
+        client
+            .get("https://api.minecraftservices.com/minecraft/profile")
+            .header("Authorization", format!("Bearer {}", mc_access_token)),
+    )
+    .await?;
 
     let profile_data: serde_json::Value = profile_response.json().await?;
-    let username = profile_data["name"].as_str().unwrap();
-    let uuid = profile_data["id"].as_str().unwrap();
-
-    println!("Successfully authenticated as: {}", username);
+    let username = profile_data["name"]
+        .as_str()
+        .ok_or("The account has no Minecraft profile")?;
+    let uuid = profile_data["id"]
+        .as_str()
+        .ok_or("The account has no Minecraft profile")?;
 
     let fresh_account = Account {
         name: username.to_string(),
         uuid: Some(uuid.to_string()),
         offline: false,
+        expires_at: Some(now() + expires_in),
     };
-    fresh_account.store_access_token(mc_access_token).unwrap();
+    fresh_account.store_access_token(mc_access_token)?;
+    if let Some(refresh_token) = token_data["refresh_token"].as_str() {
+        fresh_account.store_refresh_token(refresh_token)?;
+    }
 
     Ok(fresh_account)
 }
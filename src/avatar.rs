@@ -0,0 +1,158 @@
+use colored::Colorize;
+use image::{Rgba, RgbaImage, imageops::FilterType};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{
+    auth::Account, config::load_config, dir::get_app_support_dir, net, profile::profile_textures,
+    skin::get_local_skin_path,
+};
+
+const SESSION_PROFILE_URL: &str = "https://sessionserver.mojang.com/session/minecraft/profile";
+/// Faces are downloaded again after this, skins rarely change
+const CACHE_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Size of the face in skin pixels
+const FACE: u32 = 8;
+
+fn get_avatar_path(account: &Account) -> PathBuf {
+    let key = account.uuid.clone().unwrap_or_else(|| account.name.clone());
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("avatars")
+        .join(format!("{}.png", key.replace('-', "")))
+}
+
+fn download(url: &str) -> Option<Vec<u8>> {
+    let bytes = net::blocking_get(url)
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .bytes()
+        .ok()?;
+    Some(bytes.to_vec())
+}
+
+fn online_skin_url(uuid: &str) -> Option<String> {
+    let url = format!("{}/{}", SESSION_PROFILE_URL, uuid.replace('-', ""));
+    let data: Value = net::blocking_get(&url)
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .ok()?;
+    profile_textures(&data)?["textures"]["SKIN"]["url"]
+        .as_str()
+        .map(String::from)
+}
+
+/// The face of a skin with its hat layer on top
+fn crop_face(skin: &RgbaImage) -> Option<RgbaImage> {
+    if skin.width() < 64 || skin.height() < 32 {
+        return None;
+    }
+    let mut face = image::imageops::crop_imm(skin, 8, 8, FACE, FACE).to_image();
+    let hat = image::imageops::crop_imm(skin, 40, 8, FACE, FACE).to_image();
+    image::imageops::overlay(&mut face, &hat, 0, 0);
+    Some(face)
+}
+
+/// The face of an account: from the head render service set as `avatar_url`, else from the
+/// skin of the account (the local skin for offline accounts)
+fn fetch_face(account: &Account) -> Option<RgbaImage> {
+    if let Some(template) = load_config().avatar_url {
+        let url = template
+            .replace("{uuid}", account.uuid.as_deref().unwrap_or_default())
+            .replace("{name}", &account.name);
+        let head = image::load_from_memory(&download(&url)?).ok()?;
+        return Some(
+            head.resize_exact(FACE, FACE, FilterType::Nearest)
+                .to_rgba8(),
+        );
+    }
+
+    let skin = match (account.offline, &account.uuid) {
+        (true, _) => fs::read(get_local_skin_path(&account.name)).ok()?,
+        (false, Some(uuid)) => download(&online_skin_url(uuid)?)?,
+        (false, None) => return None,
+    };
+    crop_face(&image::load_from_memory(&skin).ok()?.to_rgba8())
+}
+
+/// Faces need colors, they are left out when the output is not a color terminal
+fn avatars_enabled() -> bool {
+    load_config().avatars && colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// The face of an account, from the cache when it is recent enough
+fn account_face(account: &Account) -> Option<RgbaImage> {
+    if !avatars_enabled() {
+        return None;
+    }
+    let path = get_avatar_path(account);
+    let cached = image::open(&path).ok().map(|image| image.to_rgba8());
+    let fresh = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < CACHE_DURATION);
+    if fresh && cached.is_some() {
+        return cached;
+    }
+
+    // A face that cannot be refreshed is still better than none
+    match fetch_face(account) {
+        Some(face) => {
+            let _ = fs::create_dir_all(path.parent().unwrap());
+            let _ = face.save(&path);
+            Some(face)
+        }
+        None => cached,
+    }
+}
+
+/// Two pixels stacked in one character cell, transparent ones are left blank
+fn half_block(top: &Rgba<u8>, bottom: &Rgba<u8>) -> String {
+    let opaque = |pixel: &Rgba<u8>| pixel[3] >= 128;
+    match (opaque(top), opaque(bottom)) {
+        (true, true) => "▀"
+            .truecolor(top[0], top[1], top[2])
+            .on_truecolor(bottom[0], bottom[1], bottom[2])
+            .to_string(),
+        (true, false) => "▀".truecolor(top[0], top[1], top[2]).to_string(),
+        (false, true) => "▄".truecolor(bottom[0], bottom[1], bottom[2]).to_string(),
+        (false, false) => " ".to_string(),
+    }
+}
+
+fn render(image: &RgbaImage) -> Vec<String> {
+    (0..image.height() / 2)
+        .map(|row| {
+            (0..image.width())
+                .map(|x| half_block(image.get_pixel(x, row * 2), image.get_pixel(x, row * 2 + 1)))
+                .collect()
+        })
+        .collect()
+}
+
+/// The 8x8 face of an account drawn with colored half blocks, four lines of eight columns
+pub fn avatar_lines(account: &Account) -> Option<Vec<String>> {
+    Some(render(&account_face(account)?))
+}
+
+/// The face squeezed into a single line, to fit next to a name in a list. Blank when there
+/// is no face, so the names stay aligned.
+pub fn avatar_inline(account: &Account) -> String {
+    if !avatars_enabled() {
+        return String::new();
+    }
+    match account_face(account) {
+        Some(face) => {
+            let face = image::imageops::resize(&face, FACE, 2, FilterType::Triangle);
+            render(&face).remove(0) + " "
+        }
+        None => " ".repeat(FACE as usize + 1),
+    }
+}
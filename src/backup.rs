@@ -0,0 +1,81 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::{ZipWriter, write::FileOptions};
+
+/// Older backups are deleted once there are more than this many
+const MAX_BACKUPS: usize = 10;
+
+pub fn get_backups_dir(server_dir: &Path) -> PathBuf {
+    server_dir.join("backups")
+}
+
+/// Zip `world_dir` into the backups folder of the server and prune old backups
+pub fn backup_world(
+    server_dir: &Path,
+    world_dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let backups_dir = get_backups_dir(server_dir);
+    fs::create_dir_all(&backups_dir)?;
+
+    let world_name = world_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("world".to_string());
+    let backup_path = backups_dir.join(format!(
+        "{}-{}.zip",
+        world_name,
+        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+
+    let mut zip = ZipWriter::new(File::create(&backup_path)?);
+    add_dir_to_zip(&mut zip, world_dir, world_dir)?;
+    zip.finish()?;
+
+    prune_backups(&backups_dir);
+    Ok(backup_path)
+}
+
+fn add_dir_to_zip(
+    zip: &mut ZipWriter<File>,
+    root: &Path,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        if path.is_dir() {
+            zip.add_directory(format!("{}/", name), options)?;
+            add_dir_to_zip(zip, root, &path)?;
+        } else if name != "session.lock" {
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn prune_backups(backups_dir: &Path) {
+    let Ok(entries) = fs::read_dir(backups_dir) else {
+        return;
+    };
+
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "zip"))
+        .collect();
+    backups.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+
+    let excess = backups.len().saturating_sub(MAX_BACKUPS);
+    for path in backups.into_iter().take(excess) {
+        let _ = fs::remove_file(path);
+    }
+}
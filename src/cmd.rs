@@ -1,13 +1,79 @@
 use colored::Colorize;
-use inquire::Select;
+use inquire::{MultiSelect, Select};
+use serde::Serialize;
 use std::io::{self, Write};
+use std::sync::OnceLock;
 
 use crate::{
     auth::{Account, Accounts},
+    avatar::avatar_inline,
     map::Map,
+    theme::Themed,
+    timings::exit,
 };
 
+static NON_INTERACTIVE: OnceLock<()> = OnceLock::new();
+static JSON_OUTPUT: OnceLock<()> = OnceLock::new();
+
+/// Answer every question with its default for the rest of the run, as `--non-interactive` does
+pub fn use_non_interactive() {
+    let _ = NON_INTERACTIVE.set(());
+}
+
+pub fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.get().is_some()
+}
+
+/// Print the results of commands as JSON for the rest of the run, as `--json` does. The
+/// program reading them cannot answer questions either.
+pub fn use_json_output() {
+    let _ = JSON_OUTPUT.set(());
+    use_non_interactive();
+}
+
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.get().is_some()
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize the output: {}", e),
+    }
+}
+
+/// Print the result of a command: as JSON with `--json`, with `human` otherwise
+pub fn print_output<T: Serialize + ?Sized>(value: &T, human: impl FnOnce(&T)) {
+    match is_json_output() {
+        true => print_json(value),
+        false => human(value),
+    }
+}
+
+/// Refuse a question that has no default answer when nobody can answer it
+pub fn check_interactive(question: &str) -> Result<(), String> {
+    match is_non_interactive() {
+        true => Err(format!(
+            "\"{}\" has no default answer, run the command without --non-interactive",
+            question.trim_end_matches(':')
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Stop the command on a question without a default in non-interactive mode
+fn require_interactive(question: &str) {
+    if let Err(e) = check_interactive(question) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
 pub fn ask_yes_no(question: &str) -> bool {
+    if is_non_interactive() {
+        eprintln!("\n{} [Y/n]: y", question);
+        return true;
+    }
     loop {
         print!("\n{} [Y/n]: ", question);
         io::stdout().flush().expect("Failed to flush stdout");
@@ -27,7 +93,21 @@ pub fn ask_yes_no(question: &str) -> bool {
     }
 }
 
+/// `ask_yes_no` for questions that overwrite something of the player or accept terms on
+/// their behalf, those are answered no in non-interactive mode
+pub fn ask_yes_no_attended(question: &str) -> bool {
+    if is_non_interactive() {
+        eprintln!("\n{} [Y/n]: n (not without asking)", question);
+        return false;
+    }
+    ask_yes_no(question)
+}
+
 pub fn ask_no_yes(question: &str) -> bool {
+    if is_non_interactive() {
+        eprintln!("\n{} [y/N]: n", question);
+        return false;
+    }
     loop {
         print!("\n{} [y/N]: ", question);
         io::stdout().flush().expect("Failed to flush stdout");
@@ -49,8 +129,13 @@ pub fn ask_no_yes(question: &str) -> bool {
 
 pub fn ask_input(question: &str, default: Option<&str>) -> String {
     if let Some(default_val) = default {
+        if is_non_interactive() {
+            eprintln!("\n{} [{}]: {}", question, default_val, default_val);
+            return default_val.to_string();
+        }
         print!("\n{} [{}]: ", question, default_val);
     } else {
+        require_interactive(question);
         print!("\n{}: ", question);
     }
     io::stdout().flush().expect("Failed to flush stdout");
@@ -69,10 +154,27 @@ pub fn ask_input(question: &str, default: Option<&str>) -> String {
 }
 
 pub fn select_from_multiple_maps(maps: Vec<Map>) -> Option<Map> {
-    let format_map = |m: &Map| format!("[{:6}] {} - {}", m.version, m.name.bold(), m.description);
+    let format_map = |m: &Map| {
+        // Maps of other catalogs can have the same name as an OpenVoxel one
+        let name = match &m.source {
+            Some(source) => format!("{} {}", m.name.bold(), format!("({})", source).muted()),
+            None => m.name.bold().to_string(),
+        };
+        match &m.rating {
+            Some(rating) => format!(
+                "[{:6}] {} {} - {}",
+                m.version,
+                name,
+                rating.describe().warning(),
+                m.description
+            ),
+            None => format!("[{:6}] {} - {}", m.version, name, m.description),
+        }
+    };
 
     let options: Vec<String> = maps.iter().map(format_map).collect();
 
+    require_interactive("Select a map to play:");
     match Select::new("Select a map to play:", options).prompt() {
         Ok(choice) => maps.into_iter().find(|m| format_map(m) == choice),
         Err(_) => {
@@ -83,21 +185,51 @@ pub fn select_from_multiple_maps(maps: Vec<Map>) -> Option<Map> {
 }
 
 pub fn select_from_multiple_accounts(accounts: Accounts) -> Option<Account> {
+    if is_non_interactive() {
+        let selected = accounts.selected.clone();
+        return accounts.accounts.into_iter().find(|a| a.name == selected);
+    }
     let format_account = |a: &Account| {
         let status = if a.offline { "(Offline)" } else { "(Online)" };
         let name = if a.name == accounts.selected {
-            format!("{} {}", a.name.bold().green(), status)
+            format!("{} {}", a.name.bold().success(), status)
         } else {
             format!("{} {}", a.name.bold(), status)
         };
-        name
+        format!("{}{}", avatar_inline(a), name)
     };
     let options: Vec<String> = accounts.accounts.iter().map(format_account).collect();
-    match Select::new("Select an account:", options).prompt() {
-        Ok(choice) => accounts
-            .accounts
-            .into_iter()
-            .find(|a| format_account(a) == choice),
+    match Select::new("Select an account:", options).raw_prompt() {
+        Ok(choice) => accounts.accounts.into_iter().nth(choice.index),
+        Err(_) => {
+            println!("Cancelled.");
+            None
+        }
+    }
+}
+
+pub fn select_extra_mods(options: &[(&str, &str)], selected: &[String]) -> Option<Vec<String>> {
+    if is_non_interactive() {
+        return Some(selected.to_vec());
+    }
+    let labels: Vec<String> = options.iter().map(|(_, label)| label.to_string()).collect();
+    let defaults: Vec<usize> = options
+        .iter()
+        .enumerate()
+        .filter(|(_, (slug, _))| selected.iter().any(|s| s == slug))
+        .map(|(i, _)| i)
+        .collect();
+
+    match MultiSelect::new("Select the optional mods to install:", labels)
+        .with_default(&defaults)
+        .raw_prompt()
+    {
+        Ok(choices) => Some(
+            choices
+                .into_iter()
+                .map(|choice| options[choice.index].0.to_string())
+                .collect(),
+        ),
         Err(_) => {
             println!("Cancelled.");
             None
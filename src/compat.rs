@@ -0,0 +1,174 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::java::check_java_version;
+use crate::natives::{is_arm64, x86_only_natives};
+use crate::versions::fetch_version_json;
+
+/// Oldest version the launcher can start, alpha and beta builds are not supported
+const OLDEST_SUPPORTED: &str = "1.0";
+
+/// How a Minecraft version expects to be launched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionEra {
+    /// 1.13 and newer: `arguments` object, assets read from the object store
+    Modern,
+    /// 1.6 to 1.12: single `minecraftArguments` string
+    Legacy,
+    /// Before 1.6: assets have to be copied into `resources/`
+    PreAssets,
+}
+
+#[derive(Debug, Clone)]
+pub struct VersionSupport {
+    pub era: VersionEra,
+    /// Java major version the game was built for
+    pub java_version: u32,
+    pub asset_index: String,
+    /// Libraries without arm64 natives, only filled in on arm64
+    pub x86_only_natives: Vec<String>,
+}
+
+/// Work out how a version has to be launched, or why it cannot be.
+/// Returns `None` when the version details cannot be fetched, the launch is then attempted as is.
+pub async fn check_version_support(version: &str) -> Result<Option<VersionSupport>, String> {
+    let json = match fetch_version_json(version).await {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not read the details of Minecraft {}: {}",
+                version, e
+            );
+            return Ok(None);
+        }
+    };
+
+    if matches!(json["type"].as_str(), Some("old_alpha" | "old_beta")) {
+        return Err(format!(
+            "Minecraft {} is an alpha or beta version, versions below {} are not supported.",
+            version, OLDEST_SUPPORTED
+        ));
+    }
+
+    Ok(Some(VersionSupport {
+        era: detect_era(&json),
+        java_version: json["javaVersion"]["majorVersion"].as_u64().unwrap_or(8) as u32,
+        asset_index: json["assetIndex"]["id"]
+            .as_str()
+            .or(json["assets"].as_str())
+            .unwrap_or(version)
+            .to_string(),
+        x86_only_natives: if is_arm64() {
+            x86_only_natives(&json)
+        } else {
+            Vec::new()
+        },
+    }))
+}
+
+fn detect_era(json: &Value) -> VersionEra {
+    if json.get("arguments").is_some() {
+        VersionEra::Modern
+    } else if matches!(json["assets"].as_str(), Some("pre-1.6" | "legacy")) {
+        VersionEra::PreAssets
+    } else {
+        VersionEra::Legacy
+    }
+}
+
+impl VersionSupport {
+    /// Print what is different about launching this version
+    pub fn print_notes(&self, version: &str) {
+        if self.era == VersionEra::Modern {
+            return;
+        }
+
+        println!(
+            "Minecraft {} is an old version, mods are not available and assets use the legacy layout.",
+            version
+        );
+
+        if let Ok(installed) = check_java_version()
+            && self.java_version <= 8
+            && installed > 8
+        {
+            eprintln!(
+                "Warning: Minecraft {} was made for Java 8 and may crash on Java {}. Put the path of a Java 8 executable in settings/java_path.txt if it does.",
+                version, installed
+            );
+        }
+    }
+}
+
+/// Where the assets of an index are copied as plain files, `None` for the indexes read from
+/// the object store
+fn legacy_target(game_dir: &Path, asset_index: &str, index: &Value) -> Option<PathBuf> {
+    if index["map_to_resources"].as_bool() == Some(true) {
+        Some(game_dir.join("resources"))
+    } else if index["virtual"].as_bool() == Some(true) {
+        Some(game_dir.join("assets").join("virtual").join(asset_index))
+    } else {
+        None
+    }
+}
+
+/// The folder `prepare_legacy_assets` copies the assets of an index to, the `game_assets`
+/// of the launch arguments
+pub fn legacy_assets_dir(game_dir: &Path, asset_index: &str) -> Option<PathBuf> {
+    let index: Value = serde_json::from_str(
+        &fs::read_to_string(
+            game_dir
+                .join("assets")
+                .join("indexes")
+                .join(format!("{}.json", asset_index)),
+        )
+        .ok()?,
+    )
+    .ok()?;
+    legacy_target(game_dir, asset_index, &index)
+}
+
+/// Copy assets out of the object store for versions that read them from plain files.
+/// The asset index says where: `virtual` indexes go to `assets/virtual/<id>`, and
+/// `map_to_resources` ones to `resources/` in the game folder.
+pub fn prepare_legacy_assets(
+    game_dir: &Path,
+    asset_index: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let assets_dir = game_dir.join("assets");
+    let index: Value = serde_json::from_str(&fs::read_to_string(
+        assets_dir
+            .join("indexes")
+            .join(format!("{}.json", asset_index)),
+    )?)?;
+
+    let Some(target) = legacy_target(game_dir, asset_index, &index) else {
+        return Ok(0);
+    };
+
+    let Some(objects) = index["objects"].as_object() else {
+        return Ok(0);
+    };
+
+    let mut copied = 0;
+    for (path, object) in objects {
+        let Some(hash) = object["hash"].as_str().filter(|hash| hash.len() > 2) else {
+            continue;
+        };
+
+        let destination = target.join(path);
+        if destination.exists() {
+            continue;
+        }
+
+        let source = assets_dir.join("objects").join(&hash[..2]).join(hash);
+        if let Some(parent_dir) = destination.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        fs::copy(source, destination)?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
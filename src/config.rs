@@ -0,0 +1,578 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{
+    cmd::{ask_yes_no, print_output},
+    dir::get_app_support_dir,
+    map::OverwritePolicy,
+    mirrors::MapSource,
+    net::IpVersion,
+    packs::ServerPackPolicy,
+    schema::{Schema, VERSION_KEY},
+    theme::{Theme, ThemeColors, Themed},
+    timings::{exit, timed},
+    world::QuickPlayLookup,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Optional Modrinth projects installed next to the bundled mods
+    pub extra_mods: Vec<String>,
+    /// Seconds to wait for the game window before the launch is considered hung (0 disables)
+    pub launch_timeout_secs: u64,
+    /// Size of the game window for accounts without their own, e.g. `1920x1080`
+    pub resolution: Option<String>,
+    /// Maximum memory of the game in MB, the launcher default when 0
+    pub max_memory_mb: u64,
+    /// Account to play with when `--account` is not given, instead of the selected one
+    pub default_account: Option<String>,
+    /// Launch without the managed mods, `--only-mods` still keeps some
+    pub no_mods: bool,
+    /// Whether servers may push their resource pack without asking
+    pub server_resource_packs: ServerPackPolicy,
+    /// Server resource packs unused for longer than this are deleted
+    pub server_resource_packs_max_age_days: u64,
+    /// Size the server-resource-packs folder is trimmed down to
+    pub server_resource_packs_max_mb: u64,
+    /// Proxy used for every request (e.g. `http://proxy.local:8080`)
+    pub proxy: Option<String>,
+    /// `ipv4` or `ipv6` to send every request through one IP stack only
+    pub ip_version: IpVersion,
+    /// PEM file of extra certificate authorities to trust, for networks that intercept TLS
+    pub ca_bundle: Option<String>,
+    /// Whether the certificate authorities of the system are trusted
+    pub system_certificates: bool,
+    /// Command run on every downloaded zip and jar before it is installed (e.g. `clamdscan
+    /// --no-summary`), `{file}` is replaced with its path. A failing scan blocks the install.
+    pub scan_command: Option<String>,
+    /// Maximum minutes of play per day across all sessions (0 disables)
+    pub daily_limit_minutes: u64,
+    /// Command started by `ovl server run --tunnel`, `{port}` is replaced with the server port
+    pub tunnel_command: Option<String>,
+    /// LWJGL version to use instead of the bundled one, per Minecraft version
+    pub lwjgl_overrides: HashMap<String, String>,
+    /// Check the chunks of every world right after it is extracted
+    pub verify_worlds: bool,
+    /// What happens when a map is installed over a world with the same name
+    pub overwrite_policy: OverwritePolicy,
+    /// How a world to quick play is found when no saves folder has its name
+    pub quick_play_lookup: QuickPlayLookup,
+    /// Install a controller mod for couch and Steam Deck play
+    pub controller_support: bool,
+    /// Colors of the output and the prompts
+    pub theme: Theme,
+    /// Colors replacing those of the theme, by kind of output
+    pub theme_colors: ThemeColors,
+    /// Show the skin face of accounts in `whoami` and the account selector
+    pub avatars: bool,
+    /// Head render service to take the faces from instead of the skins, `{uuid}` and
+    /// `{name}` are replaced
+    pub avatar_url: Option<String>,
+    /// Argon2 hash of the PIN asked before playing with an online account or changing
+    /// accounts, set with `ovl accounts pin`
+    pub parental_pin: Option<String>,
+    /// Beta map ids whose sessions end with the survey of their team, set with `ovl survey`
+    pub survey_maps: Vec<String>,
+    /// Settings that follow each account, by account name
+    pub accounts: HashMap<String, AccountSettings>,
+    /// Read-only mode for shared machines, only maps can be searched and played
+    pub locked: bool,
+    /// Map ids playable in locked mode, every map when empty
+    pub allowed_maps: Vec<String>,
+    /// Where the map catalog is downloaded from
+    pub catalog_url: String,
+    /// Other map catalogs by name, merged with the OpenVoxel one as `<name>:<id>`
+    pub catalog_sources: HashMap<String, String>,
+    /// RSS or Atom feed of the studio's announcements
+    pub announcements_url: String,
+    /// Base64 Ed25519 key the catalog must be signed with, checked whatever TLS trusts
+    pub catalog_public_key: Option<String>,
+    /// GitHub token for the release lookups, anonymous ones are limited to 60 an hour
+    pub github_token: Option<String>,
+    /// Tag of the map release to install from, the latest when unset
+    pub map_release: Option<String>,
+    /// Tag of the OpenVoxel resource pack release
+    pub pack_release: String,
+    /// Whether the latest map release can be a prerelease
+    pub prereleases: bool,
+    /// Map zips are served from `<map_cdn_url>/<id>.zip`
+    pub map_cdn_url: String,
+    /// Whether map zips come from the CDN or GitHub first, the other one is the fallback
+    pub map_source: MapSource,
+    /// Whether maps with a torrent in the catalog are downloaded from its web seeds first
+    pub torrent_downloads: bool,
+    /// Whether maps and mods are looked for on a LAN cache (`ovl cache serve`) first
+    pub lan_cache: bool,
+    /// Address of the LAN cache, found with mDNS when unset
+    pub lan_cache_url: Option<String>,
+}
+
+/// Settings an administrator provides for every user of the computer
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SystemConfig {
+    /// Used for the keys the user did not set
+    defaults: toml::Table,
+    /// Always win over the user config
+    enforced: toml::Table,
+}
+
+/// Defaults applied when an account plays, so people sharing a computer keep their own
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccountSettings {
+    /// Size of the game window, e.g. `1920x1080`
+    pub resolution: Option<String>,
+    /// Game language, e.g. `fr_fr`
+    pub language: Option<String>,
+    /// Map ids listed first in `ovl search`
+    pub favorite_maps: Vec<String>,
+}
+
+/// Parse a `<width>x<height>` window size
+pub fn parse_resolution(input: &str) -> Result<(u32, u32), String> {
+    input
+        .trim()
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|(w, h)| *w > 0 && *h > 0)
+        .ok_or_else(|| format!("Invalid resolution, expected WIDTHxHEIGHT: {}", input))
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            extra_mods: Vec::new(),
+            launch_timeout_secs: 180,
+            resolution: None,
+            max_memory_mb: 0,
+            default_account: None,
+            no_mods: false,
+            server_resource_packs: ServerPackPolicy::default(),
+            server_resource_packs_max_age_days: 30,
+            server_resource_packs_max_mb: 1024,
+            proxy: None,
+            ip_version: IpVersion::default(),
+            ca_bundle: None,
+            system_certificates: true,
+            scan_command: None,
+            daily_limit_minutes: 0,
+            tunnel_command: None,
+            lwjgl_overrides: HashMap::new(),
+            verify_worlds: false,
+            overwrite_policy: OverwritePolicy::default(),
+            quick_play_lookup: QuickPlayLookup::default(),
+            controller_support: false,
+            theme: Theme::default(),
+            theme_colors: ThemeColors::default(),
+            avatars: true,
+            avatar_url: None,
+            parental_pin: None,
+            survey_maps: Vec::new(),
+            accounts: HashMap::new(),
+            locked: false,
+            allowed_maps: Vec::new(),
+            catalog_url: "https://openvoxel.studio/maps.json".to_string(),
+            catalog_sources: HashMap::new(),
+            catalog_public_key: None,
+            announcements_url: "https://openvoxel.studio/feed.xml".to_string(),
+            github_token: None,
+            map_release: None,
+            pack_release: "latest".to_string(),
+            prereleases: false,
+            map_cdn_url: "https://openvoxel.studio/maps".to_string(),
+            map_source: MapSource::default(),
+            torrent_downloads: true,
+            lan_cache: false,
+            lan_cache_url: None,
+        }
+    }
+}
+
+static LOCKED_FLAG: OnceLock<()> = OnceLock::new();
+/// Read from disk the first time it is needed, then reused for the rest of the run
+static CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+
+/// Turn on locked mode for this run, as `--locked` does
+pub fn use_locked_mode() {
+    let _ = LOCKED_FLAG.set(());
+}
+
+pub fn is_locked_mode() -> bool {
+    LOCKED_FLAG.get().is_some() || load_config().locked
+}
+
+/// Whether a map can be played, only `allowed_maps` can in locked mode
+pub fn is_map_allowed(id: &str) -> bool {
+    if !is_locked_mode() {
+        return true;
+    }
+    let allowed = load_config().allowed_maps;
+    allowed.is_empty() || allowed.iter().any(|m| m.eq_ignore_ascii_case(id))
+}
+
+pub fn get_config_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join("settings")
+        .join("config.toml")
+}
+
+/// System-wide config for managed deployments, `OVL_SYSTEM_CONFIG` points elsewhere
+pub fn get_system_config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("OVL_SYSTEM_CONFIG") {
+        return PathBuf::from(path);
+    }
+
+    if cfg!(windows) {
+        PathBuf::from(std::env::var_os("ProgramData").unwrap_or_else(|| r"C:\ProgramData".into()))
+            .join("OpenVoxel")
+            .join("ovl.toml")
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/OpenVoxel/ovl.toml")
+    } else {
+        PathBuf::from("/etc/openvoxel/ovl.toml")
+    }
+}
+
+fn load_system_config() -> SystemConfig {
+    match std::fs::read_to_string(get_system_config_path()) {
+        Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Could not parse the system config, ignoring it: {}",
+                e
+            );
+            SystemConfig::default()
+        }),
+        Err(_) => SystemConfig::default(),
+    }
+}
+
+/// Lay `overlay` over `base`, tables are merged key by key
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn build_config(system: &SystemConfig, user: toml::Table) -> Result<Config, toml::de::Error> {
+    let mut table = system.defaults.clone();
+    merge(&mut table, user);
+    merge(&mut table, system.enforced.clone());
+    toml::Value::Table(table).try_into()
+}
+
+pub fn load_config() -> Config {
+    let mut cached = CONFIG.lock().unwrap();
+    if let Some(config) = cached.as_ref() {
+        return config.clone();
+    }
+    let config = timed("config", read_config);
+    *cached = Some(config.clone());
+    config
+}
+
+/// Use other settings for the rest of this run only, nothing is written to disk
+pub fn use_config_for_run(config: Config) {
+    *CONFIG.lock().unwrap() = Some(config);
+}
+
+/// Versions of `config.toml`, see [`Schema`]
+const CONFIG_SCHEMA: Schema = Schema {
+    name: "config.toml",
+    migrations: &[
+        // 1: `schema_version` itself, nothing else changed
+        |_| {},
+    ],
+};
+
+/// Upgrade the user config in place when an older ovl wrote it. A config from a newer ovl
+/// stops the launcher, saving it would drop the settings this one does not know about.
+fn upgrade_config(path: &Path, table: toml::Table) -> toml::Table {
+    let upgraded = serde_json::to_value(&table)
+        .map_err(|e| e.to_string())
+        .and_then(|mut value| {
+            CONFIG_SCHEMA.upgrade_file(path, &mut value, |value| {
+                let table: toml::Table =
+                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+                toml::to_string_pretty(&table).map_err(|e| e.to_string())
+            })?;
+            serde_json::from_value(value).map_err(|e| e.to_string())
+        });
+    match upgraded {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// The user config as written, without the system one
+fn read_user_table() -> Result<toml::Table, String> {
+    let path = get_config_path();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => content
+            .parse::<toml::Table>()
+            .map(|table| upgrade_config(&path, table))
+            .map_err(|e| e.to_string()),
+        Err(_) => Ok(toml::Table::new()),
+    }
+}
+
+fn read_config() -> Config {
+    let system = load_system_config();
+    let user = read_user_table().unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Could not parse config.toml, using defaults: {}",
+            e
+        );
+        toml::Table::new()
+    });
+
+    build_config(&system, user).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Could not parse config.toml, using defaults: {}",
+            e
+        );
+        // The administrator's settings still apply without the user's
+        build_config(&system, toml::Table::new()).unwrap_or_default()
+    })
+}
+
+fn write_user_table(mut table: toml::Table) -> Result<(), Box<dyn Error>> {
+    let path = get_config_path();
+    if let Some(parent_dir) = path.parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+
+    table.insert(
+        VERSION_KEY.to_string(),
+        toml::Value::Integer(CONFIG_SCHEMA.current() as i64),
+    );
+
+    std::fs::write(path, toml::to_string_pretty(&table)?)?;
+    *CONFIG.lock().unwrap() = None;
+    Ok(())
+}
+
+pub fn save_config(config: &Config) -> Result<(), Box<dyn Error>> {
+    let toml::Value::Table(mut table) = toml::Value::try_from(config)? else {
+        return Err("The config is not a table".into());
+    };
+
+    // Enforced settings and untouched defaults stay in the system config
+    let system = load_system_config();
+    for key in system.enforced.keys() {
+        table.remove(key);
+    }
+    for (key, value) in &system.defaults {
+        if table.get(key) == Some(value) {
+            table.remove(key);
+        }
+    }
+
+    write_user_table(table)
+}
+
+/// Keys `ovl config list` does not print
+const SECRET_KEYS: &[&str] = &["github_token", "parental_pin"];
+
+/// Every key of the config, the unset ones included
+fn known_keys() -> Vec<String> {
+    match serde_json::to_value(Config::default()) {
+        Ok(serde_json::Value::Object(keys)) => keys.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The setting a dotted key (e.g. `accounts.Steve.language`) is in, when it exists
+fn check_key(key: &str) -> Result<&str, String> {
+    let setting = key.split('.').next().unwrap_or_default();
+    if !known_keys().iter().any(|k| k == setting) {
+        return Err(format!(
+            "Unknown setting: {}, see `ovl config list`",
+            setting
+        ));
+    }
+    Ok(setting)
+}
+
+/// A value typed on the command line: TOML (`true`, `4096`, `["a", "b"]`), or else a string
+fn parse_value(input: &str) -> toml::Value {
+    format!("value = {}", input)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(input.to_string()))
+}
+
+/// Print every setting as it applies, with the ones the administrator enforces marked
+pub fn print_config() -> Result<(), Box<dyn Error>> {
+    let toml::Value::Table(mut table) = toml::Value::try_from(load_config())? else {
+        return Err("The config is not a table".into());
+    };
+    let enforced = load_system_config().enforced;
+    let secrets: Vec<String> = SECRET_KEYS
+        .iter()
+        .filter(|key| table.remove(**key).is_some())
+        .map(|key| key.to_string())
+        .collect();
+    print_output(&table, |table| print_settings(table, &enforced, &secrets));
+    Ok(())
+}
+
+fn print_settings(table: &toml::Table, enforced: &toml::Table, secrets: &[String]) {
+    for key in known_keys() {
+        let value = match table.get(&key) {
+            None if secrets.contains(&key) => "(hidden)".muted().to_string(),
+            None => "(not set)".muted().to_string(),
+            Some(value) => value.to_string(),
+        };
+        if enforced.contains_key(&key) {
+            println!("{} = {} {}", key, value, "(enforced)".warning());
+        } else {
+            println!("{} = {}", key, value);
+        }
+    }
+    println!("\n{}", get_config_path().display().to_string().muted());
+}
+
+/// Print the value of one setting, nothing when it is not set
+pub fn print_config_value(key: &str) -> Result<(), Box<dyn Error>> {
+    check_key(key)?;
+    let config = toml::Value::try_from(load_config())?;
+    let value = key
+        .split('.')
+        .try_fold(&config, |value, part| value.get(part));
+    print_output(&value, |value| match value {
+        Some(toml::Value::String(value)) => println!("{}", value),
+        Some(value) => println!("{}", value),
+        None => {}
+    });
+    Ok(())
+}
+
+/// Change one setting of the user config, or go back to its default with `None`
+pub fn set_config_value(key: &str, input: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let setting = check_key(key)?;
+    if setting == "parental_pin" {
+        return Err("The parental PIN is set with `ovl accounts pin`".into());
+    }
+    let system = load_system_config();
+    if system.enforced.contains_key(setting) {
+        return Err(format!("{} is set by the administrator of this computer", setting).into());
+    }
+
+    let mut table = read_user_table()?;
+    let parts: Vec<&str> = key.split('.').collect();
+    let (name, parents) = parts.split_last().unwrap();
+    let mut target = &mut table;
+    for part in parents {
+        target = target
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("{} is not a table", part))?;
+    }
+    match input {
+        Some(input) => {
+            target.insert(name.to_string(), parse_value(input));
+        }
+        None => {
+            target.remove(*name);
+        }
+    }
+
+    build_config(&system, table.clone()).map_err(|e| format!("Invalid {}: {}", key, e))?;
+    write_user_table(table)
+}
+
+/// `$VISUAL`, `$EDITOR`, or the editor every system has
+fn get_editor() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// Open `config.toml` in an editor, the previous one is put back when the result is invalid
+/// and not fixed
+pub fn edit_config() -> Result<(), Box<dyn Error>> {
+    let path = get_config_path();
+    if !path.exists() {
+        write_user_table(toml::Table::new())?;
+    }
+    let previous = std::fs::read_to_string(&path)?;
+    let system = load_system_config();
+    let editor = get_editor();
+
+    loop {
+        // The editor can come with arguments, e.g. `code --wait`
+        let mut words = editor.split_whitespace();
+        let status = std::process::Command::new(words.next().unwrap_or_default())
+            .args(words)
+            .arg(&path)
+            .status()
+            .map_err(|e| format!("Failed to start {}: {}", editor, e))?;
+        if !status.success() {
+            return Err(format!("{} exited with {}", editor, status).into());
+        }
+
+        let checked = std::fs::read_to_string(&path)?
+            .parse::<toml::Table>()
+            .map_err(|e| e.to_string())
+            .and_then(|table| build_config(&system, table).map_err(|e| e.to_string()));
+        match checked {
+            Ok(_) => break,
+            Err(e) => {
+                eprintln!("The config is invalid: {}", e);
+                if !ask_yes_no("Edit it again?") {
+                    std::fs::write(&path, previous)?;
+                    println!("The previous config was put back.");
+                    break;
+                }
+            }
+        }
+    }
+
+    *CONFIG.lock().unwrap() = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_resolutions() {
+        assert_eq!(parse_resolution("1920x1080"), Ok((1920, 1080)));
+        assert_eq!(parse_resolution(" 854X480 "), Ok((854, 480)));
+    }
+
+    #[test]
+    fn rejects_invalid_resolutions() {
+        for input in [
+            "",
+            "1920",
+            "0x1080",
+            "1920x0",
+            "x1080",
+            "1920x",
+            "-1x5",
+            "1920*1080",
+        ] {
+            assert!(parse_resolution(input).is_err(), "{:?}", input);
+        }
+    }
+}
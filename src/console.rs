@@ -0,0 +1,272 @@
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use colored::Colorize;
+use rustyline::{DefaultEditor, ExternalPrinter, error::ReadlineError};
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin};
+use std::sync::mpsc::{SyncSender, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::theme::Themed;
+
+/// Lines of server output replayed to a console when it attaches
+const BACKLOG_LINES: usize = 100;
+/// Lines waiting to be sent to a console before it is dropped as stalled, at least the backlog
+const CLIENT_QUEUE: usize = 1000;
+/// Time a connection has to send the token
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time a write to a console can block before the console is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a running server tells `ovl server console` how to reach it
+fn get_console_file(dir: &Path) -> PathBuf {
+    dir.join(".ovl-console")
+}
+
+/// Shares the server stdin and stdout between the terminal that runs it and attached consoles
+pub struct ConsoleHub {
+    console_file: PathBuf,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    /// Output queues of the attached consoles, each drained by its own thread
+    clients: Arc<Mutex<Vec<SyncSender<String>>>>,
+    backlog: Arc<Mutex<VecDeque<String>>>,
+    /// Players online, when tracked
+    players: Option<Arc<Mutex<BTreeSet<String>>>>,
+}
+
+/// Name of the player in a `<name> joined the game` or `<name> left the game` log line
+fn parse_presence(line: &str) -> Option<(&str, bool)> {
+    let message = line.rsplit_once("]: ")?.1;
+    let (name, joined) = match message.strip_suffix(" joined the game") {
+        Some(name) => (name, true),
+        None => (message.strip_suffix(" left the game")?, false),
+    };
+    // Chat messages are `<name> text`, player names have no spaces
+    (!name.is_empty() && !name.contains([' ', '<'])).then_some((name, joined))
+}
+
+impl ConsoleHub {
+    pub fn start(dir: &Path) -> Result<ConsoleHub, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut token = [0u8; 16];
+        OsRng.fill_bytes(&mut token);
+        let token: String = token.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let console_file = get_console_file(dir);
+        std::fs::write(
+            &console_file,
+            format!("{}\n{}\n", listener.local_addr()?.port(), token),
+        )?;
+
+        let hub = ConsoleHub {
+            console_file,
+            stdin: Arc::new(Mutex::new(None)),
+            clients: Arc::new(Mutex::new(Vec::new())),
+            backlog: Arc::new(Mutex::new(VecDeque::new())),
+            players: None,
+        };
+
+        // Commands typed in the terminal running the server
+        let stdin = hub.stdin.clone();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                send_command(&stdin, &line);
+            }
+        });
+
+        let stdin = hub.stdin.clone();
+        let clients = hub.clients.clone();
+        let backlog = hub.backlog.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let (token, stdin, clients, backlog) = (
+                    token.clone(),
+                    stdin.clone(),
+                    clients.clone(),
+                    backlog.clone(),
+                );
+                // A connection that never sends the token must not hold up the others
+                std::thread::spawn(move || {
+                    accept_client(stream, &token, &stdin, &clients, &backlog);
+                });
+            }
+        });
+
+        Ok(hub)
+    }
+
+    /// Print who is online each time a player joins or leaves
+    pub fn track_players(&mut self) {
+        self.players = Some(Arc::new(Mutex::new(BTreeSet::new())));
+    }
+
+    /// Take over the stdin and stdout of a freshly spawned server
+    pub fn attach(&self, process: &mut Child) {
+        *self.stdin.lock().unwrap() = process.stdin.take();
+
+        let Some(stdout) = process.stdout.take() else {
+            return;
+        };
+        let clients = self.clients.clone();
+        let backlog = self.backlog.clone();
+        let players = self.players.clone();
+        // A restarted server starts empty
+        if let Some(players) = &players {
+            players.lock().unwrap().clear();
+        }
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{}", line);
+
+                if let Some(players) = &players
+                    && let Some((name, joined)) = parse_presence(&line)
+                {
+                    let mut players = players.lock().unwrap();
+                    match joined {
+                        true => players.insert(name.to_string()),
+                        false => players.remove(name),
+                    };
+                    let online: Vec<&str> = players.iter().map(String::as_str).collect();
+                    println!(
+                        "{}",
+                        format!("Players online ({}): {}", online.len(), online.join(", "))
+                            .bold()
+                            .accent()
+                    );
+                }
+
+                let mut backlog = backlog.lock().unwrap();
+                backlog.push_back(line.clone());
+                if backlog.len() > BACKLOG_LINES {
+                    backlog.pop_front();
+                }
+
+                // A console whose queue is full is not reading, it is dropped instead of
+                // holding up the server output
+                clients
+                    .lock()
+                    .unwrap()
+                    .retain(|client| client.try_send(line.clone()).is_ok());
+            }
+        });
+    }
+}
+
+impl Drop for ConsoleHub {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.console_file);
+    }
+}
+
+fn send_command(stdin: &Mutex<Option<ChildStdin>>, command: &str) {
+    if let Some(stdin) = stdin.lock().unwrap().as_mut() {
+        let _ = writeln!(stdin, "{}", command);
+    }
+}
+
+fn accept_client(
+    stream: TcpStream,
+    token: &str,
+    stdin: &Arc<Mutex<Option<ChildStdin>>>,
+    clients: &Arc<Mutex<Vec<SyncSender<String>>>>,
+    backlog: &Arc<Mutex<VecDeque<String>>>,
+) {
+    let Ok(reader_stream) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader_stream);
+
+    let mut line = String::new();
+    if stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err()
+        || reader.read_line(&mut line).is_err()
+        || line.trim() != token
+        || stream.set_read_timeout(None).is_err()
+        || stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err()
+    {
+        return;
+    }
+
+    let (sender, receiver) = sync_channel::<String>(CLIENT_QUEUE);
+    {
+        // The backlog stays locked until the console is registered, so no line is missed
+        let backlog = backlog.lock().unwrap();
+        for line in backlog.iter() {
+            let _ = sender.try_send(line.clone());
+        }
+        clients.lock().unwrap().push(sender);
+    }
+
+    let mut writer = stream;
+    std::thread::spawn(move || {
+        for line in receiver {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+        // Also ends the thread reading the commands of this console
+        let _ = writer.shutdown(std::net::Shutdown::Both);
+    });
+
+    for line in reader.lines().map_while(Result::ok) {
+        send_command(stdin, &line);
+    }
+}
+
+/// Connect to the console of a server started with `ovl server run`. Lines written to the
+/// stream are run as commands, and the server output (recent backlog first) can be read from it.
+pub fn connect_console(dir: &Path) -> Result<TcpStream, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(get_console_file(dir))
+        .map_err(|_| "No server is running, start one with `ovl server run`")?;
+    let mut lines = content.lines();
+    let port: u16 = lines.next().unwrap_or_default().trim().parse()?;
+    let token = lines.next().unwrap_or_default().trim();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port))
+        .map_err(|_| "Could not reach the server, it may have stopped")?;
+    writeln!(stream, "{}", token)?;
+    Ok(stream)
+}
+
+/// Attach to the console of a server started with `ovl server run`
+pub fn attach_console(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = connect_console(dir)?;
+
+    let mut editor = DefaultEditor::new()?;
+    let mut printer = editor.create_external_printer()?;
+    let output = BufReader::new(stream.try_clone()?);
+    std::thread::spawn(move || {
+        for line in output.lines().map_while(Result::ok) {
+            let _ = printer.print(format!("{}\n", line));
+        }
+        let _ = printer.print(format!("{}\n", "The server stopped.".error()));
+    });
+
+    println!(
+        "{}",
+        "Attached to the server console. Press Ctrl+D or Ctrl+C to detach, the server keeps running."
+            .bold()
+    );
+
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(line);
+                if writeln!(stream, "{}", line.trim_start_matches('/')).is_err() {
+                    return Err("Lost the connection to the server".into());
+                }
+            }
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => {
+                println!("Detached from the server console.");
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
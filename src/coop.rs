@@ -0,0 +1,88 @@
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{
+    audit::audit,
+    cmd::is_non_interactive,
+    dir::get_app_support_dir,
+    install::{commit_staged, discard_staged, stage_dir},
+    map::{Map, map_path, select_map, update_cached_map},
+    server::{ServerRunOptions, get_server_port, run_server, set_server_property},
+    world::{nbt_get, nbt_number, read_level_dat},
+    zipper::extract_zip,
+};
+
+/// Server folder of a co-op session, one per map so progress is kept between sessions
+pub fn get_coop_dir(map_id: &str) -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join("coop")
+        .join(map_path(map_id, ""))
+}
+
+/// `gamemode` value of server.properties for the `GameType` of a level.dat
+fn game_mode_name(game_type: i64) -> &'static str {
+    match game_type {
+        1 => "creative",
+        2 => "adventure",
+        3 => "spectator",
+        _ => "survival",
+    }
+}
+
+/// Put the map world in the co-op server folder, unless a previous session already did
+fn prepare_world(map: &Map) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = get_coop_dir(&map.id);
+    let world = dir.join("world");
+    fs::create_dir_all(&dir)?;
+
+    if !world.join("level.dat").exists() {
+        let zip_path = update_cached_map(&map.id)?;
+        println!("Extracting {} for the co-op server...", map.name);
+        let staged = stage_dir(&format!("coop-{}", map.id))?;
+        if let Err(e) = extract_zip(&zip_path, &staged) {
+            discard_staged(&staged);
+            return Err(e.into());
+        }
+        commit_staged(&staged, &world)?;
+        audit("download", &format!("co-op world of {}", map.id));
+    } else {
+        println!("Continuing the co-op world of {}.", map.name);
+    }
+
+    let game_type = read_level_dat(&world)
+        .ok()
+        .and_then(|level| nbt_number(nbt_get(&level, &["Data", "GameType"])))
+        .unwrap_or(0.0);
+    set_server_property(&dir, "level-name", "world")?;
+    set_server_property(&dir, "gamemode", game_mode_name(game_type as i64))?;
+    set_server_property(&dir, "motd", &map.name)?;
+    Ok(dir)
+}
+
+/// Host a catalog map for friends: install it as a server world, share an invite and show
+/// who is online until the server stops
+pub async fn coop(input: String, options: &ServerRunOptions) -> Result<(), Box<dyn Error>> {
+    let Some(map) = select_map(input.to_lowercase(), None, is_non_interactive()) else {
+        return Ok(());
+    };
+    let dir = prepare_world(&map)?;
+
+    let options = ServerRunOptions {
+        invite_map: Some(map.id.clone()),
+        track_players: true,
+        ..options.clone()
+    };
+    println!(
+        "Join your own session with {}",
+        format!(
+            "ovl run {} localhost:{}",
+            map.version,
+            get_server_port(&dir)
+        )
+        .bold()
+    );
+    run_server(&dir, Some(&map.version), &options).await
+}
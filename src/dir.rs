@@ -1,4 +1,7 @@
 use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static GAME_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 pub fn get_app_support_dir() -> Option<PathBuf> {
     dirs::data_dir().map(|path| path.join("OpenVoxel"))
@@ -13,3 +16,50 @@ pub fn get_minecraft_support_dir() -> Option<PathBuf> {
         }
     })
 }
+
+pub fn get_profiles_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join("profiles")
+}
+
+/// Names of the profiles on disk
+pub fn list_profiles() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(get_profiles_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Profile names are folder names, only letters, digits, `-` and `_` are allowed
+pub fn parse_profile_name(input: &str) -> Result<String, String> {
+    let valid = !input.is_empty()
+        && input
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(input.to_string()),
+        false => Err("Profile names are letters, digits, '-' or '_'".to_string()),
+    }
+}
+
+/// Run this process against another game directory than the shared one
+pub fn use_game_dir(dir: PathBuf) {
+    let _ = GAME_DIR.set(dir);
+}
+
+/// Run this process against the game directory of a profile
+pub fn use_profile(name: &str) {
+    use_game_dir(get_profiles_dir().join(name));
+}
+
+/// The `.minecraft` folder the game runs in, `profiles/<name>` when a profile is in use
+pub fn get_game_dir() -> PathBuf {
+    match GAME_DIR.get() {
+        Some(dir) => dir.clone(),
+        None => get_app_support_dir().unwrap().join(".minecraft"),
+    }
+}
@@ -0,0 +1,276 @@
+use colored::Colorize;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::{get_system_config_path, load_config},
+    dir::{get_app_support_dir, get_game_dir},
+    gpu::{check_gpu, gpu_info},
+    java::check_java_version,
+    mirrors::print_mirror_health,
+    natives::platform_name,
+    net::{self, IpVersion},
+    theme::Themed,
+};
+
+/// Endpoints the launcher depends on, probed by `ovl doctor --network`
+/// (the catalog is probed too, its URL comes from the config)
+const ENDPOINTS: &[(&str, &str)] = &[
+    (
+        "GitHub releases",
+        "https://github.com/OpenVoxelStudios/Maps/releases",
+    ),
+    ("Modrinth API", "https://api.modrinth.com/v2/"),
+    ("Fabric meta", "https://meta.fabricmc.net/v2/versions/game"),
+    ("Quilt meta", "https://meta.quiltmc.org/v3/versions/game"),
+    (
+        "Forge promotions",
+        "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json",
+    ),
+    (
+        "Mojang versions",
+        "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
+    ),
+    ("Microsoft login", "https://login.microsoftonline.com/"),
+];
+
+/// Hosts connected to over each IP stack to find which one is broken
+const STACK_PROBE_HOSTS: &[&str] = &["github.com", "api.modrinth.com", "piston-meta.mojang.com"];
+
+pub fn print_check(ok: bool, label: &str, detail: &str) {
+    let mark = if ok { "✓".success() } else { "✗".error() };
+    println!("  {} {}: {}", mark, label.bold(), detail);
+}
+
+pub async fn run_doctor(network: bool, gpu: bool) {
+    println!("{}", "Environment".bold());
+
+    let data_dir = get_app_support_dir().unwrap();
+    print_check(
+        data_dir.exists(),
+        "Data directory",
+        &data_dir.display().to_string(),
+    );
+
+    print_check(true, "Platform", &platform_name());
+
+    match check_java_version() {
+        Ok(version) => print_check(version >= 21, "Java", &format!("version {}", version)),
+        Err(e) => print_check(false, "Java", &e),
+    }
+
+    match load_config().ip_version {
+        IpVersion::Auto => {}
+        IpVersion::Ipv4 => print_check(true, "IP version", "IPv4 only"),
+        IpVersion::Ipv6 => print_check(true, "IP version", "IPv6 only"),
+    }
+
+    match load_config().proxy {
+        Some(proxy) => match reqwest::Proxy::all(&proxy) {
+            Ok(_) => print_check(true, "Proxy", &proxy),
+            Err(e) => print_check(false, "Proxy", &format!("{} is invalid: {}", proxy, e)),
+        },
+        None => print_check(true, "Proxy", "none"),
+    }
+
+    if let Some(ca_bundle) = load_config().ca_bundle {
+        print_check(
+            std::path::Path::new(&ca_bundle).is_file(),
+            "CA bundle",
+            &ca_bundle,
+        );
+    }
+
+    let system_config = get_system_config_path();
+    if system_config.exists() {
+        print_check(true, "System config", &system_config.display().to_string());
+    }
+
+    if is_steam_deck() {
+        check_steam_deck();
+    }
+
+    if gpu {
+        check_graphics();
+    }
+
+    if network {
+        check_network().await;
+    }
+}
+
+/// SteamOS, or the Valve hardware when another distribution is installed on the Deck
+fn is_steam_deck() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+
+    let os_release = std::fs::read_to_string("/etc/os-release").unwrap_or_default();
+    let board =
+        std::fs::read_to_string("/sys/devices/virtual/dmi/id/board_vendor").unwrap_or_default();
+    os_release.lines().any(|l| l == "ID=steamos") || board.trim() == "Valve"
+}
+
+fn check_steam_deck() {
+    println!("\n{}", "Steam Deck".bold());
+
+    if load_config().controller_support {
+        print_check(true, "Controller support", "enabled");
+    } else {
+        print_check(
+            false,
+            "Controller support",
+            "set controller_support = true in settings/config.toml to play with the built-in controls",
+        );
+    }
+
+    let options = std::fs::read_to_string(get_game_dir().join("options.txt")).unwrap_or_default();
+    let option = |key: &str| {
+        options
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix(':'))
+            .map(|v| v.trim().to_string())
+    };
+
+    match option("renderDistance").and_then(|v| v.parse::<u32>().ok()) {
+        Some(distance) if distance > 8 => print_check(
+            false,
+            "Render distance",
+            &format!(
+                "{} chunks, 8 or less keeps the frame rate and battery life up",
+                distance
+            ),
+        ),
+        Some(distance) => print_check(true, "Render distance", &format!("{} chunks", distance)),
+        None => {}
+    }
+
+    match option("fullscreen").as_deref() {
+        Some("true") => print_check(true, "Fullscreen", "on"),
+        Some(_) => print_check(
+            false,
+            "Fullscreen",
+            "off, turn it on in the video settings for Gaming Mode",
+        ),
+        None => {}
+    }
+}
+
+fn check_graphics() {
+    println!("\n{}", "Graphics".bold());
+
+    // Detected again, a driver may have been installed without a reboot
+    let info = gpu_info(true);
+    if info.gpus.is_empty() {
+        print_check(false, "GPU", "could not be detected");
+    }
+    for (name, driver) in &info.gpus {
+        match driver {
+            Some(driver) => print_check(true, "GPU", &format!("{} ({})", name, driver)),
+            None => print_check(true, "GPU", name),
+        }
+    }
+
+    for issue in check_gpu(&info) {
+        print_check(false, &issue.problem, issue.guidance);
+    }
+}
+
+/// Whether a TCP connection can be opened to any of the addresses, `None` without any
+fn connects(addresses: &[SocketAddr]) -> Option<bool> {
+    if addresses.is_empty() {
+        return None;
+    }
+    Some(
+        addresses
+            .iter()
+            .any(|address| TcpStream::connect_timeout(address, Duration::from_secs(5)).is_ok()),
+    )
+}
+
+/// Connect to the services over IPv4 and IPv6 separately, downloads hang when the system
+/// prefers a stack that resolves but does not route
+fn check_ip_stacks() {
+    let (mut v4, mut v6) = (Vec::new(), Vec::new());
+    for host in STACK_PROBE_HOSTS {
+        for address in (*host, 443).to_socket_addrs().into_iter().flatten() {
+            match address {
+                SocketAddr::V4(_) => v4.push(address),
+                SocketAddr::V6(_) => v6.push(address),
+            }
+        }
+    }
+
+    let ipv4 = connects(&v4);
+    let ipv6 = connects(&v6);
+    for (label, result) in [("IPv4", ipv4), ("IPv6", ipv6)] {
+        match result {
+            Some(ok) => print_check(
+                ok,
+                label,
+                if ok {
+                    "connected"
+                } else {
+                    "addresses resolve but connections fail"
+                },
+            ),
+            None => print_check(true, label, "no addresses, not used"),
+        }
+    }
+
+    match (ipv4, ipv6) {
+        (Some(true), Some(false)) => println!(
+            "  IPv6 is broken on this network, set ip_version = \"ipv4\" in the config or pass --ipv4."
+        ),
+        (Some(false), Some(true)) => println!(
+            "  IPv4 is broken on this network, set ip_version = \"ipv6\" in the config or pass --ipv6."
+        ),
+        _ => {}
+    }
+}
+
+async fn check_network() {
+    println!("\n{}", "Network".bold());
+
+    let config = load_config();
+    let mut sources: Vec<(String, &str)> = config
+        .catalog_sources
+        .iter()
+        .map(|(name, url)| (format!("{} catalog", name), url.as_str()))
+        .collect();
+    sources.sort();
+    let endpoints = std::iter::once(("OpenVoxel catalog", config.catalog_url.as_str()))
+        .chain(sources.iter().map(|(label, url)| (label.as_str(), *url)))
+        .chain(ENDPOINTS.iter().copied());
+
+    check_ip_stacks();
+
+    for (label, url) in endpoints {
+        let started = Instant::now();
+        match net::get(url).await {
+            Ok(response) => print_check(
+                response.status().is_success(),
+                label,
+                &format!(
+                    "HTTP {} in {:?}",
+                    response.status().as_u16(),
+                    started.elapsed()
+                ),
+            ),
+            Err(e) => print_check(
+                false,
+                label,
+                &format!("{} after {:?}", e, started.elapsed()),
+            ),
+        }
+    }
+
+    print_mirror_health();
+
+    let stats = net::stats();
+    println!(
+        "\n  {} requests, {} failed, {} retried, {:?} total",
+        stats.requests, stats.failures, stats.retries, stats.elapsed
+    );
+    println!("  Set OVL_DEBUG=1 to log every request made by other commands.");
+}
@@ -0,0 +1,164 @@
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::{natives::library_applies, net, versions::fetch_version_json};
+
+/// The end of central directory record is at most this far from the end of a zip
+const EOCD_SEARCH: u64 = 22 + 65535;
+
+/// How much a map or version install is going to take
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DownloadEstimate {
+    pub download: u64,
+    /// Unknown when the archive could not be inspected
+    pub disk: Option<u64>,
+}
+
+impl DownloadEstimate {
+    pub fn describe(&self) -> String {
+        match self.disk {
+            Some(disk) => format!(
+                "This will download ~{} and use ~{} on disk.",
+                format_size(self.download),
+                format_size(disk)
+            ),
+            None => format!("This will download ~{}.", format_size(self.download)),
+        }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let mb = bytes as f64 / 1024.0 / 1024.0;
+    if mb >= 1024.0 {
+        format!("{:.1} GB", mb / 1024.0)
+    } else if mb >= 10.0 {
+        format!("{:.0} MB", mb)
+    } else {
+        format!("{:.1} MB", mb)
+    }
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u64> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as u64)
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u64> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?) as u64)
+}
+
+fn fetch_range(url: &str, range: String) -> Option<(Vec<u8>, Option<u64>)> {
+    let response = net::blocking_client()
+        .get(url)
+        .header(RANGE, range)
+        .send()
+        .ok()?;
+    // A server that ignores the range sends the whole file, it is not downloaded here
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    // `bytes <start>-<end>/<total>`
+    let total = response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit('/').next())
+        .and_then(|v| v.parse().ok());
+    Some((response.bytes().ok()?.to_vec(), total))
+}
+
+/// Sum of the uncompressed sizes in a zip central directory
+fn uncompressed_size(directory: &[u8]) -> Option<u64> {
+    let mut total = 0;
+    let mut pos = 0;
+    while read_u32(directory, pos) == Some(0x0201_4b50) {
+        total += read_u32(directory, pos + 24)?;
+        pos += 46
+            + read_u16(directory, pos + 28)? as usize
+            + read_u16(directory, pos + 30)? as usize
+            + read_u16(directory, pos + 32)? as usize;
+    }
+    Some(total)
+}
+
+/// Size of a remote zip and of its extracted content, read from its central directory with
+/// range requests so nothing else is downloaded
+pub fn estimate_zip(url: &str) -> Option<DownloadEstimate> {
+    let (tail, total) = fetch_range(url, format!("bytes=-{}", EOCD_SEARCH))?;
+    let size = total.unwrap_or(tail.len() as u64);
+    let estimate = |extracted: Option<u64>| DownloadEstimate {
+        download: size,
+        // The zip stays in the cache next to the extracted world
+        disk: extracted.map(|e| size + e),
+    };
+
+    let Some(eocd) = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(&tail, i) == Some(0x0605_4b50))
+    else {
+        return Some(estimate(None));
+    };
+    let directory_size = read_u32(&tail, eocd + 12)?;
+    let directory_offset = read_u32(&tail, eocd + 16)?;
+    // Zip64 archives keep the real values elsewhere
+    if directory_offset == 0xFFFF_FFFF {
+        return Some(estimate(None));
+    }
+
+    let tail_start = size - tail.len() as u64;
+    let extracted = if directory_offset >= tail_start {
+        let start = (directory_offset - tail_start) as usize;
+        uncompressed_size(tail.get(start..start + directory_size as usize)?)
+    } else {
+        fetch_range(
+            url,
+            format!(
+                "bytes={}-{}",
+                directory_offset,
+                directory_offset + directory_size - 1
+            ),
+        )
+        .and_then(|(directory, _)| uncompressed_size(&directory))
+    };
+    Some(estimate(extracted))
+}
+
+/// Size of the client jar, libraries and assets of a version that are not installed yet
+pub async fn estimate_version(game_dir: &Path, version: &str) -> Option<DownloadEstimate> {
+    let json: Value = fetch_version_json(version).await.ok()?;
+    let mut download = 0;
+
+    let client_jar = game_dir
+        .join("versions")
+        .join(version)
+        .join(format!("{}.jar", version));
+    if !client_jar.exists() {
+        download += json["downloads"]["client"]["size"].as_u64().unwrap_or(0);
+    }
+
+    let libraries_dir = game_dir.join("libraries");
+    for library in json["libraries"].as_array().into_iter().flatten() {
+        let artifact = &library["downloads"]["artifact"];
+        if library_applies(library)
+            && let (Some(path), Some(size)) = (artifact["path"].as_str(), artifact["size"].as_u64())
+            && !libraries_dir.join(path).exists()
+        {
+            download += size;
+        }
+    }
+
+    let index_id = json["assetIndex"]["id"].as_str().unwrap_or(version);
+    let index = game_dir
+        .join("assets")
+        .join("indexes")
+        .join(format!("{}.json", index_id));
+    // Assets are shared between versions, an existing index means they are mostly there
+    if !index.exists() {
+        download += json["assetIndex"]["totalSize"].as_u64().unwrap_or(0);
+    }
+
+    Some(DownloadEstimate {
+        download,
+        disk: Some(download),
+    })
+}
@@ -0,0 +1,301 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    dir::{get_game_dir, get_profiles_dir, use_profile},
+    filesys::{check_jar_name, getsha256, is_plain_file_name},
+    map::{cached_map_path, download_map, install_map_from_path},
+    mc::{LaunchOptions, launch},
+    mismatch::HashMismatch,
+    net,
+    objects::write_shared,
+    scan::scan_download,
+    theme::Themed,
+};
+
+const EVENTS_URL: &str = "https://openvoxel.studio/events";
+const MANIFEST_FILE: &str = ".ovl-event.json";
+
+/// The exact setup every participant of an event runs, published by the organizers
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventManifest {
+    pub name: String,
+    pub minecraft: String,
+    /// Fabric loader version, vanilla when missing
+    pub fabric: Option<String>,
+    pub map: Option<EventMap>,
+    #[serde(default)]
+    pub mods: Vec<EventMod>,
+    /// Fixed `options.txt` values, e.g. `"renderDistance": "12"`
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventMap {
+    pub id: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventMod {
+    pub filename: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl EventManifest {
+    /// Refuse file names that would be written outside of the profile
+    pub fn check(&self) -> Result<(), String> {
+        for event_mod in &self.mods {
+            check_jar_name(&event_mod.filename)?;
+        }
+        if let Some(map) = &self.map
+            && !is_plain_file_name(&map.id)
+        {
+            return Err(format!("{:?} is not a valid map id", map.id));
+        }
+        Ok(())
+    }
+}
+
+/// Keep the setup a profile was made for in it, so that it is launched the same way later
+pub fn save_profile_manifest(
+    game_dir: &Path,
+    manifest: &EventManifest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(game_dir)?;
+    fs::write(
+        game_dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(manifest)?,
+    )?;
+    Ok(())
+}
+
+/// The setup of an event or thawed profile, `None` for the other game folders
+pub fn read_profile_manifest(game_dir: &Path) -> Option<EventManifest> {
+    let content = fs::read_to_string(game_dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Event codes name a profile folder and a URL, only letters, digits, `-` and `_` are allowed
+fn check_code(code: &str) -> Result<(), String> {
+    match !code.is_empty()
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        true => Ok(()),
+        false => Err(format!(
+            "{:?} is not an event code, codes only have letters, digits, - and _",
+            code
+        )),
+    }
+}
+
+fn profile_name(code: &str) -> String {
+    format!("event-{}", code.to_lowercase())
+}
+
+/// Fetch the manifest of an event, falling back to the copy saved in its profile when offline
+async fn fetch_manifest(code: &str) -> Result<EventManifest, Box<dyn std::error::Error>> {
+    let saved = get_profiles_dir()
+        .join(profile_name(code))
+        .join(MANIFEST_FILE);
+
+    let fetched = async {
+        net::get(&format!("{}/{}.json", EVENTS_URL, code.to_lowercase()))
+            .await?
+            .error_for_status()?
+            .json::<EventManifest>()
+            .await
+    }
+    .await;
+
+    let manifest = match fetched {
+        Ok(manifest) => manifest,
+        Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            return Err(format!("There is no event with the code {}", code).into());
+        }
+        Err(e) if saved.exists() => {
+            eprintln!(
+                "{} Could not fetch the event ({}), using the saved copy.",
+                "Warning:".warning(),
+                e
+            );
+            serde_json::from_str(&fs::read_to_string(saved)?)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    manifest.check()?;
+    Ok(manifest)
+}
+
+pub fn read_options(path: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Bring the profile in line with the manifest: mods, options and map
+pub async fn install_event(
+    game_dir: &Path,
+    manifest: &EventManifest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    manifest.check()?;
+    let mods_dir = game_dir.join("mods");
+    fs::create_dir_all(&mods_dir)?;
+
+    for event_mod in &manifest.mods {
+        let path = mods_dir.join(&event_mod.filename);
+        if path.exists() && getsha256(&path)? == event_mod.sha256 {
+            continue;
+        }
+
+        println!("Downloading {}...", event_mod.filename);
+        let content = loop {
+            let content = net::get(&event_mod.url)
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            let hash = sha256::digest(content.as_ref());
+            if hash == event_mod.sha256 {
+                break content;
+            }
+            // The event pins its mods, only its organizers can change them
+            HashMismatch {
+                what: &format!("mod {}", event_mod.filename),
+                file_name: &event_mod.filename,
+                url: &event_mod.url,
+                algorithm: "SHA256",
+                expected: &event_mod.sha256,
+                actual: &hash,
+                can_repin: false,
+            }
+            .resolve(&content)?;
+        };
+        scan_download(&event_mod.filename, &content)?;
+        write_shared(&path, &content)?;
+    }
+
+    let expected: HashSet<&str> = manifest.mods.iter().map(|m| m.filename.as_str()).collect();
+    for entry in fs::read_dir(&mods_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.extension().is_some_and(|ext| ext == "jar") && !expected.contains(name.as_str()) {
+            println!("Removing {}, it is not part of the setup.", name);
+            fs::remove_file(path)?;
+        }
+    }
+
+    let options_path = game_dir.join("options.txt");
+    let mut options = read_options(&options_path);
+    options.extend(manifest.options.clone());
+    let contents: Vec<String> = options
+        .iter()
+        .map(|(key, value)| format!("{}:{}", key, value))
+        .collect();
+    fs::write(&options_path, contents.join("\n") + "\n")?;
+
+    if let Some(map) = &manifest.map {
+        let map_path = cached_map_path(&map.id);
+        fs::create_dir_all(map_path.parent().unwrap())?;
+        if !map_path.exists() || getsha256(&map_path)? != map.sha256 {
+            println!("Downloading the event map...");
+            download_map(map.id.clone(), map.sha256.clone())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Everything in the profile that differs from the manifest
+pub fn verify_event(game_dir: &Path, manifest: &EventManifest) -> Vec<String> {
+    let mut problems = vec![];
+    let mods_dir = game_dir.join("mods");
+
+    if let Err(e) = manifest.check() {
+        return vec![e];
+    }
+    for event_mod in &manifest.mods {
+        let path = mods_dir.join(&event_mod.filename);
+        if !path.exists() {
+            problems.push(format!("{} is missing", event_mod.filename));
+        } else if getsha256(&path).ok().as_deref() != Some(event_mod.sha256.as_str()) {
+            problems.push(format!("{} was modified", event_mod.filename));
+        }
+    }
+
+    let expected: HashSet<&str> = manifest.mods.iter().map(|m| m.filename.as_str()).collect();
+    if let Ok(entries) = fs::read_dir(&mods_dir) {
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if path.extension().is_some_and(|ext| ext == "jar") && !expected.contains(name.as_str())
+            {
+                problems.push(format!("{} is not part of the setup", name));
+            }
+        }
+    }
+
+    let options = read_options(&game_dir.join("options.txt"));
+    for (key, value) in &manifest.options {
+        if options.get(key) != Some(value) {
+            problems.push(format!("option {} is not {}", key, value));
+        }
+    }
+
+    if let Some(map) = &manifest.map {
+        let map_path = cached_map_path(&map.id);
+        if !map_path.exists() || getsha256(&map_path).ok().as_deref() != Some(map.sha256.as_str()) {
+            problems.push(format!("the map {} does not match the event", map.id));
+        }
+    }
+
+    problems
+}
+
+/// Install the setup of an event in its own profile, check it and launch it
+pub async fn join_event(
+    code: &str,
+    options: &LaunchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_code(code)?;
+    let manifest = fetch_manifest(code).await?;
+    println!("Joining {}", manifest.name.bold());
+
+    use_profile(&profile_name(code));
+    let game_dir = get_game_dir();
+    save_profile_manifest(&game_dir, &manifest)?;
+
+    install_event(&game_dir, &manifest).await?;
+
+    let problems = verify_event(&game_dir, &manifest);
+    if !problems.is_empty() {
+        eprintln!("{}", "The event setup could not be verified:".error());
+        for problem in problems {
+            eprintln!("  - {}", problem);
+        }
+        return Err("run `ovl event join` again to repair it".into());
+    }
+    println!("{}", "Event setup verified.".success());
+
+    let world = match &manifest.map {
+        Some(map) => Some(install_map_from_path(cached_map_path(&map.id), false)?),
+        None => None,
+    };
+
+    let options = LaunchOptions {
+        locked: true,
+        fabric_version: manifest.fabric.clone(),
+        ..options.clone()
+    };
+    launch(manifest.minecraft.clone(), world.as_ref(), None, &options).await;
+    Ok(())
+}
@@ -3,7 +3,7 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
-use crate::dir::get_app_support_dir;
+use crate::dir::get_game_dir;
 
 pub fn ensure_folder_exists(path: &str) -> std::io::Result<()> {
     let folder = Path::new(path);
@@ -13,6 +13,28 @@ pub fn ensure_folder_exists(path: &str) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Write a file as a new one and move it over `path`. The old file may be hard-linked
+/// into the object store, writing through it would change the object too.
+pub fn replace_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    let temp = path.with_extension("ovl-tmp");
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)
+}
+
+/// Whether a name from a manifest or lock file names a file directly in its folder, so
+/// that joining it cannot leave the folder
+pub fn is_plain_file_name(name: &str) -> bool {
+    Path::new(name).file_name().and_then(|n| n.to_str()) == Some(name)
+}
+
+/// Refuse a jar name from a manifest or lock file that is not a plain `.jar` file name
+pub fn check_jar_name(name: &str) -> Result<(), String> {
+    match is_plain_file_name(name) && name.ends_with(".jar") {
+        true => Ok(()),
+        false => Err(format!("{:?} is not a valid jar file name", name)),
+    }
+}
+
 pub fn getsha256(path: &PathBuf) -> Result<String, Box<dyn std::error::Error>> {
     let bytes = std::fs::read(&path).unwrap();
     let local_hash = sha256::digest(&bytes);
@@ -34,25 +56,32 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<
 }
 
 pub fn used_version_save(version: String) {
-    if let Ok(mut file) = File::create(
-        get_app_support_dir()
-            .unwrap()
-            .join(".minecraft")
-            .join("mods")
-            .join(".ovl")
-            .to_str()
-            .unwrap(),
-    ) {
+    if let Ok(mut file) = File::create(get_game_dir().join("mods").join(".ovl").to_str().unwrap()) {
         let _ = file.write_all(version.as_bytes());
     }
 }
 
+/// Remember the Fabric loader of the last launch next to its version, for `ovl freeze`
+pub fn used_fabric_save(fabric_version: Option<&str>) {
+    let path = get_game_dir().join("mods").join(".ovl-fabric");
+    match fabric_version {
+        Some(fabric_version) => {
+            let _ = fs::write(path, fabric_version);
+        }
+        None => {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+pub fn get_used_fabric_save() -> Option<String> {
+    fs::read_to_string(get_game_dir().join("mods").join(".ovl-fabric"))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
 pub fn get_used_version_save() -> Option<String> {
-    let path = get_app_support_dir()
-        .unwrap()
-        .join(".minecraft")
-        .join("mods")
-        .join(".ovl");
+    let path = get_game_dir().join("mods").join(".ovl");
 
     if path.exists() {
         if let Ok(content) = fs::read_to_string(path) {
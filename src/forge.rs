@@ -0,0 +1,151 @@
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::mismatch::HashMismatch;
+use crate::net;
+use crate::scan::scan_download;
+
+const PROMOTIONS_URL: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const MAVEN_URL: &str = "https://maven.minecraftforge.net/net/minecraftforge/forge";
+
+#[derive(Debug, Deserialize)]
+struct Promotions {
+    /// `<minecraft>-recommended` and `<minecraft>-latest` to a Forge version
+    promos: HashMap<String, String>,
+}
+
+/// The recommended Forge build of a Minecraft version, the latest one when none is
+/// recommended yet. `None` when Forge does not support the version.
+pub async fn fetch_forge(version: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let promotions: Promotions = net::get(PROMOTIONS_URL)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(["recommended", "latest"]
+        .iter()
+        .find_map(|kind| promotions.promos.get(&format!("{}-{}", version, kind)))
+        .cloned())
+}
+
+/// The SHA1 Maven publishes next to a file, in `<url>.sha1`
+async fn fetch_sha1(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let text = net::get(&format!("{}.sha1", url))
+        .await?
+        .error_for_status()
+        .map_err(|e| format!("The Forge installer has no published hash: {}", e))?
+        .text()
+        .await?;
+    let hash = text
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "The Forge installer has an invalid published hash: {}",
+            text
+        )
+        .into());
+    }
+    Ok(hash)
+}
+
+fn sha1_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(content))
+}
+
+/// The version folder the Forge installer created for this build, named
+/// `<minecraft>-forge-<forge>` by recent installers
+pub fn installed_forge_id(game_dir: &Path, version: &str, forge: &str) -> Option<String> {
+    fs::read_dir(game_dir.join("versions"))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .find(|id| {
+            id.starts_with(version)
+                && id.contains("forge")
+                && id.ends_with(forge)
+                && game_dir
+                    .join("versions")
+                    .join(id)
+                    .join(format!("{}.json", id))
+                    .exists()
+        })
+}
+
+/// Run the Forge installer for a build, unless it is already installed. The vanilla version
+/// has to be installed first. Returns the version id of Forge.
+pub async fn install_forge(
+    game_dir: &Path,
+    version: &str,
+    forge: &str,
+    java_path: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(id) = installed_forge_id(game_dir, version, forge) {
+        return Ok(id);
+    }
+
+    println!("Installing Forge {}...", forge);
+    let build = format!("{}-{}", version, forge);
+    let name = format!("forge-{}-installer.jar", build);
+    let url = format!("{}/{}/{}", MAVEN_URL, build, name);
+    // The installer runs with the rights of the player, it has to be the published one
+    let expected = fetch_sha1(&url).await?;
+    let bytes = loop {
+        let bytes = net::get(&url).await?.error_for_status()?.bytes().await?;
+        let actual = sha1_hex(&bytes);
+        if actual == expected {
+            break bytes;
+        }
+        HashMismatch {
+            what: &format!("Forge {} installer", forge),
+            file_name: &name,
+            url: &url,
+            algorithm: "SHA1",
+            expected: &expected,
+            actual: &actual,
+            can_repin: false,
+        }
+        .resolve(&bytes)?;
+    };
+    let installer = game_dir.join(format!(".ovl-forge-{}-installer.jar", build));
+    scan_download(&name, &bytes)?;
+    fs::write(&installer, bytes)?;
+
+    // The installer refuses folders that do not look like a launcher installation
+    let profiles = game_dir.join("launcher_profiles.json");
+    if !profiles.exists() {
+        fs::write(&profiles, "{\"profiles\":{}}")?;
+    }
+
+    let output = Command::new(java_path)
+        .arg("-jar")
+        .arg(&installer)
+        .arg("--installClient")
+        .arg(game_dir)
+        .current_dir(game_dir)
+        .output();
+    let _ = fs::remove_file(&installer);
+    // It also leaves its log next to the jar
+    let _ = fs::remove_file(installer.with_extension("jar.log"));
+    let output = output?;
+
+    if !output.status.success() {
+        let log = String::from_utf8_lossy(&output.stdout);
+        let tail: Vec<&str> = log.lines().rev().take(10).collect();
+        for line in tail.iter().rev() {
+            eprintln!("  {}", line);
+        }
+        return Err(format!("the installer exited with {}", output.status).into());
+    }
+
+    installed_forge_id(game_dir, version, forge)
+        .ok_or_else(|| "the installer did not create the Forge version".into())
+}
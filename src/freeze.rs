@@ -0,0 +1,226 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    dir::{get_game_dir, use_profile},
+    event::{
+        EventManifest, EventMod, install_event, read_options, save_profile_manifest, verify_event,
+    },
+    filesys::{check_jar_name, get_used_fabric_save, get_used_version_save, getsha256},
+    manifest::file_origin,
+    mc::{LaunchOptions, launch},
+    mods::read_lock,
+    net,
+    releases::get_pack_url,
+    scan::scan_download,
+    theme::Themed,
+};
+
+/// Options that depend on the machine rather than on the setup
+const MACHINE_OPTIONS: &[&str] = &[
+    "fullscreenResolution",
+    "overrideWidth",
+    "overrideHeight",
+    "soundDevice",
+    "lastServer",
+    "joinedFirstServer",
+    "onboardAccessibility",
+    "tutorialStep",
+];
+
+/// Everything needed to rebuild a game setup on another machine
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EnvLock {
+    pub created: String,
+    pub minecraft: String,
+    /// Fabric loader version, vanilla when missing
+    pub fabric: Option<String>,
+    pub mods: Vec<FrozenMod>,
+    pub pack: Option<FrozenPack>,
+    /// `options.txt` values, without the machine-specific ones
+    pub options: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrozenMod {
+    pub filename: String,
+    /// Modrinth version id, when the mod came from the lock file
+    pub version_id: Option<String>,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl EnvLock {
+    /// Read a lock file, refusing mod file names that would be written outside of the profile
+    pub fn read(file: &Path) -> Result<(Self, String), Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(file)?;
+        let lock: EnvLock = serde_json::from_str(&content)?;
+        for frozen in &lock.mods {
+            check_jar_name(&frozen.filename)
+                .map_err(|e| format!("{} is not a valid lock file: {}", file.display(), e))?;
+        }
+        Ok((lock, content))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrozenPack {
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Modrinth version id of a managed jar, from the locked mods and extras
+fn locked_version_id(version: &str, filename: &str) -> Option<String> {
+    let name = filename
+        .strip_suffix("-AUTOUPDATE.jar")
+        .or_else(|| filename.strip_suffix("-EXTRA.jar"))?;
+    let lock = read_lock();
+    let bundled = lock.versions.get(version).into_iter().flatten();
+    let extras = lock.extras.get(version).into_iter().flat_map(|e| &e.mods);
+    bundled
+        .chain(extras)
+        .find(|m| m.name == name)
+        .map(|m| m.version_id.clone())
+}
+
+/// Capture the current game setup, after at least one launch
+pub fn freeze(out: &Path) -> Result<EnvLock, Box<dyn std::error::Error>> {
+    let game_dir = get_game_dir();
+    let minecraft =
+        get_used_version_save().ok_or("No game launched yet, launch a version first")?;
+
+    let mut mods = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(game_dir.join("mods"))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jar"))
+        .collect();
+    entries.sort();
+    for path in entries {
+        let filename = path.file_name().unwrap().to_string_lossy().to_string();
+        let Some(url) = file_origin(&path) else {
+            eprintln!(
+                "{} {} was not installed by the launcher and cannot be downloaded elsewhere, it is left out.",
+                "Warning:".warning(),
+                filename
+            );
+            continue;
+        };
+        mods.push(FrozenMod {
+            version_id: locked_version_id(&minecraft, &filename),
+            sha256: getsha256(&path)?,
+            filename,
+            url,
+        });
+    }
+
+    let pack_path = game_dir.join("resourcepacks").join("OVP.zip");
+    let pack = match pack_path.exists() {
+        true => Some(FrozenPack {
+            url: get_pack_url()?,
+            sha256: getsha256(&pack_path)?,
+        }),
+        false => None,
+    };
+
+    let mut options = read_options(&game_dir.join("options.txt"));
+    options.retain(|key, _| !MACHINE_OPTIONS.contains(&key.as_str()));
+
+    let lock = EnvLock {
+        created: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        minecraft,
+        fabric: get_used_fabric_save(),
+        mods,
+        pack,
+        options,
+    };
+    fs::write(out, serde_json::to_string_pretty(&lock)?)?;
+    Ok(lock)
+}
+
+async fn install_pack(
+    game_dir: &Path,
+    pack: &FrozenPack,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = game_dir.join("resourcepacks").join("OVP.zip");
+    if path.exists() && getsha256(&path)? == pack.sha256 {
+        return Ok(());
+    }
+
+    println!("Downloading the resource pack...");
+    let content = net::get(&pack.url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    // The pack release is usually a moving tag, older builds cannot be fetched
+    if sha256::digest(content.as_ref()) != pack.sha256 {
+        eprintln!(
+            "{} The resource pack was updated since the freeze, the latest one is used.",
+            "Warning:".warning()
+        );
+    }
+    scan_download("OVP.zip", &content)?;
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Rebuild a frozen setup in its own profile, check it and launch it
+pub async fn thaw(file: &Path, options: &LaunchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let (lock, content) = EnvLock::read(file)?;
+    println!(
+        "Rebuilding the setup frozen on {} (Minecraft {}, {} mods)",
+        lock.created,
+        lock.minecraft,
+        lock.mods.len()
+    );
+
+    // Same file, same profile, so thawing again only repairs what changed
+    use_profile(&format!("thaw-{}", &sha256::digest(&content)[..12]));
+    let game_dir = get_game_dir();
+    fs::create_dir_all(&game_dir)?;
+
+    let manifest = EventManifest {
+        name: format!("environment frozen on {}", lock.created),
+        minecraft: lock.minecraft.clone(),
+        fabric: lock.fabric.clone(),
+        map: None,
+        mods: lock
+            .mods
+            .iter()
+            .map(|m| EventMod {
+                filename: m.filename.clone(),
+                url: m.url.clone(),
+                sha256: m.sha256.clone(),
+            })
+            .collect(),
+        options: lock.options.clone(),
+    };
+    install_event(&game_dir, &manifest).await?;
+    save_profile_manifest(&game_dir, &manifest)?;
+    if let Some(pack) = &lock.pack {
+        install_pack(&game_dir, pack).await?;
+    }
+
+    let problems = verify_event(&game_dir, &manifest);
+    if !problems.is_empty() {
+        eprintln!("{}", "The setup could not be verified:".error());
+        for problem in problems {
+            eprintln!("  - {}", problem);
+        }
+        return Err("run `ovl thaw` again to repair it".into());
+    }
+    println!("{}", "Setup verified.".success());
+
+    let options = LaunchOptions {
+        locked: true,
+        fabric_version: lock.fabric.clone(),
+        ..options.clone()
+    };
+    launch(lock.minecraft.clone(), None, None, &options).await;
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use inquire::Select;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    audit::audit,
+    cmd::check_interactive,
+    dir::get_app_support_dir,
+    filesys::getsha256,
+    install::stage_dir,
+    net,
+    releases::{ReleaseAsset, download_repo_archive, get_repo_release},
+    scan::scan_download,
+    zipper::extract_zip,
+};
+
+const PREFIX: &str = "gh:";
+/// How deep in a repository archive a world is looked for
+const MAX_WORLD_DEPTH: usize = 4;
+
+/// `gh:<owner>/<repo>[@tag]` as given to `ovl open`
+pub struct GithubSource {
+    pub repo: String,
+    pub tag: Option<String>,
+}
+
+impl GithubSource {
+    fn name(&self) -> &str {
+        self.repo.rsplit('/').next().unwrap_or(&self.repo)
+    }
+}
+
+pub fn parse_github_source(input: &str) -> Option<Result<GithubSource, String>> {
+    let rest = input.strip_prefix(PREFIX)?;
+    let (repo, tag) = match rest.split_once('@') {
+        Some((repo, tag)) => (repo, Some(tag.to_string())),
+        None => (rest, None),
+    };
+    let valid = repo.split('/').count() == 2
+        && repo.split('/').all(|part| {
+            !part.is_empty()
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+    if !valid || tag.as_deref() == Some("") {
+        return Some(Err(format!(
+            "Expected gh:<owner>/<repo> or gh:<owner>/<repo>@<tag>, got {}",
+            input
+        )));
+    }
+    Some(Ok(GithubSource {
+        repo: repo.to_string(),
+        tag,
+    }))
+}
+
+/// The zip asset of a release that holds the world, asking when there are several
+fn pick_asset(assets: Vec<&ReleaseAsset>) -> Option<&ReleaseAsset> {
+    if assets.len() <= 1 {
+        return assets.into_iter().next();
+    }
+    let names: Vec<String> = assets.iter().map(|a| a.name.clone()).collect();
+    let question = "The release has several zips, which one is the map?";
+    check_interactive(question)
+        .map_err(|e| eprintln!("{}", e))
+        .ok()?;
+    let name = Select::new(question, names).prompt().ok()?;
+    assets.into_iter().find(|a| a.name == name)
+}
+
+fn download_asset(source: &GithubSource, asset: &ReleaseAsset) -> Result<PathBuf, Box<dyn Error>> {
+    let cache_dir = get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("github")
+        .join(source.repo.replace('/', "-"));
+    fs::create_dir_all(&cache_dir)?;
+    let path = cache_dir.join(&asset.name);
+
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|d| d.strip_prefix("sha256:"));
+    if let Some(expected) = expected
+        && path.exists()
+        && getsha256(&path)?.trim() == expected
+    {
+        return Ok(path);
+    }
+
+    println!("Downloading {} from {}...", asset.name, source.repo);
+    let content = net::blocking_get(&asset.browser_download_url)?
+        .error_for_status()?
+        .bytes()?;
+    let hash = sha256::digest(content.as_ref());
+    if expected.is_some_and(|expected| expected != hash) {
+        return Err(format!("{} does not match the hash published by GitHub", asset.name).into());
+    }
+    scan_download(&asset.name, &content)?;
+    fs::write(&path, content)?;
+    audit(
+        "download",
+        &format!("{} sha256={} from gh:{}", asset.name, hash, source.repo),
+    );
+    Ok(path)
+}
+
+/// The shallowest folder with a `level.dat`
+fn find_world(dir: &Path, depth: usize) -> Option<PathBuf> {
+    if dir.join("level.dat").is_file() {
+        return Some(dir.to_path_buf());
+    }
+    if depth == 0 {
+        return None;
+    }
+    let mut children: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    children.sort();
+    // Check every level before going deeper, so a backup folder inside the world is ignored
+    children
+        .iter()
+        .find(|child| child.join("level.dat").is_file())
+        .cloned()
+        .or_else(|| {
+            children
+                .iter()
+                .find_map(|child| find_world(child, depth - 1))
+        })
+}
+
+/// Download the world of a GitHub repository: a zip asset of its release, else the files of
+/// the repository. Returns the zip, or the world folder extracted to the staging area.
+pub fn fetch_github_map(source: &GithubSource) -> Result<PathBuf, Box<dyn Error>> {
+    match get_repo_release(&source.repo, source.tag.as_deref()) {
+        Ok(release) => {
+            let zips: Vec<&ReleaseAsset> = release
+                .assets
+                .iter()
+                .filter(|a| a.name.to_lowercase().ends_with(".zip"))
+                .collect();
+            if let Some(asset) = pick_asset(zips) {
+                return download_asset(source, asset);
+            }
+            println!(
+                "The release {} has no zip, looking for the world in the repository files.",
+                release.tag_name
+            );
+        }
+        Err(e) => println!("{}, looking for the world in the repository files.", e),
+    }
+
+    println!("Downloading {}...", source.repo);
+    let archive = download_repo_archive(&source.repo, source.tag.as_deref())?;
+    scan_download(&format!("{}.zip", source.name()), &archive)?;
+    let staged = stage_dir(&format!("gh-{}", source.name()))?;
+    let archive_path = staged.join("archive.zip");
+    fs::write(&archive_path, &archive)?;
+    audit(
+        "download",
+        &format!(
+            "repository archive sha256={} from gh:{}",
+            sha256::digest(archive.as_slice()),
+            source.repo
+        ),
+    );
+
+    // The world folder is named after the repository when it is at its root
+    let extracted = staged.join(source.name());
+    extract_zip(&archive_path, &extracted)?;
+    let _ = fs::remove_file(&archive_path);
+
+    find_world(&extracted, MAX_WORLD_DEPTH)
+        .ok_or_else(|| format!("No world (level.dat) found in {}", source.repo).into())
+}
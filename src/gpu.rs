@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::dir::get_app_support_dir;
+
+/// A graphics setup known to break Minecraft, and what to do about it
+#[derive(Debug)]
+pub struct GpuIssue {
+    pub problem: String,
+    pub guidance: &'static str,
+}
+
+/// What the graphics checks found, detecting it runs slow commands (PowerShell,
+/// `system_profiler`, `glxinfo`) so it is kept until the next boot or driver update
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GpuInfo {
+    /// Graphics adapters as reported by the OS, with their driver version when known
+    pub gpus: Vec<(String, Option<String>)>,
+    pub has_libgl: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedGpuInfo {
+    /// See [`system_key`]
+    key: String,
+    info: GpuInfo,
+}
+
+fn get_cache_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("gpu.json")
+}
+
+/// Modification time of a file in seconds, as a string
+fn modified(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs()
+            .to_string(),
+    )
+}
+
+/// Changes when the detected graphics may have changed: the driver store on Windows, the
+/// system version on macOS (drivers come with it) and the boot on Linux. `None` when it
+/// cannot be read, nothing is cached then.
+fn system_key() -> Option<String> {
+    if cfg!(target_os = "windows") {
+        let windows = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+        modified(&Path::new(&windows).join(r"System32\DriverStore\FileRepository"))
+    } else if cfg!(target_os = "macos") {
+        modified(Path::new(
+            "/System/Library/CoreServices/SystemVersion.plist",
+        ))
+    } else {
+        fs::read_to_string("/proc/sys/kernel/random/boot_id")
+            .ok()
+            .map(|id| id.trim().to_string())
+    }
+}
+
+/// The graphics of this computer, from the cache unless `fresh` or the system changed
+pub fn gpu_info(fresh: bool) -> GpuInfo {
+    let key = system_key();
+    let path = get_cache_path();
+    if !fresh
+        && let Some(key) = &key
+        && let Ok(content) = fs::read_to_string(&path)
+        && let Ok(cached) = serde_json::from_str::<CachedGpuInfo>(&content)
+        && &cached.key == key
+    {
+        return cached.info;
+    }
+
+    let info = GpuInfo {
+        gpus: detect_gpus(),
+        has_libgl: !cfg!(target_os = "linux") || has_libgl(),
+    };
+    if let Some(key) = key {
+        let cached = CachedGpuInfo { key, info };
+        let _ = fs::create_dir_all(path.parent().unwrap());
+        if let Ok(content) = serde_json::to_string(&cached) {
+            let _ = fs::write(&path, content);
+        }
+        return cached.info;
+    }
+    info
+}
+
+/// Graphics adapters as reported by the OS, with their driver version when known
+fn detect_gpus() -> Vec<(String, Option<String>)> {
+    if cfg!(target_os = "windows") {
+        let output = run(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_VideoController | ForEach-Object { $_.Name + '|' + $_.DriverVersion }",
+            ],
+        );
+        output
+            .lines()
+            .filter_map(|line| {
+                let (name, driver) = line.trim().split_once('|')?;
+                Some((name.to_string(), Some(driver.to_string())))
+            })
+            .collect()
+    } else if cfg!(target_os = "macos") {
+        run("system_profiler", &["SPDisplaysDataType"])
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("Chipset Model:"))
+            .map(|name| (name.trim().to_string(), None))
+            .collect()
+    } else {
+        let glxinfo = run("glxinfo", &["-B"]);
+        let renderer = find_value(&glxinfo, "OpenGL renderer string:");
+        let version = find_value(&glxinfo, "OpenGL version string:");
+        match renderer {
+            Some(renderer) => vec![(renderer, version)],
+            None => run("lspci", &[])
+                .lines()
+                .filter(|line| line.contains("VGA") || line.contains("3D controller"))
+                .filter_map(|line| line.split_once(": "))
+                .map(|(_, name)| (name.to_string(), None))
+                .collect(),
+        }
+    }
+}
+
+/// Look for graphics setups that are known to crash or run terribly
+pub fn check_gpu(info: &GpuInfo) -> Vec<GpuIssue> {
+    let mut issues = Vec::new();
+
+    for (name, driver) in &info.gpus {
+        let lower = name.to_lowercase();
+
+        if lower.contains("microsoft basic display") {
+            issues.push(GpuIssue {
+                problem: format!("{} is in use, no graphics driver is installed", name),
+                guidance: "Install the driver from your GPU or computer manufacturer.",
+            });
+        }
+
+        if cfg!(target_os = "windows")
+            && lower.contains("intel")
+            && driver
+                .as_deref()
+                .and_then(|d| d.split('.').next())
+                .and_then(|major| major.parse::<u32>().ok())
+                .is_some_and(|major| major < 20)
+        {
+            issues.push(GpuIssue {
+                problem: format!(
+                    "{} uses an old driver ({})",
+                    name,
+                    driver.as_deref().unwrap_or_default()
+                ),
+                guidance: "Old Intel drivers often crash Minecraft, update it from intel.com/support.",
+            });
+        }
+
+        if lower.contains("llvmpipe") || lower.contains("softpipe") {
+            issues.push(GpuIssue {
+                problem: "OpenGL falls back to software rendering".to_string(),
+                guidance: "Install the Mesa drivers for your GPU (or the proprietary NVIDIA driver).",
+            });
+        }
+
+        if cfg!(target_os = "linux")
+            && let Some(gl) = driver
+                .as_deref()
+                .and_then(|v| v.split_whitespace().next())
+                .and_then(parse_gl_version)
+            && gl < (3, 2)
+        {
+            issues.push(GpuIssue {
+                problem: format!("{} only supports OpenGL {}.{}", name, gl.0, gl.1),
+                guidance: "Minecraft 1.17 and newer need OpenGL 3.2, try an older version or update Mesa.",
+            });
+        }
+    }
+
+    if !info.has_libgl {
+        issues.push(GpuIssue {
+            problem: "libGL was not found".to_string(),
+            guidance: "Install Mesa (libgl1-mesa-dri on Debian/Ubuntu, mesa on Fedora/Arch).",
+        });
+    }
+
+    issues
+}
+
+fn has_libgl() -> bool {
+    let output = run("ldconfig", &["-p"]);
+    output.is_empty() || output.contains("libGL.so.1")
+}
+
+fn parse_gl_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn find_value(output: &str, key: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(key))
+        .map(|value| value.trim().to_string())
+}
+
+/// Output of a command, empty when it is not available
+fn run(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default()
+}
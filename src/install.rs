@@ -0,0 +1,214 @@
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::dir::get_app_support_dir;
+use crate::filesys::copy_dir_all;
+use crate::timings::exit;
+
+/// Created by `ovl cancel` to stop an install running in another terminal
+fn get_cancel_file() -> PathBuf {
+    get_app_support_dir().unwrap().join(".cancel-install")
+}
+
+/// Ask the install running in another terminal to stop
+pub fn request_cancel() -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(get_cancel_file(), "")?;
+    Ok(())
+}
+
+async fn wait_for_cancel_request() {
+    let cancel_file = get_cancel_file();
+    loop {
+        if cancel_file.exists() {
+            let _ = fs::remove_file(&cancel_file);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Run an install until it is done, or until Ctrl+C or `ovl cancel`. Returns `None` when cancelled.
+pub async fn run_cancellable<F: Future>(task: F) -> Option<F::Output> {
+    // A request left over from an earlier install must not cancel this one
+    let _ = fs::remove_file(get_cancel_file());
+    println!("Press Ctrl+C or run `ovl cancel` to cancel the install.");
+
+    let output = tokio::select! {
+        output = task => Some(output),
+        _ = tokio::signal::ctrl_c() => None,
+        _ = wait_for_cancel_request() => None,
+    };
+
+    // Listening for Ctrl+C replaced the default handler, exit like it would have
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            exit(130);
+        }
+    });
+
+    output
+}
+
+fn sha1_of(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(
+        Sha1::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    )
+}
+
+/// Files under `dir` written since `since`
+fn files_modified_since(dir: &Path, since: SystemTime, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            files_modified_since(&path, since, files);
+        } else if fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= since)
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Expected sha1 of the libraries and client jar of a version, by path relative to the game folder
+fn expected_hashes(game_dir: &Path, version: &str) -> HashMap<PathBuf, String> {
+    let mut hashes = HashMap::new();
+    let version_dir = game_dir.join("versions").join(version);
+    let Some(json) = fs::read_to_string(version_dir.join(format!("{}.json", version)))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    else {
+        return hashes;
+    };
+
+    if let Some(sha1) = json["downloads"]["client"]["sha1"].as_str() {
+        hashes.insert(
+            version_dir.join(format!("{}.jar", version)),
+            sha1.to_string(),
+        );
+    }
+
+    for library in json["libraries"].as_array().into_iter().flatten() {
+        let downloads = &library["downloads"];
+        let classifiers = downloads["classifiers"]
+            .as_object()
+            .into_iter()
+            .flat_map(|c| c.values());
+        for download in std::iter::once(&downloads["artifact"]).chain(classifiers) {
+            if let (Some(path), Some(sha1)) = (download["path"].as_str(), download["sha1"].as_str())
+            {
+                hashes.insert(game_dir.join("libraries").join(path), sha1.to_string());
+            }
+        }
+    }
+
+    hashes
+}
+
+/// After a cancelled install, delete the files written since it started that this install
+/// has a hash for and that do not match it, so the next launch downloads them again instead
+/// of using them. Returns how many files were removed.
+pub fn remove_partial_downloads(game_dir: &Path, version: &str, since: SystemTime) -> usize {
+    let hashes = expected_hashes(game_dir, version);
+    let mut files = Vec::new();
+    for dir in ["versions", "libraries", "assets"] {
+        files_modified_since(&game_dir.join(dir), since, &mut files);
+    }
+
+    let objects_dir = game_dir.join("assets").join("objects");
+    let mut removed = 0;
+    for file in files {
+        // Asset objects are named after their hash
+        let expected = if file.starts_with(&objects_dir) {
+            file.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        } else {
+            hashes.get(&file).cloned()
+        };
+
+        // Files this install has no hash for may belong to another launcher or version
+        let Some(expected) = expected else {
+            continue;
+        };
+        let complete = sha1_of(&file).is_some_and(|sha1| sha1 == expected);
+
+        if !complete && fs::remove_file(&file).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Staging folders older than this belong to installs that were killed
+const STALE_STAGING: Duration = Duration::from_secs(60 * 60);
+
+fn get_staging_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join(".staging")
+}
+
+/// An empty folder to extract or copy a world into before it is moved into `saves/`, so a
+/// failed install never leaves a half-written world behind
+pub fn stage_dir(name: &str) -> std::io::Result<PathBuf> {
+    let staging_dir = get_staging_dir();
+    if let Ok(entries) = fs::read_dir(&staging_dir) {
+        for path in entries.flatten().map(|e| e.path()) {
+            let stale = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_STAGING);
+            if stale {
+                let _ = fs::remove_dir_all(&path);
+            }
+        }
+    }
+
+    let staged = staging_dir.join(format!("{}-{}", name, std::process::id()));
+    if staged.exists() {
+        fs::remove_dir_all(&staged)?;
+    }
+    fs::create_dir_all(&staged)?;
+    Ok(staged)
+}
+
+/// Move a finished staged folder to `target`, replacing what was there. A rename is atomic;
+/// when the game folder is on another drive the copy goes to a hidden sibling first.
+pub fn commit_staged(staged: &Path, target: &Path) -> std::io::Result<()> {
+    let name = target.file_name().unwrap().to_string_lossy().to_string();
+    let parent = target.parent().unwrap();
+
+    // Left over by an install that was killed while moving
+    let incoming = parent.join(format!(".ovl-incoming-{}", name));
+    let _ = fs::remove_dir_all(&incoming);
+    if fs::rename(staged, &incoming).is_err() {
+        copy_dir_all(staged, &incoming)?;
+        let _ = fs::remove_dir_all(staged);
+    }
+
+    let replaced = parent.join(format!(".ovl-replaced-{}", name));
+    if target.exists() {
+        let _ = fs::remove_dir_all(&replaced);
+        fs::rename(target, &replaced)?;
+    }
+    fs::rename(&incoming, target)?;
+    let _ = fs::remove_dir_all(&replaced);
+    Ok(())
+}
+
+/// Delete a staged folder after a failed or declined install
+pub fn discard_staged(staged: &Path) {
+    let _ = fs::remove_dir_all(staged);
+}
@@ -0,0 +1,171 @@
+use chrono::Local;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    dir::{get_app_support_dir, get_game_dir},
+    releases::cached_map_release,
+    theme::Themed,
+};
+
+/// Where a map zip came from and whether it matched its published hash
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MapOrigin {
+    pub id: String,
+    /// URL the zip was downloaded from, `None` for zips cached before origins were recorded
+    pub source: Option<String>,
+    /// Tag of the release the expected hash came from, `None` when it came from the CDN
+    pub release: Option<String>,
+    pub sha256: String,
+    /// Whether the zip matched the published hash the last time it was checked
+    pub verified: bool,
+    /// When the zip was last checked against the published hash (RFC 3339)
+    pub verified_at: String,
+}
+
+/// A folder of `saves`, with the origin of its map when the launcher installed it
+#[derive(Debug, Serialize)]
+pub struct InstalledMap {
+    pub folder: String,
+    pub origin: Option<MapOrigin>,
+}
+
+fn get_origins_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("games")
+        .join("origins.json")
+}
+
+fn get_installed_path() -> PathBuf {
+    get_game_dir().join(".ovl-maps.json")
+}
+
+fn read_origins(path: &Path) -> BTreeMap<String, MapOrigin> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_origins(path: &Path, origins: &BTreeMap<String, MapOrigin>) {
+    let written = serde_json::to_string_pretty(origins)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Warning: Failed to record the origin of the map: {}", e);
+    }
+}
+
+/// Record where the cached zip of a map was just downloaded from, after its hash was checked
+pub fn record_map_download(id: &str, source: &str, sha256: &str) {
+    let path = get_origins_path();
+    let mut origins = read_origins(&path);
+    origins.insert(
+        id.to_string(),
+        MapOrigin {
+            id: id.to_string(),
+            source: Some(source.to_string()),
+            release: cached_map_release(),
+            sha256: sha256.trim().to_string(),
+            verified: true,
+            verified_at: Local::now().to_rfc3339(),
+        },
+    );
+    write_origins(&path, &origins);
+}
+
+/// Record the result of checking the cached zip of a map against the published hash
+pub fn record_map_check(id: &str, sha256: &str, verified: bool) {
+    let path = get_origins_path();
+    let mut origins = read_origins(&path);
+    let origin = origins.entry(id.to_string()).or_insert_with(|| MapOrigin {
+        id: id.to_string(),
+        source: None,
+        release: None,
+        sha256: String::new(),
+        verified,
+        verified_at: String::new(),
+    });
+    origin.release = cached_map_release().or(origin.release.take());
+    origin.sha256 = sha256.trim().to_string();
+    origin.verified = verified;
+    origin.verified_at = Local::now().to_rfc3339();
+    write_origins(&path, &origins);
+}
+
+/// Remember which map a save folder was installed from, and print its origin
+pub fn record_map_install(folder: &str, id: &str) {
+    let Some(origin) = read_origins(&get_origins_path()).remove(id) else {
+        return;
+    };
+    print_origin(&origin);
+
+    let path = get_installed_path();
+    let mut installed = read_origins(&path);
+    installed.insert(folder.to_string(), origin);
+    write_origins(&path, &installed);
+}
+
+fn print_origin(origin: &MapOrigin) {
+    println!(
+        "  {} {}",
+        "Source:".muted(),
+        origin.source.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "  {} {}",
+        "Release:".muted(),
+        origin
+            .release
+            .as_deref()
+            .unwrap_or("unknown (hash from the CDN)")
+    );
+    println!("  {} {}", "SHA256:".muted(), origin.sha256);
+    let status = match origin.verified {
+        true => format!("verified {}", origin.verified_at).success(),
+        false => "not verified".error(),
+    };
+    println!("  {} {}", "Integrity:".muted(), status);
+}
+
+/// Every world in `saves`, with the origin of those installed from the catalog
+pub fn list_installed() -> Vec<InstalledMap> {
+    let mut origins = read_origins(&get_installed_path());
+    let mut folders: Vec<String> = fs::read_dir(get_game_dir().join("saves"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    folders.sort();
+
+    folders
+        .into_iter()
+        .map(|folder| InstalledMap {
+            origin: origins.remove(&folder),
+            folder,
+        })
+        .collect()
+}
+
+pub fn print_installed(maps: &[InstalledMap]) {
+    if maps.is_empty() {
+        println!("No worlds installed.");
+        return;
+    }
+    for map in maps {
+        match &map.origin {
+            Some(origin) => {
+                println!("{} ({})", map.folder.bold(), origin.id);
+                print_origin(origin);
+            }
+            None => println!("{} {}", map.folder.bold(), "(not installed by ovl)".muted()),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::fs;
 use std::process::Command;
 
 use crate::dir::get_app_support_dir;
+use crate::timings::exit;
 
 // TODO: Add java handling for every MC version
 pub fn get_java_path(_version: &String) -> String {
@@ -31,7 +32,7 @@ pub fn get_java_path(_version: &String) -> String {
                     Ok(path) => path,
                     Err(e) => {
                         eprintln!("Error finding Java executable: {}", e);
-                        std::process::exit(1);
+                        exit(1);
                     }
                 }
             } else {
@@ -39,12 +40,12 @@ pub fn get_java_path(_version: &String) -> String {
                     "Error: Java version {} is outdated. Java > 21 is required.",
                     version
                 );
-                std::process::exit(1);
+                exit(1);
             }
         }
         Err(e) => {
             eprintln!("Error checking Java version: {}", e);
-            std::process::exit(1);
+            exit(1);
         }
     };
 
@@ -71,7 +72,7 @@ fn test_java_path(java_path: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn check_java_version() -> Result<u32, String> {
+pub fn check_java_version() -> Result<u32, String> {
     let output = Command::new("java")
         .arg("-version")
         .output()
@@ -0,0 +1,402 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::{
+    cmd::ask_yes_no_attended,
+    config::{load_config, use_config_for_run},
+    map::fetch_maps,
+    mc::{LaunchOptions, launch},
+    net,
+    releases::get_pack_release,
+    theme::Themed,
+    versions::resolve_version,
+};
+
+const INVITE_PREFIX: &str = "ovl://join/";
+const DEFAULT_PORT: u16 = 25565;
+/// Servers run by ovl describe their setup over HTTP on the port right after the game port
+const INFO_PORT_OFFSET: u16 = 1;
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+const INFO_TIMEOUT: Duration = Duration::from_secs(3);
+/// Status responses are a few KiB, a bigger length is a broken or hostile server
+const MAX_STATUS_LENGTH: i32 = 32 * 1024;
+
+/// What a guest needs to match the host, served by `ovl server run` and `ovl coop`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HostInfo {
+    pub minecraft: String,
+    /// Catalog map being hosted
+    pub map: Option<String>,
+    /// Extra mods the host plays with
+    #[serde(default)]
+    pub mods: Vec<String>,
+    /// Release tag of the OpenVoxel resource pack
+    pub resource_pack: Option<String>,
+}
+
+/// Where to join, from an `ovl://join/<address>?map=<id>` invite or a plain address
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invite {
+    pub host: String,
+    pub port: u16,
+    pub map: Option<String>,
+}
+
+impl Invite {
+    pub fn address(&self) -> String {
+        match self.host.contains(':') {
+            true => format!("[{}]:{}", self.host, self.port),
+            false => format!("{}:{}", self.host, self.port),
+        }
+    }
+}
+
+pub fn parse_invite(input: &str) -> Result<Invite, String> {
+    let input = input.trim();
+    let (address, query) = match input.strip_prefix(INVITE_PREFIX) {
+        Some(rest) => rest.split_once('?').unwrap_or((rest, "")),
+        None => (input, ""),
+    };
+    let address = address.trim_end_matches('/');
+
+    let (host, port) = match address.strip_prefix('[') {
+        // IPv6 addresses are written `[::1]:25565`
+        Some(rest) => {
+            let (host, port) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("Invalid address: {}", address))?;
+            (host, port.strip_prefix(':'))
+        }
+        None => match address.rsplit_once(':') {
+            Some((host, port)) if !host.contains(':') => (host, Some(port)),
+            _ => (address, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port
+            .parse()
+            .map_err(|_| format!("Invalid port in {}", address))?,
+        None => DEFAULT_PORT,
+    };
+    if host.is_empty()
+        || !host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':'))
+    {
+        return Err(format!("Invalid address: {}", address));
+    }
+
+    let map = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("map="))
+        .filter(|id| {
+            !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_string);
+
+    Ok(Invite {
+        host: host.to_string(),
+        port,
+        map,
+    })
+}
+
+/// Answer every request on the port after `port` with the host info, for as long as the
+/// process runs. Guests on older versions of ovl simply never ask.
+pub fn serve_host_info(port: u16, info: &HostInfo) {
+    let Some(info_port) = port.checked_add(INFO_PORT_OFFSET) else {
+        return;
+    };
+    let listener = match TcpListener::bind(("0.0.0.0", info_port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "Warning: Guests will not get the server setup, port {} is not available: {}",
+                info_port, e
+            );
+            return;
+        }
+    };
+    let body = serde_json::to_string(info).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let _ = stream.set_read_timeout(Some(INFO_TIMEOUT));
+            // The request itself does not matter
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+}
+
+fn write_varint(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(stream: &mut impl Read) -> std::io::Result<i32> {
+    let mut value = 0;
+    for i in 0..5 {
+        let mut byte = [0];
+        stream.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7F) as i32) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "VarInt is too long",
+    ))
+}
+
+fn write_packet(stream: &mut TcpStream, packet: &[u8]) -> std::io::Result<()> {
+    let mut framed = Vec::new();
+    write_varint(&mut framed, packet.len() as i32);
+    framed.extend_from_slice(packet);
+    stream.write_all(&framed)
+}
+
+/// Status of a server with the Server List Ping, as shown in the multiplayer menu
+pub fn ping_server(host: &str, port: u16) -> Result<Value, Box<dyn Error>> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Could not resolve {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&address, PING_TIMEOUT)?;
+    stream.set_read_timeout(Some(PING_TIMEOUT))?;
+
+    let mut handshake = vec![0x00];
+    // Any protocol version is fine for a status request
+    write_varint(&mut handshake, -1);
+    write_varint(&mut handshake, host.len() as i32);
+    handshake.extend_from_slice(host.as_bytes());
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    write_packet(&mut stream, &handshake)?;
+    write_packet(&mut stream, &[0x00])?;
+
+    let _length = read_varint(&mut stream)?;
+    let _packet_id = read_varint(&mut stream)?;
+    let json_length = read_varint(&mut stream)?;
+    if !(0..=MAX_STATUS_LENGTH).contains(&json_length) {
+        return Err(format!("The server sent a status of {} bytes", json_length).into());
+    }
+    let mut json = vec![0; json_length as usize];
+    stream.read_exact(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+fn fetch_host_info(invite: &Invite) -> Option<HostInfo> {
+    let port = invite.port.checked_add(INFO_PORT_OFFSET)?;
+    let url = match invite.host.contains(':') {
+        true => format!("http://[{}]:{}/", invite.host, port),
+        false => format!("http://{}:{}/", invite.host, port),
+    };
+    net::blocking_client()
+        .get(url)
+        .timeout(INFO_TIMEOUT)
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .ok()
+}
+
+/// Minecraft version announced in a ping, modded servers put their name first
+/// (e.g. "Paper 1.21.5")
+async fn ping_version(status: &Value) -> Option<String> {
+    let name = status["version"]["name"].as_str()?;
+    for candidate in name.split_whitespace().rev() {
+        if let Ok(version) = resolve_version(candidate).await {
+            return Some(version.id);
+        }
+    }
+    None
+}
+
+/// Offer to play with what the host plays with that this setup is missing, for this session
+/// only so that joining a server does not change the settings
+fn match_host_setup(info: &HostInfo) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config();
+    let mut changed = false;
+
+    let missing: Vec<String> = info
+        .mods
+        .iter()
+        .filter(|m| !config.extra_mods.contains(m))
+        .cloned()
+        .collect();
+    if !missing.is_empty()
+        && ask_yes_no_attended(&format!(
+            "The host plays with extra mods you do not have: {}. Install them?",
+            missing.join(", ")
+        ))
+    {
+        config.extra_mods.extend(missing);
+        changed = true;
+    }
+
+    if let Some(tag) = &info.resource_pack {
+        let ours = get_pack_release().map(|r| r.tag_name).ok();
+        if ours.as_ref() != Some(tag)
+            && ask_yes_no_attended(&format!(
+                "The host uses the resource pack {}. Use it too?",
+                tag
+            ))
+        {
+            config.pack_release = tag.clone();
+            changed = true;
+        }
+    }
+
+    if changed {
+        use_config_for_run(config);
+    }
+    Ok(())
+}
+
+/// Join a server with the setup it expects: the Minecraft version from its ping or from the
+/// info of an ovl host, with its extra mods and resource pack
+pub async fn join(invite: &Invite, options: &LaunchOptions) -> Result<(), Box<dyn Error>> {
+    let address = invite.address();
+
+    println!("Checking {}...", address);
+    let status = ping_server(&invite.host, invite.port)
+        .map_err(|e| format!("{} is not reachable: {}", address, e))?;
+    if let Some(online) = status["players"]["online"].as_u64() {
+        println!(
+            "{} players online out of {}.",
+            online,
+            status["players"]["max"].as_u64().unwrap_or(0)
+        );
+    }
+
+    let info = fetch_host_info(invite);
+    let map_id = invite
+        .map
+        .clone()
+        .or_else(|| info.as_ref().and_then(|i| i.map.clone()));
+    let map = map_id.and_then(|id| fetch_maps().ok()?.into_iter().find(|m| m.id == id));
+    if let Some(map) = &map {
+        println!("The host is playing {}.", map.name.bold());
+    }
+
+    let version = match (&info, &map) {
+        // The host could send anything, only a version Mojang knows is launched
+        (Some(info), _) => Some(
+            resolve_version(&info.minecraft)
+                .await
+                .map_err(|_| {
+                    format!(
+                        "The host plays an unknown Minecraft version: {:?}",
+                        info.minecraft
+                    )
+                })?
+                .id,
+        ),
+        (None, Some(map)) => Some(map.version.clone()),
+        (None, None) => ping_version(&status).await,
+    }
+    .ok_or(
+        "Could not tell the Minecraft version of the server, use `ovl run <version> <address>`",
+    )?;
+
+    match &info {
+        Some(info) => match_host_setup(info)?,
+        None => println!(
+            "{}",
+            "The server is not hosted with ovl, only the Minecraft version is matched.".muted()
+        ),
+    }
+
+    println!("Launching Minecraft {}...\n", version);
+    launch(version, None, Some(&address), options).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite(host: &str, port: u16, map: Option<&str>) -> Invite {
+        Invite {
+            host: host.to_string(),
+            port,
+            map: map.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_plain_addresses() {
+        assert_eq!(
+            parse_invite("play.example.com"),
+            Ok(invite("play.example.com", DEFAULT_PORT, None))
+        );
+        assert_eq!(
+            parse_invite(" 192.168.1.20:25570 "),
+            Ok(invite("192.168.1.20", 25570, None))
+        );
+    }
+
+    #[test]
+    fn parses_ipv6_addresses() {
+        let parsed = parse_invite("[::1]:25566").unwrap();
+        assert_eq!(parsed, invite("::1", 25566, None));
+        assert_eq!(parsed.address(), "[::1]:25566");
+        assert_eq!(
+            parse_invite("fe80::1"),
+            Ok(invite("fe80::1", DEFAULT_PORT, None))
+        );
+    }
+
+    #[test]
+    fn parses_invites() {
+        assert_eq!(
+            parse_invite("ovl://join/10.0.0.5:25565/?map=escape-room"),
+            Ok(invite("10.0.0.5", 25565, Some("escape-room")))
+        );
+        // Map ids that are not plain are dropped, the server is still joined
+        assert_eq!(
+            parse_invite("ovl://join/10.0.0.5?map=../../x"),
+            Ok(invite("10.0.0.5", DEFAULT_PORT, None))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for input in [
+            "",
+            "ovl://join/",
+            "host:port",
+            "host:70000",
+            "[::1",
+            "a b",
+            "x;rm",
+        ] {
+            assert!(parse_invite(input).is_err(), "{:?}", input);
+        }
+    }
+}
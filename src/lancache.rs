@@ -0,0 +1,215 @@
+use colored::Colorize;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use sha2::{Digest, Sha256, Sha512};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::load_config,
+    dir::get_app_support_dir,
+    mods::get_mods_dir,
+    net,
+    objects::{add_object, get_objects_dir},
+    server::get_local_ip,
+    theme::Themed,
+};
+
+const SERVICE_TYPE: &str = "_ovl-cache._tcp.local.";
+pub const DEFAULT_PORT: u16 = 8765;
+/// How long to listen for a cache on the network before downloading from the internet
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Requests answered at the same time, the others wait for a free worker
+const MAX_WORKERS: usize = 8;
+
+/// The cache found on the network for this run, if any
+static LAN_CACHE: OnceLock<Option<String>> = OnceLock::new();
+
+/// SHA256 (maps) or SHA512 (mods) hex digest of `data`, picked by the length of `hash`
+fn matches_hash(data: &[u8], hash: &str) -> bool {
+    match hash.len() {
+        64 => sha256::digest(data) == hash,
+        128 => format!("{:x}", Sha512::digest(data)) == hash,
+        _ => false,
+    }
+}
+
+fn is_object_name(name: &str) -> bool {
+    matches!(name.len(), 64 | 128) && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Hex digest of a file, read a block at a time as map zips can be several GB
+fn hash_file<D: Digest + io::Write>(path: &Path) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Put the cached map zips and the managed mods in `.cache/objects`, named by their hash
+fn index_objects() -> std::io::Result<usize> {
+    fs::create_dir_all(get_objects_dir())?;
+    let mut added = 0;
+
+    let games_dir = get_app_support_dir().unwrap().join(".cache").join("games");
+    for path in fs::read_dir(games_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+    {
+        if path.extension().is_some_and(|ext| ext == "zip") {
+            let hash = hash_file::<Sha256>(&path)?;
+            added += add_object(&path, &hash)? as usize;
+        }
+    }
+
+    for path in fs::read_dir(get_mods_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+    {
+        if path.extension().is_some_and(|ext| ext == "jar") {
+            let hash = hash_file::<Sha512>(&path)?;
+            added += add_object(&path, &hash)? as usize;
+        }
+    }
+    Ok(added)
+}
+
+/// Serve `.cache/objects` on the LAN and announce it with mDNS until the process is stopped
+pub fn serve_cache(port: u16) -> Result<(), Box<dyn Error>> {
+    println!("Indexing the cached maps and mods...");
+    let added = index_objects()?;
+    let count = fs::read_dir(get_objects_dir())?.count();
+    println!("{} objects available ({} new).", count, added);
+
+    let local_ip = get_local_ip().ok_or("Could not find the local IP address")?;
+    let server = tiny_http::Server::http(("0.0.0.0", port)).map_err(|e| e.to_string())?;
+
+    let mdns = ServiceDaemon::new()?;
+    let host = format!("ovl-cache-{}.local.", std::process::id());
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &format!("ovl cache {}", local_ip),
+        &host,
+        local_ip,
+        port,
+        &[("version", env!("CARGO_PKG_VERSION"))][..],
+    )?;
+    mdns.register(service)?;
+
+    println!(
+        "{}",
+        format!("Serving the cache at http://{}:{}", local_ip, port).bold()
+    );
+    println!("Other computers on the network use it automatically. Press Ctrl+C to stop.");
+
+    let server = Arc::new(server);
+    let workers: Vec<_> = (0..MAX_WORKERS)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    answer_request(request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    Ok(())
+}
+
+/// Send the object a request asks for
+fn answer_request(request: tiny_http::Request) {
+    let url = request.url().to_string();
+    let object = url
+        .strip_prefix("/objects/")
+        .filter(|name| is_object_name(name))
+        .map(|name| get_objects_dir().join(name.to_lowercase()));
+
+    let result = match object.and_then(|path| File::open(path).ok()) {
+        Some(file) => {
+            println!("Sending {}", url.muted());
+            request.respond(tiny_http::Response::from_file(file))
+        }
+        None => {
+            request.respond(tiny_http::Response::from_string("Not found").with_status_code(404))
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to answer a request: {}", e);
+    }
+}
+
+/// Look for a cache announced on the network, once per run
+fn discover_cache() -> Option<String> {
+    let daemon = ServiceDaemon::new().ok()?;
+    let receiver = daemon.browse(SERVICE_TYPE).ok()?;
+    let deadline = Instant::now() + DISCOVERY_TIMEOUT;
+
+    let mut found = None;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                if let Some(ip) = info.get_addresses_v4().into_iter().next() {
+                    found = Some(format!("http://{}:{}", ip, info.get_port()));
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.shutdown();
+    found
+}
+
+pub fn lan_cache_url() -> Option<&'static str> {
+    LAN_CACHE
+        .get_or_init(|| {
+            let config = load_config();
+            // A set address is used as is, the network is only browsed when asked to
+            let url = match config.lan_cache_url {
+                Some(url) => url,
+                None if config.lan_cache => discover_cache()?,
+                None => return None,
+            };
+            eprintln!("Using the LAN cache at {}", url);
+            Some(url.trim_end_matches('/').to_string())
+        })
+        .as_deref()
+}
+
+/// A file from the LAN cache by its SHA256 or SHA512, checked before it is returned
+pub fn lan_fetch(hash: &str) -> Option<Vec<u8>> {
+    let hash = hash.trim().to_lowercase();
+    if !is_object_name(&hash) {
+        return None;
+    }
+    let url = format!("{}/objects/{}", lan_cache_url()?, hash);
+    let data = net::blocking_get(&url)
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .bytes()
+        .ok()?;
+
+    if !matches_hash(&data, &hash) {
+        eprintln!(
+            "{} The LAN cache sent a corrupted file, downloading it from the internet.",
+            "Warning:".warning()
+        );
+        return None;
+    }
+    Some(data.to_vec())
+}
@@ -0,0 +1,393 @@
+use open_launcher::auth::Auth;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::compat::legacy_assets_dir;
+use crate::forge::installed_forge_id;
+use crate::mc::Loader;
+use crate::natives::rule_os_matches;
+use crate::net;
+
+/// Shown instead of the values that identify or log in the player
+const REDACTED: &str = "<redacted>";
+
+/// What the command line is built for, beyond the installed version
+pub struct LaunchSettings<'a> {
+    pub java_path: &'a str,
+    /// The mod loader and its version, vanilla when missing
+    pub loader: Option<(Loader, &'a str)>,
+    pub player_name: &'a str,
+    pub resolution: (u32, u32),
+    /// Passed to Java before the arguments of the version, e.g. `-Xmx4096M`
+    pub java_args: &'a [String],
+    /// `("singleplayer", world)` or `("multiplayer", address)`
+    pub quick_play: Option<(&'a str, &'a str)>,
+    /// The login details to launch with, they are redacted when missing
+    pub auth: Option<&'a Auth>,
+}
+
+/// `group:artifact:version[:classifier]` to its path in a Maven repository
+fn maven_path(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split(':').collect();
+    let (group, artifact, version) = (parts.first()?, parts.get(1)?, parts.get(2)?);
+    let classifier = parts.get(3).map(|c| format!("-{}", c)).unwrap_or_default();
+    Some(format!(
+        "{}/{}/{}/{}-{}{}.jar",
+        group.replace('.', "/"),
+        artifact,
+        version,
+        artifact,
+        version,
+        classifier
+    ))
+}
+
+/// Evaluate the `rules` of a library or argument for this OS and the enabled features
+fn rules_allow(rules: Option<&Value>, features: &HashMap<&str, bool>) -> bool {
+    let Some(rules) = rules.and_then(|r| r.as_array()) else {
+        return true;
+    };
+
+    let mut allowed = false;
+    for rule in rules {
+        let os_matches = rule_os_matches(rule)
+            && rule["os"]["arch"]
+                .as_str()
+                .is_none_or(|arch| arch == "x86" && std::env::consts::ARCH == "x86");
+        let features_match = rule["features"].as_object().is_none_or(|wanted| {
+            wanted.iter().all(|(feature, value)| {
+                value.as_bool() == Some(*features.get(feature.as_str()).unwrap_or(&false))
+            })
+        });
+        if os_matches && features_match {
+            allowed = rule["action"].as_str() == Some("allow");
+        }
+    }
+    allowed
+}
+
+/// The `arguments.game` or `arguments.jvm` list of a version JSON, rules applied
+fn collect_arguments(list: &Value, features: &HashMap<&str, bool>) -> Vec<String> {
+    let mut args = Vec::new();
+    for entry in list.as_array().into_iter().flatten() {
+        match entry {
+            Value::String(arg) => args.push(arg.clone()),
+            _ if rules_allow(entry.get("rules"), features) => match &entry["value"] {
+                Value::String(arg) => args.push(arg.clone()),
+                Value::Array(values) => {
+                    args.extend(values.iter().filter_map(|v| v.as_str().map(String::from)))
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    args
+}
+
+/// Replace the `${name}` placeholders of an argument
+fn substitute(arg: &str, values: &HashMap<&str, String>) -> String {
+    let mut result = arg.to_string();
+    for (name, value) in values {
+        result = result.replace(&format!("${{{}}}", name), value);
+    }
+    result
+}
+
+/// The version JSON of a loader, listing what it adds to the vanilla version
+async fn loader_profile(
+    game_dir: &Path,
+    version: &str,
+    (loader, loader_version): (Loader, &str),
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let url = match loader {
+        Loader::Fabric => format!(
+            "https://meta.fabricmc.net/v2/versions/loader/{}/{}/profile/json",
+            version, loader_version
+        ),
+        Loader::Quilt => format!(
+            "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
+            version, loader_version
+        ),
+        // Only the installer can make it, it is read from the versions folder
+        Loader::Forge => {
+            let id = installed_forge_id(game_dir, version, loader_version)
+                .ok_or(format!("Forge {} is not installed", loader_version))?;
+            let path = game_dir
+                .join("versions")
+                .join(&id)
+                .join(format!("{}.json", id));
+            return Ok(serde_json::from_str(&fs::read_to_string(path)?)?);
+        }
+    };
+    Ok(net::get(&url).await?.error_for_status()?.json().await?)
+}
+
+/// Rebuild the java command line of an installed version, with the login details redacted
+/// unless they are given
+pub async fn build_launch_command(
+    game_dir: &Path,
+    version: &str,
+    settings: &LaunchSettings<'_>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let version_dir = game_dir.join("versions").join(version);
+    let json: Value = serde_json::from_str(&fs::read_to_string(
+        version_dir.join(format!("{}.json", version)),
+    )?)?;
+    let libraries_dir = game_dir.join("libraries");
+
+    let features: HashMap<&str, bool> = HashMap::from([
+        ("has_custom_resolution", true),
+        (
+            "is_quick_play_singleplayer",
+            matches!(settings.quick_play, Some(("singleplayer", _))),
+        ),
+        (
+            "is_quick_play_multiplayer",
+            matches!(settings.quick_play, Some(("multiplayer", _))),
+        ),
+    ]);
+
+    let mut classpath = Vec::new();
+    let mut main_class = json["mainClass"].as_str().unwrap_or_default().to_string();
+    let mut jvm_args = Vec::new();
+    let mut game_args = Vec::new();
+
+    // The loader's libraries come first so they win over the vanilla ones
+    let mut minecraft_arguments = json["minecraftArguments"].as_str();
+    let profile = match settings.loader {
+        Some(loader) => loader_profile(game_dir, version, loader).await?,
+        None => Value::Null,
+    };
+    if !profile.is_null() {
+        for library in profile["libraries"].as_array().into_iter().flatten() {
+            let path = library["downloads"]["artifact"]["path"]
+                .as_str()
+                .map(String::from)
+                .or_else(|| library["name"].as_str().and_then(maven_path));
+            if let Some(path) = path {
+                classpath.push(libraries_dir.join(path));
+            }
+        }
+        if let Some(class) = profile["mainClass"].as_str() {
+            main_class = class.to_string();
+        }
+        jvm_args.extend(collect_arguments(&profile["arguments"]["jvm"], &features));
+        game_args.extend(collect_arguments(&profile["arguments"]["game"], &features));
+        // Forge before 1.13 replaces the whole string of game arguments
+        if let Some(arguments) = profile["minecraftArguments"].as_str() {
+            minecraft_arguments = Some(arguments);
+        }
+    }
+
+    for library in json["libraries"].as_array().into_iter().flatten() {
+        // Legacy natives are extracted, not put on the classpath
+        if !rules_allow(library.get("rules"), &features) || library.get("natives").is_some() {
+            continue;
+        }
+        let path = library["downloads"]["artifact"]["path"]
+            .as_str()
+            .map(String::from)
+            .or_else(|| library["name"].as_str().and_then(maven_path));
+        if let Some(path) = path {
+            classpath.push(libraries_dir.join(path));
+        }
+    }
+    classpath.push(version_dir.join(format!("{}.jar", version)));
+
+    match json.get("arguments") {
+        Some(arguments) => {
+            jvm_args.splice(0..0, collect_arguments(&arguments["jvm"], &features));
+            game_args.splice(0..0, collect_arguments(&arguments["game"], &features));
+        }
+        // Versions before 1.13 only have a string of game arguments
+        None => {
+            jvm_args.splice(
+                0..0,
+                [
+                    "-Djava.library.path=${natives_directory}".to_string(),
+                    "-cp".to_string(),
+                    "${classpath}".to_string(),
+                ],
+            );
+            game_args.splice(
+                0..0,
+                minecraft_arguments
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .map(String::from),
+            );
+            game_args.extend(
+                [
+                    "--width",
+                    "${resolution_width}",
+                    "--height",
+                    "${resolution_height}",
+                ]
+                .map(String::from),
+            );
+        }
+    }
+
+    let separator = if cfg!(windows) { ";" } else { ":" };
+    let (quick_singleplayer, quick_multiplayer) = match settings.quick_play {
+        Some(("singleplayer", world)) => (world.to_string(), String::new()),
+        Some((_, server)) => (String::new(), server.to_string()),
+        None => (String::new(), String::new()),
+    };
+    let game_dir_string = game_dir.display().to_string();
+    let assets_dir = game_dir.join("assets").display().to_string();
+    let asset_index = json["assetIndex"]["id"].as_str().unwrap_or(version);
+    // Old versions read their assets from the copies `prepare_legacy_assets` made
+    let game_assets = legacy_assets_dir(game_dir, asset_index)
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|| assets_dir.clone());
+    let (uuid, access_token, xuid, user_type, user_properties) = match settings.auth {
+        Some(auth) => (
+            auth.uuid.clone(),
+            auth.access_token.clone(),
+            "0".to_string(),
+            auth.auth_type.clone(),
+            auth.user_properties.clone(),
+        ),
+        None => (
+            REDACTED.to_string(),
+            REDACTED.to_string(),
+            REDACTED.to_string(),
+            "msa".to_string(),
+            "{}".to_string(),
+        ),
+    };
+    let values: HashMap<&str, String> = HashMap::from([
+        ("auth_player_name", settings.player_name.to_string()),
+        ("auth_uuid", uuid),
+        ("auth_session", access_token.clone()),
+        ("auth_access_token", access_token),
+        ("auth_xuid", xuid.clone()),
+        ("clientid", xuid),
+        ("user_type", user_type),
+        ("user_properties", user_properties),
+        ("version_name", version.to_string()),
+        (
+            "version_type",
+            json["type"].as_str().unwrap_or("release").to_string(),
+        ),
+        ("game_directory", game_dir_string),
+        ("assets_root", assets_dir.clone()),
+        ("game_assets", game_assets),
+        ("assets_index_name", asset_index.to_string()),
+        (
+            "natives_directory",
+            version_dir.join("natives").display().to_string(),
+        ),
+        ("library_directory", libraries_dir.display().to_string()),
+        ("classpath_separator", separator.to_string()),
+        (
+            "classpath",
+            classpath
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(separator),
+        ),
+        ("launcher_name", "ovl".to_string()),
+        ("launcher_version", env!("CARGO_PKG_VERSION").to_string()),
+        ("resolution_width", settings.resolution.0.to_string()),
+        ("resolution_height", settings.resolution.1.to_string()),
+        ("quickPlaySingleplayer", quick_singleplayer),
+        ("quickPlayMultiplayer", quick_multiplayer),
+        ("quickPlayPath", "quickPlay/log.json".to_string()),
+    ]);
+
+    let mut command = vec![settings.java_path.to_string()];
+    command.extend(settings.java_args.iter().cloned());
+    command.extend(jvm_args.iter().map(|arg| substitute(arg, &values)));
+    command.push(main_class);
+    command.extend(game_args.iter().map(|arg| substitute(arg, &values)));
+    Ok(command)
+}
+
+/// An argument in double quotes the way Windows programs split their command line: the
+/// backslashes before a quote are doubled
+fn quote_windows(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                continue;
+            }
+            '"' => quoted.push_str(&"\\".repeat(backslashes * 2 + 1)),
+            _ => quoted.push_str(&"\\".repeat(backslashes)),
+        }
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Quote the arguments that need it, so the line can be pasted in a shell (a POSIX shell,
+/// or the command prompt on Windows)
+pub fn format_command(command: &[String]) -> String {
+    let safe = match cfg!(windows) {
+        true => "-_./:=,@%+\\",
+        false => "-_./:=,@%+",
+    };
+    command
+        .iter()
+        .map(|arg| {
+            if !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || safe.contains(c))
+            {
+                arg.clone()
+            } else if cfg!(windows) {
+                quote_windows(arg)
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::natives::current_os;
+
+    #[test]
+    fn quotes_windows_arguments() {
+        assert_eq!(
+            quote_windows("C:\\Program Files\\Java"),
+            "\"C:\\Program Files\\Java\""
+        );
+        assert_eq!(quote_windows("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_windows("dir\\"), "\"dir\\\\\"");
+        assert_eq!(quote_windows("a\\\"b"), "\"a\\\\\\\"b\"");
+    }
+
+    #[test]
+    fn applies_os_rules() {
+        let features = HashMap::new();
+        let other_os = match current_os() {
+            "windows" => "osx",
+            _ => "windows",
+        };
+        let rules = serde_json::json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": other_os } }
+        ]);
+        assert!(rules_allow(Some(&rules), &features));
+        let rules = serde_json::json!([
+            { "action": "allow", "os": { "name": current_os(), "version": "^no such version$" } }
+        ]);
+        assert!(!rules_allow(Some(&rules), &features));
+    }
+}
@@ -0,0 +1,197 @@
+use reqwest::Url;
+use reqwest::blocking::Response;
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{audit::audit, dir::get_app_support_dir, net, scan::scan_download};
+
+/// Where a link given to `ovl open` is hosted, share links do not point to the file itself
+#[derive(Debug)]
+enum LinkHost {
+    /// With the id of the file
+    GoogleDrive(String),
+    Dropbox,
+    Direct,
+}
+
+impl LinkHost {
+    fn of(url: &Url) -> Result<Self, String> {
+        let host = url.host_str().unwrap_or_default();
+        match host {
+            "drive.google.com" | "docs.google.com" | "drive.usercontent.google.com" => {
+                if url.path().contains("/folders/") {
+                    return Err(
+                        "Google Drive folders cannot be downloaded, share the zip of the world instead"
+                            .to_string(),
+                    );
+                }
+                drive_file_id(url)
+                    .map(LinkHost::GoogleDrive)
+                    .ok_or_else(|| format!("No Google Drive file in {}", url))
+            }
+            "dropbox.com" | "www.dropbox.com" => Ok(LinkHost::Dropbox),
+            _ => Ok(LinkHost::Direct),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            LinkHost::GoogleDrive(_) => "Google Drive",
+            LinkHost::Dropbox => "Dropbox",
+            LinkHost::Direct => "the link",
+        }
+    }
+
+    /// The URL that sends the file rather than its preview page
+    fn download_url(&self, url: &Url) -> Url {
+        match self {
+            LinkHost::GoogleDrive(id) => Url::parse_with_params(
+                "https://drive.usercontent.google.com/download",
+                [("id", id.as_str()), ("export", "download")],
+            )
+            .unwrap(),
+            // `dl=0` opens the preview, `dl=1` downloads, a folder as a zip
+            LinkHost::Dropbox => {
+                let pairs: Vec<(String, String)> = url
+                    .query_pairs()
+                    .filter(|(key, _)| key != "dl" && key != "raw")
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect();
+                let mut download = url.clone();
+                download
+                    .query_pairs_mut()
+                    .clear()
+                    .extend_pairs(pairs)
+                    .append_pair("dl", "1");
+                download
+            }
+            LinkHost::Direct => url.clone(),
+        }
+    }
+}
+
+/// `/file/d/<id>/view` or `?id=<id>`
+fn drive_file_id(url: &Url) -> Option<String> {
+    let segments = url.path_segments()?;
+    let from_path = segments
+        .skip_while(|segment| *segment != "d")
+        .nth(1)
+        .map(String::from);
+    from_path
+        .or_else(|| {
+            url.query_pairs()
+                .find(|(key, _)| key == "id")
+                .map(|(_, id)| id.into_owned())
+        })
+        .filter(|id| !id.is_empty())
+}
+
+/// The `value` of an attribute in an HTML tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let length = tag[start..].find('"')?;
+    Some(&tag[start..start + length])
+}
+
+/// Files too big for the virus scan of Google Drive get a warning page first, its form
+/// carries the confirmation token that lets the download through
+fn drive_confirm_url(page: &str) -> Option<Url> {
+    let form_start = page.find("<form")?;
+    let form = &page[form_start..form_start + page[form_start..].find("</form>")?];
+    let action = attribute(form, "action")?.replace("&amp;", "&");
+
+    let inputs = form.split("<input").skip(1).filter_map(|input| {
+        let input = &input[..input.find('>')?];
+        Some((attribute(input, "name")?, attribute(input, "value")?))
+    });
+    let url = Url::parse_with_params(&action, inputs).ok()?;
+    url.query_pairs()
+        .any(|(key, _)| key == "confirm")
+        .then_some(url)
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"))
+}
+
+/// The name the server gives the file, else the last part of the link
+fn file_name(response: &Response, url: &Url) -> String {
+    let disposition = response
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let from_header = disposition.split(';').find_map(|part| {
+        let part = part.trim();
+        part.strip_prefix("filename*=UTF-8''")
+            .or_else(|| part.strip_prefix("filename="))
+            .map(|name| name.trim_matches('"').to_string())
+    });
+    let name = from_header
+        .or_else(|| {
+            url.path_segments()?
+                .next_back()
+                .filter(|segment| !segment.is_empty())
+                .map(String::from)
+        })
+        .unwrap_or_default();
+    // Only the name, whatever the server sends
+    match name.rsplit(['/', '\\']).next().unwrap_or_default() {
+        "" | "." | ".." => "map.zip".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Download the zip a link points to, following the share links of Google Drive and
+/// Dropbox. Returns the downloaded zip.
+pub fn fetch_linked_map(url: &Url) -> Result<PathBuf, Box<dyn Error>> {
+    let host = LinkHost::of(url)?;
+    let mut response = net::blocking_get(host.download_url(url).as_str())?.error_for_status()?;
+
+    if let LinkHost::GoogleDrive(_) = host
+        && is_html(&response)
+    {
+        let page = response.text()?;
+        let confirm = drive_confirm_url(&page).ok_or(
+            "Google Drive did not send the file, check that it is shared with anyone who has the link",
+        )?;
+        println!("Confirming the download of a large file...");
+        response = net::blocking_get(confirm.as_str())?.error_for_status()?;
+    }
+    if is_html(&response) {
+        return Err(format!(
+            "{} sent a web page instead of the file, check that it is shared publicly",
+            host.name()
+        )
+        .into());
+    }
+
+    let name = file_name(&response, url);
+    println!("Downloading {} from {}...", name, host.name());
+    let content = response.bytes()?;
+    if !content.starts_with(b"PK") {
+        return Err(format!("{} is not a zip file", name).into());
+    }
+    scan_download(&name, &content)?;
+
+    let cache_dir = get_app_support_dir().unwrap().join(".cache").join("links");
+    fs::create_dir_all(&cache_dir)?;
+    let path = cache_dir.join(&name).with_extension("zip");
+    fs::write(&path, &content)?;
+    audit(
+        "download",
+        &format!(
+            "{} sha256={} from {}",
+            name,
+            sha256::digest(content.as_ref()),
+            url
+        ),
+    );
+    Ok(path)
+}
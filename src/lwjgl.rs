@@ -0,0 +1,74 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Where pinned LWJGL versions are downloaded from, Mojang only hosts the ones it ships
+const MAVEN_CENTRAL: &str = "https://repo1.maven.org/maven2/";
+
+/// Point every LWJGL 3 library of an installed version to another LWJGL version.
+/// Returns how many libraries were changed.
+pub fn override_lwjgl(
+    game_dir: &Path,
+    version: &str,
+    lwjgl_version: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let path = game_dir
+        .join("versions")
+        .join(version)
+        .join(format!("{}.json", version));
+    let mut json: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    let Some(libraries) = json["libraries"].as_array_mut() else {
+        return Ok(0);
+    };
+
+    let mut changed = 0;
+    for library in libraries.iter_mut() {
+        let Some(name) = library["name"].as_str() else {
+            continue;
+        };
+        let parts: Vec<&str> = name.split(':').collect();
+        if parts.len() < 3 || parts[0] != "org.lwjgl" || parts[2] == lwjgl_version {
+            continue;
+        }
+
+        let old_version = parts[2].to_string();
+        let mut new_name = parts.clone();
+        new_name[2] = lwjgl_version;
+        library["name"] = Value::String(new_name.join(":"));
+
+        let downloads = &mut library["downloads"];
+        rewrite_download(&mut downloads["artifact"], &old_version, lwjgl_version);
+        if let Some(classifiers) = downloads["classifiers"].as_object_mut() {
+            for download in classifiers.values_mut() {
+                rewrite_download(download, &old_version, lwjgl_version);
+            }
+        }
+        changed += 1;
+    }
+
+    if changed > 0 {
+        fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(changed)
+}
+
+fn rewrite_download(download: &mut Value, old_version: &str, new_version: &str) {
+    let Some(download) = download.as_object_mut() else {
+        return;
+    };
+    let Some(path) = download.get("path").and_then(|p| p.as_str()) else {
+        return;
+    };
+
+    let path = path
+        .replace(&format!("/{}/", old_version), &format!("/{}/", new_version))
+        .replace(&format!("-{}", old_version), &format!("-{}", new_version));
+    download.insert(
+        "url".to_string(),
+        Value::String(format!("{}{}", MAVEN_CENTRAL, path)),
+    );
+    download.insert("path".to_string(), Value::String(path));
+    // The checksums belong to the version Mojang ships
+    download.remove("sha1");
+    download.remove("size");
+}
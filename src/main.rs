@@ -1,21 +1,158 @@
-use clap::{Parser, Subcommand};
-use filesys::{copy_dir_all, ensure_folder_exists};
+use clap::{CommandFactory, Parser, Subcommand};
+use filesys::{copy_dir_all, ensure_folder_exists, get_used_version_save};
 use reqwest::Url;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+mod audit;
 mod auth;
+mod avatar;
+mod backup;
+mod install;
+mod installed;
 mod java;
-use auth::{Accounts, add_account, fetch_file, switch_account};
+mod join;
+mod lancache;
+mod launchcmd;
+mod links;
+use audit::{audit, print_audit, read_audit};
+use auth::{
+    Accounts, add_account, fetch_file, get_selected_account_name, switch_account, use_account,
+    write_accounts,
+};
+use avatar::avatar_lines;
+use install::{commit_staged, discard_staged, request_cancel, stage_dir};
+use installed::{list_installed, print_installed};
+use join::{join, parse_invite};
+use lancache::serve_cache;
+use links::fetch_linked_map;
 mod cmd;
+mod compat;
+mod config;
+mod console;
+mod coop;
 mod dir;
+mod doctor;
+mod estimate;
+mod event;
 mod filesys;
+mod forge;
+mod freeze;
+mod github;
+mod gpu;
+mod lwjgl;
+mod manifest;
 mod map;
 mod mods;
-use cmd::{ask_input, ask_yes_no, select_from_multiple_maps};
-use dir::get_app_support_dir;
-use map::{Map, fetch_maps, install_map_from_path, select_map};
+mod news;
+mod objects;
+use news::{fetch_announcements, print_announcements, print_news, recent_maps};
+use objects::dedupe_profiles;
+mod natives;
+use cmd::{
+    ask_input, ask_yes_no, ask_yes_no_attended, is_json_output, is_non_interactive, print_json,
+    print_output, select_extra_mods, select_from_multiple_maps, use_json_output,
+    use_non_interactive,
+};
+use config::{
+    edit_config, is_locked_mode, load_config, parse_resolution, print_config, print_config_value,
+    save_config, set_config_value, use_locked_mode,
+};
+use console::attach_console;
+use coop::coop;
+use dir::{
+    get_app_support_dir, get_game_dir, get_profiles_dir, list_profiles, parse_profile_name,
+    use_game_dir, use_profile,
+};
+use doctor::run_doctor;
+use event::{join_event, read_profile_manifest};
+use freeze::{freeze, thaw};
+use github::{fetch_github_map, parse_github_source};
+use manifest::{clean, print_status, repair};
+use map::{
+    Map, find_maps, install_map_from_path, print_map_info, resolve_collision, select_map,
+    select_random_map,
+};
 mod mc;
-use mc::{get_version_name, launch, run_map};
+mod mirrors;
+mod mismatch;
+mod modconfig;
+mod modrinth;
+mod net;
+use net::{IpVersion, use_ip_version};
+mod packs;
+mod pin;
+use pin::{check_pin, set_pin};
+mod schema;
+mod session;
+mod shell;
+use mc::{LaunchOptions, get_version_name, launch, run_map};
+use modrinth::forget_modrinth_cache;
+use mods::{EXTRA_MODS, install_extras, install_mods, read_lock, update_extras_lock, update_lock};
+use shell::Shell;
+mod profile;
+mod purge;
+use purge::purge;
+mod runtime;
+mod scan;
+use profile::print_lookup;
+use rate::rate_map;
+use replays::{list_replays, open_replay, print_replays};
+use rules::{RulePreset, parse_game_rule, parse_rule_preset, set_game_rules};
+mod rate;
+mod rcon;
+mod releases;
+mod replays;
+mod rules;
+mod searchindex;
+use searchindex::{ask_map_query, indexed_maps};
+mod server;
+use server::{
+    ServerRunOptions, deop, get_server_dir, op, print_whitelist, run_server, whitelist_add,
+    whitelist_remove,
+};
+mod shortcut;
+mod skin;
+mod snapshot;
+mod spectate;
+mod stats;
+mod steam;
+mod structure;
+mod submit;
+mod survey;
+mod theme;
+mod timings;
+use shortcut::create_shortcut;
+use skin::set_offline_skin;
+use snapshot::{print_snapshots, read_snapshots, rollback, take_snapshot};
+use spectate::spectate;
+use stats::{collect_world_stats, print_world_stats};
+use steam::add_steam_shortcut;
+use structure::import_structure;
+use submit::submit_score;
+use survey::set_survey;
+use theme::use_prompt_theme;
+use timings::{TimingsReport, exit, record, use_timings};
+mod thumbnail;
+mod torrent;
+mod transfer;
+mod tunnel;
+mod uri;
+mod vanilla;
+mod verifymap;
+mod versions;
+mod whatsnew;
+mod world;
+use transfer::{export_accounts, import_accounts};
+use uri::{handle_uri, register_uri_handler, unregister_uri_handler};
+use vanilla::export_vanilla_profile;
+use verifymap::verify_map;
+use versions::resolve_version;
+use whatsnew::{print_whats_new, record_version};
+use world::{
+    optimize_world, print_world_info, quick_play_folder, read_world_info, resolve_world,
+    verify_world,
+};
 mod zipper;
 
 #[derive(Parser)]
@@ -25,24 +162,141 @@ mod zipper;
     long_about = "The OpenVoxel Launcher directly in your terminal!"
 )]
 struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "Use this account instead of the selected one for this command"
+    )]
+    account: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Only allow searching and playing maps, for shared machines"
+    )]
+    locked: bool,
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "ipv6",
+        help = "Only connect over IPv4, for networks with a broken IPv6 setup"
+    )]
+    ipv4: bool,
+    #[arg(long, global = true, help = "Only connect over IPv6")]
+    ipv6: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print where the time of the command went when it ends"
+    )]
+    timings: bool,
+    #[arg(
+        long,
+        short = 'y',
+        global = true,
+        visible_alias = "yes",
+        help = "Never ask anything, take the default answers or fail when there is none, for scripts and CI"
+    )]
+    non_interactive: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the results as JSON, for scripts and GUIs (implies --non-interactive)"
+    )]
+    json: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    #[command(about = "Type several commands in a row at a prompt with history and completion")]
+    Shell,
+
     #[command(about = "Play an OpenVoxel Map by searching for it by name")]
-    Play { game: Vec<String> },
+    Play {
+        game: Vec<String>,
+        #[arg(
+            long,
+            conflicts_with = "game",
+            help = "Play a map you never downloaded, picked at random"
+        )]
+        random: bool,
+        #[arg(
+            long,
+            requires = "random",
+            help = "Only pick among the maps with this tag"
+        )]
+        tag: Option<String>,
+        #[arg(
+            long,
+            requires = "random",
+            help = "Pick among the maps already played too"
+        )]
+        any: bool,
+        #[arg(
+            long,
+            help = "Only look in this catalog, `openvoxel` or a name from `catalog_sources`"
+        )]
+        source: Option<String>,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
     #[command(about = "Run a specific Minecraft version (e.g. \"1.21.5\") with an optional IP")]
-    Run { version: String, ip: Option<String> },
+    Run {
+        #[arg(
+            required_unless_present = "snapshot",
+            help = "Minecraft version, snapshot ids (e.g. 24w14a) and `latest` work too"
+        )]
+        version: Option<String>,
+        ip: Option<String>,
+        #[arg(long, conflicts_with = "version", help = "Launch the latest snapshot")]
+        snapshot: bool,
+        #[arg(
+            long,
+            help = "Launch vanilla with default options in a temporary game folder, to rule out the managed setup"
+        )]
+        safe: bool,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
     #[command(
-        about = "Open an existing map from the saves or a map from a local path (zip file or folder) or from a URL"
+        about = "Open an existing map from the saves or a map from a local path (zip file or folder), a URL or a GitHub repository (gh:owner/repo)"
     )]
     #[command(alias = "import")]
-    Open { path: String },
+    Open {
+        path: String,
+        #[arg(
+            long,
+            value_parser = parse_profile_name,
+            help = "Install the world in the saves of this profile and play it there"
+        )]
+        profile: Option<String>,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
     #[command(about = "Select and play a map from the list of available maps")]
     #[command(alias = "list")]
-    Search {},
+    Search {
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
+
+    #[command(about = "Join a server as a spectator, to cast an event")]
+    Spectate {
+        host: String,
+        #[arg(
+            long,
+            help = "Minecraft version of the server (defaults to the one of the hosted server)"
+        )]
+        version: Option<String>,
+        #[arg(
+            long,
+            help = "Folder of the hosted server (defaults to the managed server)"
+        )]
+        dir: Option<PathBuf>,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
 
     #[command(about = "Logs in to your Minecraft account and saves it for later use")]
     Login {},
@@ -50,14 +304,509 @@ enum Commands {
     Logout {},
     #[command(about = "List all configured Minecraft accounts")]
     #[command(alias = "account")]
-    Accounts {},
+    Accounts {
+        #[command(subcommand)]
+        command: Option<AccountsCommands>,
+    },
     #[command(about = "Tells you on what Minecraft account you are currently logged in")]
     #[command(alias = "who-am-i")]
     Whoami {},
+
+    #[command(about = "Manage the locally hosted server")]
+    Server {
+        #[arg(
+            long,
+            global = true,
+            help = "Server folder (defaults to the managed server)"
+        )]
+        dir: Option<PathBuf>,
+        #[command(subcommand)]
+        command: ServerCommands,
+    },
+
+    #[command(about = "Host a map for friends and share an invite to join it")]
+    Coop {
+        map: String,
+        #[command(flatten)]
+        options: ServerRunOptions,
+    },
+
+    #[command(
+        about = "Join a server with the Minecraft version, mods and resource pack it expects"
+    )]
+    Join {
+        #[arg(help = "Invite from `ovl coop` (ovl://join/...) or a server address")]
+        invite: String,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
+
+    #[command(about = "Look up a Minecraft player by username or UUID")]
+    Lookup { query: String },
+
+    #[command(about = "Submit your score on a competitive map to the OpenVoxel leaderboard")]
+    Submit { map: String },
+
+    #[command(about = "Rate a map from 1 to 5 and send your feedback to OpenVoxel")]
+    Rate {
+        map: String,
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=5))]
+        rating: u8,
+        #[arg(long, short, help = "A few words about the map")]
+        comment: Option<String>,
+    },
+
+    #[command(about = "Answer the survey of a map in testing after each session of it")]
+    Survey {
+        map: String,
+        #[arg(long, help = "Stop the survey after the sessions of this map")]
+        off: bool,
+    },
+
+    #[command(
+        about = "Show what changed since your last version and which maps, mods and packs have updates"
+    )]
+    Whatsnew,
+
+    #[command(about = "Show the launcher-managed files that were modified, deleted or added")]
+    Status,
+
+    #[command(about = "List the installed worlds with where their map was downloaded from")]
+    Installed,
+
+    #[command(about = "Download the modified and deleted launcher-managed files again")]
+    Repair,
+
+    #[command(about = "Remove the files the launcher did not install from the mods folder")]
+    Clean,
+
+    #[command(
+        about = "Save the Minecraft and Fabric versions, mods, resource pack and options to a lock file"
+    )]
+    Freeze {
+        #[arg(long, default_value = "env.lock")]
+        out: PathBuf,
+    },
+
+    #[command(about = "Rebuild and launch the setup saved by `ovl freeze` in its own profile")]
+    Thaw {
+        file: PathBuf,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
+
+    #[command(about = "Restore the mods, options and resource pack from before an update")]
+    Rollback {
+        #[arg(help = "Snapshot to restore, the latest known-good one by default")]
+        id: Option<String>,
+        #[arg(long, help = "List the snapshots instead")]
+        list: bool,
+    },
+
+    #[command(about = "Show what the launcher did: logins, downloads and deletions")]
+    Audit {
+        #[arg(long, short, default_value_t = 50, help = "Number of entries to show")]
+        lines: usize,
+        #[arg(
+            long,
+            help = "Only show this action (login, logout, download, delete, rollback)"
+        )]
+        action: Option<String>,
+    },
+
+    #[command(about = "Cancel the game install running in another terminal")]
+    Cancel,
+
+    #[command(about = "Check the environment for common problems")]
+    Doctor {
+        #[arg(
+            long,
+            help = "Also check the connection to every service the launcher uses"
+        )]
+        network: bool,
+        #[arg(long, help = "Also look for known graphics driver problems")]
+        gpu: bool,
+    },
+
+    #[command(about = "Manage the automatically installed mods")]
+    Mods {
+        #[command(subcommand)]
+        command: ModsCommands,
+    },
+
+    #[command(about = "Show the details and thumbnail of an OpenVoxel map")]
+    Info {
+        map: Vec<String>,
+        #[arg(
+            long,
+            help = "Only look in this catalog, `openvoxel` or a name from `catalog_sources`"
+        )]
+        source: Option<String>,
+    },
+
+    #[command(about = "List the maps added to the catalog recently")]
+    News {
+        #[arg(long, default_value_t = 30, help = "How many days back to look")]
+        days: i64,
+        #[arg(long, help = "Show the studio's announcements instead")]
+        announcements: bool,
+    },
+
+    #[command(
+        about = "Check that a map zip or catalog map installs cleanly, exits nonzero on any problem"
+    )]
+    VerifyMap {
+        #[arg(help = "Path to a map zip, or the id of a catalog map")]
+        map: String,
+        #[arg(long, help = "Minecraft version the map is made for, for local zips")]
+        version: Option<String>,
+        #[arg(long, help = "Only run the checks, for CI")]
+        no_launch: bool,
+    },
+
+    #[command(about = "Create a desktop shortcut that plays a map in one double-click")]
+    Shortcut { map: String },
+
+    #[command(about = "Open ovl:// links from the OpenVoxel website with this launcher")]
+    RegisterUri {
+        #[arg(long, help = "Stop handling ovl:// links")]
+        remove: bool,
+    },
+    #[command(about = "Run an ovl:// link (e.g. ovl://play/<map-id>)")]
+    HandleUri { uri: String },
+
+    #[command(about = "Add games to the Steam library")]
+    Steam {
+        #[command(subcommand)]
+        command: SteamCommands,
+    },
+
+    #[command(about = "Manage the sessions recorded with the Replay Mod")]
+    #[command(alias = "replay")]
+    Replays {
+        #[command(subcommand)]
+        command: ReplaysCommands,
+    },
+
+    #[command(about = "Inspect and maintain worlds, by saves folder name or path")]
+    World {
+        #[command(subcommand)]
+        command: WorldCommands,
+    },
+
+    #[command(about = "Take part in OpenVoxel events and tournaments")]
+    Event {
+        #[command(subcommand)]
+        command: EventCommands,
+    },
+
+    #[command(
+        about = "Add a profile to the official launcher that plays a version or map with this setup"
+    )]
+    ExportVanillaProfile {
+        #[arg(help = "Minecraft version (e.g. 1.21.5, latest) or map")]
+        target: Vec<String>,
+        #[arg(
+            long,
+            allow_hyphen_values = true,
+            help = "JVM arguments of the profile (e.g. \"-Xmx4G\")"
+        )]
+        jvm_args: Option<String>,
+    },
+
+    #[command(about = "Share the downloads of this computer with the others on the network")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    #[command(about = "Manage the profiles (the event and thawed game folders)")]
+    Profiles {
+        #[command(subcommand)]
+        command: ProfilesCommands,
+    },
+
+    #[command(about = "Show or change the settings of config.toml")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    #[command(
+        about = "Remove the caches, or with --all everything the launcher created, for a clean uninstall"
+    )]
+    Purge {
+        #[arg(
+            long,
+            help = "Also remove accounts, settings, worlds, keyring entries, the ovl:// handler and shortcuts"
+        )]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfilesCommands {
+    #[command(about = "Share the identical mods of every profile on disk to reclaim space")]
+    Dedupe,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    #[command(about = "Show every setting and its value")]
+    List,
+    #[command(about = "Print the value of a setting (e.g. resolution, accounts.Steve.language)")]
+    Get { key: String },
+    #[command(
+        about = "Change a setting, the value is TOML (e.g. 4096, true, [\"a\", \"b\"]) or a plain string"
+    )]
+    Set { key: String, value: String },
+    #[command(about = "Go back to the default of a setting")]
+    Unset { key: String },
+    #[command(about = "Open config.toml in $VISUAL or $EDITOR, checking it afterwards")]
+    Edit,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    #[command(
+        about = "Serve the cached maps and mods on the LAN, other computers find it automatically"
+    )]
+    Serve {
+        #[arg(long, default_value_t = lancache::DEFAULT_PORT)]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand)]
+enum AccountsCommands {
+    #[command(about = "Export every account and its token to a password-encrypted file")]
+    Export {
+        #[arg(long, default_value = "accounts.ovlenc")]
+        out: PathBuf,
+    },
+    #[command(about = "Import the accounts from a file created by `ovl accounts export`")]
+    Import { file: PathBuf },
+    #[command(
+        about = "Show or change the resolution, language and favorite maps of the selected account"
+    )]
+    Settings {
+        #[arg(long, value_parser = parse_resolution, help = "Game window size (e.g. 1920x1080)")]
+        resolution: Option<(u32, u32)>,
+        #[arg(long, help = "Game language (e.g. en_us, fr_fr)")]
+        language: Option<String>,
+        #[arg(long, value_name = "MAP", help = "Add a map to the favorites")]
+        favorite: Option<String>,
+        #[arg(long, value_name = "MAP", help = "Remove a map from the favorites")]
+        unfavorite: Option<String>,
+        #[arg(long, help = "Go back to the defaults")]
+        reset: bool,
+    },
+    #[command(about = "Set a local PNG as the skin of the selected offline account")]
+    Skin {
+        #[arg(required_unless_present = "remove")]
+        png: Option<PathBuf>,
+        #[arg(long, help = "Go back to the default skin")]
+        remove: bool,
+    },
+    #[command(
+        about = "Set a parental PIN asked before playing with an online account or changing accounts"
+    )]
+    Pin {
+        #[arg(long, help = "Remove the PIN")]
+        remove: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServerCommands {
+    #[command(about = "Attach to the console of the running server")]
+    Console,
+    #[command(about = "Install (if needed) and run the server")]
+    Run {
+        #[arg(help = "Minecraft version, defaults to the installed one")]
+        version: Option<String>,
+        #[command(flatten)]
+        options: ServerRunOptions,
+    },
+    #[command(about = "Manage the players allowed to join")]
+    Whitelist {
+        #[command(subcommand)]
+        command: WhitelistCommands,
+    },
+    #[command(about = "Make a player operator")]
+    Op {
+        name: String,
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(1..=4))]
+        level: u8,
+    },
+    #[command(about = "Remove operator rights from a player")]
+    Deop { name: String },
+}
+
+#[derive(Subcommand)]
+enum WhitelistCommands {
+    #[command(about = "Allow a player to join")]
+    Add { name: String },
+    #[command(about = "Stop allowing a player to join")]
+    Remove { name: String },
+    #[command(about = "List the allowed players")]
+    List {},
+}
+
+#[derive(Subcommand)]
+enum ModsCommands {
+    #[command(
+        about = "Re-resolve the locked mod versions for a Minecraft version (or every locked version)"
+    )]
+    Update { version: Option<String> },
+    #[command(about = "Choose optional quality-of-life mods to install alongside the bundled ones")]
+    Extras {},
+}
+
+#[derive(Subcommand)]
+enum EventCommands {
+    #[command(
+        about = "Install the exact setup of an event in its own profile, verify it and launch it"
+    )]
+    Join {
+        code: String,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum SteamCommands {
+    #[command(
+        about = "Add an OpenVoxel map or a Minecraft version to Steam as a non-Steam game (for Big Picture and the Steam Deck)"
+    )]
+    Add {
+        #[arg(help = "Map id or name, or a Minecraft version")]
+        target: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReplaysCommands {
+    #[command(about = "List the recorded replays, newest first")]
+    List {},
+    #[command(
+        about = "Launch the game a replay was recorded with to watch it, or open the replays folder"
+    )]
+    Open {
+        name: Option<String>,
+        #[command(flatten)]
+        options: LaunchOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorldCommands {
+    #[command(about = "Check level.dat and every chunk of a world for corruption")]
+    Verify { world: String },
+    #[command(
+        about = "Show the seed, spawn point, game rules, datapacks and world border of a world"
+    )]
+    Info { world: String },
+    #[command(
+        about = "Report play time, deaths, distance and advancements of the players of a world"
+    )]
+    Stats {
+        world: String,
+        #[arg(long, help = "Only this player (name or UUID)")]
+        player: Option<String>,
+    },
+    #[command(
+        about = "Shrink the region files of a world by dropping unused data and compressing harder (close the game first)"
+    )]
+    Optimize {
+        world: String,
+        #[arg(long, default_value_t = 9, value_parser = clap::value_parser!(u32).range(1..=9), help = "zlib compression level")]
+        level: u32,
+    },
+    #[command(
+        about = "Change the game rules of a world with a preset or one by one (close the game first)"
+    )]
+    Rules {
+        world: String,
+        #[arg(
+            long,
+            value_parser = parse_rule_preset,
+            required_unless_present = "set",
+            help = "creative-testing, hardcore or peaceful"
+        )]
+        preset: Option<&'static RulePreset>,
+        #[arg(
+            long,
+            value_name = "RULE=VALUE",
+            value_parser = parse_game_rule,
+            help = "Set a game rule (e.g. keepInventory=true), can be repeated"
+        )]
+        set: Vec<(String, String)>,
+    },
+    #[command(
+        about = "Copy a structure file (.nbt) or a WorldEdit schematic (.schem) into a world for structure blocks"
+    )]
+    ImportStructure {
+        world: String,
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "Name to load it with, as namespace:name (defaults to the file name)"
+        )]
+        name: Option<String>,
+        #[arg(
+            long,
+            help = "Put it in this datapack of the world instead of the generated structures"
+        )]
+        datapack: Option<String>,
+    },
+}
+
+/// Commands left in locked mode, the maps they reach are limited to `allowed_maps`
+fn allowed_when_locked(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Play { .. }
+            | Commands::Shell
+            | Commands::Run { .. }
+            | Commands::Search { .. }
+            | Commands::Info { .. }
+            | Commands::News { .. }
+            | Commands::HandleUri { .. }
+    )
+}
+
+/// Commands that change the settings, behind the parental PIN as it is one of them
+fn changes_settings(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Config {
+            command: ConfigCommands::Set { .. }
+                | ConfigCommands::Unset { .. }
+                | ConfigCommands::Edit
+        }
+    )
+}
+
+/// Commands that add, remove, switch or export accounts, behind the parental PIN
+fn changes_accounts(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Login { .. }
+            | Commands::Logout { .. }
+            | Commands::Purge { all: true }
+            | Commands::Accounts {
+                command: None
+                    | Some(AccountsCommands::Export { .. })
+                    | Some(AccountsCommands::Import { .. })
+            }
+    )
 }
 
 #[tokio::main]
 async fn main() {
+    let started = Instant::now();
     let args: Vec<String> = std::env::args().collect();
 
     if args.len() > 1 && (args[1] == "--version" || args[1] == "-V") {
@@ -66,38 +815,277 @@ async fn main() {
     }
 
     let cli = Cli::parse();
+    let _timings = cli.timings.then(|| {
+        use_timings(started);
+        record("startup", started.elapsed());
+        TimingsReport
+    });
+
+    if cli.non_interactive {
+        use_non_interactive();
+    }
+    record_version();
+    if cli.json {
+        use_json_output();
+    }
+    use_prompt_theme();
+    if let Some(account) = &cli.account
+        && let Err(e) = use_account(account)
+    {
+        eprintln!("{}", e);
+        exit(1);
+    }
+
+    if cli.locked {
+        use_locked_mode();
+    }
+    if cli.ipv4 {
+        use_ip_version(IpVersion::Ipv4);
+    } else if cli.ipv6 {
+        use_ip_version(IpVersion::Ipv6);
+    }
+    if let Err(e) = check_allowed(&cli.command) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+    run_command(&cli.command).await;
+}
+
+/// Refuse the commands locked mode disallows, and ask for the PIN before account changes
+fn check_allowed(command: &Commands) -> Result<(), String> {
+    // The config is only read here for the commands locked mode would refuse
+    if !allowed_when_locked(command) && is_locked_mode() {
+        return Err(
+            "This command is disabled on this computer, only maps can be searched and played."
+                .into(),
+        );
+    }
+    if changes_accounts(command) {
+        check_pin("change the accounts")?;
+    }
+    if changes_settings(command) {
+        check_pin("change the settings")?;
+    }
+    Ok(())
+}
+
+/// Switch `ovl open` to a profile, created when missing. Event and thawed profiles keep their
+/// setup, the Minecraft version they are pinned to is returned.
+fn use_open_profile(name: &str, options: &mut LaunchOptions) -> Result<Option<String>, String> {
+    let dir = get_profiles_dir().join(name);
+    use_profile(name);
+    // The game folder is set once for the process
+    if get_game_dir() != dir {
+        return Err(
+            "Another game folder is already in use, run `ovl open --profile` on its own.".into(),
+        );
+    }
+
+    if !dir.is_dir() {
+        let profiles = list_profiles();
+        if !profiles.is_empty() {
+            println!("Profiles: {}", profiles.join(", "));
+        }
+        if !ask_yes_no(&format!("There is no profile named {}. Create it?", name)) {
+            return Err("Cancelled.".into());
+        }
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create the profile: {}", e))?;
+    }
+
+    let Some(manifest) = read_profile_manifest(&dir) else {
+        return Ok(None);
+    };
+    println!("Playing with the setup of {}.", manifest.name);
+    options.locked = true;
+    options.fabric_version = manifest.fabric;
+    Ok(Some(manifest.minecraft))
+}
+
+/// Run the commands typed in `ovl shell` one after the other, in this process
+async fn run_shell() {
+    let commands = Cli::command()
+        .get_subcommands()
+        .flat_map(|c| std::iter::once(c.get_name()).chain(c.get_all_aliases()))
+        .map(String::from)
+        .collect();
+    let mut shell = match Shell::start(commands) {
+        Ok(shell) => shell,
+        Err(e) => {
+            eprintln!("Failed to start the shell: {}", e);
+            return;
+        }
+    };
+    println!("Type commands without `ovl`, Tab completes them. `exit` or Ctrl+D to leave.");
+
+    while let Some(words) = shell.next_command() {
+        let cli = match Cli::try_parse_from(std::iter::once("ovl".to_string()).chain(words)) {
+            Ok(cli) => cli,
+            Err(e) => {
+                let _ = e.print();
+                continue;
+            }
+        };
+        if matches!(cli.command, Commands::Shell) {
+            eprintln!("Already in the shell.");
+            continue;
+        }
+        // They are set once for the process
+        if cli.account.is_some()
+            || cli.locked
+            || cli.ipv4
+            || cli.ipv6
+            || cli.timings
+            || cli.non_interactive
+            || cli.json
+        {
+            eprintln!("Global options apply to the whole session, pass them to `ovl shell`.");
+            continue;
+        }
+        if let Err(e) = check_allowed(&cli.command) {
+            eprintln!("{}", e);
+            continue;
+        }
+        Box::pin(run_command(&cli.command)).await;
+    }
+}
+
+async fn run_command(command: &Commands) {
+    match command {
+        Commands::Shell => run_shell().await,
 
-    match &cli.command {
         Commands::Login {} => {
             let account = add_account();
             println!("Logged in to {:?}", account.name);
         }
 
-        Commands::Accounts {} => {
-            switch_account();
-        }
+        Commands::Accounts { command } => match command {
+            None if is_json_output() => print_json(&fetch_file(false)),
+            None => switch_account(),
+            Some(AccountsCommands::Export { out }) => match export_accounts(out) {
+                Ok(count) => println!("Exported {} accounts to {}.", count, out.display()),
+                Err(e) => eprintln!("Failed to export accounts: {}", e),
+            },
+            Some(AccountsCommands::Import { file }) => match import_accounts(file) {
+                Ok(count) => println!("Imported {} accounts.", count),
+                Err(e) => eprintln!("Failed to import accounts: {}", e),
+            },
+            Some(AccountsCommands::Settings {
+                resolution,
+                language,
+                favorite,
+                unfavorite,
+                reset,
+            }) => {
+                let Some(name) = get_selected_account_name() else {
+                    eprintln!("No accounts configured.");
+                    return;
+                };
+                let mut config = load_config();
+                let settings = config.accounts.entry(name.clone()).or_default();
+
+                if *reset {
+                    *settings = Default::default();
+                }
+                if let Some((width, height)) = resolution {
+                    settings.resolution = Some(format!("{}x{}", width, height));
+                }
+                if let Some(language) = language {
+                    settings.language = Some(language.to_lowercase());
+                }
+                if let Some(map) = favorite
+                    && !settings.favorite_maps.contains(map)
+                {
+                    settings.favorite_maps.push(map.clone());
+                }
+                if let Some(map) = unfavorite {
+                    settings.favorite_maps.retain(|m| m != map);
+                }
+
+                print_output(settings, |settings| {
+                    println!("Settings of {}:", name);
+                    println!(
+                        "  Resolution: {}",
+                        settings
+                            .resolution
+                            .as_deref()
+                            .unwrap_or("1280x720 (default)")
+                    );
+                    println!(
+                        "  Language: {}",
+                        settings.language.as_deref().unwrap_or("game default")
+                    );
+                    println!(
+                        "  Favorite maps: {}",
+                        match settings.favorite_maps.is_empty() {
+                            true => "none".to_string(),
+                            false => settings.favorite_maps.join(", "),
+                        }
+                    );
+                });
+
+                if (*reset
+                    || resolution.is_some()
+                    || language.is_some()
+                    || favorite.is_some()
+                    || unfavorite.is_some())
+                    && let Err(e) = save_config(&config)
+                {
+                    eprintln!("Failed to save config: {}", e);
+                }
+            }
+            Some(AccountsCommands::Pin { remove }) => match set_pin(*remove) {
+                Ok(()) if *remove => println!("Parental PIN removed."),
+                Ok(()) => println!(
+                    "Parental PIN set, it is asked before playing with an online account or changing accounts."
+                ),
+                Err(e) => eprintln!("Failed to change the PIN: {}", e),
+            },
+            Some(AccountsCommands::Skin { png, remove }) => {
+                let png = if *remove { None } else { png.as_deref() };
+                match set_offline_skin(png) {
+                    Ok(name) if png.is_some() => println!("Skin of {} updated.", name),
+                    Ok(name) => println!("Skin of {} removed.", name),
+                    Err(e) => eprintln!("Failed to set the skin: {}", e),
+                }
+            }
+        },
 
         Commands::Whoami {} => {
             let accounts = fetch_file(false);
-            if accounts.accounts.is_empty() {
-                println!("No accounts configured.");
-            } else {
-                let selected_account = accounts
-                    .accounts
-                    .iter()
-                    .find(|a| a.name == accounts.selected);
+            let selected_account = accounts
+                .accounts
+                .iter()
+                .find(|a| a.name == accounts.selected);
+            print_output(&selected_account, |selected_account| {
+                if accounts.accounts.is_empty() {
+                    println!("No accounts configured.");
+                    return;
+                }
                 match selected_account {
                     Some(account) => {
-                        println!(
+                        let line = format!(
                             "You are currently logged in as {} (UUID: {}; Offline: {})",
                             account.name,
                             account.uuid.as_deref().unwrap_or("N/A"),
                             account.offline
                         );
+                        match avatar_lines(account) {
+                            // The name goes next to the middle of the face
+                            Some(avatar) => {
+                                for (i, row) in avatar.iter().enumerate() {
+                                    match i {
+                                        1 => println!("{}  {}", row, line),
+                                        _ => println!("{}", row),
+                                    }
+                                }
+                            }
+                            None => println!("{}", line),
+                        }
                     }
                     None => println!("No account selected."),
                 }
-            }
+            });
         }
         Commands::Logout {} => {
             let accounts = fetch_file(false);
@@ -113,6 +1101,7 @@ async fn main() {
                     account.delete_access_token().unwrap_or_else(|e| {
                         eprintln!("Failed to delete access token: {}", e);
                     });
+                    audit("logout", &account.name);
                 }
 
                 let filtered = accounts
@@ -128,57 +1117,152 @@ async fn main() {
                     filtered[0].name.clone()
                 };
 
-                std::fs::write(
-                    get_app_support_dir().unwrap().join(".accounts"),
-                    serde_json::to_string(&Accounts {
-                        accounts: filtered,
-                        selected,
-                    })
-                    .unwrap(),
-                )
+                write_accounts(&Accounts {
+                    accounts: filtered,
+                    selected,
+                })
                 .unwrap();
 
                 println!("Logged out of the {} session.", accounts.selected);
             }
         }
 
-        Commands::Play { game } => match select_map(game.join(" ").to_lowercase()) {
-            Some(map) => {
-                run_map(map).await;
+        Commands::Play {
+            game,
+            random,
+            tag,
+            any,
+            source,
+            options,
+        } => {
+            let query = match game.is_empty() && !random && !is_non_interactive() {
+                true => ask_map_query().unwrap_or_default(),
+                false => game.join(" "),
+            };
+            let map = match random {
+                true => select_random_map(tag.as_deref(), *any, is_non_interactive()),
+                false => select_map(
+                    query.to_lowercase(),
+                    source.as_deref(),
+                    is_non_interactive(),
+                ),
+            };
+            match map {
+                Some(map) => {
+                    run_map(map, options).await;
+                }
+                None => {}
             }
-            None => {}
-        },
+        }
 
-        Commands::Run { version, ip } => {
-            println!("Launching Minecraft {}...\n", version);
-            launch(version.clone(), None, ip.as_ref()).await;
+        Commands::Run {
+            version,
+            ip,
+            snapshot: _,
+            safe,
+            options,
+        } => {
+            // Without a version, --snapshot is set
+            let requested = version.as_deref().unwrap_or("snapshot");
+            let version = match resolve_version(requested).await {
+                Ok(version) => version,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+
+            if version.is_snapshot() {
+                println!(
+                    "Minecraft {} is a snapshot, back up worlds before opening them with it.",
+                    version.id
+                );
+            }
+            if !*safe {
+                println!("Launching Minecraft {}...\n", version.id);
+                launch(version.id, None, ip.as_ref(), options).await;
+                return;
+            }
+
+            let safe_dir = std::env::temp_dir().join(format!("ovl-safe-{}", std::process::id()));
+            use_game_dir(safe_dir.clone());
+            println!(
+                "Launching Minecraft {} in safe mode: no mods, no resource pack, default options.",
+                version.id
+            );
+            println!(
+                "Everything is downloaded again into a temporary folder, this takes a while.\n"
+            );
+
+            let options = LaunchOptions {
+                locked: true,
+                fabric_version: None,
+                detach: false,
+                ..options.clone()
+            };
+            launch(version.id, None, ip.as_ref(), &options).await;
+
+            if let Err(e) = std::fs::remove_dir_all(&safe_dir) {
+                eprintln!("Failed to remove the temporary game folder: {}", e);
+            }
         }
 
-        Commands::Open { path } => {
-            let input_path = Path::new(path);
+        Commands::Open {
+            path,
+            profile,
+            options,
+        } => {
+            let mut options = options.clone();
+            let pinned_version = match profile {
+                Some(name) => match use_open_profile(name, &mut options) {
+                    Ok(version) => version,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                },
+                None => None,
+            };
+
+            let downloaded = match parse_github_source(path) {
+                Some(Ok(source)) => match fetch_github_map(&source) {
+                    Ok(path) => Some(path),
+                    Err(e) => {
+                        eprintln!("Failed to get the map from GitHub: {}", e);
+                        return;
+                    }
+                },
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+                None => match Url::parse(path) {
+                    Ok(url) if url.scheme() == "https" => match fetch_linked_map(&url) {
+                        Ok(path) => Some(path),
+                        Err(e) => {
+                            eprintln!("Failed to download the map: {}", e);
+                            return;
+                        }
+                    },
+                    _ => None,
+                },
+            };
+            let input_path = downloaded.as_deref().unwrap_or(Path::new(path));
             let map_path: String;
 
-            let name_which_exists = get_app_support_dir()
-                .unwrap()
-                .join(".minecraft")
-                .join("saves")
-                .join(&path);
+            // A world of the saves, by folder name or by the name it is displayed with
+            let existing = quick_play_folder(&get_game_dir().join("saves"), path);
 
-            if name_which_exists.exists()
+            if let Some(folder) = existing
                 && ask_yes_no("Map already exists in your saves. Open it?")
             {
-                map_path = path.to_string();
+                map_path = folder;
             } else {
-                if let Ok(url) = Url::parse(path) {
-                    if url.scheme() == "https" {
-                        println!("Downloading map from URL: {}", url);
-                        return ();
-                    } else {
-                        eprintln!("Invalid URL: must start with https://");
-                        return ();
-                    }
+                if downloaded.is_none() && Url::parse(path).is_ok() {
+                    eprintln!("Invalid URL: must start with https://");
+                    return;
                 } else if input_path.extension().map_or(false, |ext| ext == "zip") {
-                    map_path = match install_map_from_path((&input_path).to_path_buf(), true) {
+                    map_path = match install_map_from_path(input_path.to_path_buf(), true) {
                         Ok(value) => value,
                         Err(e) => {
                             eprintln!("Error extracting map: {}", e);
@@ -186,14 +1270,7 @@ async fn main() {
                         }
                     };
                 } else if input_path.is_dir() {
-                    let _ = ensure_folder_exists(
-                        get_app_support_dir()
-                            .unwrap()
-                            .join(".minecraft")
-                            .join("saves")
-                            .to_str()
-                            .unwrap(),
-                    );
+                    let _ = ensure_folder_exists(get_game_dir().join("saves").to_str().unwrap());
 
                     let map_name = input_path
                         .file_name()
@@ -201,43 +1278,40 @@ async fn main() {
                         .unwrap()
                         .to_string();
 
-                    println!("Extracting map to .minecraft/saves/{}/", map_name);
-
-                    let extract_path = get_app_support_dir()
-                        .unwrap()
-                        .join(".minecraft")
-                        .join("saves")
-                        .join(&map_name);
-
-                    if extract_path.exists() {
-                        if !ask_yes_no(&format!("Map {} already exists. Overwrite?", &map_name)) {
-                            return ();
+                    map_path = match resolve_collision(&map_name) {
+                        Ok(Some(folder)) => {
+                            println!("Extracting map to .minecraft/saves/{}/", folder);
+                            let extract_path = get_game_dir().join("saves").join(&folder);
+                            let copied = stage_dir(&folder).and_then(|staged| {
+                                copy_dir_all(input_path, &staged)
+                                    .and_then(|_| commit_staged(&staged, &extract_path))
+                                    .inspect_err(|_| discard_staged(&staged))
+                            });
+                            if let Err(e) = copied {
+                                eprintln!("Failed to copy the map: {}", e);
+                                return;
+                            }
+                            folder
                         }
-
-                        std::fs::remove_dir_all(&extract_path).unwrap_or_else(|e| {
-                            eprintln!("Failed to remove extracted folder: {}", e);
-                        });
-                    }
-
-                    let _ = copy_dir_all(input_path, extract_path);
-                    map_path = map_name;
+                        Ok(None) => map_name,
+                        Err(e) => {
+                            eprintln!("Failed to replace the existing map: {}", e);
+                            return;
+                        }
+                    };
                 } else {
                     eprintln!("Invalid path: must be a .zip file, a folder or a URL (https only).");
                     return ();
                 }
             }
 
-            let full_map_path = get_app_support_dir()
-                .unwrap()
-                .join(".minecraft")
-                .join("saves")
-                .join(&map_path);
+            let full_map_path = get_game_dir().join("saves").join(&map_path);
 
             let level_dat = full_map_path.join("level.dat");
 
             if !level_dat.exists() {
                 eprintln!("Error: The map does not contain a valid level.dat file.");
-                if ask_yes_no("Delete the extracted folder?") {
+                if ask_yes_no_attended("Delete the extracted folder?") {
                     std::fs::remove_dir_all(&full_map_path).unwrap_or_else(|e| {
                         eprintln!("Failed to remove extracted folder: {}", e);
                     });
@@ -250,30 +1324,517 @@ async fn main() {
 
             let map_version = get_version_name(&level_dat);
 
-            let version = ask_input(
-                &format!(
-                    "Enter the Minecraft version (map recommends {})",
-                    &map_version
+            let version = match pinned_version {
+                Some(version) => {
+                    if version != map_version {
+                        println!(
+                            "The profile plays Minecraft {}, the map recommends {}.",
+                            version, map_version
+                        );
+                    }
+                    version
+                }
+                None => ask_input(
+                    &format!(
+                        "Enter the Minecraft version (map recommends {})",
+                        &map_version
+                    ),
+                    Some(&map_version),
                 ),
-                Some(&map_version),
-            );
+            };
 
-            launch(version.clone(), Some(&map_path), None).await;
+            launch(version.clone(), Some(&map_path), None, &options).await;
+        }
+
+        Commands::Server { dir, command } => {
+            let dir = dir.clone().unwrap_or_else(get_server_dir);
+
+            match command {
+                ServerCommands::Console => {
+                    if let Err(e) = attach_console(&dir) {
+                        eprintln!("Console error: {}", e);
+                    }
+                }
+                ServerCommands::Run { version, options } => {
+                    if let Err(e) = run_server(&dir, version.as_ref(), options).await {
+                        eprintln!("Server error: {}", e);
+                    }
+                }
+                ServerCommands::Whitelist { command } => match command {
+                    WhitelistCommands::Add { name } => match whitelist_add(&dir, name).await {
+                        Ok(name) => println!("Added {} to the whitelist.", name),
+                        Err(e) => eprintln!("Failed to update the whitelist: {}", e),
+                    },
+                    WhitelistCommands::Remove { name } => match whitelist_remove(&dir, name) {
+                        Ok(true) => println!("Removed {} from the whitelist.", name),
+                        Ok(false) => println!("{} is not on the whitelist.", name),
+                        Err(e) => eprintln!("Failed to update the whitelist: {}", e),
+                    },
+                    WhitelistCommands::List {} => {
+                        if let Err(e) = print_whitelist(&dir) {
+                            eprintln!("Failed to read the whitelist: {}", e);
+                        }
+                    }
+                },
+                ServerCommands::Op { name, level } => match op(&dir, name, *level).await {
+                    Ok(name) => println!("Made {} operator (level {}).", name, level),
+                    Err(e) => eprintln!("Failed to update ops: {}", e),
+                },
+                ServerCommands::Deop { name } => match deop(&dir, name) {
+                    Ok(true) => println!("{} is no longer operator.", name),
+                    Ok(false) => println!("{} was not operator.", name),
+                    Err(e) => eprintln!("Failed to update ops: {}", e),
+                },
+            }
+        }
+
+        Commands::Spectate {
+            host,
+            version,
+            dir,
+            options,
+        } => {
+            let dir = dir.clone().unwrap_or_else(get_server_dir);
+            if let Err(e) = spectate(host, version.as_ref(), &dir, options).await {
+                eprintln!("{}", e);
+            }
         }
 
-        Commands::Search {} => {
-            let maps: Vec<Map> = match fetch_maps() {
+        Commands::Coop { map, options } => {
+            if let Err(e) = coop(map.clone(), options).await {
+                eprintln!("Co-op error: {}", e);
+            }
+        }
+
+        Commands::Join { invite, options } => {
+            let result = match parse_invite(invite) {
+                Ok(invite) => join(&invite, options).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to join: {}", e);
+            }
+        }
+
+        Commands::Lookup { query } => {
+            print_lookup(query).await;
+        }
+
+        Commands::Submit { map } => {
+            if let Err(e) = submit_score(map).await {
+                eprintln!("Failed to submit the score: {}", e);
+            }
+        }
+
+        Commands::Rate {
+            map,
+            rating,
+            comment,
+        } => {
+            if let Err(e) = rate_map(map, *rating, comment.as_deref()).await {
+                eprintln!("Failed to send the rating: {}", e);
+            }
+        }
+
+        Commands::Survey { map, off } => {
+            if let Err(e) = set_survey(map, *off) {
+                eprintln!("{}", e);
+            }
+        }
+
+        Commands::Whatsnew => print_whats_new().await,
+
+        Commands::Installed => print_output(&list_installed(), |maps| print_installed(maps)),
+
+        Commands::Status => print_status(),
+
+        Commands::Repair => {
+            if let Err(e) = repair().await {
+                eprintln!("Failed to repair: {}", e);
+            }
+        }
+
+        Commands::Clean => {
+            if let Err(e) = clean() {
+                eprintln!("Failed to clean: {}", e);
+            }
+        }
+
+        Commands::Freeze { out } => match freeze(out) {
+            Ok(lock) => println!(
+                "Saved Minecraft {} with {} mods to {}.",
+                lock.minecraft,
+                lock.mods.len(),
+                out.display()
+            ),
+            Err(e) => eprintln!("Failed to freeze the setup: {}", e),
+        },
+
+        Commands::Thaw { file, options } => {
+            if let Err(e) = thaw(file, options).await {
+                eprintln!("Failed to thaw {}: {}", file.display(), e);
+            }
+        }
+
+        Commands::Audit { lines, action } => {
+            print_output(&read_audit(*lines, action.as_deref()), |entries| {
+                print_audit(entries)
+            })
+        }
+
+        Commands::Rollback { id, list } => {
+            if *list {
+                print_output(&read_snapshots(), |snapshots| print_snapshots(snapshots));
+            } else {
+                match rollback(id.as_ref()) {
+                    Ok(snapshot) => println!(
+                        "Restored the snapshot from {} ({}).",
+                        snapshot.id, snapshot.reason
+                    ),
+                    Err(e) => eprintln!("Failed to roll back: {}", e),
+                }
+            }
+        }
+
+        Commands::Cancel => match request_cancel() {
+            Ok(()) => println!("Asked the running install to cancel."),
+            Err(e) => eprintln!("Failed to cancel the install: {}", e),
+        },
+
+        Commands::Doctor { network, gpu } => {
+            run_doctor(*network, *gpu).await;
+        }
+
+        Commands::Mods { command } => match command {
+            ModsCommands::Update { version } => {
+                let versions: Vec<String> = match version {
+                    Some(version) => vec![version.clone()],
+                    None => read_lock().versions.into_keys().collect(),
+                };
+
+                if versions.is_empty() {
+                    println!("No locked mods yet, they will be resolved on the next launch.");
+                } else {
+                    take_snapshot("before updating the mods");
+                    // An update has to see the versions published since the last lookup
+                    forget_modrinth_cache();
+                }
+
+                for version in versions {
+                    match update_lock(&version).await {
+                        Ok(resolved) => {
+                            println!("Updated {} locked mods for {}.", resolved.len(), version);
+
+                            let mut extras = load_config().extra_mods;
+                            extras.sort();
+                            if !extras.is_empty() {
+                                match update_extras_lock(&version, &extras).await {
+                                    Ok(locked) => println!(
+                                        "Updated {} locked extras for {}.",
+                                        locked.mods.len(),
+                                        version
+                                    ),
+                                    Err(e) => eprintln!("Failed to update extras: {}", e),
+                                }
+                            }
+
+                            if get_used_version_save().as_deref() == Some(version.as_str()) {
+                                match install_mods(&resolved).await {
+                                    Ok(()) => {}
+                                    Err(e) => eprintln!("Failed to install updated mods: {}", e),
+                                }
+                                match install_extras(&version, true).await {
+                                    Ok(()) => {}
+                                    Err(e) => eprintln!("Failed to install updated extras: {}", e),
+                                }
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to update mods for {}: {}", version, e),
+                    }
+                }
+            }
+
+            ModsCommands::Extras {} => {
+                let mut config = load_config();
+
+                // Keep extras added by hand to config.toml that are not part of the menu
+                let custom: Vec<String> = config
+                    .extra_mods
+                    .iter()
+                    .filter(|m| !EXTRA_MODS.iter().any(|(slug, _)| slug == m))
+                    .cloned()
+                    .collect();
+
+                let Some(mut selected) = select_extra_mods(EXTRA_MODS, &config.extra_mods) else {
+                    return;
+                };
+                selected.extend(custom);
+                config.extra_mods = selected;
+
+                if let Err(e) = save_config(&config) {
+                    eprintln!("Failed to save config: {}", e);
+                    return;
+                }
+
+                match get_used_version_save() {
+                    Some(version) => {
+                        take_snapshot("before changing the extras");
+                        match install_extras(&version, false).await {
+                            Ok(()) => println!("Extras updated for {}.", version),
+                            Err(e) => eprintln!("Failed to install extras: {}", e),
+                        }
+                    }
+                    None => println!("Extras will be installed on the next launch."),
+                }
+            }
+        },
+
+        Commands::Event { command } => match command {
+            EventCommands::Join { code, options } => {
+                if let Err(e) = join_event(code, options).await {
+                    eprintln!("Failed to join the event: {}", e);
+                }
+            }
+        },
+
+        Commands::ExportVanillaProfile { target, jvm_args } => {
+            match export_vanilla_profile(&target.join(" "), jvm_args.as_deref()).await {
+                Ok(name) => println!(
+                    "Added the profile {} to the official launcher, restart it to see it.",
+                    name
+                ),
+                Err(e) => eprintln!("Failed to export the profile: {}", e),
+            }
+        }
+
+        Commands::Cache { command } => match command {
+            CacheCommands::Serve { port } => {
+                if let Err(e) = serve_cache(*port) {
+                    eprintln!("Failed to serve the cache: {}", e);
+                }
+            }
+        },
+
+        Commands::Config { command } => {
+            let result = match command {
+                ConfigCommands::List => print_config(),
+                ConfigCommands::Get { key } => print_config_value(key),
+                ConfigCommands::Set { key, value } => set_config_value(key, Some(value)),
+                ConfigCommands::Unset { key } => set_config_value(key, None),
+                ConfigCommands::Edit => edit_config(),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+
+        Commands::Purge { all } => {
+            if let Err(e) = purge(*all) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+
+        Commands::Profiles { command } => match command {
+            ProfilesCommands::Dedupe => match dedupe_profiles() {
+                Ok((0, _)) => println!("No duplicate mods found."),
+                Ok((linked, reclaimed)) => println!(
+                    "Linked {} duplicate mods, {:.1} MB reclaimed.",
+                    linked,
+                    reclaimed as f64 / 1_000_000.0
+                ),
+                Err(e) => eprintln!("Failed to deduplicate the profiles: {}", e),
+            },
+        },
+
+        Commands::VerifyMap {
+            map,
+            version,
+            no_launch,
+        } => {
+            if verify_map(map, version.clone(), *no_launch).await > 0 {
+                exit(1);
+            }
+        }
+
+        Commands::Info { map, source } => match find_maps(map.join(" "), source.as_deref()) {
+            Some(maps) if maps.len() == 1 => print_output(&maps[0], print_map_info),
+            Some(maps) => {
+                if let Some(map) = select_from_multiple_maps(maps) {
+                    print_output(&map, print_map_info);
+                }
+            }
+            None => print_output(&(), |_| println!("No maps found.")),
+        },
+
+        Commands::News {
+            announcements: true,
+            ..
+        } => match fetch_announcements() {
+            Ok(announcements) => print_output(&announcements, |announcements| {
+                print_announcements(announcements)
+            }),
+            Err(e) => eprintln!("Failed to fetch the announcements: {}", e),
+        },
+
+        Commands::News { days, .. } => match recent_maps(*days) {
+            Ok(maps) => print_output(&maps, |maps| print_news(maps, *days)),
+            Err(e) => eprintln!("Failed to fetch the catalog: {}", e),
+        },
+
+        Commands::Shortcut { map } => match create_shortcut(map) {
+            Ok(path) => println!("Created {}", path.display()),
+            Err(e) => eprintln!("Failed to create the shortcut: {}", e),
+        },
+
+        Commands::RegisterUri { remove: false } => match register_uri_handler() {
+            Ok(()) => println!("ovl:// links now open with this launcher."),
+            Err(e) => eprintln!("Failed to register the ovl:// handler: {}", e),
+        },
+        Commands::RegisterUri { remove: true } => match unregister_uri_handler() {
+            Ok(()) => println!("ovl:// links are no longer handled."),
+            Err(e) => eprintln!("Failed to remove the ovl:// handler: {}", e),
+        },
+        Commands::HandleUri { uri } => {
+            if let Err(e) = handle_uri(uri).await {
+                eprintln!("{}", e);
+            }
+        }
+
+        Commands::Steam { command } => match command {
+            SteamCommands::Add { target } => {
+                if let Err(e) = add_steam_shortcut(target).await {
+                    eprintln!("Failed to add the Steam shortcut: {}", e);
+                }
+            }
+        },
+
+        Commands::Replays { command } => match command {
+            ReplaysCommands::List {} => {
+                print_output(&list_replays(), |replays| print_replays(replays))
+            }
+            ReplaysCommands::Open { name, options } => {
+                if let Err(e) = open_replay(name.as_ref(), options).await {
+                    eprintln!("Failed to open the replay: {}", e);
+                }
+            }
+        },
+
+        Commands::World { command } => match command {
+            WorldCommands::Verify { world } => {
+                let world_dir = match resolve_world(world) {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                };
+
+                eprintln!("Verifying {}...", world_dir.display());
+                let report = verify_world(&world_dir);
+                print_output(&report, |report| report.print(&world_dir));
+                if !report.is_ok() {
+                    exit(1);
+                }
+            }
+            WorldCommands::Info { world } => {
+                let result = resolve_world(world)
+                    .map_err(|e| e.into())
+                    .and_then(|world_dir| read_world_info(&world_dir));
+                match result {
+                    Ok(info) => print_output(&info, print_world_info),
+                    Err(e) => eprintln!("Failed to read the world: {}", e),
+                }
+            }
+            WorldCommands::Stats { world, player } => {
+                let world_dir = match resolve_world(world) {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                };
+
+                match collect_world_stats(&world_dir, player.as_ref()).await {
+                    Ok(stats) => print_output(&stats, |stats| print_world_stats(stats)),
+                    Err(e) => eprintln!("Failed to read the stats: {}", e),
+                }
+            }
+            WorldCommands::Optimize { world, level } => {
+                let world_dir = match resolve_world(world) {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                };
+
+                let (before, after) = optimize_world(&world_dir, *level);
+                println!(
+                    "Region files went from {:.1} MB to {:.1} MB.",
+                    before as f64 / 1024.0 / 1024.0,
+                    after as f64 / 1024.0 / 1024.0
+                );
+            }
+            WorldCommands::Rules { world, preset, set } => {
+                let result = resolve_world(world)
+                    .map_err(|e| e.into())
+                    .and_then(|world_dir| set_game_rules(&world_dir, *preset, set));
+                match result {
+                    Ok(()) => println!("Saved, the previous level.dat is kept as level.dat_old."),
+                    Err(e) => eprintln!("Failed to change the game rules: {}", e),
+                }
+            }
+            WorldCommands::ImportStructure {
+                world,
+                file,
+                name,
+                datapack,
+            } => {
+                let result = resolve_world(world)
+                    .map_err(|e| e.into())
+                    .and_then(|world_dir| {
+                        import_structure(&world_dir, file, name.as_deref(), datapack.as_deref())
+                    });
+                match result {
+                    Ok((id, path)) => {
+                        println!("Imported to {}", path.display());
+                        println!(
+                            "Load it in a structure block or with /place template {}",
+                            id
+                        );
+                    }
+                    Err(e) => eprintln!("Failed to import the structure: {}", e),
+                }
+            }
+        },
+
+        Commands::Search { options } => {
+            let maps: Vec<Map> = match indexed_maps() {
                 Ok(maps) => maps,
                 Err(e) => {
                     eprintln!("Error fetching maps: {}", e);
                     return;
                 }
             };
+            let favorites = get_selected_account_name()
+                .and_then(|name| load_config().accounts.get(&name).cloned())
+                .unwrap_or_default()
+                .favorite_maps;
+            let (mut maps, others): (Vec<Map>, Vec<Map>) =
+                maps.into_iter().partition(|m| favorites.contains(&m.id));
+            maps.extend(others);
+            // Wrappers list the maps themselves, and play one with `ovl play <id>`
+            if is_json_output() {
+                print_json(&maps);
+                return;
+            }
             let map = select_from_multiple_maps(maps);
 
             match map {
                 Some(map) => {
-                    run_map(map).await;
+                    run_map(map, options).await;
                 }
                 None => println!("No map selected."),
             }
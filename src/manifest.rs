@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::{
+    audit::audit,
+    cmd::{ask_yes_no, print_output},
+    dir::get_game_dir,
+    net,
+    scan::scan_download,
+    theme::Themed,
+};
+
+/// Folders where any file missing from the manifest is reported as unknown
+const SCANNED_DIRS: &[&str] = &["mods"];
+
+/// A file the launcher wrote, and where its content came from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub sha256: String,
+    /// Download URL, or `<zip url>#<entry>` for files extracted from an archive
+    pub origin: String,
+}
+
+type Manifest = BTreeMap<String, ManifestEntry>;
+
+fn get_manifest_path() -> PathBuf {
+    get_game_dir().join(".ovl-manifest.json")
+}
+
+fn read_manifest() -> Manifest {
+    fs::read_to_string(get_manifest_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_manifest(manifest: &Manifest) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(get_manifest_path(), serde_json::to_string_pretty(manifest)?)?;
+    Ok(())
+}
+
+/// Manifest key of a file, its path relative to the game folder
+fn manifest_key(path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(get_game_dir()).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+fn update_manifest(path: &Path, origin: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(key) = manifest_key(path) else {
+        return Ok(());
+    };
+    let mut manifest = read_manifest();
+
+    let origin_given = origin.is_some();
+    let origin = match (origin, manifest.get(&key)) {
+        (Some(origin), _) => origin.to_string(),
+        (None, Some(entry)) => entry.origin.clone(),
+        // Untracked files stay untracked
+        (None, None) => return Ok(()),
+    };
+    let sha256 = sha256::digest(&fs::read(path)?);
+    if origin_given {
+        audit(
+            "download",
+            &format!("{} sha256 {} from {}", key, sha256, origin),
+        );
+    }
+    manifest.insert(key, ManifestEntry { sha256, origin });
+    write_manifest(&manifest)
+}
+
+/// Track a file the launcher just wrote
+pub fn record_file(path: &Path, origin: &str) {
+    if let Err(e) = update_manifest(path, Some(origin)) {
+        eprintln!("Warning: Failed to update the file manifest: {}", e);
+    }
+}
+
+/// Take the new content of a tracked file as the expected one, after a rollback
+pub fn refresh_file(path: &Path) {
+    if let Err(e) = update_manifest(path, None) {
+        eprintln!("Warning: Failed to update the file manifest: {}", e);
+    }
+}
+
+/// Where a tracked file was downloaded from
+pub fn file_origin(path: &Path) -> Option<String> {
+    let entry = read_manifest().remove(&manifest_key(path)?)?;
+    Some(entry.origin)
+}
+
+/// Stop tracking a file the launcher removed
+pub fn forget_file(path: &Path) {
+    let Some(key) = manifest_key(path) else {
+        return;
+    };
+    audit("delete", &key);
+    let mut manifest = read_manifest();
+    if manifest.remove(&key).is_some()
+        && let Err(e) = write_manifest(&manifest)
+    {
+        eprintln!("Warning: Failed to update the file manifest: {}", e);
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IntegrityReport {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// Compare the files on disk to the manifest
+pub fn check_integrity() -> IntegrityReport {
+    let game_dir = get_game_dir();
+    let manifest = read_manifest();
+    let mut report = IntegrityReport::default();
+
+    for (key, entry) in &manifest {
+        let path = game_dir.join(key);
+        match fs::read(&path) {
+            Ok(content) if sha256::digest(&content) != entry.sha256 => {
+                report.modified.push(key.clone())
+            }
+            Ok(_) => {}
+            Err(_) => report.missing.push(key.clone()),
+        }
+    }
+
+    for dir in SCANNED_DIRS {
+        let Ok(entries) = fs::read_dir(game_dir.join(dir)) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            // Dotfiles are the launcher's own markers
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if !path.is_file() || hidden {
+                continue;
+            }
+            if let Some(key) = manifest_key(&path)
+                && !manifest.contains_key(&key)
+            {
+                report.unknown.push(key);
+            }
+        }
+    }
+
+    report.unknown.sort();
+    report
+}
+
+pub fn print_status() {
+    let tracked = !read_manifest().is_empty();
+    print_output(&check_integrity(), |report| print_report(report, tracked));
+}
+
+fn print_report(report: &IntegrityReport, tracked: bool) {
+    if !tracked {
+        println!("No launcher-managed files yet, they are tracked from the next launch.");
+        return;
+    }
+    if report.is_clean() {
+        println!("Every launcher-managed file is as it was installed.");
+        return;
+    }
+
+    for key in &report.modified {
+        println!("{} {}", "modified".warning(), key);
+    }
+    for key in &report.missing {
+        println!("{}  {}", "deleted".error(), key);
+    }
+    for key in &report.unknown {
+        println!("{}  {}", "unknown".muted(), key);
+    }
+
+    if !report.modified.is_empty() || !report.missing.is_empty() {
+        println!("\nRun `ovl repair` to restore the modified and deleted files.");
+    }
+    if !report.unknown.is_empty() {
+        println!("Run `ovl clean` to remove the files the launcher did not install.");
+    }
+}
+
+async fn fetch_origin(origin: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (url, entry) = match origin.split_once('#') {
+        Some((url, entry)) => (url, Some(entry)),
+        None => (origin, None),
+    };
+    let bytes = net::get(url).await?.error_for_status()?.bytes().await?;
+
+    match entry {
+        Some(entry) => {
+            let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+            let mut file = archive.by_name(entry)?;
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            Ok(content)
+        }
+        None => Ok(bytes.to_vec()),
+    }
+}
+
+/// Download the modified and deleted files again from where they came from
+pub async fn repair() -> Result<(), Box<dyn std::error::Error>> {
+    let report = check_integrity();
+    let manifest = read_manifest();
+    let broken: Vec<&String> = report.modified.iter().chain(&report.missing).collect();
+    if broken.is_empty() {
+        println!("Nothing to repair.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for key in broken {
+        let entry = &manifest[key];
+        let content = match fetch_origin(&entry.origin).await {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to download {}: {}", key, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        if sha256::digest(&content) != entry.sha256 {
+            eprintln!(
+                "{} changed upstream since it was installed, run `ovl mods update` or relaunch to get the new version.",
+                key
+            );
+            failed += 1;
+            continue;
+        }
+
+        let path = get_game_dir().join(key);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Err(e) = scan_download(&name, &content) {
+            eprintln!("{}", e);
+            failed += 1;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Jars can be links to a shared object, writing into them would change every profile
+        let _ = fs::remove_file(&path);
+        fs::write(&path, content)?;
+        println!("Repaired {}", key);
+    }
+
+    if failed > 0 {
+        return Err(format!("{} files could not be repaired", failed).into());
+    }
+    Ok(())
+}
+
+/// Remove the files the launcher did not install from the managed folders
+pub fn clean() -> Result<(), Box<dyn std::error::Error>> {
+    let report = check_integrity();
+    if report.unknown.is_empty() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    for key in &report.unknown {
+        println!("  {}", key);
+    }
+    if !ask_yes_no(&format!(
+        "Remove these {} files the launcher did not install?",
+        report.unknown.len()
+    )) {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    for key in &report.unknown {
+        fs::remove_file(get_game_dir().join(key))?;
+        audit("delete", key);
+    }
+    println!("Removed {} files.", report.unknown.len());
+    Ok(())
+}
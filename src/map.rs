@@ -1,19 +1,58 @@
-use reqwest;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use colored::Colorize;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use reqwest::blocking;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
 use std::fs;
 use std::fs::File;
-use std::io;
-use std::{error::Error, path::PathBuf};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zip::ZipArchive;
 
 use crate::{
-    cmd::{ask_yes_no, select_from_multiple_maps},
-    filesys::{ensure_folder_exists, getsha256},
+    audit::audit,
+    backup::backup_world,
+    cmd::{ask_yes_no, ask_yes_no_attended, select_from_multiple_maps},
+    config::{is_map_allowed, load_config},
+    dir::get_game_dir,
+    estimate::{DownloadEstimate, estimate_zip},
+    filesys::{ensure_folder_exists, getsha256, replace_file},
     get_app_support_dir,
+    install::{commit_staged, discard_staged, stage_dir},
+    installed::{record_map_check, record_map_download, record_map_install},
+    lancache::{lan_cache_url, lan_fetch},
+    mirrors::{map_hash, map_mirrors, record_failure, record_success, source_map_url},
+    mismatch::{HashMismatch, MismatchChoice},
+    net,
+    news::record_catalog,
+    releases::{forget_releases, get_map_release},
+    scan::{scan_download, scan_file},
+    searchindex::{indexed_maps, search_maps, update_index},
+    survey::Survey,
+    theme::Themed,
+    thumbnail::{print_thumbnail, supports_graphics},
+    timings::timed,
+    torrent::download_torrent,
+    world::verify_world,
     zipper::{extract_zip, get_root_folder_name},
 };
 
-#[derive(Debug, Deserialize, Clone)]
+/// What happens when a map is installed over a world with the same name
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverwritePolicy {
+    #[default]
+    Ask,
+    Never,
+    Rename,
+    OverwriteWithBackup,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Map {
     pub id: String,
     pub name: String,
@@ -24,26 +63,227 @@ pub struct Map {
     #[serde(rename = "type")]
     pub map_type: String,
     pub version: String,
+    /// Set for competitive maps whose results can be submitted with `ovl submit`
+    #[serde(default)]
+    pub leaderboard: Option<Leaderboard>,
+    /// URL of the icon or banner shown in `ovl info`
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Average of the ratings sent with `ovl rate`, when the catalog provides it
+    #[serde(default)]
+    pub rating: Option<Rating>,
+    /// `.torrent` of huge maps, downloaded from its web seeds before the mirrors
+    #[serde(default)]
+    pub torrent: Option<String>,
+    /// `beta` for maps still being tested
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Questions asked after sessions of a beta map, to testers who opted in with `ovl survey`
+    #[serde(default)]
+    pub survey: Option<Survey>,
+    /// Catalog of `catalog_sources` the map comes from, `None` for the OpenVoxel one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
-pub fn fetch_maps() -> Result<Vec<Map>, Box<dyn Error>> {
-    let response: blocking::Response =
-        blocking::get("https://openvoxel.studio/maps.json")?.error_for_status()?;
+/// Name of the OpenVoxel catalog for `--source`
+pub const MAIN_CATALOG: &str = "openvoxel";
+
+impl Map {
+    pub fn source(&self) -> &str {
+        self.source.as_deref().unwrap_or(MAIN_CATALOG)
+    }
+
+    /// The id of the map in its own catalog, without the `<source>:` prefix
+    pub fn bare_id(&self) -> &str {
+        self.id
+            .split_once(':')
+            .map_or(self.id.as_str(), |(_, id)| id)
+    }
+}
+
+/// Path of a file or folder named after a map, relative to the folder it is kept in. Maps
+/// of other catalogs get a folder named after their catalog, `:` is not allowed on Windows.
+pub fn map_path(id: &str, suffix: &str) -> PathBuf {
+    match id.split_once(':') {
+        Some((source, id)) => Path::new(source).join(format!("{}{}", id, suffix)),
+        None => PathBuf::from(format!("{}{}", id, suffix)),
+    }
+}
+
+/// Where the zip of a map is cached
+pub fn cached_map_path(id: &str) -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("games")
+        .join(map_path(id, ".zip"))
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Rating {
+    /// From 1 to 5
+    pub average: f32,
+    pub count: u32,
+}
+
+impl Rating {
+    pub fn describe(&self) -> String {
+        format!("★ {:.1} ({})", self.average, self.count)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Leaderboard {
+    /// Scoreboard objective holding the result of each player
+    pub objective: String,
+}
+
+/// Check the catalog against `<catalog_url>.sig`, a base64 Ed25519 signature of its bytes
+fn verify_catalog(catalog_url: &str, body: &[u8], public_key: &str) -> Result<(), Box<dyn Error>> {
+    let key: [u8; 32] = STANDARD
+        .decode(public_key.trim())?
+        .try_into()
+        .map_err(|_| "catalog_public_key is not an Ed25519 key")?;
+    let key = VerifyingKey::from_bytes(&key)?;
+
+    let signature = net::blocking_get(&format!("{}.sig", catalog_url))?
+        .error_for_status()?
+        .text()?;
+    let signature: [u8; 64] = STANDARD
+        .decode(signature.trim())?
+        .try_into()
+        .map_err(|_| "the catalog signature is not an Ed25519 signature")?;
+
+    key.verify(body, &Signature::from_bytes(&signature))
+        .map_err(|_| "the catalog signature does not match the pinned key".into())
+}
+
+/// Download a catalog and check it against `public_key` when one is pinned
+fn read_catalog(url: &str, public_key: Option<&str>) -> Result<Vec<Map>, Box<dyn Error>> {
+    let body = timed("catalog", || -> Result<_, Box<dyn Error>> {
+        let response: blocking::Response = net::blocking_get(url)?.error_for_status()?;
+        Ok(response.bytes()?)
+    })?;
+
+    if let Some(public_key) = public_key {
+        verify_catalog(url, &body, public_key)?;
+    }
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Catalog names and map ids of `catalog_sources` end up in paths and URLs
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Whether a map id from a catalog can be put in paths, scripts and links as it is
+pub fn is_safe_map_id(id: &str) -> bool {
+    match id.split_once(':') {
+        Some((source, id)) => is_safe_name(source) && is_safe_name(id),
+        None => is_safe_name(id),
+    }
+}
+
+/// The maps of a catalog from `catalog_sources`, with their ids prefixed by its name
+fn read_source_catalog(source: &str, url: &str) -> Result<Vec<Map>, Box<dyn Error>> {
+    if source == MAIN_CATALOG || !is_safe_name(source) {
+        return Err(format!("{:?} is not a valid catalog name", source).into());
+    }
+    let maps = read_catalog(url, None)?
+        .into_iter()
+        .filter_map(|mut map| {
+            if !is_safe_name(&map.id) {
+                eprintln!(
+                    "Skipping the map {:?} of the {} catalog, its id is not valid",
+                    map.id, source
+                );
+                return None;
+            }
+            map.id = format!("{}:{}", source, map.id);
+            map.source = Some(source.to_string());
+            Some(map)
+        })
+        .collect();
+    Ok(maps)
+}
+
+/// Tell once per run about the map ids found in several catalogs, they are told apart by
+/// their `<source>:` prefix
+fn report_conflicts(maps: &[Map]) {
+    static REPORTED: Once = Once::new();
+    let mut catalogs: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for map in maps {
+        catalogs
+            .entry(map.bare_id())
+            .or_default()
+            .push(map.source());
+    }
+    REPORTED.call_once(|| {
+        for (id, sources) in catalogs.iter().filter(|(_, sources)| sources.len() > 1) {
+            eprintln!(
+                "{}",
+                format!(
+                    "{} is in the {} catalogs, pick one with `--source` or as `<catalog>:{}`",
+                    id,
+                    sources.join(" and "),
+                    id
+                )
+                .muted()
+            );
+        }
+    });
+}
+
+/// Download and check the catalog and the ones of `catalog_sources`, without recording them
+pub fn download_catalog() -> Result<Vec<Map>, Box<dyn Error>> {
+    let config = load_config();
+    let mut maps = read_catalog(&config.catalog_url, config.catalog_public_key.as_deref())?;
+    for map in &mut maps {
+        map.source = None;
+    }
+
+    let mut sources: Vec<(&String, &String)> = config.catalog_sources.iter().collect();
+    sources.sort();
+    for (source, url) in sources {
+        match read_source_catalog(source, url) {
+            Ok(source_maps) => maps.extend(source_maps),
+            // The other catalogs stay usable
+            Err(e) => eprintln!("Failed to fetch the {} catalog: {}", source, e),
+        }
+    }
 
-    let maps: Vec<Map> = response.json()?;
-    let maps = maps.into_iter().filter(|m| m.r#map_type == "map").collect();
+    let maps: Vec<Map> = maps
+        .into_iter()
+        .filter(|m| m.r#map_type == "map" && is_map_allowed(&m.id))
+        .collect();
+    report_conflicts(&maps);
+    Ok(maps)
+}
 
+pub fn fetch_maps() -> Result<Vec<Map>, Box<dyn Error>> {
+    let maps = download_catalog()?;
+    record_catalog(&maps);
+    update_index(&maps);
     Ok(maps)
 }
 
-pub fn find_maps(input: String) -> Option<Vec<Map>> {
-    let maps: Vec<Map> = fetch_maps().ok()?;
+/// Maps matching `input`, only the ones of the catalog named `source` when given
+pub fn find_maps(input: String, source: Option<&str>) -> Option<Vec<Map>> {
+    let in_source = |map: &Map| source.is_none_or(|source| map.source() == source);
+    let maps: Vec<Map> = indexed_maps().ok()?.into_iter().filter(in_source).collect();
     let input: String = input.to_lowercase();
 
     let mut exact_matches: Vec<Map> = vec![];
 
     for map in &maps {
         if map.id.to_lowercase() == input
+            || map.bare_id().to_lowercase() == input
             || map.name.to_lowercase() == input
             || map.tags.iter().any(|tag| tag.to_lowercase() == input)
         {
@@ -55,31 +295,19 @@ pub fn find_maps(input: String) -> Option<Vec<Map>> {
         return Some(exact_matches);
     }
 
-    let keywords: Vec<String> = input.split_whitespace().map(|s| s.to_lowercase()).collect();
-
-    fn match_score(map: &Map, keywords: &[String]) -> usize {
-        let all_fields = format!("{} {} {}", map.id, map.name, map.tags.join(" ")).to_lowercase();
-        keywords.iter().filter(|k| all_fields.contains(*k)).count()
-    }
-
-    let scored: Vec<(Map, usize)> = maps
+    let matches: Vec<Map> = search_maps(&input)
+        .ok()?
         .into_iter()
-        .map(|m| {
-            let score = match_score(&m, &keywords);
-            (m, score)
-        })
-        .filter(|(_, score)| *score > 0)
+        .filter(in_source)
         .collect();
-
-    if scored.is_empty() {
+    if matches.is_empty() {
         return None;
     }
-
-    return Some(scored.into_iter().map(|(m, _)| m).collect());
+    Some(matches)
 }
 
-pub fn select_map(input: String) -> Option<Map> {
-    let matches = match find_maps(input) {
+pub fn select_map(input: String, source: Option<&str>, yes: bool) -> Option<Map> {
+    let matches = match find_maps(input, source) {
         Some(maps) => maps,
         None => {
             println!("No maps found.");
@@ -94,89 +322,438 @@ pub fn select_map(input: String) -> Option<Map> {
 
     if matches.len() == 1 {
         let map = &matches[0];
-        if ask_yes_no(&format!("Play {:?}?", map.name)) {
+        if yes {
+            return Some(map.clone());
+        }
+        if supports_graphics() {
+            print_thumbnail(map.icon.as_deref(), &map.name);
+        }
+        let question = match estimate_map(&map.id) {
+            Some(estimate) => format!("{} Play {:?}?", estimate.describe(), map.name),
+            None => format!("Play {:?}?", map.name),
+        };
+        if ask_yes_no(&question) {
             return Some(map.clone());
         } else {
             println!("Cancelled.");
             return None;
         }
     } else {
-        return select_from_multiple_maps(matches);
+        let map = select_from_multiple_maps(matches)?;
+        if !yes
+            && let Some(estimate) = estimate_map(&map.id)
+            && !ask_yes_no(&format!("{} Continue?", estimate.describe()))
+        {
+            println!("Cancelled.");
+            return None;
+        }
+        return Some(map);
+    }
+}
+
+fn is_map_downloaded(id: &str) -> bool {
+    cached_map_path(id).exists()
+}
+
+/// Pick a catalog map at random, only among the ones never downloaded unless `any` is set,
+/// and ask before playing it after a short teaser
+pub fn select_random_map(tag: Option<&str>, any: bool, yes: bool) -> Option<Map> {
+    let maps: Vec<Map> = match fetch_maps() {
+        Ok(maps) => maps,
+        Err(e) => {
+            eprintln!("Error fetching maps: {}", e);
+            return None;
+        }
+    };
+    let candidates: Vec<Map> = maps
+        .into_iter()
+        .filter(|m| tag.is_none_or(|tag| m.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+        .filter(|m| any || !is_map_downloaded(&m.id))
+        .collect();
+
+    if candidates.is_empty() {
+        match any {
+            true => println!("No maps found."),
+            false => println!("You have played every map already! Use --any to pick among them."),
+        }
+        return None;
+    }
+
+    // Good enough to pick a map, no need for a random number generator
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as usize)
+        .unwrap_or(0);
+    let map = candidates[seed % candidates.len()].clone();
+    if yes {
+        return Some(map);
+    }
+
+    print_thumbnail(map.icon.as_deref(), &map.name);
+    println!("{} ({})", map.name.bold(), map.id);
+    if !map.tags.is_empty() {
+        println!("  {}", map.tags.join(", ").muted());
+    }
+    // The first sentence is enough to tease it
+    let teaser = map
+        .description
+        .split_inclusive(['.', '!', '?'])
+        .next()
+        .unwrap_or_default();
+    println!("  {}\n", teaser.trim());
+
+    let question = match estimate_map(&map.id) {
+        Some(estimate) => format!("{} Play it?", estimate.describe()),
+        None => "Play it?".to_string(),
+    };
+    if ask_yes_no(&question) {
+        Some(map)
+    } else {
+        println!("Cancelled.");
+        None
+    }
+}
+
+/// Download and disk size of a map that is not in the cache yet
+fn estimate_map(id: &str) -> Option<DownloadEstimate> {
+    if is_map_downloaded(id) {
+        return None;
+    }
+    if let Some((source, id)) = id.split_once(':') {
+        return estimate_zip(&source_map_url(source, id).ok()?);
+    }
+    let release = get_map_release().ok()?;
+    let asset = release.find_asset(&format!("{}.zip", id))?;
+    // The release already gives the download size when the zip cannot be inspected
+    estimate_zip(&asset.browser_download_url).or(Some(DownloadEstimate {
+        download: asset.size,
+        disk: None,
+    }))
+}
+
+/// Print the catalog details of a map with its thumbnail
+pub fn print_map_info(map: &Map) {
+    print_thumbnail(map.icon.as_deref(), &map.name);
+    println!("{} ({})", map.name.bold(), map.id);
+    println!("  Minecraft {}", map.version);
+    if let Some(rating) = &map.rating {
+        println!(
+            "  Rated {:.1}/5 by {} players",
+            rating.average, rating.count
+        );
+    }
+    if !map.tags.is_empty() {
+        println!("  Tags: {}", map.tags.join(", "));
+    }
+    if map.leaderboard.is_some() {
+        println!(
+            "  Has a leaderboard, submit your score with `ovl submit {}`",
+            map.id
+        );
     }
+    println!("\n{}", map.description);
 }
 
+/// Download a map zip to the cache and scan it, returns its path
 pub fn download_map(id: String, should_hash: String) -> Result<String, Box<dyn Error>> {
-    let map_path = get_app_support_dir()
-        .unwrap()
-        .join(".cache")
-        .join("games")
-        .join(format!("{}.zip", id));
+    let path = fetch_map_zip(id, should_hash)?;
+    scan_file(Path::new(&path))?;
+    Ok(path)
+}
+
+fn fetch_map_zip(id: String, should_hash: String) -> Result<String, Box<dyn Error>> {
+    let map_path = cached_map_path(&id);
+    fs::create_dir_all(map_path.parent().unwrap())?;
+
+    if let Some(content) = lan_fetch(&should_hash) {
+        replace_file(&map_path, &content)?;
+        let source = format!(
+            "{}/objects/{}",
+            lan_cache_url().unwrap_or_default(),
+            should_hash.trim()
+        );
+        record_map_download(&id, &source, &should_hash);
+        audit(
+            "download",
+            &format!(
+                "{}.zip sha256 {} from the LAN cache",
+                id,
+                should_hash.trim()
+            ),
+        );
+        eprintln!("Downloaded map to: {:?}", map_path);
+        return Ok(map_path.to_str().unwrap().to_string());
+    }
+
+    let torrent = match load_config().torrent_downloads {
+        true => fetch_maps()
+            .ok()
+            .and_then(|maps| maps.into_iter().find(|m| m.id == id)?.torrent),
+        false => None,
+    };
+    if let Some(torrent) = torrent {
+        let downloaded = download_torrent(&torrent, &map_path).and_then(|_| {
+            if getsha256(&map_path)?.trim() != should_hash.trim() {
+                fs::remove_file(&map_path)?;
+                return Err("the downloaded map does not match the release hash".into());
+            }
+            Ok(())
+        });
+        match downloaded {
+            Ok(()) => {
+                audit(
+                    "download",
+                    &format!("{}.zip sha256 {} from {}", id, should_hash.trim(), torrent),
+                );
+                record_map_download(&id, &torrent, &should_hash);
+                eprintln!("Downloaded map to: {:?}", map_path);
+                return Ok(map_path.to_str().unwrap().to_string());
+            }
+            Err(e) => eprintln!("Failed to download the map from its web seeds: {}", e),
+        }
+    }
+
+    let mut last_error: Box<dyn Error> = "No mirror to download the map from".into();
+    for (mirror, url) in map_mirrors(&id) {
+        match download_map_from(&url, &map_path, &should_hash) {
+            Ok(()) => {
+                record_success(mirror);
+                record_map_download(&id, &url, &should_hash);
+                audit(
+                    "download",
+                    &format!("{}.zip sha256 {} from {}", id, should_hash.trim(), url),
+                );
+                eprintln!("Downloaded map to: {:?}", map_path);
+                return Ok(map_path.to_str().unwrap().to_string());
+            }
+            Err(e) => {
+                eprintln!("Failed to download the map from {}: {}", mirror, e);
+                record_failure(mirror);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
 
-    let response = reqwest::blocking::get(format!(
-        "https://github.com/OpenVoxelStudios/Maps/releases/latest/download/{}.zip",
-        id
-    ))?
-    .error_for_status()?;
+fn download_map_from(url: &str, map_path: &Path, should_hash: &str) -> Result<(), Box<dyn Error>> {
+    let file_name = map_path.file_name().unwrap().to_string_lossy().to_string();
+    let id = file_name.trim_end_matches(".zip");
+    loop {
+        let content = net::blocking_get(url)?.error_for_status()?.bytes()?;
+        let hash = sha256::digest(content.as_ref());
+        if hash == should_hash.trim() {
+            replace_file(map_path, &content)?;
+            return Ok(());
+        }
 
-    let mut file = File::create(&map_path)?;
-    let mut content = io::Cursor::new(response.bytes()?);
-    io::copy(&mut content, &mut file)?;
+        let mismatch = HashMismatch {
+            what: &format!("map {}", id),
+            file_name: &file_name,
+            url,
+            algorithm: "SHA256",
+            expected: should_hash.trim(),
+            actual: &hash,
+            can_repin: false,
+        };
+        match mismatch.resolve(&content)? {
+            MismatchChoice::Retry => println!("Downloading the map again..."),
+            MismatchChoice::Repin => unreachable!("maps cannot be re-pinned"),
+        }
+    }
+}
 
-    let local_hash = getsha256(&map_path)?;
-    if local_hash.trim() != should_hash.trim() {
-        fs::remove_file(&map_path)?;
-        return Err(Box::new(io::Error::new(
-            io::ErrorKind::Other,
-            "Downloaded map hash does not match expected hash.",
-        )));
+/// Update a cached map zip in place with a `<id>-<from>-<to>.patch` release asset, where
+/// `from` and `to` are the first 12 characters of the zip hashes. Patches are made with
+/// `zstd --patch-from=<old>.zip <new>.zip`. Returns `false` when no patch is published.
+fn patch_map(
+    id: &str,
+    map_path: &Path,
+    from_hash: &str,
+    to_hash: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let (Some(from), Some(to)) = (from_hash.get(..12), to_hash.get(..12)) else {
+        return Ok(false);
+    };
+    // Patches are only published with the OpenVoxel maps
+    if id.contains(':') {
+        return Ok(false);
     }
 
-    println!("Downloaded map to: {:?}", map_path);
-    Ok(map_path.to_str().unwrap().to_string())
+    let release = get_map_release()?;
+    let Some(asset) = release.find_asset(&format!("{}-{}-{}.patch", id, from, to)) else {
+        return Ok(false);
+    };
+    let response = net::blocking_get(&asset.browser_download_url)?;
+    // Releases looked up without the API do not list their patches
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    let patch = response.error_for_status()?.bytes()?;
+
+    println!("Found an update patch, applying it...");
+    let old = fs::read(map_path)?;
+    let mut decoder = zstd::stream::read::Decoder::with_ref_prefix(patch.as_ref(), &old)?;
+    // The whole old zip is the reference, the window has to reach back to its start
+    decoder.window_log_max(31)?;
+    let mut new = Vec::new();
+    io::copy(&mut decoder, &mut new)?;
+
+    if sha256::digest(&new).trim() != to_hash {
+        return Err("patched map hash does not match expected hash".into());
+    }
+    scan_download(&format!("{}.zip", id), &new)?;
+
+    replace_file(map_path, &new)?;
+    record_map_download(id, &asset.browser_download_url, to_hash);
+    Ok(true)
+}
+
+/// Folder of `saves/` to put a world named `name` in, following `overwrite_policy` when a
+/// world already has that name. `None` means the existing world is used instead.
+pub fn resolve_collision(name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let saves_dir = get_game_dir().join("saves");
+    let existing = saves_dir.join(name);
+    if !existing.exists() {
+        return Ok(Some(name.to_string()));
+    }
+
+    match load_config().overwrite_policy {
+        OverwritePolicy::Ask => {
+            if !ask_yes_no_attended(&format!("Map {} already exists. Overwrite?", name)) {
+                return Ok(None);
+            }
+            audit("delete", &format!("saves/{}", name));
+        }
+        OverwritePolicy::Never => {
+            println!("Map {} already exists, using the existing one.", name);
+            return Ok(None);
+        }
+        OverwritePolicy::Rename => {
+            let free = (2..)
+                .map(|n| format!("{} ({})", name, n))
+                .find(|candidate| !saves_dir.join(candidate).exists())
+                .unwrap();
+            println!("Map {} already exists, installing it as {}.", name, free);
+            return Ok(Some(free));
+        }
+        OverwritePolicy::OverwriteWithBackup => {
+            let backup = backup_world(&get_game_dir(), &existing)?;
+            println!("Backed up the existing {} to {}", name, backup.display());
+            audit(
+                "delete",
+                &format!("saves/{} (backed up to {})", name, backup.display()),
+            );
+        }
+    }
+    Ok(Some(name.to_string()))
 }
 
+/// Extract a map zip into `saves/`. When `overwrite_ask` is false an existing world with the
+/// same name is used as is, otherwise `overwrite_policy` decides.
 pub fn install_map_from_path(
     map_path: PathBuf,
     overwrite_ask: bool,
 ) -> Result<String, Box<dyn Error>> {
-    let _ = ensure_folder_exists(
-        get_app_support_dir()
-            .unwrap()
-            .join(".minecraft")
-            .join("saves")
-            .to_str()
-            .unwrap(),
-    );
+    let _ = ensure_folder_exists(get_game_dir().join("saves").to_str().unwrap());
 
     let root_folder_name = get_root_folder_name(&map_path)?;
-    println!("Extracting map to .minecraft/saves/{}/", root_folder_name);
 
-    let extract_path = get_app_support_dir()
-        .unwrap()
-        .join(".minecraft")
-        .join("saves")
-        .join(&root_folder_name);
-
-    if extract_path.exists() {
-        if overwrite_ask {
-            if !ask_yes_no(&format!(
-                "Map {} already exists. Overwrite?",
-                root_folder_name
-            )) {
-                return Ok(root_folder_name);
-            }
-        } else {
-            println!("Map already exists, using existing map.");
-            return Ok(root_folder_name);
+    if !overwrite_ask
+        && get_game_dir()
+            .join("saves")
+            .join(&root_folder_name)
+            .exists()
+    {
+        println!("Map already exists, using existing map.");
+        return Ok(root_folder_name);
+    }
+    let Some(folder) = resolve_collision(&root_folder_name)? else {
+        return Ok(root_folder_name);
+    };
+
+    println!("Extracting map to .minecraft/saves/{}/", folder);
+    let extract_path = get_game_dir().join("saves").join(&folder);
+
+    let staged = stage_dir(&folder)?;
+    if let Err(e) = extract_zip(&map_path, &staged) {
+        discard_staged(&staged);
+        return Err(e.into());
+    }
+
+    if load_config().verify_worlds {
+        println!("Verifying the extracted world...");
+        let report = verify_world(&staged);
+        report.print(&staged);
+        if !report.is_ok() && !ask_yes_no("The world has problems. Play it anyway?") {
+            discard_staged(&staged);
+            return Err("The extracted world is corrupted".into());
         }
     }
 
-    extract_zip(&map_path, &extract_path)?;
+    commit_staged(&staged, &extract_path)?;
+    return Ok(folder);
+}
 
-    return Ok(root_folder_name);
+/// Copy the `icon.png` of the world in a cached map zip next to the launcher data
+pub fn extract_map_icon(map_id: &str) -> Option<PathBuf> {
+    let zip_path = cached_map_path(map_id);
+    let root = get_root_folder_name(&zip_path).ok()?;
+
+    let mut archive = ZipArchive::new(File::open(&zip_path).ok()?).ok()?;
+    let nested = format!("{}/icon.png", root);
+    let name = match archive.by_name(&nested) {
+        Ok(_) => nested.as_str(),
+        Err(_) => "icon.png",
+    };
+    let mut icon = Vec::new();
+    archive.by_name(name).ok()?.read_to_end(&mut icon).ok()?;
+
+    let path = get_app_support_dir()
+        .unwrap()
+        .join("icons")
+        .join(map_path(map_id, ".png"));
+    fs::create_dir_all(path.parent()?).ok()?;
+    fs::write(&path, icon).ok()?;
+    Some(path)
 }
 
-pub fn install_map(id: String) -> Result<String, Box<dyn Error>> {
+/// Times the published hash of a map is fetched again before the cached zip is replaced
+const HASH_RECHECKS: u32 = 3;
+
+/// Fetch the published hash of a map again when the cached zip does not match it, the hash
+/// file can lag behind the zip on a CDN. Returns the last hash seen.
+fn recheck_map_hash(
+    id: &str,
+    local_hash: &str,
+    expected: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut expected = expected;
+    for attempt in 1..=HASH_RECHECKS {
+        println!(
+            "Map hash does not match, checking the published hash again ({}/{})...",
+            attempt, HASH_RECHECKS
+        );
+        std::thread::sleep(Duration::from_secs(attempt as u64));
+        forget_releases();
+        let fresh = map_hash(id)?;
+        if fresh.trim() == local_hash {
+            println!("The published hash was out of date, the cached map is fine.");
+            return Ok(fresh);
+        }
+        // The same answer twice is not going to change with more asking
+        if fresh.trim() == expected.trim() {
+            break;
+        }
+        expected = fresh;
+    }
+    Ok(expected)
+}
+
+/// Make sure the cached zip of a catalog map is the published one, patching or downloading
+/// it when needed
+pub fn update_cached_map(id: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let id = id.to_string();
     let _ = ensure_folder_exists(
         get_app_support_dir()
             .unwrap()
@@ -186,34 +763,83 @@ pub fn install_map(id: String) -> Result<String, Box<dyn Error>> {
             .unwrap(),
     );
 
-    let map_path = get_app_support_dir()
-        .unwrap()
-        .join(".cache")
-        .join("games")
-        .join(format!("{}.zip", id));
+    let map_path = cached_map_path(&id);
+    fs::create_dir_all(map_path.parent().unwrap())?;
 
-    let expected_hash = reqwest::blocking::get(format!(
-        "https://github.com/OpenVoxelStudios/Maps/releases/latest/download/{}.zip.sha256",
-        id
-    ))?
-    .error_for_status()?;
-    let expected_hash = expected_hash.text()?;
+    let mut expected_hash = map_hash(&id)?;
 
     if map_path.exists() {
         println!("Found cached map, verifying hash...");
         let local_hash = getsha256(&map_path)?;
 
-        if local_hash.trim() == expected_hash.trim() {
+        if local_hash.trim() != expected_hash.trim() {
+            expected_hash = recheck_map_hash(&id, local_hash.trim(), expected_hash)?;
+        }
+
+        let verified = local_hash.trim() == expected_hash.trim();
+        record_map_check(&id, &local_hash, verified);
+        if verified {
             println!("Map is already downloaded and verified.");
+        } else if patch_map(&id, &map_path, local_hash.trim(), expected_hash.trim()).unwrap_or_else(
+            |e| {
+                eprintln!("Failed to apply the map update patch: {}", e);
+                false
+            },
+        ) {
+            println!("Map updated with a patch.");
         } else {
             println!("Map hash does not match, re-downloading...");
-            fs::remove_file(&map_path)?;
-            let _ = download_map(id.clone(), expected_hash.clone());
+            let quarantine = map_path.with_extension("zip.quarantine");
+            fs::rename(&map_path, &quarantine)?;
+            if let Err(e) = download_map(id.clone(), expected_hash.clone()) {
+                // Better the previous zip than none, it is checked again next time
+                fs::rename(&quarantine, &map_path)?;
+                return Err(e);
+            }
+            println!(
+                "The previous zip is kept as {}, delete it once the map works.",
+                quarantine.display()
+            );
         }
     } else {
-        let _ = download_map(id.clone(), expected_hash.clone());
+        download_map(id.clone(), expected_hash.clone())?;
     }
 
+    Ok(map_path)
+}
+
+pub fn install_map(id: String) -> Result<String, Box<dyn Error>> {
+    let map_path = update_cached_map(&id)?;
     let root_folder_name = install_map_from_path(map_path, false)?;
+    record_map_install(&root_folder_name, &id);
     return Ok(root_folder_name);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_paths_keep_other_catalogs_apart() {
+        assert_eq!(
+            map_path("escape-room", ".zip"),
+            PathBuf::from("escape-room.zip")
+        );
+        assert_eq!(
+            map_path("school:escape-room", ".zip"),
+            Path::new("school").join("escape-room.zip")
+        );
+        assert_eq!(
+            map_path("school:escape-room", ""),
+            Path::new("school").join("escape-room")
+        );
+    }
+
+    #[test]
+    fn safe_names_stay_in_their_folder() {
+        assert!(is_safe_name("escape-room_2.1"));
+        for name in ["", ".", "..", ".hidden", "a/b", "a\\b", "a:b", "a?b"] {
+            assert!(!is_safe_name(name), "{:?}", name);
+        }
+    }
+}
@@ -1,20 +1,154 @@
 use fastnbt::{Value, from_reader};
 use flate2::bufread::GzDecoder;
-use open_launcher::{Launcher, version};
-use reqwest;
+use open_launcher::{Launcher, auth, version};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write, stdout};
 use std::path::{Path, PathBuf};
-
-use crate::auth::get_auth;
-use crate::dir::get_minecraft_support_dir;
-use crate::filesys::{getsha256, used_version_save};
-use crate::get_app_support_dir;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use crate::auth::{choose_account, get_auth, get_selected_account_name};
+use crate::cmd::{ask_yes_no, is_non_interactive};
+use crate::compat::{VersionEra, VersionSupport, check_version_support, prepare_legacy_assets};
+use crate::config::{load_config, parse_resolution};
+use crate::dir::{get_game_dir, get_minecraft_support_dir};
+use crate::estimate::estimate_version;
+use crate::filesys::{getsha256, used_fabric_save, used_version_save};
+use crate::forge::{fetch_forge, install_forge};
+use crate::gpu::{check_gpu, gpu_info};
+use crate::install::{remove_partial_downloads, run_cancellable};
 use crate::java::get_java_path;
+use crate::launchcmd::{LaunchSettings, build_launch_command, format_command};
+use crate::lwjgl::override_lwjgl;
+use crate::manifest::record_file;
 use crate::map::{Map, install_map};
-use crate::mods::download_mods;
+use crate::mismatch::HashMismatch;
+use crate::modconfig::apply_mod_configs;
+use crate::mods::{disable_mods, download_mods, restore_disabled_mods};
+use crate::natives::{check_natives, prefer_arm64_natives};
+use crate::net;
+use crate::packs::{apply_server_pack_policy, prune_server_resource_packs};
+use crate::releases::{get_pack_release, get_pack_url};
+use crate::runtime::{ensure_runtime, rosetta_installed};
+use crate::scan::scan_download;
+use crate::session::{
+    SessionReport, SupervisorOptions, parse_duration, played_today, record_playtime, supervise,
+};
+use crate::snapshot::{mark_good, take_snapshot};
+use crate::survey::{print_survey_hint, run_exit_survey};
+use crate::theme::Themed;
+use crate::timings::exit;
+use crate::world::quick_play_folder;
+
+/// Flags shared by every command that launches the game
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct LaunchOptions {
+    #[arg(
+        long,
+        help = "Never overwrite your mod config files with the recommended ones"
+    )]
+    pub keep_my_configs: bool,
+    #[arg(long, help = "Launch without any of the managed mods")]
+    pub no_mods: bool,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with = "no_mods",
+        help = "Only keep these managed mods enabled (e.g. fabric-api,dcwa)"
+    )]
+    pub only_mods: Vec<String>,
+    #[arg(
+        long,
+        help = "Exit right after launching instead of waiting for the game"
+    )]
+    pub detach: bool,
+    #[arg(
+        long,
+        value_parser = parse_duration,
+        help = "Close the game after this much time (e.g. 90m, 1h30m)"
+    )]
+    pub time_limit: Option<Duration>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = parse_player_name,
+        help = "Play with a temporary offline account that is not saved"
+    )]
+    pub guest: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "guest",
+        help = "Pick the account to play with before launching, when there are several"
+    )]
+    pub choose_account: bool,
+    #[arg(
+        long,
+        help = "Print the command line of the game instead of launching it, with the login details redacted"
+    )]
+    pub print_cmd: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "fabric",
+        help = "Mod loader to launch with, the managed mods are skipped with Forge"
+    )]
+    pub loader: Loader,
+    /// Leave the mods, configs and resource pack of the game folder as they are (event profiles, safe mode)
+    #[arg(skip)]
+    pub locked: bool,
+    /// Fabric loader version to use instead of the latest stable one
+    #[arg(skip)]
+    pub fabric_version: Option<String>,
+}
+
+/// Mod loader the game is launched with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Loader {
+    /// The loader of the managed mods
+    #[default]
+    Fabric,
+    /// Runs the Fabric mods too
+    Quilt,
+    /// None of the managed mods, they are Fabric mods
+    Forge,
+}
+
+impl Loader {
+    pub fn name(self) -> &'static str {
+        match self {
+            Loader::Fabric => "Fabric",
+            Loader::Quilt => "Quilt",
+            Loader::Forge => "Forge",
+        }
+    }
+}
+
+fn parse_player_name(input: &str) -> Result<String, String> {
+    let valid = (3..=16).contains(&input.len())
+        && input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    match valid {
+        true => Ok(input.to_string()),
+        false => Err("Player names are 3 to 16 letters, digits or underscores".to_string()),
+    }
+}
+
+impl LaunchOptions {
+    /// Whether some managed mods are disabled for this session, `no_mods` of the config
+    /// disables them all unless `--only-mods` is given. Forge cannot load any of them.
+    pub fn disables_mods(&self) -> bool {
+        self.no_mods
+            || !self.only_mods.is_empty()
+            || load_config().no_mods
+            || self.loader == Loader::Forge
+    }
+
+    /// Whether the launcher has to stay around until the game exits
+    pub fn needs_supervision(&self) -> bool {
+        !self.detach || self.disables_mods() || self.time_limit.is_some()
+    }
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct FabricVersion {
@@ -30,7 +164,7 @@ pub struct FabricVersionId {
 pub async fn fetch_fabric(
     version: String,
 ) -> Result<Vec<FabricVersion>, Box<dyn std::error::Error>> {
-    let response = reqwest::get(format!(
+    let response = net::get(&format!(
         "https://meta.fabricmc.net/v2/versions/loader/{}",
         version
     ))
@@ -46,6 +180,78 @@ pub async fn fetch_fabric(
     Ok(latest)
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuiltVersion {
+    pub loader: QuiltVersionId,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuiltVersionId {
+    pub version: String,
+}
+
+/// Quilt loader versions supporting a Minecraft version, newest first and without the betas
+pub async fn fetch_quilt(version: String) -> Result<Vec<QuiltVersion>, Box<dyn std::error::Error>> {
+    let response = net::get(&format!(
+        "https://meta.quiltmc.org/v3/versions/loader/{}",
+        version
+    ))
+    .await?
+    .error_for_status()?;
+
+    let versions: Vec<QuiltVersion> = response.json().await?;
+    Ok(versions
+        .into_iter()
+        .filter(|v| !v.loader.version.contains('-'))
+        .collect())
+}
+
+/// The loader version to launch `version` with, `None` for vanilla when the loader does not
+/// support it
+async fn latest_loader_version(loader: Loader, version: &str) -> Option<String> {
+    let latest = match loader {
+        Loader::Fabric => match fetch_fabric(version.to_string()).await {
+            Ok(versions) => Ok(versions.first().map(|v| v.loader.version.clone())),
+            Err(_) => {
+                println!("Failed to fetch Fabric version, falling back to 0.16.14");
+                return Some("0.16.14".to_string());
+            }
+        },
+        Loader::Quilt => fetch_quilt(version.to_string())
+            .await
+            .map(|versions| versions.first().map(|v| v.loader.version.clone())),
+        Loader::Forge => fetch_forge(version).await,
+    };
+
+    match latest {
+        Ok(Some(loader_version)) => Some(loader_version),
+        Ok(None) => {
+            println!(
+                "{}",
+                format!(
+                    "Warning: {} does not support Minecraft {} yet, launching vanilla without mods.",
+                    loader.name(),
+                    version
+                )
+                .warning()
+            );
+            None
+        }
+        Err(e) => {
+            println!(
+                "{}",
+                format!(
+                    "Warning: Failed to fetch the {} versions, launching vanilla without mods: {}",
+                    loader.name(),
+                    e
+                )
+                .warning()
+            );
+            None
+        }
+    }
+}
+
 /// Deduplicate libraries by keeping only the highest version of each library
 fn deduplicate_libraries(libraries_dir: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     let mut library_versions: HashMap<String, Vec<(String, PathBuf)>> = HashMap::new();
@@ -217,46 +423,203 @@ fn compare_versions(v1: &str, v2: &str) -> std::cmp::Ordering {
     std::cmp::Ordering::Equal
 }
 
+/// Launch a version, returning what happened in the session when the launcher waited for the
+/// game to exit
 pub async fn launch(
     version: String,
     quick_play_map: Option<&String>,
     quick_play_server: Option<&String>,
-) {
-    let home = get_app_support_dir().unwrap();
-    init_minecraft(&version).await;
-
-    let fabric_version = fetch_fabric(version.clone())
-        .await
-        .ok()
-        .and_then(|versions| versions.first().map(|v| v.loader.version.clone()))
-        .or_else(|| {
-            println!("Failed to fetch Fabric version, falling back to 0.16.14");
-            Some("0.16.14".to_string())
-        });
-
-    println!("Using Fabric version: {}", fabric_version.clone().unwrap());
-    let java_path = get_java_path(&version);
+    options: &LaunchOptions,
+) -> Option<SessionReport> {
+    let game_dir = get_game_dir();
+
+    let daily_limit = match load_config().daily_limit_minutes {
+        0 => None,
+        minutes => {
+            let remaining = Duration::from_secs(minutes * 60).saturating_sub(played_today());
+            if remaining.is_zero() {
+                println!(
+                    "The daily play time limit of {} minutes is reached.",
+                    minutes
+                );
+                return None;
+            }
+            println!(
+                "{} minutes of play time left today.",
+                remaining.as_secs() / 60
+            );
+            Some(remaining)
+        }
+    };
+    if options.choose_account {
+        choose_account();
+    }
+
+    let time_limit = match (options.time_limit, daily_limit) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    let support = match check_version_support(&version).await {
+        Ok(support) => support,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+    if let Some(support) = &support {
+        support.print_notes(&version);
+    }
+
+    // Warn before the install rather than after a crash
+    for issue in check_gpu(&gpu_info(false)) {
+        println!(
+            "{} {}. {}",
+            "Warning:".warning(),
+            issue.problem,
+            issue.guidance
+        );
+    }
+
+    let client_jar = game_dir
+        .join("versions")
+        .join(&version)
+        .join(format!("{}.jar", version));
+    if !is_non_interactive()
+        && !client_jar.exists()
+        && let Some(estimate) = estimate_version(&game_dir, &version).await
+        && estimate.download > 0
+        && !ask_yes_no(&format!("{} Continue?", estimate.describe()))
+    {
+        println!("Cancelled.");
+        return None;
+    }
+
+    // A previous --no-mods session may have been interrupted before restoring
+    if let Err(e) = restore_disabled_mods() {
+        eprintln!("Warning: Failed to restore disabled mods: {}", e);
+    }
+
+    // The managed mods and their configs are Fabric ones
+    if !options.locked && options.loader != Loader::Forge {
+        init_minecraft(&version).await;
+
+        if let Err(e) = apply_mod_configs(options.keep_my_configs).await {
+            eprintln!("Failed to apply recommended mod configs: {}", e);
+        }
+    }
+
+    if options.disables_mods() {
+        match disable_mods(&options.only_mods) {
+            Ok(count) => println!("Disabled {} managed mods for this session.", count),
+            Err(e) => eprintln!("Failed to disable mods: {}", e),
+        }
+    }
+
+    let loader_version = match options.loader {
+        Loader::Fabric if options.locked => options.fabric_version.clone(),
+        loader => latest_loader_version(loader, &version).await,
+    };
+    let loader = loader_version
+        .as_ref()
+        .map(|loader_version| (options.loader, loader_version.as_str()));
+
+    if let Some(loader_version) = &loader_version {
+        println!(
+            "Using {} version: {}",
+            options.loader.name(),
+            loader_version
+        );
+    }
+    let rosetta_java = match &support {
+        Some(support) => rosetta_java_path(&version, support).await,
+        None => None,
+    };
+    let java_path = rosetta_java.unwrap_or_else(|| get_java_path(&version));
     println!("Using Java path: {}", java_path);
 
+    // Forge is installed over the vanilla version, with its own installer
+    let launcher_loader = loader.filter(|(loader, _)| *loader != Loader::Forge);
     println!("");
     let mut launcher = Launcher::new(
-        home.join(".minecraft").to_str().unwrap(),
+        game_dir.to_str().unwrap(),
         &java_path,
         version::Version {
             minecraft_version: version.clone(),
-            loader: Some("fabric".to_string()),
-            loader_version: fabric_version,
+            loader: launcher_loader.map(|(loader, _)| loader.name().to_lowercase()),
+            loader_version: launcher_loader.map(|(_, version)| version.to_string()),
         },
     )
     .await;
 
-    used_version_save(version);
+    used_version_save(version.clone());
+    used_fabric_save(
+        loader_version
+            .as_deref()
+            .filter(|_| options.loader == Loader::Fabric),
+    );
 
     launcher.silence(true);
-    launcher.auth(get_auth());
-    launcher.custom_resolution(1280, 720);
+    let login = match &options.guest {
+        Some(name) => {
+            println!(
+                "Playing as the guest {}, nothing is saved to your accounts.",
+                name
+            );
+            Some(auth::OfflineAuth::new(name))
+        }
+        // The command is only printed, there is no need to log in
+        None if options.print_cmd => None,
+        None => Some(get_auth().await),
+    };
+    // Forge is launched without open_launcher, which only knows Fabric and Quilt
+    let mut forge_login = None;
+    match login {
+        Some(login) if options.loader == Loader::Forge => forge_login = Some(login),
+        Some(login) => launcher.auth(login),
+        None => {}
+    }
+
+    let settings = get_selected_account_name()
+        .filter(|_| options.guest.is_none() && !options.locked)
+        .and_then(|name| load_config().accounts.get(&name).cloned())
+        .unwrap_or_default();
+    let (width, height) = settings
+        .resolution
+        .or_else(|| load_config().resolution)
+        .as_deref()
+        .and_then(|r| {
+            parse_resolution(r)
+                .map_err(|e| eprintln!("Warning: {}", e))
+                .ok()
+        })
+        .unwrap_or((1280, 720));
+    launcher.custom_resolution(width as i32, height as i32);
+    let java_args: Vec<String> = match load_config().max_memory_mb {
+        0 => Vec::new(),
+        mb => vec![format!("-Xmx{}M", mb)],
+    };
+    launcher.custom_java_args(java_args.clone());
+    if let Some(language) = &settings.language
+        && let Err(e) = set_game_option(&game_dir, "lang", language)
+    {
+        eprintln!("Warning: Failed to set the game language: {}", e);
+    }
     // launcher.fullscreen(true);
 
+    // Quick Play only knows folder names, a renamed world is found by its display name
+    let quick_play_map = quick_play_map.filter(|map| !map.is_empty()).map(|map| {
+        let folder = quick_play_folder(&game_dir.join("saves"), map);
+        if folder.is_none() {
+            eprintln!(
+                "Warning: No world named {} in the saves, opening the world list instead.",
+                map
+            );
+        }
+        folder.unwrap_or_default()
+    });
+    let quick_play_map = quick_play_map.as_ref();
+
     if let Some(map) = quick_play_map {
         if !map.is_empty() {
             launcher.quick_play("singleplayer", map);
@@ -294,35 +657,138 @@ pub async fn launch(
         }
     });
 
-    match launcher.install_version().await {
-        Ok(_) => print!("... version install success\n"),
-        Err(e) => println!("An error occurred while installing the version: {}", e),
-    };
+    let install_started = SystemTime::now();
+    let installed = run_cancellable(async {
+        match launcher.install_version().await {
+            Ok(_) => print!("... version install success\n"),
+            Err(e) => println!("An error occurred while installing the version: {}", e),
+        };
 
-    match launcher.install_assets().await {
-        Ok(_) => print!("... assets install success\n"),
-        Err(e) => println!("An error occurred while installing the assets: {}", e),
-    };
+        if let Some(lwjgl_version) = load_config().lwjgl_overrides.get(&version) {
+            match override_lwjgl(&game_dir, &version, lwjgl_version) {
+                Ok(0) => {}
+                Ok(count) => println!(
+                    "Using LWJGL {} for {} libraries (lwjgl_overrides)",
+                    lwjgl_version, count
+                ),
+                Err(e) => eprintln!("Warning: Failed to override the LWJGL version: {}", e),
+            }
+        }
+
+        match prefer_arm64_natives(&game_dir, &version) {
+            Ok(0) => {}
+            Ok(count) => println!("Using arm64 natives instead of {} x86 ones", count),
+            Err(e) => eprintln!("Warning: Failed to select the arm64 natives: {}", e),
+        }
+
+        match launcher.install_assets().await {
+            Ok(_) => print!("... assets install success\n"),
+            Err(e) => println!("An error occurred while installing the assets: {}", e),
+        };
+
+        if let Some(support) = support.as_ref().filter(|s| s.era != VersionEra::Modern) {
+            match prepare_legacy_assets(&game_dir, &support.asset_index) {
+                Ok(0) => {}
+                Ok(count) => println!("Copied {} assets to the legacy layout", count),
+                Err(e) => eprintln!("Failed to prepare the legacy assets: {}", e),
+            }
+        }
+
+        match launcher.install_libraries().await {
+            Ok(_) => print!("... libraries install success\n"),
+            Err(e) => println!("An error occurred while installing the libraries: {}", e),
+        };
+
+        match check_natives(&game_dir, &version) {
+            Ok(report) => report.print(),
+            Err(e) => eprintln!("Warning: Failed to check the natives: {}", e),
+        }
 
-    match launcher.install_libraries().await {
-        Ok(_) => print!("... libraries install success\n"),
-        Err(e) => println!("An error occurred while installing the libraries: {}", e),
+        // Deduplicate libraries to resolve version conflicts (especially ASM library)
+        let libraries_dir = game_dir.join("libraries");
+        if libraries_dir.exists() {
+            println!("Checking for duplicate libraries...");
+            if let Err(e) = deduplicate_libraries(&libraries_dir) {
+                eprintln!("Warning: Failed to deduplicate libraries: {}", e);
+            }
+        }
+    })
+    .await;
+
+    if installed.is_none() {
+        println!("\nInstall cancelled.");
+        let removed = remove_partial_downloads(&game_dir, &version, install_started);
+        if removed > 0 {
+            println!(
+                "Removed {} incomplete files, the next launch resumes the install.",
+                removed
+            );
+        }
+        if let Err(e) = restore_disabled_mods() {
+            eprintln!("Failed to restore disabled mods: {}", e);
+        }
+        return None;
+    }
+
+    if let Some((Loader::Forge, forge)) = loader
+        && let Err(e) = install_forge(&game_dir, &version, forge, &java_path).await
+    {
+        eprintln!(
+            "{}",
+            format!("Failed to install Forge {}: {}", forge, e).error()
+        );
+        if options.disables_mods() {
+            let _ = restore_disabled_mods();
+        }
+        return None;
+    }
+
+    let player_name = match &forge_login {
+        Some(login) => login.username.clone(),
+        None => options
+            .guest
+            .clone()
+            .or_else(get_selected_account_name)
+            .unwrap_or_else(|| "Player".to_string()),
+    };
+    let quick_play = match (quick_play_map, quick_play_server) {
+        (Some(map), _) if !map.is_empty() => Some(("singleplayer", map.as_str())),
+        (None, Some(server)) if !server.is_empty() => Some(("multiplayer", server.as_str())),
+        _ => None,
+    };
+    let settings = LaunchSettings {
+        java_path: &java_path,
+        loader,
+        player_name: &player_name,
+        resolution: (width, height),
+        java_args: &java_args,
+        quick_play,
+        auth: forge_login.as_ref(),
     };
 
-    // Deduplicate libraries to resolve version conflicts (especially ASM library)
-    let libraries_dir = home.join(".minecraft").join("libraries");
-    if libraries_dir.exists() {
-        println!("Checking for duplicate libraries...");
-        if let Err(e) = deduplicate_libraries(&libraries_dir) {
-            eprintln!("Warning: Failed to deduplicate libraries: {}", e);
+    if options.print_cmd {
+        match build_launch_command(&game_dir, &version, &settings).await {
+            Ok(command) => println!("\n{}", format_command(&command)),
+            Err(e) => eprintln!("Failed to build the launch command: {}", e),
+        }
+        if options.disables_mods() {
+            let _ = restore_disabled_mods();
         }
+        return None;
     }
 
-    let process = match launcher.launch() {
+    let process = match settings.auth {
+        Some(_) => launch_forge(&game_dir, &version, &settings)
+            .await
+            .map_err(|e| e.to_string()),
+        None => launcher.launch().map_err(|e| e.to_string()),
+    };
+    let mut process = match process {
         Ok(p) => p,
         Err(e) => {
             println!("An error occurred while launching the game: {}", e);
-            std::process::exit(1);
+            let _ = restore_disabled_mods();
+            exit(1);
         }
     };
 
@@ -330,14 +796,105 @@ pub async fn launch(
         "\nMinecraft launched successfully! Process ID: {}",
         process.id()
     );
+
+    if !options.needs_supervision() && daily_limit.is_none() {
+        return None;
+    }
+
+    println!("Waiting for Minecraft to exit...");
+    let timeout = match load_config().launch_timeout_secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+    // The supervisor polls the game with blocking sleeps for the whole session, this keeps
+    // the other tasks of the runtime running meanwhile
+    let report = tokio::task::block_in_place(|| {
+        supervise(
+            &mut process,
+            &game_dir,
+            SupervisorOptions {
+                window_timeout: timeout,
+                time_limit,
+            },
+        )
+    });
+    record_playtime(report.duration);
+    if !report.crashed() && !options.locked {
+        mark_good();
+    }
+
+    if options.disables_mods() {
+        match restore_disabled_mods() {
+            Ok(()) => println!("Restored the disabled mods."),
+            Err(e) => eprintln!("Failed to restore disabled mods: {}", e),
+        }
+    }
+
+    report.print(&version);
+    Some(report)
 }
 
-pub async fn download_resourcepack() {
-    let resourcepack_path = get_app_support_dir()
-        .unwrap()
-        .join(".minecraft")
-        .join("resourcepacks")
-        .join("OVP.zip");
+/// Start Forge from the command line of its version, quiet like the open_launcher launches
+async fn launch_forge(
+    game_dir: &Path,
+    version: &str,
+    settings: &LaunchSettings<'_>,
+) -> Result<Child, Box<dyn std::error::Error>> {
+    let command = build_launch_command(game_dir, version, settings).await?;
+    Ok(Command::new(&command[0])
+        .args(&command[1..])
+        .current_dir(game_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?)
+}
+
+/// Set one `key:value` line of options.txt, adding it when missing
+fn set_game_option(game_dir: &Path, key: &str, value: &str) -> std::io::Result<()> {
+    let path = game_dir.join("options.txt");
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let prefix = format!("{}:", key);
+
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter(|line| !line.starts_with(&prefix))
+        .map(|line| line.to_string())
+        .collect();
+    lines.push(format!("{}{}", prefix, value));
+    fs::write(path, lines.join("\n") + "\n")
+}
+
+/// On Apple Silicon, offer to run versions without arm64 natives with an x86_64 Java under Rosetta
+async fn rosetta_java_path(version: &str, support: &VersionSupport) -> Option<String> {
+    if !cfg!(target_os = "macos") || support.x86_only_natives.is_empty() {
+        return None;
+    }
+
+    println!(
+        "Minecraft {} has no Apple Silicon natives for: {}",
+        version,
+        support.x86_only_natives.join(", ")
+    );
+    if !ask_yes_no("Run it with an x86_64 Java through Rosetta instead?") {
+        return None;
+    }
+    if !rosetta_installed() {
+        eprintln!("Rosetta is not installed, install it with: softwareupdate --install-rosetta");
+        return None;
+    }
+
+    match ensure_runtime(support.java_version, "x64").await {
+        Ok(path) => Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            eprintln!("Failed to set up the x86_64 Java runtime: {}", e);
+            None
+        }
+    }
+}
+
+/// Download the OpenVoxel resource pack, checked against the SHA256 of its release
+pub async fn download_resourcepack(expected_sha256: &str) {
+    let resourcepack_path = get_game_dir().join("resourcepacks").join("OVP.zip");
 
     // Ensure the resourcepacks directory exists
     if let Some(parent_dir) = resourcepack_path.parent() {
@@ -347,25 +904,62 @@ pub async fn download_resourcepack() {
         }
     }
 
-    match reqwest::get("https://github.com/OpenVoxelStudios/OVP/releases/download/latest/OVP.zip")
-        .await
-    {
-        Ok(response) => {
-            let mut file = File::create(&resourcepack_path).unwrap();
-            let mut content = Cursor::new(response.bytes().await.unwrap());
-            std::io::copy(&mut content, &mut file).unwrap();
+    let url = match get_pack_url() {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Failed to find the resource pack: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        let bytes = match net::get(&url).await {
+            Ok(response) => response.bytes().await.unwrap(),
+            Err(e) => {
+                eprintln!("Failed to download resource pack: {}", e);
+                return;
+            }
+        };
+
+        let hash = sha256::digest(bytes.as_ref());
+        if hash != expected_sha256.trim() {
+            let mismatch = HashMismatch {
+                what: "resource pack",
+                file_name: "OVP.zip",
+                url: &url,
+                algorithm: "SHA256",
+                expected: expected_sha256.trim(),
+                actual: &hash,
+                can_repin: false,
+            };
+            match mismatch.resolve(&bytes) {
+                Ok(_) => {
+                    println!("Downloading the resource pack again...");
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Err(e) = scan_download("OVP.zip", &bytes) {
+            eprintln!("{}", e);
+            return;
         }
-        Err(e) => eprintln!("Failed to download resource pack: {}", e),
+        let mut file = File::create(&resourcepack_path).unwrap();
+        let mut content = Cursor::new(bytes);
+        std::io::copy(&mut content, &mut file).unwrap();
+        record_file(&resourcepack_path, &url);
+        return;
     }
 }
 
 pub async fn init_minecraft(version: &String) {
     let options_exist = get_minecraft_support_dir().unwrap().join("options.txt");
 
-    let options_new = get_app_support_dir()
-        .unwrap()
-        .join(".minecraft")
-        .join("options.txt");
+    let options_new = get_game_dir().join("options.txt");
 
     if !options_new.exists() && options_exist.exists() {
         if let Err(e) = std::fs::copy(options_exist, &options_new) {
@@ -375,30 +969,10 @@ pub async fn init_minecraft(version: &String) {
         }
     }
 
-    let resourcepack_path = get_app_support_dir()
-        .unwrap()
-        .join(".minecraft")
-        .join("resourcepacks")
-        .join("OVP.zip");
+    let resourcepack_path = get_game_dir().join("resourcepacks").join("OVP.zip");
 
-    let resourcepack_shouldsha256 = match reqwest::get(
-        "https://github.com/OpenVoxelStudios/OVP/releases/download/latest/OVP.zip.sha256",
-    )
-    .await
-    {
-        Ok(response) => match response.error_for_status() {
-            Ok(resp) => match resp.text().await {
-                Ok(text) => text,
-                Err(e) => {
-                    eprintln!("Failed to read response text: {}", e);
-                    return;
-                }
-            },
-            Err(e) => {
-                eprintln!("HTTP error: {}", e);
-                return;
-            }
-        },
+    let resourcepack_shouldsha256 = match get_pack_release().and_then(|r| r.sha256("OVP.zip")) {
+        Ok(hash) => hash,
         Err(e) => {
             eprintln!("Failed to fetch resourcepack SHA256: {}", e);
             return;
@@ -416,11 +990,12 @@ pub async fn init_minecraft(version: &String) {
 
         if resourcepack_issha256.trim() != resourcepack_shouldsha256.trim() {
             println!("Resource pack SHA256 mismatch, downloading...");
-            download_resourcepack().await;
+            take_snapshot("before updating the resource pack");
+            download_resourcepack(&resourcepack_shouldsha256).await;
         }
     } else {
         println!("Resource pack not found, downloading...");
-        download_resourcepack().await;
+        download_resourcepack(&resourcepack_shouldsha256).await;
     }
 
     if let Ok(mut options_file) = File::options().read(true).write(true).open(&options_new) {
@@ -460,6 +1035,23 @@ pub async fn init_minecraft(version: &String) {
         }
     }
 
+    let config = load_config();
+    match prune_server_resource_packs(
+        Duration::from_secs(config.server_resource_packs_max_age_days * 24 * 60 * 60),
+        config.server_resource_packs_max_mb * 1024 * 1024,
+    ) {
+        Ok(0) => {}
+        Ok(freed) => println!(
+            "Pruned {} MB of old server resource packs.",
+            freed / 1024 / 1024
+        ),
+        Err(e) => eprintln!("Failed to prune server resource packs: {}", e),
+    }
+
+    if let Err(e) = apply_server_pack_policy(config.server_resource_packs) {
+        eprintln!("Failed to apply the server resource pack policy: {}", e);
+    }
+
     match download_mods(version).await {
         Ok(()) => {}
         Err(e) => eprintln!("Failed to get mod download URLs: {}", e),
@@ -518,7 +1110,7 @@ pub fn get_version_name(level_dat: &Path) -> String {
     "none".to_string()
 }
 
-pub async fn run_map(map: Map) {
+pub async fn run_map(map: Map, options: &LaunchOptions) {
     let map_path = match install_map(map.id.clone()) {
         Ok(value) => value,
         Err(e) => {
@@ -527,6 +1119,9 @@ pub async fn run_map(map: Map) {
         }
     };
 
+    print_survey_hint(&map);
     println!("Launching Minecraft {}...\n", map.version);
-    launch(map.version.clone(), Some(&map_path), None).await;
+    if let Some(report) = launch(map.version.clone(), Some(&map_path), None, options).await {
+        run_exit_survey(&map, &report).await;
+    }
 }
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::Url;
+
+use crate::{
+    config::load_config, dir::get_app_support_dir, net, releases::get_map_release, theme::Themed,
+};
+
+/// Failures in a row after which a mirror is tried last
+const MAX_FAILURES: u32 = 3;
+/// How long a failing mirror stays at the back of the queue
+const COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+/// Where map zips are downloaded from first, the other one is the fallback
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapSource {
+    #[default]
+    Cdn,
+    Github,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+struct MirrorHealth {
+    /// Failures since the last success
+    failures: u32,
+    /// Seconds since the epoch
+    last_failure: u64,
+}
+
+fn get_health_path() -> PathBuf {
+    get_app_support_dir().unwrap().join("mirrors.json")
+}
+
+fn read_health() -> BTreeMap<String, MirrorHealth> {
+    fs::read_to_string(get_health_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_health(health: &BTreeMap<String, MirrorHealth>) {
+    if let Ok(content) = serde_json::to_string_pretty(health) {
+        let _ = fs::write(get_health_path(), content);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn is_down(health: &MirrorHealth) -> bool {
+    health.failures >= MAX_FAILURES && now() < health.last_failure + COOLDOWN.as_secs()
+}
+
+/// Remember a mirror failed, so the next downloads try the other one first
+pub fn record_failure(mirror: &str) {
+    let mut health = read_health();
+    let entry = health.entry(mirror.to_string()).or_default();
+    entry.failures += 1;
+    entry.last_failure = now();
+    write_health(&health);
+}
+
+pub fn record_success(mirror: &str) {
+    let mut health = read_health();
+    if health.remove(mirror).is_some() {
+        write_health(&health);
+    }
+}
+
+/// The mirrors a map zip can be downloaded from, in the order to try them: the preferred
+/// source first unless it has been failing lately
+pub fn map_mirrors(id: &str) -> Vec<(&'static str, String)> {
+    // Maps of other catalogs are only served next to their catalog
+    if let Some((source, id)) = id.split_once(':') {
+        return match source_map_url(source, id) {
+            Ok(url) => vec![("catalog", url)],
+            Err(e) => {
+                eprintln!("{}", e);
+                Vec::new()
+            }
+        };
+    }
+    let config = load_config();
+    let mut mirrors = vec![(
+        "cdn",
+        format!("{}/{}.zip", config.map_cdn_url.trim_end_matches('/'), id),
+    )];
+    // The CDN keeps working when the GitHub API is unreachable or rate limited
+    match get_map_release()
+        .map_err(|e| e.to_string())
+        .and_then(|release| Ok(release.asset(&format!("{}.zip", id))?.browser_download_url))
+    {
+        Ok(url) => mirrors.push(("github", url)),
+        Err(e) => eprintln!("GitHub is not available as a fallback: {}", e),
+    }
+    if config.map_source == MapSource::Github {
+        mirrors.reverse();
+    }
+
+    let health = read_health();
+    // Stable, so the configured order is kept between healthy mirrors
+    mirrors.sort_by_key(|(name, _)| health.get(*name).is_some_and(is_down));
+    mirrors
+}
+
+/// Expected SHA256 of a map zip, from its release or from `<map_cdn_url>/<id>.zip.sha256`
+/// when GitHub cannot be reached
+pub fn map_hash(id: &str) -> Result<String, Box<dyn Error>> {
+    if let Some((source, id)) = id.split_once(':') {
+        let url = format!("{}.sha256", source_map_url(source, id)?);
+        let text = net::blocking_get(&url)?.error_for_status()?.text()?;
+        return Ok(text
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string());
+    }
+    let name = format!("{}.zip", id);
+    match get_map_release().and_then(|release| release.sha256(&name)) {
+        Ok(hash) => Ok(hash),
+        Err(e) => {
+            let url = format!(
+                "{}/{}.sha256",
+                load_config().map_cdn_url.trim_end_matches('/'),
+                name
+            );
+            let text = net::blocking_get(&url)
+                .and_then(|r| r.error_for_status()?.text())
+                .map_err(|_| e)?;
+            Ok(text
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string())
+        }
+    }
+}
+
+/// `<id>.zip` next to the catalog of `catalog_sources` named `source`, with its SHA256 in
+/// `<id>.zip.sha256` like on the CDN
+pub fn source_map_url(source: &str, id: &str) -> Result<String, String> {
+    let config = load_config();
+    let catalog = config
+        .catalog_sources
+        .get(source)
+        .ok_or_else(|| format!("There is no {} catalog in catalog_sources", source))?;
+    Url::parse(catalog)
+        .and_then(|url| url.join(&format!("{}.zip", id)))
+        .map(String::from)
+        .map_err(|e| format!("Invalid URL for the {} catalog: {}", source, e))
+}
+
+/// Mirrors that have been failing, for `ovl doctor --network`
+pub fn print_mirror_health() {
+    for (name, health) in read_health() {
+        let status = if is_down(&health) {
+            "tried last for now".error()
+        } else {
+            "recovering".warning()
+        };
+        println!(
+            "  Map mirror {}: {} failures in a row, {}",
+            name, health.failures, status
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use inquire::Select;
+use reqwest::Url;
+use std::fs;
+
+use crate::audit::audit;
+use crate::cmd::is_non_interactive;
+use crate::scan::{get_quarantine_dir, quarantine_path};
+use crate::theme::Themed;
+
+const ISSUES_URL: &str = "https://github.com/OpenVoxelStudios/CLI/issues/new";
+
+/// A download that does not match the hash it was published or pinned with
+pub struct HashMismatch<'a> {
+    /// What was downloaded, e.g. `map parkour-paradise` or `mod sodium`
+    pub what: &'a str,
+    /// Name of the file, it is kept in the quarantine folder under this name
+    pub file_name: &'a str,
+    pub url: &'a str,
+    /// `SHA256` or `SHA512`
+    pub algorithm: &'a str,
+    pub expected: &'a str,
+    pub actual: &'a str,
+    /// Whether the new hash can replace the pinned one, for `mods.lock`
+    pub can_repin: bool,
+}
+
+/// What to do after a mismatch, when the install goes on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchChoice {
+    Retry,
+    /// Trust the new file and pin its hash instead
+    Repin,
+}
+
+const RETRY: &str = "Download it again";
+const REPIN: &str = "Trust the new file and pin its hash";
+const QUARANTINE: &str = "Keep the file in quarantine and stop";
+const REPORT: &str = "Report it to OpenVoxel and stop";
+
+impl HashMismatch<'_> {
+    fn print(&self, size: usize) {
+        eprintln!(
+            "\n{}",
+            format!("{} hash mismatch for the {}", self.algorithm, self.what).error()
+        );
+        eprintln!("  Expected: {}", self.expected);
+        eprintln!("  Actual:   {}", self.actual);
+        eprintln!("  Source:   {}", self.url);
+        eprintln!("  Size:     {} bytes", size);
+        eprintln!(
+            "{}",
+            "The file is corrupted, was changed after it was published, or something between you and the server altered it."
+                .muted()
+        );
+    }
+
+    fn keep(&self, content: &[u8]) -> Result<String, String> {
+        let path = quarantine_path(self.file_name)?;
+        fs::create_dir_all(get_quarantine_dir())
+            .and_then(|_| fs::write(&path, content))
+            .map_err(|e| format!("Failed to keep {} in quarantine: {}", self.file_name, e))?;
+        Ok(path.display().to_string())
+    }
+
+    fn report_url(&self, size: usize) -> String {
+        let body = format!(
+            "The {} does not match its published hash.\n\n- Source: {}\n- Expected {}: `{}`\n- Actual {}: `{}`\n- Size: {} bytes\n- ovl {} on {}",
+            self.what,
+            self.url,
+            self.algorithm,
+            self.expected,
+            self.algorithm,
+            self.actual,
+            size,
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS
+        );
+        let title = format!("Hash mismatch for the {}", self.what);
+        Url::parse_with_params(ISSUES_URL, [("title", title), ("body", body)])
+            .map(String::from)
+            .unwrap_or_else(|_| ISSUES_URL.to_string())
+    }
+
+    /// Show both hashes, record the mismatch in the audit log and ask what to do. The
+    /// download is kept in quarantine when it stops, which is the answer in non-interactive
+    /// mode.
+    pub fn resolve(&self, content: &[u8]) -> Result<MismatchChoice, String> {
+        let size = content.len();
+        self.print(size);
+        audit(
+            "hash-mismatch",
+            &format!(
+                "{} from {} ({} bytes) expected {} {} got {}",
+                self.file_name,
+                self.url,
+                size,
+                self.algorithm.to_lowercase(),
+                self.expected,
+                self.actual
+            ),
+        );
+
+        let mut choices = vec![RETRY];
+        if self.can_repin {
+            choices.push(REPIN);
+        }
+        choices.extend([QUARANTINE, REPORT]);
+        let choice = match is_non_interactive() {
+            true => QUARANTINE,
+            false => Select::new("What do you want to do?", choices)
+                .prompt()
+                .unwrap_or(QUARANTINE),
+        };
+
+        match choice {
+            RETRY => Ok(MismatchChoice::Retry),
+            REPIN => {
+                audit(
+                    "hash-mismatch",
+                    &format!("{} re-pinned to {}", self.file_name, self.actual),
+                );
+                Ok(MismatchChoice::Repin)
+            }
+            _ => {
+                let kept = self.keep(content)?;
+                audit(
+                    "hash-mismatch",
+                    &format!("{} kept in {}", self.file_name, kept),
+                );
+                if choice == REPORT {
+                    let url = self.report_url(size);
+                    if open::that(&url).is_err() {
+                        println!("Open this link to report it: {}", url);
+                    }
+                }
+                Err(format!(
+                    "The {} does not match its {} hash, it is kept in {}",
+                    self.what, self.algorithm, kept
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use zip::ZipArchive;
+
+use crate::{
+    cmd::ask_yes_no_attended,
+    dir::{get_app_support_dir, get_game_dir},
+    filesys::getsha256,
+    manifest::record_file,
+    mismatch::HashMismatch,
+    net,
+    releases::{CONFIGS_REPO, get_release},
+    scan::scan_file,
+    theme::Themed,
+};
+
+const BUNDLE: &str = "configs.zip";
+
+fn get_config_dir() -> PathBuf {
+    get_game_dir().join("config")
+}
+
+/// Remembers which bundle was applied last, so unchanged bundles are not offered again
+fn get_applied_marker() -> PathBuf {
+    get_config_dir().join(".ovl-configs")
+}
+
+/// Print the lines that only exist on one side, good enough for small config files
+pub fn print_diff(old: &str, new: &str) {
+    let old_lines: HashSet<&str> = old.lines().collect();
+    let new_lines: HashSet<&str> = new.lines().collect();
+
+    for line in old.lines().filter(|l| !new_lines.contains(l)) {
+        println!("{}", format!("- {}", line).error());
+    }
+    for line in new.lines().filter(|l| !old_lines.contains(l)) {
+        println!("{}", format!("+ {}", line).success());
+    }
+}
+
+pub async fn apply_mod_configs(keep_my_configs: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let bundle_path = get_app_support_dir().unwrap().join(".cache").join(BUNDLE);
+    fs::create_dir_all(bundle_path.parent().unwrap())?;
+
+    // The cached bundle is kept until the release publishes another one
+    let release = get_release(CONFIGS_REPO, None)?;
+    let url = release.asset(BUNDLE)?.browser_download_url;
+    let bundle_hash = release.sha256(BUNDLE)?;
+    if !bundle_path.exists() || getsha256(&bundle_path)? != bundle_hash {
+        let content = loop {
+            let content = net::get(&url).await?.error_for_status()?.bytes().await?;
+            let actual = sha256::digest(content.as_ref());
+            if actual == bundle_hash {
+                break content;
+            }
+            HashMismatch {
+                what: "recommended mod configs",
+                file_name: BUNDLE,
+                url: &url,
+                algorithm: "SHA256",
+                expected: &bundle_hash,
+                actual: &actual,
+                can_repin: false,
+            }
+            .resolve(&content)?;
+        };
+        fs::write(&bundle_path, content)?;
+    }
+
+    let applied_hash = fs::read_to_string(get_applied_marker()).unwrap_or_default();
+    if applied_hash.trim() == bundle_hash {
+        return Ok(());
+    }
+    scan_file(&bundle_path)?;
+
+    let mut archive = ZipArchive::new(File::open(&bundle_path)?)?;
+    fs::create_dir_all(get_config_dir())?;
+
+    // Files that are kept are offered again with the next bundle check
+    let mut kept = false;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(relative) = entry.enclosed_name().map(|p| p.to_path_buf()) else {
+            continue;
+        };
+
+        let mut recommended = String::new();
+        entry.read_to_string(&mut recommended)?;
+
+        let target = get_config_dir().join(&relative);
+        if target.exists() {
+            let current = fs::read_to_string(&target).unwrap_or_default();
+            if current == recommended {
+                continue;
+            }
+
+            if keep_my_configs {
+                println!("Keeping your version of config/{}", relative.display());
+                kept = true;
+                continue;
+            }
+
+            println!("\nRecommended changes to config/{}:", relative.display());
+            print_diff(&current, &recommended);
+            if !ask_yes_no_attended(&format!("Overwrite config/{}?", relative.display())) {
+                kept = true;
+                continue;
+            }
+        }
+
+        if let Some(parent_dir) = target.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        fs::write(&target, recommended)?;
+        record_file(&target, &format!("{}#{}", url, entry.name()));
+        println!("Applied recommended config/{}", relative.display());
+    }
+
+    if !kept {
+        fs::write(get_applied_marker(), bundle_hash)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::{dir::get_app_support_dir, net};
+
+const API_BASE: &str = "https://api.modrinth.com/v2";
+const MAX_RETRIES: u32 = 3;
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub struct ModrinthClient {
+    cache_dir: PathBuf,
+}
+
+fn get_cache_dir() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("modrinth")
+}
+
+/// Drop the cached version lists, for lookups that must see what was published in the last hour
+pub fn forget_modrinth_cache() {
+    let _ = std::fs::remove_dir_all(get_cache_dir());
+}
+
+impl ModrinthClient {
+    pub fn new() -> Self {
+        ModrinthClient {
+            cache_dir: get_cache_dir(),
+        }
+    }
+
+    /// List the versions of a project for a game version and loader, newest first
+    pub async fn project_versions(
+        &self,
+        project: &str,
+        game_version: &str,
+        loader: &str,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let cache_file = self
+            .cache_dir
+            .join(format!("{}-{}-{}.json", project, game_version, loader));
+
+        if let Some(cached) = read_cache(&cache_file) {
+            return Ok(cached);
+        }
+
+        let url = format!(
+            "{}/project/{}/version?loaders=[\"{}\"]&game_versions=[\"{}\"]",
+            API_BASE, project, loader, game_version
+        );
+        let json = self.get_json(&url).await?;
+
+        if std::fs::create_dir_all(&self.cache_dir).is_ok() {
+            let _ = std::fs::write(&cache_file, serde_json::to_string(&json)?);
+        }
+
+        Ok(json)
+    }
+
+    /// GET a JSON document, waiting out 429 responses as long as Modrinth tells us to
+    pub async fn get_json(&self, url: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        let mut attempt = 0;
+
+        loop {
+            let response = net::get(url).await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RETRIES {
+                attempt += 1;
+                let wait = response
+                    .headers()
+                    .get("Retry-After")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .unwrap_or(5);
+
+                println!("Modrinth rate limit reached, retrying in {}s...", wait);
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+
+            return Ok(response.error_for_status()?.json().await?);
+        }
+    }
+
+    pub async fn download(&self, url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let response = net::get(url).await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+impl Default for ModrinthClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_cache(path: &PathBuf) -> Option<Value> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let age = SystemTime::now().duration_since(modified).ok()?;
+
+    if age > CACHE_TTL {
+        return None;
+    }
+
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
@@ -1,6 +1,21 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha512};
+use std::collections::{HashMap, HashSet};
 
-use crate::{dir::get_app_support_dir, filesys::get_used_version_save};
+use crate::{
+    cmd::ask_yes_no,
+    config::load_config,
+    dir::{get_app_support_dir, get_game_dir},
+    filesys::get_used_version_save,
+    lancache::lan_fetch,
+    manifest::{forget_file, record_file},
+    mismatch::{HashMismatch, MismatchChoice},
+    modrinth::ModrinthClient,
+    objects::write_shared,
+    scan::scan_download,
+    snapshot::take_snapshot,
+};
 
 pub const MODS: &[&str] = &[
     "dcwa",
@@ -15,47 +30,174 @@ pub const MODS_ID: &[&str] = &[
     "HdwRs3kc", "P7dR8mSH", "gvQqBUqZ", "YL57xq9U", "mOgUt4GM", "AANobbMI", "eXts2L7r",
 ];
 
-#[derive(Debug)]
+/// Optional quality-of-life mods offered in `ovl mods extras`
+pub const EXTRA_MODS: &[(&str, &str)] = &[
+    ("zoomify", "Zoomify - Zoom in with a key"),
+    (
+        "dynamic-fps",
+        "Dynamic FPS - Reduce FPS when the game is in the background",
+    ),
+    (
+        "appleskin",
+        "AppleSkin - Show food and saturation in the HUD",
+    ),
+    ("mouse-tweaks", "Mouse Tweaks - Better inventory handling"),
+    (
+        "replaymod",
+        "Replay Mod - Record sessions and watch them with `ovl replays`",
+    ),
+];
+
+/// Modrinth slug of the controller mod installed with `controller_support`
+pub const CONTROLLER_MOD: &str = "controlify";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ModDownload {
     pub name: String,
+    pub version_id: String,
     pub url: String,
+    pub sha512: String,
+}
+
+/// Exact Modrinth versions resolved for each Minecraft version, so installs stay reproducible
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ModsLock {
+    pub versions: HashMap<String, Vec<ModDownload>>,
+    #[serde(default)]
+    pub extras: HashMap<String, LockedExtras>,
+}
+
+/// Extras are locked separately so the bundled set can be reinstalled without touching them
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LockedExtras {
+    pub selected: Vec<String>,
+    pub mods: Vec<ModDownload>,
+}
+
+pub fn get_mods_dir() -> std::path::PathBuf {
+    get_game_dir().join("mods")
+}
+
+fn get_lock_path() -> std::path::PathBuf {
+    get_app_support_dir().unwrap().join("mods.lock")
+}
+
+pub fn read_lock() -> ModsLock {
+    std::fs::read_to_string(get_lock_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_lock(lock: &ModsLock) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(get_lock_path(), serde_json::to_string_pretty(lock)?)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct ModDependency {
+    pub project_id: String,
+    pub version_id: Option<String>,
+    pub incompatible: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModNode {
+    pub project_id: String,
+    pub download: ModDownload,
+    pub dependencies: Vec<ModDependency>,
+    pub required_by: Vec<String>,
+}
+
+/// What is about to be installed, and everything that looked wrong while resolving it
+#[derive(Debug, Default)]
+pub struct ResolutionPlan {
+    pub mods: Vec<ModNode>,
+    pub issues: Vec<String>,
+}
+
+impl ResolutionPlan {
+    pub fn downloads(&self) -> Vec<ModDownload> {
+        self.mods.iter().map(|m| m.download.clone()).collect()
+    }
+
+    pub fn print(&self) {
+        println!("Resolved {} mods:", self.mods.len());
+        for node in &self.mods {
+            if node.required_by.is_empty() {
+                println!("  {} ({})", node.download.name, node.download.version_id);
+            } else {
+                println!(
+                    "  {} ({}) required by {}",
+                    node.download.name,
+                    node.download.version_id,
+                    node.required_by.join(", ")
+                );
+            }
+        }
+
+        if !self.issues.is_empty() {
+            println!("\n{} issue(s) found:", self.issues.len());
+            for issue in &self.issues {
+                println!("  - {}", issue);
+            }
+        }
+    }
 }
 
 async fn fetch_mod_version_data(
-    client: &reqwest::Client,
+    client: &ModrinthClient,
     mod_id: &str,
     version: &str,
-) -> Result<Option<(String, Vec<String>)>, Box<dyn std::error::Error>> {
-    let url = format!(
-        "https://api.modrinth.com/v2/project/{}/version?loaders=[\"fabric\"]&game_versions=[\"{}\"]",
-        mod_id, version
-    );
-
-    let response = client.get(&url).send().await?;
-    let json: Value = response.json().await?;
+) -> Result<Option<ModNode>, Box<dyn std::error::Error>> {
+    let json: Value = client.project_versions(mod_id, version, "fabric").await?;
 
     if let Some(array) = json.as_array() {
         if let Some(first_obj) = array.first() {
             if let Some(files) = first_obj["files"].as_array() {
                 if let Some(first_file) = files.first() {
-                    if let Some(download_url) = first_file["url"].as_str() {
+                    if let (Some(download_url), Some(version_id)) =
+                        (first_file["url"].as_str(), first_obj["id"].as_str())
+                    {
                         let mut dependencies = Vec::new();
 
                         if let Some(deps) = first_obj["dependencies"].as_array() {
                             for dep in deps {
-                                if let Some(dep_type) = dep["dependency_type"].as_str() {
-                                    if dep_type == "required" {
-                                        if let Some(dep_id) = dep["project_id"].as_str() {
-                                            if !MODS_ID.contains(&dep_id) {
-                                                dependencies.push(dep_id.to_string());
-                                            }
-                                        }
-                                    }
+                                let incompatible = match dep["dependency_type"].as_str() {
+                                    Some("required") => false,
+                                    Some("incompatible") => true,
+                                    _ => continue,
+                                };
+
+                                if let Some(dep_id) = dep["project_id"].as_str() {
+                                    dependencies.push(ModDependency {
+                                        project_id: dep_id.to_string(),
+                                        version_id: dep["version_id"].as_str().map(String::from),
+                                        incompatible,
+                                    });
                                 }
                             }
                         }
 
-                        return Ok(Some((download_url.to_string(), dependencies)));
+                        let download = ModDownload {
+                            name: mod_id.to_string(),
+                            version_id: version_id.to_string(),
+                            url: download_url.to_string(),
+                            sha512: first_file["hashes"]["sha512"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                        };
+
+                        return Ok(Some(ModNode {
+                            project_id: first_obj["project_id"]
+                                .as_str()
+                                .unwrap_or(mod_id)
+                                .to_string(),
+                            download,
+                            dependencies,
+                            required_by: Vec::new(),
+                        }));
                     }
                 }
             }
@@ -65,82 +207,356 @@ async fn fetch_mod_version_data(
     Ok(None)
 }
 
-pub async fn get_mod_download_urls(
+/// Project id of a dependency, using the bundled slug when the dependency is one of ours
+fn dependency_name(project_id: &str) -> String {
+    match MODS_ID.iter().position(|id| *id == project_id) {
+        Some(index) => MODS[index].to_string(),
+        None => project_id.to_string(),
+    }
+}
+
+/// Resolve `roots` and their dependencies, skipping anything listed in `provided`
+pub async fn resolve_mods(
     version: &str,
-) -> Result<Vec<ModDownload>, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let mut download_urls = Vec::new();
-    let mut visited = std::collections::HashSet::new();
-    let mut to_process: Vec<String> = MODS.iter().map(|&s| s.to_string()).collect();
+    roots: &[String],
+    provided: &HashSet<String>,
+) -> Result<ResolutionPlan, Box<dyn std::error::Error>> {
+    let client = ModrinthClient::new();
+    let mut plan = ResolutionPlan::default();
+    // project id -> index in plan.mods
+    let mut by_project: HashMap<String, usize> = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut to_process: Vec<(String, Option<String>)> =
+        roots.iter().rev().map(|s| (s.clone(), None)).collect();
+
+    while let Some((mod_id, parent)) = to_process.pop() {
+        if provided.contains(&mod_id) {
+            continue;
+        }
 
-    while let Some(mod_id) = to_process.pop() {
         if visited.contains(&mod_id) {
+            if let (Some(parent), Some(&index)) = (&parent, by_project.get(&mod_id)) {
+                plan.mods[index].required_by.push(parent.clone());
+            }
             continue;
         }
         visited.insert(mod_id.clone());
 
-        if let Some((download_url, dependencies)) =
-            fetch_mod_version_data(&client, &mod_id, version).await?
-        {
-            download_urls.push(ModDownload {
-                name: mod_id,
-                url: download_url,
-            });
-
-            for dep_id in dependencies {
-                if !visited.contains(&dep_id) {
-                    to_process.push(dep_id);
-                }
+        let Some(mut node) = fetch_mod_version_data(&client, &mod_id, version).await? else {
+            if let Some(parent) = parent {
+                plan.issues.push(format!(
+                    "{} requires {}, which has no Fabric build for {}",
+                    parent, mod_id, version
+                ));
+            }
+            continue;
+        };
+
+        if let Some(&index) = by_project.get(&node.project_id) {
+            let existing = &mut plan.mods[index];
+            if existing.download.version_id != node.download.version_id {
+                plan.issues.push(format!(
+                    "{} resolves to {} but {} is already selected for the same project",
+                    mod_id, node.download.version_id, existing.download.version_id
+                ));
             }
+            if let Some(parent) = parent {
+                existing.required_by.push(parent);
+            }
+            continue;
+        }
+
+        if let Some(parent) = parent {
+            node.required_by.push(parent);
         }
+
+        for dep in node.dependencies.iter().filter(|d| !d.incompatible) {
+            to_process.push((
+                dependency_name(&dep.project_id),
+                Some(node.download.name.clone()),
+            ));
+        }
+
+        by_project.insert(node.project_id.clone(), plan.mods.len());
+        by_project.insert(node.download.name.clone(), plan.mods.len());
+        plan.mods.push(node);
+    }
+
+    for node in &plan.mods {
+        for dep in &node.dependencies {
+            let Some(&index) = by_project.get(&dep.project_id) else {
+                continue;
+            };
+            let other = &plan.mods[index].download;
+
+            if dep.incompatible {
+                plan.issues.push(format!(
+                    "{} is incompatible with {}",
+                    node.download.name, other.name
+                ));
+            } else if let Some(wanted) = &dep.version_id
+                && *wanted != other.version_id
+            {
+                plan.issues.push(format!(
+                    "{} requires {} version {} but {} is selected",
+                    node.download.name, other.name, wanted, other.version_id
+                ));
+            }
+        }
+    }
+
+    Ok(plan)
+}
+
+fn confirm_plan(plan: &ResolutionPlan) -> Result<(), Box<dyn std::error::Error>> {
+    plan.print();
+
+    if !plan.issues.is_empty() && !ask_yes_no("Continue with this resolution plan?") {
+        return Err("Mod resolution cancelled".into());
+    }
+
+    Ok(())
+}
+
+/// Resolve the mods for a version again and replace its entry in `mods.lock`
+pub async fn update_lock(version: &str) -> Result<Vec<ModDownload>, Box<dyn std::error::Error>> {
+    let roots: Vec<String> = MODS.iter().map(|s| s.to_string()).collect();
+    let plan = resolve_mods(version, &roots, &HashSet::new()).await?;
+    confirm_plan(&plan)?;
+
+    let resolved = plan.downloads();
+
+    let mut lock = read_lock();
+    lock.versions.insert(version.to_string(), resolved.clone());
+    write_lock(&lock)?;
+
+    Ok(resolved)
+}
+
+/// Resolve the selected extras, leaving out whatever the bundled mods already provide
+pub async fn update_extras_lock(
+    version: &str,
+    selected: &[String],
+) -> Result<LockedExtras, Box<dyn std::error::Error>> {
+    let mut lock = read_lock();
+
+    let mut provided: HashSet<String> = MODS.iter().map(|s| s.to_string()).collect();
+    if let Some(required) = lock.versions.get(version) {
+        provided.extend(required.iter().map(|m| m.name.clone()));
     }
 
-    Ok(download_urls)
+    let plan = resolve_mods(version, selected, &provided).await?;
+    confirm_plan(&plan)?;
+
+    let extras = LockedExtras {
+        selected: selected.to_vec(),
+        mods: plan.downloads(),
+    };
+    lock.extras.insert(version.to_string(), extras.clone());
+    write_lock(&lock)?;
+
+    Ok(extras)
 }
 
 pub async fn download_mods(version: &str) -> Result<(), Box<dyn std::error::Error>> {
     let previous_version = get_used_version_save();
-    if previous_version.is_some() && previous_version.unwrap() == version {
+    let version_changed = previous_version.as_deref() != Some(version);
+
+    take_snapshot(&format!("before launching {}", version));
+    if version_changed {
+        let locked = match read_lock().versions.get(version) {
+            Some(mods) => mods.clone(),
+            None => {
+                println!("No locked mods for {}, resolving...", version);
+                update_lock(version).await?
+            }
+        };
+
+        install_mods(&locked).await?;
+    } else {
         println!("Mods for version {} already downloaded.", version);
-        return Ok(());
     }
 
+    install_extras(version, version_changed).await
+}
+
+async fn download_mod(
+    client: &ModrinthClient,
+    mod_download: &ModDownload,
+    suffix: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file_name = format!("{}-{}.jar", mod_download.name, suffix);
+    // The LAN cache is looked up with blocking requests, they cannot run on the runtime
+    let hash = mod_download.sha512.clone();
+    let from_lan = tokio::task::spawn_blocking(move || lan_fetch(&hash))
+        .await
+        .ok()
+        .flatten();
+    let mut content = match from_lan {
+        Some(content) => content,
+        None => client.download(&mod_download.url).await?,
+    };
+
+    while !mod_download.sha512.is_empty() {
+        let actual = format!("{:x}", Sha512::digest(&content));
+        if actual == mod_download.sha512 {
+            break;
+        }
+
+        let mismatch = HashMismatch {
+            what: &format!("mod {} ({})", mod_download.name, mod_download.version_id),
+            file_name: &file_name,
+            url: &mod_download.url,
+            algorithm: "SHA512",
+            expected: &mod_download.sha512,
+            actual: &actual,
+            can_repin: true,
+        };
+        match mismatch.resolve(&content)? {
+            MismatchChoice::Retry => content = client.download(&mod_download.url).await?,
+            MismatchChoice::Repin => {
+                repin_mod(&mod_download.url, &actual)?;
+                break;
+            }
+        }
+    }
+
+    let path = get_mods_dir().join(&file_name);
+    scan_download(&file_name, &content)?;
+    write_shared(&path, &content)?;
+    record_file(&path, &mod_download.url);
+    println!("Downloaded mod: {}", mod_download.name);
+
+    Ok(())
+}
+
+/// Pin another hash for every locked download of this URL
+fn repin_mod(url: &str, sha512: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lock = read_lock();
+    let locked = lock
+        .versions
+        .values_mut()
+        .flatten()
+        .chain(lock.extras.values_mut().flat_map(|extras| &mut extras.mods));
+    for mod_download in locked.filter(|m| m.url == url) {
+        mod_download.sha512 = sha512.to_string();
+    }
+    write_lock(&lock)
+}
+
+pub async fn install_mods(mods: &[ModDownload]) -> Result<(), Box<dyn std::error::Error>> {
     // Ensure the mods directory exists
-    let _ = std::fs::create_dir_all(
-        get_app_support_dir()
-            .unwrap()
-            .join(".minecraft")
-            .join("mods"),
-    );
+    let _ = std::fs::create_dir_all(get_mods_dir());
 
     for mod_name in MODS {
-        let file_path = get_app_support_dir()
-            .unwrap()
-            .join(".minecraft")
-            .join("mods")
-            .join(mod_name.to_string() + "-AUTOUPDATE.jar");
+        let file_path = get_mods_dir().join(mod_name.to_string() + "-AUTOUPDATE.jar");
 
         if file_path.exists() {
-            std::fs::remove_file(file_path)?;
+            std::fs::remove_file(&file_path)?;
+            forget_file(&file_path);
         }
     }
 
-    let download_urls: Vec<ModDownload> = get_mod_download_urls(version).await?;
+    let client = ModrinthClient::new();
 
-    for mod_download in download_urls {
-        let response = reqwest::get(&mod_download.url).await?;
-        let content = response.bytes().await?;
+    for mod_download in mods {
+        download_mod(&client, mod_download, "AUTOUPDATE").await?;
+    }
 
-        std::fs::write(
-            get_app_support_dir()
-                .unwrap()
-                .join(".minecraft")
-                .join("mods")
-                .join(mod_download.name.clone() + "-AUTOUPDATE.jar"),
-            content,
-        )?;
-        println!("Downloaded mod: {}", mod_download.name);
+    Ok(())
+}
+
+/// Bring the `-EXTRA.jar` files in line with the extras selected in the config
+pub async fn install_extras(version: &str, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config();
+    let mut selected = config.extra_mods;
+    if config.controller_support && !selected.iter().any(|m| m == CONTROLLER_MOD) {
+        selected.push(CONTROLLER_MOD.to_string());
+    }
+    selected.sort();
+
+    let wanted = if selected.is_empty() {
+        Vec::new()
+    } else {
+        match read_lock().extras.get(version) {
+            Some(extras) if extras.selected == selected => extras.mods.clone(),
+            _ => update_extras_lock(version, &selected).await?.mods,
+        }
+    };
+
+    let _ = std::fs::create_dir_all(get_mods_dir());
+
+    for entry in std::fs::read_dir(get_mods_dir())? {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix("-EXTRA.jar"))
+        else {
+            continue;
+        };
+
+        if force || !wanted.iter().any(|m| m.name == name) {
+            std::fs::remove_file(&path)?;
+            forget_file(&path);
+        }
+    }
+
+    let client = ModrinthClient::new();
+
+    for mod_download in &wanted {
+        let file_path = get_mods_dir().join(format!("{}-EXTRA.jar", mod_download.name));
+        if !file_path.exists() {
+            download_mod(&client, mod_download, "EXTRA").await?;
+        }
     }
 
     Ok(())
 }
+
+fn get_disabled_dir() -> std::path::PathBuf {
+    get_mods_dir().join(".disabled")
+}
+
+/// Name of a launcher-managed jar (`<name>-AUTOUPDATE.jar` or `<name>-EXTRA.jar`)
+fn managed_mod_name(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_suffix("-AUTOUPDATE.jar")
+        .or_else(|| file_name.strip_suffix("-EXTRA.jar"))
+}
+
+/// Move every managed jar not listed in `keep` to `mods/.disabled/` for one session
+pub fn disable_mods(keep: &[String]) -> std::io::Result<usize> {
+    std::fs::create_dir_all(get_disabled_dir())?;
+    let mut disabled = 0;
+
+    for entry in std::fs::read_dir(get_mods_dir())? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        if let Some(name) = managed_mod_name(file_name)
+            && !keep.iter().any(|k| k == name)
+        {
+            std::fs::rename(&path, get_disabled_dir().join(file_name))?;
+            disabled += 1;
+        }
+    }
+
+    Ok(disabled)
+}
+
+/// Put back the jars moved away by `disable_mods`, also after an interrupted session
+pub fn restore_disabled_mods() -> std::io::Result<()> {
+    if !get_disabled_dir().exists() {
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(get_disabled_dir())? {
+        let entry = entry?;
+        std::fs::rename(entry.path(), get_mods_dir().join(entry.file_name()))?;
+    }
+
+    std::fs::remove_dir(get_disabled_dir())
+}
@@ -0,0 +1,237 @@
+use colored::Colorize;
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::theme::Themed;
+
+/// Operating system name as used in the rules of the version JSON
+pub fn current_os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "osx",
+        "windows" => "windows",
+        _ => "linux",
+    }
+}
+
+/// Version of the operating system as Java reports it in `os.version`, which the `os.version`
+/// patterns of the rules are matched against
+pub fn os_version() -> &'static str {
+    static OS_VERSION: OnceLock<String> = OnceLock::new();
+    OS_VERSION.get_or_init(|| {
+        let output = match std::env::consts::OS {
+            "windows" => Command::new("cmd").args(["/C", "ver"]).output().ok(),
+            "macos" => Command::new("sw_vers").arg("-productVersion").output().ok(),
+            _ => {
+                return fs::read_to_string("/proc/sys/kernel/osrelease")
+                    .map(|release| release.trim().to_string())
+                    .unwrap_or_default();
+            }
+        };
+        let text = output
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            .unwrap_or_default();
+        match std::env::consts::OS {
+            // "Microsoft Windows [Version 10.0.22631.3880]", Java only keeps "10.0"
+            "windows" => text
+                .split("Version ")
+                .nth(1)
+                .unwrap_or_default()
+                .split('.')
+                .take(2)
+                .collect::<Vec<_>>()
+                .join("."),
+            _ => text.trim().to_string(),
+        }
+    })
+}
+
+/// Whether the `os` of a rule matches this OS, by name and version
+pub fn rule_os_matches(rule: &Value) -> bool {
+    rule["os"]["name"]
+        .as_str()
+        .is_none_or(|name| name == current_os())
+        && rule["os"]["version"].as_str().is_none_or(|pattern| {
+            Regex::new(pattern).is_ok_and(|version| version.is_match(os_version()))
+        })
+}
+
+pub fn is_arm64() -> bool {
+    std::env::consts::ARCH == "aarch64"
+}
+
+pub fn platform_name() -> String {
+    format!("{}-{}", current_os(), std::env::consts::ARCH)
+}
+
+fn get_version_json_path(game_dir: &Path, version: &str) -> PathBuf {
+    game_dir
+        .join("versions")
+        .join(version)
+        .join(format!("{}.json", version))
+}
+
+/// Whether the `rules` of a library allow it on this OS
+pub fn library_applies(library: &Value) -> bool {
+    let Some(rules) = library["rules"].as_array() else {
+        return true;
+    };
+
+    let mut allowed = false;
+    for rule in rules {
+        if rule_os_matches(rule) {
+            allowed = rule["action"].as_str() == Some("allow");
+        }
+    }
+    allowed
+}
+
+/// `group:artifact:version` and the natives classifier of a library, if it is one
+fn split_native(library: &Value) -> Option<(String, String)> {
+    let name = library["name"].as_str()?;
+    let parts: Vec<&str> = name.split(':').collect();
+
+    if let Some(classifier) = parts.get(3).filter(|c| c.starts_with("natives-")) {
+        return Some((parts[..3].join(":"), classifier.to_string()));
+    }
+
+    let classifier = library["natives"][current_os()].as_str()?;
+    Some((
+        parts[..3.min(parts.len())].join(":"),
+        classifier.to_string(),
+    ))
+}
+
+fn is_arm64_classifier(classifier: &str) -> bool {
+    classifier.ends_with("-arm64") || classifier.ends_with("-aarch64")
+}
+
+/// On arm64, drop the x86 natives of libraries that also ship arm64 ones so only those get loaded.
+/// Returns how many libraries were removed from the version JSON.
+pub fn prefer_arm64_natives(
+    game_dir: &Path,
+    version: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if !is_arm64() {
+        return Ok(0);
+    }
+
+    let path = get_version_json_path(game_dir, version);
+    let mut json: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    let Some(libraries) = json["libraries"].as_array_mut() else {
+        return Ok(0);
+    };
+
+    let arm64: Vec<(String, String)> = libraries
+        .iter()
+        .filter(|library| library_applies(library))
+        .filter_map(split_native)
+        .filter(|(_, classifier)| is_arm64_classifier(classifier))
+        .collect();
+
+    let before = libraries.len();
+    libraries.retain(|library| match split_native(library) {
+        Some((artifact, classifier)) if !is_arm64_classifier(&classifier) => !arm64
+            .iter()
+            .any(|(a, c)| *a == artifact && c.starts_with(&classifier)),
+        _ => true,
+    });
+    let removed = before - libraries.len();
+
+    if removed > 0 {
+        fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(removed)
+}
+
+#[derive(Debug, Default)]
+pub struct NativesReport {
+    /// Native jars the version needs on this platform but that are not on disk
+    pub missing: Vec<String>,
+    /// Libraries that only ship x86 natives while running on arm64
+    pub without_arm64: Vec<String>,
+}
+
+impl NativesReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.without_arm64.is_empty()
+    }
+
+    pub fn print(&self) {
+        if self.is_ok() {
+            return;
+        }
+
+        println!("\n{} ({})", "Natives report".bold(), platform_name());
+        for library in &self.missing {
+            println!("  {} Missing: {}", "!".error(), library);
+        }
+        for library in &self.without_arm64 {
+            println!("  {} No arm64 natives: {}", "!".warning(), library);
+        }
+        if !self.without_arm64.is_empty() {
+            println!("  This version may only start with an x86_64 Java runtime.");
+        }
+    }
+}
+
+/// Natives of a version for the current OS, with the library they belong to
+fn native_libraries(json: &Value) -> Vec<(&Value, String, String)> {
+    json["libraries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|library| library_applies(library))
+        .filter_map(|library| split_native(library).map(|(a, c)| (library, a, c)))
+        .collect()
+}
+
+/// Libraries of a version JSON that only have x86 natives for the current OS
+pub fn x86_only_natives(json: &Value) -> Vec<String> {
+    let natives = native_libraries(json);
+
+    let mut x86_only: Vec<String> = natives
+        .iter()
+        .filter(|(_, artifact, classifier)| {
+            !is_arm64_classifier(classifier)
+                && !natives
+                    .iter()
+                    .any(|(_, a, c)| a == artifact && is_arm64_classifier(c))
+        })
+        .map(|(_, artifact, _)| artifact.clone())
+        .collect();
+    x86_only.dedup();
+    x86_only
+}
+
+/// Check the natives of an installed version against the current platform
+pub fn check_natives(
+    game_dir: &Path,
+    version: &str,
+) -> Result<NativesReport, Box<dyn std::error::Error>> {
+    let json: Value = serde_json::from_str(&fs::read_to_string(get_version_json_path(
+        game_dir, version,
+    ))?)?;
+    let libraries_dir = game_dir.join("libraries");
+    let mut report = NativesReport::default();
+
+    for (library, artifact, classifier) in native_libraries(&json) {
+        let path = library["downloads"]["classifiers"][classifier.as_str()]["path"]
+            .as_str()
+            .or(library["downloads"]["artifact"]["path"].as_str());
+        if let Some(path) = path
+            && !libraries_dir.join(path).exists()
+        {
+            report.missing.push(format!("{}:{}", artifact, classifier));
+        }
+    }
+
+    if is_arm64() {
+        report.without_arm64 = x86_only_natives(&json);
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::exit;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{config::load_config, timings::timed};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_RETRIES: u32 = 2;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+static BLOCKING_CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+
+static IP_VERSION_FLAG: OnceLock<IpVersion> = OnceLock::new();
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+static RETRIES: AtomicU64 = AtomicU64::new(0);
+static ELAPSED_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Sent with every request, Modrinth and GitHub throttle anonymous clients
+pub fn user_agent() -> String {
+    format!(
+        "OpenVoxelStudios/CLI/{} (https://github.com/OpenVoxelStudios/CLI)",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Set `OVL_DEBUG=1` to print every request and how long it took
+pub fn debug_enabled() -> bool {
+    std::env::var_os("OVL_DEBUG").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Which IP stack requests go through, for networks where IPv6 (or IPv4) is broken
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpVersion {
+    #[default]
+    Auto,
+    Ipv4,
+    Ipv6,
+}
+
+/// Force an IP stack for this run, as `--ipv4` and `--ipv6` do. Has to be called before the
+/// first request.
+pub fn use_ip_version(version: IpVersion) {
+    let _ = IP_VERSION_FLAG.set(version);
+}
+
+/// Binding to the unspecified address of one family only lets connections through that stack
+fn local_address() -> Option<IpAddr> {
+    match IP_VERSION_FLAG
+        .get()
+        .copied()
+        .unwrap_or(load_config().ip_version)
+    {
+        IpVersion::Auto => None,
+        IpVersion::Ipv4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        IpVersion::Ipv6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    }
+}
+
+/// Certificates of the `ca_bundle` in the config, for networks that intercept TLS
+fn extra_root_certificates() -> Vec<reqwest::Certificate> {
+    let Some(path) = load_config().ca_bundle else {
+        return Vec::new();
+    };
+    match std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|pem| reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| e.to_string()))
+    {
+        Ok(certificates) => certificates,
+        Err(e) => {
+            eprintln!("Warning: Ignoring the CA bundle {}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// The `proxy` of the config. Requests never go around a proxy that is set, so an invalid one
+/// stops the command instead of being ignored.
+fn configured_proxy() -> Option<reqwest::Proxy> {
+    let proxy = load_config().proxy?;
+    match reqwest::Proxy::all(&proxy) {
+        Ok(proxy) => Some(proxy),
+        Err(e) => {
+            eprintln!("Invalid proxy {} in the config: {}", proxy, e);
+            exit(1);
+        }
+    }
+}
+
+/// A plain client would skip the proxy, CA bundle and IP version of the config
+fn client_failed(e: reqwest::Error) -> ! {
+    eprintln!("Failed to set up the HTTP client: {}", e);
+    exit(1);
+}
+
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        timed("http client", || {
+            let mut builder = reqwest::Client::builder()
+                .user_agent(user_agent())
+                .connect_timeout(CONNECT_TIMEOUT)
+                .read_timeout(READ_TIMEOUT)
+                .local_address(local_address())
+                .tls_built_in_root_certs(load_config().system_certificates);
+            for certificate in extra_root_certificates() {
+                builder = builder.add_root_certificate(certificate);
+            }
+
+            if let Some(proxy) = configured_proxy() {
+                builder = builder.proxy(proxy);
+            }
+
+            builder.build().unwrap_or_else(|e| client_failed(e))
+        })
+    })
+}
+
+pub fn blocking_client() -> &'static reqwest::blocking::Client {
+    BLOCKING_CLIENT.get_or_init(|| {
+        timed("http client", || {
+            let mut builder = reqwest::blocking::Client::builder()
+                .user_agent(user_agent())
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(None)
+                .local_address(local_address())
+                .tls_built_in_root_certs(load_config().system_certificates);
+            for certificate in extra_root_certificates() {
+                builder = builder.add_root_certificate(certificate);
+            }
+
+            if let Some(proxy) = configured_proxy() {
+                builder = builder.proxy(proxy);
+            }
+
+            builder.build().unwrap_or_else(|e| client_failed(e))
+        })
+    })
+}
+
+fn should_retry(result: &Result<reqwest::StatusCode, &reqwest::Error>) -> bool {
+    match result {
+        Ok(status) => status.is_server_error(),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+fn record(method: &str, url: &str, started: Instant, status: Option<reqwest::StatusCode>) {
+    let elapsed = started.elapsed();
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    ELAPSED_MS.fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+
+    if !status.is_some_and(|s| s.is_success()) {
+        FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    if debug_enabled() {
+        let status = status
+            .map(|s| s.as_u16().to_string())
+            .unwrap_or("error".to_string());
+        eprintln!("[debug] {} {} -> {} in {:?}", method, url, status, elapsed);
+    }
+}
+
+/// Send a request, retrying connection errors and 5xx responses
+pub async fn send(
+    mut request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut attempt = 0;
+
+    loop {
+        let next = request.try_clone();
+        let (client, built) = request.build_split();
+        let built = built?;
+        let (method, url) = (built.method().to_string(), built.url().to_string());
+
+        let started = Instant::now();
+        let result = client.execute(built).await;
+        record(
+            &method,
+            &url,
+            started,
+            result.as_ref().ok().map(|r| r.status()),
+        );
+
+        match next {
+            Some(next)
+                if attempt < MAX_RETRIES && should_retry(&result.as_ref().map(|r| r.status())) =>
+            {
+                attempt += 1;
+                RETRIES.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                request = next;
+            }
+            _ => return result,
+        }
+    }
+}
+
+pub async fn get(url: &str) -> Result<reqwest::Response, reqwest::Error> {
+    send(client().get(url)).await
+}
+
+/// Blocking counterpart of `send`, for the synchronous map code
+pub fn blocking_send(
+    mut request: reqwest::blocking::RequestBuilder,
+) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    let mut attempt = 0;
+
+    loop {
+        let next = request.try_clone();
+        let (client, built) = request.build_split();
+        let built = built?;
+        let (method, url) = (built.method().to_string(), built.url().to_string());
+
+        let started = Instant::now();
+        let result = client.execute(built);
+        record(
+            &method,
+            &url,
+            started,
+            result.as_ref().ok().map(|r| r.status()),
+        );
+
+        match next {
+            Some(next)
+                if attempt < MAX_RETRIES && should_retry(&result.as_ref().map(|r| r.status())) =>
+            {
+                attempt += 1;
+                RETRIES.fetch_add(1, Ordering::Relaxed);
+                std::thread::sleep(Duration::from_secs(attempt as u64));
+                request = next;
+            }
+            _ => return result,
+        }
+    }
+}
+
+pub fn blocking_get(url: &str) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    blocking_send(blocking_client().get(url))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NetStats {
+    pub requests: u64,
+    pub failures: u64,
+    pub retries: u64,
+    pub elapsed: Duration,
+}
+
+pub fn stats() -> NetStats {
+    NetStats {
+        requests: REQUESTS.load(Ordering::Relaxed),
+        failures: FAILURES.load(Ordering::Relaxed),
+        retries: RETRIES.load(Ordering::Relaxed),
+        elapsed: Duration::from_millis(ELAPSED_MS.load(Ordering::Relaxed)),
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{
+    config::load_config,
+    dir::get_app_support_dir,
+    map::{Map, fetch_maps},
+    net,
+    theme::Themed,
+};
+
+/// Announcements shown by `ovl news --announcements`
+const MAX_ANNOUNCEMENTS: usize = 10;
+/// Characters of each announcement body shown in the terminal
+const SUMMARY_LENGTH: usize = 280;
+
+/// Date each catalog map was first seen, by map id (empty for the first catalog fetched)
+type SeenMaps = BTreeMap<String, String>;
+
+fn get_seen_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("catalog-seen.json")
+}
+
+fn read_seen() -> Option<SeenMaps> {
+    let content = fs::read_to_string(get_seen_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Remember the maps of a freshly fetched catalog and print a one-line notice about the
+/// ones that are new since the last fetch. Nothing is printed the first time.
+pub fn record_catalog(maps: &[Map]) {
+    let existing = read_seen();
+    let first_time = existing.is_none();
+    let mut seen = existing.unwrap_or_default();
+    let today = Local::now().format("%Y-%m-%d").to_string();
+
+    let new: Vec<&Map> = maps.iter().filter(|m| !seen.contains_key(&m.id)).collect();
+    if new.is_empty() {
+        return;
+    }
+    // The maps already there the first time are not news, they get no date
+    let first_seen = if first_time { String::new() } else { today };
+    for map in &new {
+        seen.insert(map.id.clone(), first_seen.clone());
+    }
+    let path = get_seen_path();
+    let _ = fs::create_dir_all(path.parent().unwrap());
+    if let Ok(content) = serde_json::to_string_pretty(&seen) {
+        let _ = fs::write(path, content);
+    }
+
+    if first_time {
+        return;
+    }
+    let names: Vec<&str> = new.iter().map(|m| m.name.as_str()).collect();
+    let notice = match new.len() {
+        1 => "1 new map since last time".to_string(),
+        count => format!("{} new maps since last time", count),
+    };
+    // Any command can fetch the catalog, stdout may be the JSON of `--json`
+    eprintln!(
+        "{}: {} (ovl info {})",
+        notice.success(),
+        names.join(", "),
+        new[0].id
+    );
+}
+
+#[derive(Serialize)]
+pub struct NewMap {
+    pub date: String,
+    pub id: String,
+    pub name: String,
+    pub version: String,
+}
+
+/// Catalog maps first seen in the last `days` days, newest first
+pub fn recent_maps(days: i64) -> Result<Vec<NewMap>, Box<dyn std::error::Error>> {
+    let maps = fetch_maps()?;
+    let seen = read_seen().unwrap_or_default();
+    let today = Local::now().date_naive();
+
+    let mut recent: Vec<(NaiveDate, &Map)> = maps
+        .iter()
+        .filter_map(|map| {
+            let date = NaiveDate::parse_from_str(seen.get(&map.id)?, "%Y-%m-%d").ok()?;
+            ((today - date).num_days() <= days).then_some((date, map))
+        })
+        .collect();
+    recent.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    Ok(recent
+        .into_iter()
+        .map(|(date, map)| NewMap {
+            date: date.format("%Y-%m-%d").to_string(),
+            id: map.id.clone(),
+            name: map.name.clone(),
+            version: map.version.clone(),
+        })
+        .collect())
+}
+
+pub fn print_news(maps: &[NewMap], days: i64) {
+    if maps.is_empty() {
+        println!("No new maps in the last {} days.", days);
+        return;
+    }
+    for map in maps {
+        println!(
+            "{}  {} ({}), Minecraft {}",
+            map.date.muted(),
+            map.name.bold(),
+            map.id,
+            map.version
+        );
+    }
+}
+
+/// Plain text of an HTML announcement body, cut to `SUMMARY_LENGTH` characters
+fn summarize(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match text.char_indices().nth(SUMMARY_LENGTH) {
+        Some((cut, _)) => format!("{}...", text[..cut].trim_end()),
+        None => text,
+    }
+}
+
+#[derive(Serialize)]
+pub struct Announcement {
+    pub date: String,
+    pub title: String,
+    pub summary: Option<String>,
+    pub link: Option<String>,
+}
+
+/// Latest posts of the studio's RSS or Atom feed, `announcements_url` in the config
+pub fn fetch_announcements() -> Result<Vec<Announcement>, Box<dyn std::error::Error>> {
+    let body = net::blocking_get(&load_config().announcements_url)?
+        .error_for_status()?
+        .bytes()?;
+    let feed = feed_rs::parser::parse(body.as_ref())?;
+
+    Ok(feed
+        .entries
+        .iter()
+        .take(MAX_ANNOUNCEMENTS)
+        .map(|entry| Announcement {
+            date: entry
+                .published
+                .or(entry.updated)
+                .map(|d| d.with_timezone(&Local).format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            title: entry
+                .title
+                .as_ref()
+                .map(|t| t.content.clone())
+                .unwrap_or_default(),
+            summary: entry
+                .summary
+                .as_ref()
+                .map(|s| s.content.as_str())
+                .or_else(|| entry.content.as_ref().and_then(|c| c.body.as_deref()))
+                .map(summarize),
+            link: entry.links.first().map(|link| link.href.clone()),
+        })
+        .collect())
+}
+
+pub fn print_announcements(announcements: &[Announcement]) {
+    if announcements.is_empty() {
+        println!("No announcements yet.");
+        return;
+    }
+    for announcement in announcements {
+        println!(
+            "{}  {}",
+            announcement.date.muted(),
+            announcement.title.bold()
+        );
+        if let Some(summary) = &announcement.summary {
+            println!("  {}", summary);
+        }
+        if let Some(link) = &announcement.link {
+            println!("  {}", link.underline());
+        }
+        println!();
+    }
+}
@@ -0,0 +1,120 @@
+use sha2::{Digest, Sha512};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dir::{get_app_support_dir, get_profiles_dir};
+
+/// Files stored once by their hash and hard-linked wherever they are used
+pub fn get_objects_dir() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("objects")
+}
+
+fn sha512_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha512::digest(content))
+}
+
+/// Link a file into the objects folder under its hash, copying when links are not supported
+pub fn add_object(path: &Path, hash: &str) -> std::io::Result<bool> {
+    let object = get_objects_dir().join(hash);
+    if object.exists() {
+        return Ok(false);
+    }
+    fs::create_dir_all(get_objects_dir())?;
+    if fs::hard_link(path, &object).is_err() {
+        fs::copy(path, &object)?;
+    }
+    Ok(true)
+}
+
+/// Whether two paths are links to the same file
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+/// Put `target` in place as a link to its object, replacing what was there. Linking over
+/// an existing file would write through to every other profile sharing it, so it is
+/// always removed first.
+fn link_object(object: &Path, target: &Path) -> std::io::Result<bool> {
+    let temp = target.with_extension("ovl-link");
+    let _ = fs::remove_file(&temp);
+    if fs::hard_link(object, &temp).is_err() {
+        return Ok(false);
+    }
+    fs::rename(&temp, target)?;
+    Ok(true)
+}
+
+/// Write a downloaded jar through the object store, so profiles with the same mods share
+/// one copy on disk
+pub fn write_shared(target: &Path, content: &[u8]) -> std::io::Result<()> {
+    let object = get_objects_dir().join(sha512_hex(content));
+    if !object.exists() {
+        fs::create_dir_all(get_objects_dir())?;
+        let temp = object.with_extension("part");
+        fs::write(&temp, content)?;
+        fs::rename(&temp, &object)?;
+    }
+
+    let _ = fs::remove_file(target);
+    if !link_object(&object, target)? {
+        fs::write(target, content)?;
+    }
+    Ok(())
+}
+
+/// Every `mods` folder: the shared game folder and each profile
+fn mods_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        get_app_support_dir()
+            .unwrap()
+            .join(".minecraft")
+            .join("mods"),
+    ];
+    if let Ok(entries) = fs::read_dir(get_profiles_dir()) {
+        dirs.extend(entries.flatten().map(|e| e.path().join("mods")));
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+/// Replace the copies of identical jars across profiles with links to a single object.
+/// Returns the number of jars linked and the bytes reclaimed.
+pub fn dedupe_profiles() -> std::io::Result<(usize, u64)> {
+    let (mut linked, mut reclaimed) = (0, 0);
+
+    for dir in mods_dirs() {
+        for path in fs::read_dir(&dir)?.flatten().map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "jar") {
+                continue;
+            }
+            let content = fs::read(&path)?;
+            let hash = sha512_hex(&content);
+            // The first copy becomes the object, it takes no extra space
+            if add_object(&path, &hash)? {
+                continue;
+            }
+            let object = get_objects_dir().join(&hash);
+            if same_file(&object, &path) {
+                continue;
+            }
+            if link_object(&object, &path)? {
+                linked += 1;
+                reclaimed += content.len() as u64;
+            }
+        }
+    }
+    Ok((linked, reclaimed))
+}
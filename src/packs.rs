@@ -0,0 +1,106 @@
+use fastnbt::Value;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::dir::get_game_dir;
+
+/// What the game does when a server pushes a resource pack
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServerPackPolicy {
+    #[default]
+    Prompt,
+    Enabled,
+    Disabled,
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else {
+            files.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete server resource packs older than `max_age`, then the oldest ones until the
+/// folder fits in `max_bytes`. Returns the number of bytes freed.
+pub fn prune_server_resource_packs(max_age: Duration, max_bytes: u64) -> std::io::Result<u64> {
+    let dir = get_game_dir().join("server-resource-packs");
+
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut files = Vec::new();
+    collect_files(&dir, &mut files)?;
+    // Oldest first
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    let now = SystemTime::now();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    let mut freed = 0;
+
+    for (path, size, modified) in files {
+        let too_old = now.duration_since(modified).is_ok_and(|age| age > max_age);
+
+        if too_old || total > max_bytes {
+            fs::remove_file(&path)?;
+            total -= size;
+            freed += size;
+        }
+    }
+
+    Ok(freed)
+}
+
+/// Write the resource pack policy to every server saved in servers.dat
+pub fn apply_server_pack_policy(
+    policy: ServerPackPolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let servers_dat = get_game_dir().join("servers.dat");
+
+    if !servers_dat.exists() {
+        return Ok(());
+    }
+
+    let bytes = fs::read(&servers_dat)?;
+    let mut root: Value = fastnbt::from_bytes(&bytes)?;
+    let mut modified = false;
+
+    if let Value::Compound(root) = &mut root
+        && let Some(Value::List(servers)) = root.get_mut("servers")
+    {
+        for server in servers {
+            let Value::Compound(server) = server else {
+                continue;
+            };
+
+            // With `prompt`, the answers given in the game for each server are kept
+            let wanted = match policy {
+                ServerPackPolicy::Prompt => continue,
+                ServerPackPolicy::Enabled => Value::Byte(1),
+                ServerPackPolicy::Disabled => Value::Byte(0),
+            };
+
+            if server.get("acceptTextures") != Some(&wanted) {
+                server.insert("acceptTextures".to_string(), wanted);
+                modified = true;
+            }
+        }
+    }
+
+    if modified {
+        fs::write(&servers_dat, fastnbt::to_bytes(&root)?)?;
+    }
+
+    Ok(())
+}
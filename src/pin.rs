@@ -0,0 +1,79 @@
+use argon2::Argon2;
+use argon2::password_hash::{
+    PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng,
+};
+use inquire::Password;
+
+use crate::{
+    audit::audit,
+    cmd::check_interactive,
+    config::{load_config, save_config},
+};
+
+/// Wrong PINs in a row before the command gives up
+const MAX_ATTEMPTS: u32 = 3;
+
+fn hash_pin(pin: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash the PIN: {}", e))
+}
+
+fn verify_pin(pin: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(pin.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+fn parse_pin(pin: &str) -> Result<String, String> {
+    let pin = pin.trim();
+    if pin.len() < 4 || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err("The PIN must be at least 4 digits".into());
+    }
+    Ok(pin.to_string())
+}
+
+/// Ask for the parental PIN when one is set, before `action` (e.g. "play as Steve")
+pub fn check_pin(action: &str) -> Result<(), String> {
+    let Some(hash) = load_config().parental_pin else {
+        return Ok(());
+    };
+
+    let question = format!("Parental PIN to {}:", action);
+    check_interactive(&question)?;
+    for _ in 0..MAX_ATTEMPTS {
+        let pin = Password::new(&question)
+            .without_confirmation()
+            .prompt()
+            .map_err(|_| "Cancelled.".to_string())?;
+        if verify_pin(pin.trim(), &hash) {
+            return Ok(());
+        }
+        eprintln!("Wrong PIN.");
+    }
+    audit("pin", &format!("wrong parental PIN to {}", action));
+    Err("Wrong parental PIN".into())
+}
+
+/// Set a new parental PIN, or remove it with `remove`. The current one is asked first.
+pub fn set_pin(remove: bool) -> Result<(), Box<dyn std::error::Error>> {
+    check_pin("change the PIN")?;
+    let mut config = load_config();
+
+    if remove {
+        config.parental_pin = None;
+        audit("pin", "parental PIN removed");
+    } else {
+        check_interactive("New parental PIN:")?;
+        let pin = Password::new("New parental PIN:")
+            .with_custom_confirmation_message("Repeat the PIN:")
+            .prompt()?;
+        config.parental_pin = Some(hash_pin(&parse_pin(&pin)?)?);
+        audit("pin", "parental PIN set");
+    }
+    save_config(&config)
+}
@@ -0,0 +1,162 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cmd::print_output;
+use crate::net;
+use crate::theme::Themed;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub uuid: String,
+    pub name: String,
+    pub skin_url: Option<String>,
+    pub slim: bool,
+    pub cape_url: Option<String>,
+}
+
+/// Format a UUID with dashes, as expected in whitelist.json and ops.json
+pub fn dashed_uuid(uuid: &str) -> String {
+    let uuid = uuid.replace('-', "");
+    if uuid.len() != 32 {
+        return uuid;
+    }
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &uuid[0..8],
+        &uuid[8..12],
+        &uuid[12..16],
+        &uuid[16..20],
+        &uuid[20..32]
+    )
+}
+
+fn looks_like_uuid(input: &str) -> bool {
+    let stripped = input.replace('-', "");
+    stripped.len() == 32 && stripped.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Minecraft usernames are 3 to 16 letters, digits or underscores
+fn is_valid_username(input: &str) -> bool {
+    (3..=16).contains(&input.len()) && input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The skin and cape of a session server profile, a base64 encoded JSON document inside
+/// its properties
+pub fn profile_textures(data: &Value) -> Option<Value> {
+    data["properties"]
+        .as_array()
+        .and_then(|props| props.iter().find(|p| p["name"] == "textures"))
+        .and_then(|p| p["value"].as_str())
+        .and_then(|value| STANDARD.decode(value).ok())
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+}
+
+/// Resolve a username or UUID to a Mojang profile, `None` when nobody has it
+pub async fn lookup_profile(query: &str) -> Result<Option<Profile>, Box<dyn std::error::Error>> {
+    let uuid = if looks_like_uuid(query) {
+        query.replace('-', "")
+    } else if !is_valid_username(query) {
+        return Err(format!("{} is neither a Minecraft username nor a UUID", query).into());
+    } else {
+        let response = net::get(&format!(
+            "https://api.mojang.com/users/profiles/minecraft/{}",
+            query
+        ))
+        .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND
+            || response.status() == reqwest::StatusCode::NO_CONTENT
+        {
+            return Ok(None);
+        }
+
+        let data: Value = response.error_for_status()?.json().await?;
+        data["id"]
+            .as_str()
+            .ok_or("Invalid profile response")?
+            .to_string()
+    };
+
+    let response = net::get(&format!(
+        "https://sessionserver.mojang.com/session/minecraft/profile/{}",
+        uuid
+    ))
+    .await?;
+
+    if response.status() == reqwest::StatusCode::NO_CONTENT
+        || response.status() == reqwest::StatusCode::NOT_FOUND
+    {
+        return Ok(None);
+    }
+
+    let data: Value = response.error_for_status()?.json().await?;
+    let mut profile = Profile {
+        uuid: data["id"].as_str().unwrap_or(&uuid).to_string(),
+        name: data["name"].as_str().unwrap_or_default().to_string(),
+        skin_url: None,
+        slim: false,
+        cape_url: None,
+    };
+
+    if let Some(textures) = profile_textures(&data) {
+        let skin = &textures["textures"]["SKIN"];
+        profile.skin_url = skin["url"].as_str().map(String::from);
+        profile.slim = skin["metadata"]["model"] == "slim";
+        profile.cape_url = textures["textures"]["CAPE"]["url"]
+            .as_str()
+            .map(String::from);
+    }
+
+    Ok(Some(profile))
+}
+
+pub async fn print_lookup(query: &str) {
+    match lookup_profile(query).await {
+        Ok(profile) => print_output(&profile, |profile| match profile {
+            Some(profile) => {
+                println!("{}", profile.name.bold());
+                println!("  UUID: {}", dashed_uuid(&profile.uuid));
+                println!(
+                    "  Skin: {}{}",
+                    profile.skin_url.as_deref().unwrap_or("default"),
+                    if profile.slim { " (slim)" } else { "" }
+                );
+                if let Some(cape) = &profile.cape_url {
+                    println!("  Cape: {}", cape);
+                }
+            }
+            None if looks_like_uuid(query) => println!("No profile found for UUID {}.", query),
+            None => {
+                println!("No player is named {}.", query.bold());
+                // Names of deleted or renamed accounts are held back for a while
+                println!(
+                    "  {}",
+                    "Mojang removed its name history API, so whether the name can be claimed is unknown."
+                        .muted()
+                );
+            }
+        }),
+        Err(e) => eprintln!("Failed to look up {}: {}", query, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_lookups() {
+        assert!(is_valid_username("Notch"));
+        assert!(is_valid_username("a_b_c"));
+        assert!(!is_valid_username("ab"));
+        assert!(!is_valid_username("seventeen_chars__"));
+        assert!(!is_valid_username("../profiles"));
+        assert!(looks_like_uuid("069a79f4-44e9-4726-a5be-fca90e38aaf5"));
+        assert!(looks_like_uuid("069a79f444e94726a5befca90e38aaf5"));
+        assert!(!looks_like_uuid("069a79f444e94726a5befca90e38aafz"));
+    }
+}
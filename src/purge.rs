@@ -0,0 +1,187 @@
+use colored::Colorize;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    auth::{fetch_file, get_selected_account_name},
+    cmd::ask_no_yes,
+    dir::get_app_support_dir,
+    estimate::format_size,
+    shortcut::find_shortcuts,
+    steam::{count_steam_shortcuts, remove_steam_shortcuts},
+    theme::Themed,
+    uri::{unregister_uri_handler, uri_handler_location},
+    vanilla::{exported_profiles, remove_exported_profiles},
+};
+
+/// Folders left in the temporary folder by `--safe` launches and `verify-map`
+const TEMP_PREFIXES: &[&str] = &["ovl-safe-", "ovl-verify-"];
+
+type Removal = Box<dyn FnOnce() -> Result<(), Box<dyn Error>>>;
+
+/// Something the launcher created, and how to remove it
+struct Item {
+    description: String,
+    remove: Removal,
+}
+
+impl Item {
+    fn new(
+        description: String,
+        remove: impl FnOnce() -> Result<(), Box<dyn Error>> + 'static,
+    ) -> Self {
+        Item {
+            description,
+            remove: Box::new(remove),
+        }
+    }
+
+    fn folder(label: &str, path: PathBuf) -> Option<Self> {
+        if !path.exists() {
+            return None;
+        }
+        let description = format!(
+            "{} {} ({})",
+            label,
+            path.display(),
+            format_size(dir_size(&path))
+        );
+        Some(Item::new(description, move || {
+            Ok(fs::remove_dir_all(&path)?)
+        }))
+    }
+}
+
+/// Size of a folder on disk, links are not followed
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| dir_size(&e.path()))
+        .sum()
+}
+
+fn temp_dirs() -> Vec<PathBuf> {
+    fs::read_dir(std::env::temp_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            TEMP_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|e| e.path())
+        .collect()
+}
+
+/// What `purge` removes: only the caches, or with `all` everything the launcher ever created
+fn collect_items(all: bool) -> Vec<Item> {
+    let app_dir = get_app_support_dir().unwrap();
+    if !all {
+        return Item::folder("Caches", app_dir.join(".cache"))
+            .into_iter()
+            .collect();
+    }
+
+    let mut items = Vec::new();
+
+    // The tokens are in the system keyring, the accounts file says which ones are there
+    if get_selected_account_name().is_some() {
+        for account in fetch_file(false).accounts {
+            if account.offline {
+                continue;
+            }
+            items.push(Item::new(
+                format!("Sign-in of {} in the system keyring", account.name),
+                move || Ok(account.delete_access_token()?),
+            ));
+        }
+    }
+
+    if let Some(location) = uri_handler_location() {
+        items.push(Item::new(
+            format!("ovl:// link handler ({})", location),
+            unregister_uri_handler,
+        ));
+    }
+
+    for path in find_shortcuts() {
+        items.push(Item::new(
+            format!("Desktop shortcut {}", path.display()),
+            move || Ok(fs::remove_file(&path)?),
+        ));
+    }
+
+    let steam_shortcuts = count_steam_shortcuts();
+    if steam_shortcuts > 0 {
+        items.push(Item::new(
+            format!(
+                "{} OpenVoxel shortcut(s) in the Steam library (Steam has to be closed)",
+                steam_shortcuts
+            ),
+            || remove_steam_shortcuts().map(|_| ()),
+        ));
+    }
+
+    let profiles = exported_profiles();
+    if !profiles.is_empty() {
+        items.push(Item::new(
+            format!("Official launcher profiles: {}", profiles.join(", ")),
+            remove_exported_profiles,
+        ));
+    }
+
+    items.extend(
+        temp_dirs()
+            .into_iter()
+            .filter_map(|dir| Item::folder("Temporary folder", dir)),
+    );
+
+    // Last, everything above is found through it
+    items.extend(Item::folder(
+        "Launcher folder (accounts, settings, profiles, worlds and caches)",
+        app_dir,
+    ));
+    items
+}
+
+/// List what the launcher created, and remove it once confirmed
+pub fn purge(all: bool) -> Result<(), Box<dyn Error>> {
+    let items = collect_items(all);
+    if items.is_empty() {
+        println!("Nothing to remove.");
+        return Ok(());
+    }
+
+    println!("{}", "This removes:".bold());
+    for item in &items {
+        println!("  - {}", item.description);
+    }
+    if !ask_no_yes("Remove all of this? It cannot be undone") {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for item in items {
+        match (item.remove)() {
+            Ok(()) => println!("{} {}", "Removed".success(), item.description),
+            Err(e) => {
+                eprintln!("Failed to remove {}: {}", item.description, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} item(s) could not be removed", failed).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use serde_json::json;
+
+use crate::{auth::prove_online_session, map::fetch_maps, net};
+
+const RATINGS_API: &str = "https://openvoxel.studio/api/ratings";
+
+/// Rate a catalog map from 1 to 5 with the selected Microsoft account
+pub async fn rate_map(
+    map_id: &str,
+    rating: u8,
+    comment: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let map = fetch_maps()?
+        .into_iter()
+        .find(|m| m.id.eq_ignore_ascii_case(map_id))
+        .ok_or_else(|| format!("Unknown map: {}", map_id))?;
+    if map.source.is_some() {
+        return Err(format!(
+            "{} is not an OpenVoxel map, it cannot be rated here",
+            map.name
+        )
+        .into());
+    }
+
+    let (account, session) = prove_online_session().await?;
+    net::send(
+        net::client()
+            .post(format!("{}/{}", RATINGS_API, map.id))
+            .json(&json!({
+                "session": session,
+                "rating": rating,
+                "comment": comment,
+                "version": map.version,
+            })),
+    )
+    .await?
+    .error_for_status()?;
+
+    println!(
+        "{} rated {} {}/5, thanks for the feedback!",
+        account.name, map.name, rating
+    );
+    Ok(())
+}
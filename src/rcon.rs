@@ -0,0 +1,63 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const LOGIN: i32 = 3;
+const COMMAND: i32 = 2;
+
+/// Minimal client for the Source RCON protocol spoken by Minecraft servers
+pub struct Rcon {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl Rcon {
+    pub fn connect(port: u16, password: &str) -> Result<Rcon, Box<dyn std::error::Error>> {
+        let stream = TcpStream::connect(("127.0.0.1", port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+        let mut rcon = Rcon { stream, next_id: 1 };
+        let (id, _) = rcon.request(LOGIN, password)?;
+        if id == -1 {
+            return Err("RCON login refused, check rcon.password in server.properties".into());
+        }
+
+        Ok(rcon)
+    }
+
+    /// Run a console command and return its output
+    pub fn command(&mut self, command: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.request(COMMAND, command)?.1)
+    }
+
+    fn request(
+        &mut self,
+        kind: i32,
+        body: &str,
+    ) -> Result<(i32, String), Box<dyn std::error::Error>> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut packet = Vec::with_capacity(body.len() + 14);
+        packet.extend_from_slice(&(body.len() as i32 + 10).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet)?;
+
+        let mut length = [0; 4];
+        self.stream.read_exact(&mut length)?;
+        let length = i32::from_le_bytes(length);
+        if !(10..=4096 + 10).contains(&length) {
+            return Err(format!("Invalid RCON packet length: {}", length).into());
+        }
+
+        let mut response = vec![0; length as usize];
+        self.stream.read_exact(&mut response)?;
+        let id = i32::from_le_bytes(response[0..4].try_into()?);
+        let body = String::from_utf8_lossy(&response[8..response.len() - 2]).to_string();
+
+        Ok((id, body))
+    }
+}
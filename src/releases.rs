@@ -0,0 +1,336 @@
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{config::load_config, dir::get_app_support_dir, net};
+
+const API_URL: &str = "https://api.github.com/repos";
+const DOWNLOAD_URL: &str = "https://github.com";
+const ORGANIZATION: &str = "OpenVoxelStudios";
+pub const MAPS_REPO: &str = "Maps";
+pub const PACK_REPO: &str = "OVP";
+pub const CONFIGS_REPO: &str = "Configs";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    pub assets: Vec<ReleaseAsset>,
+    /// Set when GitHub's API could not be reached: the assets are not known, they are
+    /// downloaded by name from this URL
+    #[serde(skip)]
+    download_base: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+    /// `sha256:<hex>`, GitHub computes it for the assets uploaded since mid 2025
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// A release saved on disk with the ETag GitHub sent for it
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedRelease {
+    etag: Option<String>,
+    release: Release,
+}
+
+/// What GitHub answered for a release
+enum Lookup {
+    Found(Release, Option<String>),
+    /// Same ETag as the saved copy, these answers do not count against the rate limit
+    NotModified,
+    Missing(String),
+}
+
+/// Releases already looked up in this run, anonymous API calls are limited to 60 an hour
+static RELEASES: OnceLock<Mutex<HashMap<String, Release>>> = OnceLock::new();
+
+/// Look the releases up again on their next use, when one may have changed during the run
+pub fn forget_releases() {
+    if let Some(cache) = RELEASES.get() {
+        cache.lock().unwrap().clear();
+    }
+}
+
+fn api_get(url: &str, etag: Option<&str>) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    let mut request = net::blocking_client()
+        .get(url)
+        .header("Accept", "application/vnd.github+json");
+    if let Some(token) = load_config().github_token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request.send()?;
+
+    let rate_limited = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .is_some_and(|remaining| remaining == "0");
+    if rate_limited
+        && matches!(
+            response.status(),
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        )
+    {
+        return Err(
+            "GitHub's rate limit is reached, set github_token in the config to raise it".into(),
+        );
+    }
+    Ok(response)
+}
+
+/// A release of one of the OpenVoxelStudios repositories: the tag `pin` when given, else the
+/// newest one (prereleases included when `prereleases` is set in the config)
+pub fn get_release(repo: &str, pin: Option<&str>) -> Result<Release, Box<dyn Error>> {
+    fetch_release(
+        &format!("{}/{}", ORGANIZATION, repo),
+        pin,
+        load_config().prereleases,
+        true,
+    )
+}
+
+/// A release of any GitHub repository (`owner/repo`): the tag `pin` when given, else the
+/// latest one
+pub fn get_repo_release(repo: &str, pin: Option<&str>) -> Result<Release, Box<dyn Error>> {
+    fetch_release(repo, pin, false, false)
+}
+
+fn get_cached_release_path(repo: &str, pin: Option<&str>, prereleases: bool) -> PathBuf {
+    let key = match (pin, prereleases) {
+        (Some(tag), _) => format!("{}@{}", repo, tag),
+        (None, true) => format!("{}@prerelease", repo),
+        (None, false) => format!("{}@latest", repo),
+    };
+    let name: String = key
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '@') {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("releases")
+        .join(format!("{}.json", name))
+}
+
+fn read_cached_release(path: &Path) -> Option<CachedRelease> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_cached_release(path: &Path, cached: &CachedRelease) {
+    let _ = fs::create_dir_all(path.parent().unwrap());
+    if let Ok(content) = serde_json::to_string(cached) {
+        let _ = fs::write(path, content);
+    }
+}
+
+fn request_release(
+    repo: &str,
+    pin: Option<&str>,
+    prereleases: bool,
+    etag: Option<&str>,
+) -> Result<Lookup, Box<dyn Error>> {
+    let url = match (pin, prereleases) {
+        (Some(tag), _) => format!("{}/{}/releases/tags/{}", API_URL, repo, tag),
+        (None, true) => format!("{}/{}/releases", API_URL, repo),
+        (None, false) => format!("{}/{}/releases/latest", API_URL, repo),
+    };
+    let response = api_get(&url, etag)?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED if etag.is_some() => return Ok(Lookup::NotModified),
+        StatusCode::NOT_FOUND => {
+            return Ok(Lookup::Missing(match pin {
+                Some(tag) => format!("{} has no release {}", repo, tag),
+                None => format!("{} has no release yet", repo),
+            }));
+        }
+        _ => {}
+    }
+
+    let response = response.error_for_status()?;
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let release = match pin.is_none() && prereleases {
+        true => {
+            let releases: Vec<Release> = response.json()?;
+            match releases.into_iter().find(|r| !r.draft) {
+                Some(release) => release,
+                None => return Ok(Lookup::Missing(format!("{} has no release yet", repo))),
+            }
+        }
+        false => response.json()?,
+    };
+    Ok(Lookup::Found(release, etag))
+}
+
+/// Look a release up, asking GitHub whether the copy saved on disk changed. The saved copy
+/// is used when GitHub cannot be reached, and with `fallback` the assets are downloaded by
+/// name from the release page when there is no saved copy either.
+fn fetch_release(
+    repo: &str,
+    pin: Option<&str>,
+    prereleases: bool,
+    fallback: bool,
+) -> Result<Release, Box<dyn Error>> {
+    let key = format!("{}@{}", repo, pin.unwrap_or_default());
+    let cache = RELEASES.get_or_init(Default::default);
+    if let Some(release) = cache.lock().unwrap().get(&key) {
+        return Ok(release.clone());
+    }
+
+    let path = get_cached_release_path(repo, pin, prereleases);
+    let saved = read_cached_release(&path);
+    let etag = saved.as_ref().and_then(|s| s.etag.as_deref());
+    let release = match request_release(repo, pin, prereleases, etag) {
+        Ok(Lookup::Found(release, etag)) => {
+            let cached = CachedRelease { etag, release };
+            write_cached_release(&path, &cached);
+            cached.release
+        }
+        Ok(Lookup::NotModified) => saved.unwrap().release,
+        Ok(Lookup::Missing(message)) => return Err(message.into()),
+        Err(e) => match saved {
+            Some(saved) => {
+                eprintln!(
+                    "Could not look the release of {} up ({}), using the saved copy.",
+                    repo, e
+                );
+                saved.release
+            }
+            None if fallback => {
+                eprintln!(
+                    "Could not look the release of {} up ({}), downloading from the release page.",
+                    repo, e
+                );
+                let base = match pin {
+                    Some(tag) => format!("{}/{}/releases/download/{}", DOWNLOAD_URL, repo, tag),
+                    None => format!("{}/{}/releases/latest/download", DOWNLOAD_URL, repo),
+                };
+                Release {
+                    tag_name: pin.unwrap_or("latest").to_string(),
+                    draft: false,
+                    assets: vec![],
+                    download_base: Some(base),
+                }
+            }
+            None => return Err(e),
+        },
+    };
+
+    cache.lock().unwrap().insert(key, release.clone());
+    Ok(release)
+}
+
+/// Zip of the files of a repository at a tag or branch, the default branch when `None`
+pub fn download_repo_archive(
+    repo: &str,
+    reference: Option<&str>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let url = match reference {
+        Some(reference) => format!("{}/{}/zipball/{}", API_URL, repo, reference),
+        None => format!("{}/{}/zipball", API_URL, repo),
+    };
+    let response = api_get(&url, None)?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(format!(
+            "{} was not found on GitHub (private repositories need github_token)",
+            repo
+        )
+        .into());
+    }
+    Ok(response.error_for_status()?.bytes()?.to_vec())
+}
+
+/// The release maps are installed from, `map_release` in the config pins one
+pub fn get_map_release() -> Result<Release, Box<dyn Error>> {
+    get_release(MAPS_REPO, load_config().map_release.as_deref())
+}
+
+/// Tag of the map release already looked up in this run, without asking GitHub
+pub fn cached_map_release() -> Option<String> {
+    let key = format!(
+        "{}/{}@{}",
+        ORGANIZATION,
+        MAPS_REPO,
+        load_config().map_release.unwrap_or_default()
+    );
+    let cache = RELEASES.get()?.lock().unwrap();
+    cache
+        .get(&key)
+        .filter(|release| release.download_base.is_none())
+        .map(|release| release.tag_name.clone())
+}
+
+/// The release of the OpenVoxel resource pack, `pack_release` in the config
+pub fn get_pack_release() -> Result<Release, Box<dyn Error>> {
+    get_release(PACK_REPO, Some(&load_config().pack_release))
+}
+
+/// Download URL of the resource pack in its configured release
+pub fn get_pack_url() -> Result<String, Box<dyn Error>> {
+    let release = get_pack_release()?;
+    Ok(release.asset("OVP.zip")?.browser_download_url)
+}
+
+impl Release {
+    pub fn find_asset(&self, name: &str) -> Option<ReleaseAsset> {
+        if let Some(base) = &self.download_base {
+            return Some(ReleaseAsset {
+                name: name.to_string(),
+                size: 0,
+                browser_download_url: format!("{}/{}", base, name),
+                digest: None,
+            });
+        }
+        self.assets.iter().find(|a| a.name == name).cloned()
+    }
+
+    pub fn asset(&self, name: &str) -> Result<ReleaseAsset, String> {
+        self.find_asset(name)
+            .ok_or_else(|| format!("The release {} has no {}", self.tag_name, name))
+    }
+
+    /// Expected SHA256 of an asset, from its GitHub digest or its `.sha256` companion asset
+    pub fn sha256(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let asset = self.asset(name)?;
+        if let Some(hash) = asset
+            .digest
+            .as_deref()
+            .and_then(|d| d.strip_prefix("sha256:"))
+        {
+            return Ok(hash.to_string());
+        }
+
+        let companion = self.asset(&format!("{}.sha256", name))?;
+        let text = net::blocking_get(&companion.browser_download_url)?
+            .error_for_status()?
+            .text()?;
+        // `sha256sum` output also has the file name after the hash
+        Ok(text
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string())
+    }
+}
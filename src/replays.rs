@@ -0,0 +1,157 @@
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::{
+    cmd::ask_yes_no,
+    config::{load_config, save_config},
+    dir::get_game_dir,
+    mc::{LaunchOptions, launch},
+    theme::Themed,
+};
+
+/// Modrinth slug of the Replay Mod, offered in `ovl mods extras`
+pub const REPLAY_MOD: &str = "replaymod";
+
+/// `metaData.json` written by the Replay Mod inside each `.mcpr` file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayMetadata {
+    #[serde(default)]
+    server_name: Option<String>,
+    /// Length of the recording in milliseconds
+    #[serde(default)]
+    duration: u64,
+    mcversion: String,
+}
+
+pub fn get_replays_dir() -> PathBuf {
+    get_game_dir().join("replay_recordings")
+}
+
+fn read_metadata(path: &Path) -> Result<ReplayMetadata, Box<dyn std::error::Error>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let metadata = archive.by_name("metaData.json")?;
+    Ok(serde_json::from_reader(metadata)?)
+}
+
+/// Recorded replays, newest first
+fn find_replays() -> Vec<PathBuf> {
+    let mut replays: Vec<PathBuf> = fs::read_dir(get_replays_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "mcpr"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    replays.sort_by_key(|p| std::cmp::Reverse(p.metadata().and_then(|m| m.modified()).ok()));
+    replays
+}
+
+/// A recorded replay, without its details when the metadata could not be read
+#[derive(Serialize)]
+pub struct Replay {
+    pub name: String,
+    pub recorded: String,
+    /// Length of the recording in milliseconds
+    pub duration: Option<u64>,
+    pub minecraft: Option<String>,
+    pub server: Option<String>,
+}
+
+pub fn list_replays() -> Vec<Replay> {
+    find_replays()
+        .into_iter()
+        .map(|path| {
+            let metadata = read_metadata(&path).ok();
+            Replay {
+                name: path.file_stem().unwrap().to_string_lossy().to_string(),
+                recorded: path
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .map(|t| {
+                        DateTime::<Local>::from(t)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default(),
+                duration: metadata.as_ref().map(|m| m.duration),
+                minecraft: metadata.as_ref().map(|m| m.mcversion.clone()),
+                server: metadata.and_then(|m| m.server_name),
+            }
+        })
+        .collect()
+}
+
+pub fn print_replays(replays: &[Replay]) {
+    if replays.is_empty() {
+        println!(
+            "No replays yet. Add the Replay Mod with `ovl mods extras` and record one in game."
+        );
+        return;
+    }
+
+    for replay in replays {
+        match (replay.duration, &replay.minecraft) {
+            (Some(duration), Some(minecraft)) => println!(
+                "{}  {}  {}m{:02}s  Minecraft {}{}",
+                replay.name.bold(),
+                replay.recorded,
+                duration / 60000,
+                duration / 1000 % 60,
+                minecraft,
+                replay
+                    .server
+                    .as_ref()
+                    .map(|s| format!(" on {}", s))
+                    .unwrap_or_default()
+            ),
+            _ => println!(
+                "{}  {}  {}",
+                replay.name.bold(),
+                replay.recorded,
+                "unreadable".error()
+            ),
+        }
+    }
+}
+
+/// Open the replays folder, or launch the game a replay was recorded with so it can be watched
+pub async fn open_replay(
+    name: Option<&String>,
+    options: &LaunchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(name) = name else {
+        fs::create_dir_all(get_replays_dir())?;
+        open::that(get_replays_dir())?;
+        return Ok(());
+    };
+
+    let path = get_replays_dir().join(format!("{}.mcpr", name.trim_end_matches(".mcpr")));
+    if !path.exists() {
+        return Err(format!("No replay named {}, see `ovl replays list`", name).into());
+    }
+    let metadata = read_metadata(&path)?;
+
+    let mut config = load_config();
+    if !config.extra_mods.iter().any(|m| m == REPLAY_MOD) {
+        if !ask_yes_no("Watching replays needs the Replay Mod. Install it?") {
+            return Ok(());
+        }
+        config.extra_mods.push(REPLAY_MOD.to_string());
+        save_config(&config)?;
+    }
+
+    println!(
+        "Launching Minecraft {}, open the replay from the Replay Viewer of the main menu.\n",
+        metadata.mcversion
+    );
+    launch(metadata.mcversion, None, None, options).await;
+    Ok(())
+}
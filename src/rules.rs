@@ -0,0 +1,198 @@
+use colored::Colorize;
+use fastnbt::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::theme::Themed;
+use crate::world::{nbt_string, read_level_dat, write_level_dat};
+
+/// Game rules and level settings applied together by `ovl world rules --preset`
+pub struct RulePreset {
+    pub name: &'static str,
+    /// 0 peaceful, 1 easy, 2 normal, 3 hard
+    pub difficulty: Option<i8>,
+    pub hardcore: Option<bool>,
+    pub allow_commands: Option<bool>,
+    pub rules: &'static [(&'static str, &'static str)],
+}
+
+pub const RULE_PRESETS: &[RulePreset] = &[
+    RulePreset {
+        name: "creative-testing",
+        difficulty: None,
+        hardcore: None,
+        allow_commands: Some(true),
+        rules: &[
+            ("doDaylightCycle", "false"),
+            ("doWeatherCycle", "false"),
+            ("doMobSpawning", "false"),
+            ("doFireTick", "false"),
+            ("mobGriefing", "false"),
+            ("keepInventory", "true"),
+            ("doInsomnia", "false"),
+            ("doPatrolSpawning", "false"),
+            ("doTraderSpawning", "false"),
+            ("announceAdvancements", "false"),
+        ],
+    },
+    RulePreset {
+        name: "hardcore",
+        difficulty: Some(3),
+        hardcore: Some(true),
+        allow_commands: Some(false),
+        rules: &[
+            ("keepInventory", "false"),
+            ("naturalRegeneration", "true"),
+            ("doImmediateRespawn", "false"),
+        ],
+    },
+    RulePreset {
+        name: "peaceful",
+        difficulty: Some(0),
+        hardcore: Some(false),
+        allow_commands: None,
+        rules: &[
+            ("keepInventory", "true"),
+            ("doInsomnia", "false"),
+            ("doPatrolSpawning", "false"),
+            ("mobGriefing", "false"),
+        ],
+    },
+];
+
+pub fn parse_rule_preset(input: &str) -> Result<&'static RulePreset, String> {
+    RULE_PRESETS
+        .iter()
+        .find(|preset| preset.name == input)
+        .ok_or_else(|| {
+            let names: Vec<&str> = RULE_PRESETS.iter().map(|p| p.name).collect();
+            format!("Unknown preset, expected one of: {}", names.join(", "))
+        })
+}
+
+/// `name=value` where the value is `true`, `false` or a number, as in `/gamerule`
+pub fn parse_game_rule(input: &str) -> Result<(String, String), String> {
+    let (name, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("Expected name=value: {}", input))?;
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid game rule name: {}", name));
+    }
+    if value != "true" && value != "false" && value.parse::<i64>().is_err() {
+        return Err(format!(
+            "Game rule values are true, false or a number: {}",
+            value
+        ));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn set_flag(data: &mut HashMap<String, Value>, key: &str, value: i8, label: &str) {
+    let old = nbt_string(data.get(key)).unwrap_or("unset".to_string());
+    if old != value.to_string() {
+        println!(
+            "  {}: {} -> {}",
+            label,
+            old.muted(),
+            value.to_string().bold()
+        );
+    }
+    data.insert(key.to_string(), Value::Byte(value));
+}
+
+/// Apply a preset and then individual rules to the level.dat of a world
+pub fn set_game_rules(
+    world_dir: &Path,
+    preset: Option<&RulePreset>,
+    rules: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut level = read_level_dat(world_dir)?;
+    let Value::Compound(root) = &mut level else {
+        return Err("level.dat is not a compound".into());
+    };
+    let Some(Value::Compound(data)) = root.get_mut("Data") else {
+        return Err("level.dat has no Data".into());
+    };
+
+    if let Some(preset) = preset {
+        println!("Applying the {} preset:", preset.name.bold());
+        if let Some(difficulty) = preset.difficulty {
+            set_flag(data, "Difficulty", difficulty, "difficulty");
+        }
+        if let Some(hardcore) = preset.hardcore {
+            set_flag(data, "hardcore", hardcore as i8, "hardcore");
+        }
+        if let Some(allow_commands) = preset.allow_commands {
+            set_flag(data, "allowCommands", allow_commands as i8, "cheats");
+        }
+    }
+
+    let Value::Compound(game_rules) = data
+        .entry("GameRules".to_string())
+        .or_insert_with(|| Value::Compound(Default::default()))
+    else {
+        return Err("The game rules of level.dat are not a compound".into());
+    };
+
+    let preset_rules = preset
+        .map(|p| p.rules)
+        .unwrap_or_default()
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+    // Individual rules come last so they win over the preset
+    for (name, value) in preset_rules.chain(rules.iter().cloned()) {
+        let old = nbt_string(game_rules.get(&name));
+        if old.is_none() {
+            println!(
+                "  {} {} is not a game rule of this world yet, check its spelling.",
+                "Note:".warning(),
+                name
+            );
+        }
+        if old.as_ref() != Some(&value) {
+            println!(
+                "  {}: {} -> {}",
+                name,
+                old.unwrap_or("unset".to_string()).muted(),
+                value.bold()
+            );
+        }
+        // Game rules are stored as strings in level.dat
+        game_rules.insert(name, Value::String(value));
+    }
+
+    write_level_dat(world_dir, &level)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_game_rules() {
+        assert_eq!(
+            parse_game_rule("keepInventory=true"),
+            Ok(("keepInventory".to_string(), "true".to_string()))
+        );
+        assert_eq!(
+            parse_game_rule(" randomTickSpeed = -3 "),
+            Ok(("randomTickSpeed".to_string(), "-3".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_game_rules() {
+        for input in [
+            "keepInventory",
+            "=true",
+            "keep inventory=true",
+            "a;b=1",
+            "doFireTick=yes",
+            "x=",
+        ] {
+            assert!(parse_game_rule(input).is_err(), "{:?}", input);
+        }
+    }
+}
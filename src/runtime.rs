@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{dir::get_app_support_dir, net};
+
+/// Java runtimes downloaded by the launcher, one folder per version and architecture
+pub fn get_runtimes_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join("runtimes")
+}
+
+fn adoptium_os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "mac",
+        "windows" => "windows",
+        _ => "linux",
+    }
+}
+
+/// Find the `java` executable inside an extracted runtime
+fn find_java(dir: &Path) -> Option<PathBuf> {
+    let name = if cfg!(target_os = "windows") {
+        "javaw.exe"
+    } else {
+        "java"
+    };
+
+    for entry in fs::read_dir(dir).ok()?.filter_map(|entry| entry.ok()) {
+        let root = entry.path();
+        for candidate in [
+            root.join("bin").join(name),
+            root.join("Contents").join("Home").join("bin").join(name),
+        ] {
+            if candidate.exists() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+/// Path to a Java runtime of this major version and architecture (`x64`, `aarch64`),
+/// downloaded from Adoptium the first time it is needed
+pub async fn ensure_runtime(
+    java_version: u32,
+    arch: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let runtime_dir = get_runtimes_dir().join(format!("java-{}-{}", java_version, arch));
+    if let Some(java) = find_java(&runtime_dir) {
+        return Ok(java);
+    }
+
+    println!("Downloading Java {} ({})...", java_version, arch);
+    let url = format!(
+        "https://api.adoptium.net/v3/binary/latest/{}/ga/{}/{}/jre/hotspot/normal/eclipse",
+        java_version,
+        adoptium_os(),
+        arch
+    );
+    let bytes = net::get(&url).await?.error_for_status()?.bytes().await?;
+
+    fs::create_dir_all(&runtime_dir)?;
+    let archive = runtime_dir.join(if cfg!(target_os = "windows") {
+        "runtime.zip"
+    } else {
+        "runtime.tar.gz"
+    });
+    fs::write(&archive, bytes)?;
+
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&runtime_dir)
+        .status()?;
+    let _ = fs::remove_file(&archive);
+    if !status.success() {
+        let _ = fs::remove_dir_all(&runtime_dir);
+        return Err(format!("Failed to extract Java {}", java_version).into());
+    }
+
+    find_java(&runtime_dir)
+        .ok_or_else(|| format!("No java executable in Java {}", java_version).into())
+}
+
+/// Whether x86_64 programs can run through Rosetta on this Mac
+pub fn rosetta_installed() -> bool {
+    Command::new("arch")
+        .arg("-x86_64")
+        .arg("/usr/bin/true")
+        .status()
+        .is_ok_and(|status| status.success())
+}
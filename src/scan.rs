@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::audit::audit;
+use crate::config::load_config;
+use crate::dir::get_app_support_dir;
+use crate::filesys::is_plain_file_name;
+
+/// Downloads wait here while they are scanned, and stay here when the scan fails
+pub fn get_quarantine_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join("quarantine")
+}
+
+/// Where a download named `name` is kept in the quarantine folder, names that are not a
+/// plain file name are refused so that nothing is written outside of it
+pub fn quarantine_path(name: &str) -> Result<PathBuf, String> {
+    match is_plain_file_name(name) {
+        true => Ok(get_quarantine_dir().join(name)),
+        false => Err(format!("{:?} is not a valid file name", name)),
+    }
+}
+
+/// The path as one shell word
+fn quote(path: &Path) -> String {
+    let path = path.display().to_string();
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+/// Run the `scan_command` of the config on a file, `{file}` is replaced with its path or the
+/// path is added at the end
+fn run_scan(command: &str, path: &Path) -> Result<(), String> {
+    let command = match command.contains("{file}") {
+        true => command.replace("{file}", &quote(path)),
+        false => format!("{} {}", command, quote(path)),
+    };
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let output = shell
+        .arg(&command)
+        .output()
+        .map_err(|e| format!("Could not start the scan `{}`: {}", command, e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+    for line in String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stderr).lines())
+        .filter(|line| !line.trim().is_empty())
+    {
+        eprintln!("  {}", line);
+    }
+    Err(format!("the scan exited with {}", output.status))
+}
+
+fn blocked(name: &str, quarantined: &Path, reason: &str) -> String {
+    audit(
+        "scan",
+        &format!(
+            "{} blocked ({}), kept in {}",
+            name,
+            reason,
+            quarantined.display()
+        ),
+    );
+    format!(
+        "{} was blocked by the scan command ({}), it is kept in {}",
+        name,
+        reason,
+        quarantined.display()
+    )
+}
+
+/// Check a download with the `scan_command` of the config before it is written where it is
+/// used. Does nothing when no command is set.
+pub fn scan_download(name: &str, content: &[u8]) -> Result<(), String> {
+    let Some(command) = load_config().scan_command else {
+        return Ok(());
+    };
+
+    let path = quarantine_path(name)?;
+    fs::create_dir_all(get_quarantine_dir())
+        .and_then(|_| fs::write(&path, content))
+        .map_err(|e| format!("Failed to write {} for the scan: {}", name, e))?;
+
+    match run_scan(&command, &path) {
+        Ok(()) => {
+            let _ = fs::remove_file(&path);
+            Ok(())
+        }
+        Err(e) => Err(blocked(name, &path, &e)),
+    }
+}
+
+/// Check a downloaded file in place with the `scan_command` of the config before it is
+/// installed. A file that fails is moved to the quarantine folder.
+pub fn scan_file(path: &Path) -> Result<(), String> {
+    let Some(command) = load_config().scan_command else {
+        return Ok(());
+    };
+
+    let Err(e) = run_scan(&command, path) else {
+        return Ok(());
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let quarantined = get_quarantine_dir().join(&name);
+    match fs::create_dir_all(get_quarantine_dir()).and_then(|_| fs::rename(path, &quarantined)) {
+        Ok(()) => Err(blocked(&name, &quarantined, &e)),
+        Err(_) => {
+            // It must not stay where it would be installed from
+            let _ = fs::remove_file(path);
+            Err(format!(
+                "{} was blocked by the scan command ({}) and deleted",
+                name, e
+            ))
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Key of the version in every versioned file
+pub const VERSION_KEY: &str = "schema_version";
+
+/// A file format of the launcher and the migrations that brought it to its current version
+pub struct Schema {
+    /// How the file is called in messages, e.g. `accounts file`
+    pub name: &'static str,
+    /// `migrations[n]` upgrades version `n` to `n + 1`. Files from before versioning are
+    /// version 0.
+    pub migrations: &'static [fn(&mut Value)],
+}
+
+fn version_of(value: &Value) -> u64 {
+    value[VERSION_KEY].as_u64().unwrap_or(0)
+}
+
+impl Schema {
+    /// The version this build writes
+    pub fn current(&self) -> u64 {
+        self.migrations.len() as u64
+    }
+
+    /// Mark a file about to be written with the current version
+    pub fn stamp(&self, value: &mut Value) {
+        if let Some(object) = value.as_object_mut() {
+            object.insert(VERSION_KEY.to_string(), self.current().into());
+        }
+    }
+
+    /// Bring a file up to the current version. Returns whether it changed, and an error when a
+    /// newer ovl wrote it, as this one would lose what it does not know about.
+    pub fn migrate(&self, value: &mut Value) -> Result<bool, String> {
+        let version = version_of(value);
+        if version > self.current() {
+            return Err(format!(
+                "The {} was written by a newer version of ovl (schema {}, this one reads up to {}). Update ovl to use it.",
+                self.name,
+                version,
+                self.current()
+            ));
+        }
+        if version == self.current() {
+            return Ok(false);
+        }
+
+        for migration in &self.migrations[version as usize..] {
+            migration(value);
+        }
+        self.stamp(value);
+        Ok(true)
+    }
+
+    /// Upgrade a file read from `path` in place when an older ovl wrote it. The old file is kept
+    /// next to it as `<name>.v<version>.bak`. When the file cannot be written, the upgrade only
+    /// applies to this run.
+    pub fn upgrade_file(
+        &self,
+        path: &Path,
+        value: &mut Value,
+        serialize: impl FnOnce(&Value) -> Result<String, String>,
+    ) -> Result<(), String> {
+        let version = version_of(value);
+        if !self.migrate(value)? {
+            return Ok(());
+        }
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let backup = path.with_file_name(format!("{}.v{}.bak", file_name, version));
+        let written = fs::copy(path, &backup)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serialize(value))
+            .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => eprintln!(
+                "Upgraded the {} to schema {}, the previous one is kept as {}",
+                self.name,
+                self.current(),
+                backup.display()
+            ),
+            Err(e) => eprintln!("Warning: Failed to upgrade the {}: {}", self.name, e),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use inquire::{CustomUserError, Text, autocompletion::Replacement};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    config::is_map_allowed,
+    dir::get_app_support_dir,
+    map::{Map, download_catalog, fetch_maps},
+};
+
+/// After this, lookups still use the index but download the catalog again in the background
+const REFRESH_AFTER: Duration = Duration::from_secs(60 * 60);
+/// After this, the index is too old to be trusted and the catalog is downloaded first
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Suggestions shown while typing a map name
+const MAX_SUGGESTIONS: usize = 8;
+
+/// The catalog as last downloaded, with the maps each word of their id, name and tags leads to
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CatalogIndex {
+    /// Unix timestamp of the download the index was built from
+    built_at: u64,
+    maps: Vec<Map>,
+    /// Lowercase word to the positions of the maps it appears in
+    terms: BTreeMap<String, BTreeSet<usize>>,
+}
+
+fn get_index_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("catalog-index.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Split a name into lowercase words, `sky-wars_2` gives `sky`, `wars` and `2`
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl CatalogIndex {
+    fn build(maps: &[Map]) -> Self {
+        let mut terms: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for (position, map) in maps.iter().enumerate() {
+            let fields = [map.id.as_str(), map.name.as_str()]
+                .into_iter()
+                .chain(map.tags.iter().map(String::as_str));
+            for field in fields {
+                for word in words(field) {
+                    terms.entry(word).or_default().insert(position);
+                }
+            }
+        }
+        CatalogIndex {
+            built_at: now(),
+            maps: maps.to_vec(),
+            terms,
+        }
+    }
+
+    fn load() -> Option<Self> {
+        let content = fs::read(get_index_path()).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.built_at))
+    }
+
+    /// How well a word of the index matches a word of the query: 3 for the same word, 2 when
+    /// the query is the start of it (typing), 1 for a typo or a part of it
+    fn term_score(term: &str, query: &str) -> usize {
+        if term == query {
+            3
+        } else if term.starts_with(query) {
+            2
+        } else if (query.len() >= 3 && term.contains(query))
+            || edit_distance(term, query) <= typos_allowed(query)
+        {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Maps matching any word of the query, best first: the most words matched, then the
+    /// closest matches, then the catalog order
+    fn search(&self, query: &str) -> Vec<Map> {
+        let mut scores: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+        for word in words(query) {
+            let mut best: BTreeMap<usize, usize> = BTreeMap::new();
+            for (term, positions) in &self.terms {
+                let score = Self::term_score(term, &word);
+                if score == 0 {
+                    continue;
+                }
+                for position in positions {
+                    let entry = best.entry(*position).or_default();
+                    *entry = (*entry).max(score);
+                }
+            }
+            for (position, score) in best {
+                let entry = scores.entry(position).or_default();
+                entry.0 += 1;
+                entry.1 += score;
+            }
+        }
+
+        let mut ranked: Vec<(usize, (usize, usize))> = scores.into_iter().collect();
+        ranked.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then(a.cmp(b)));
+        ranked
+            .into_iter()
+            .map(|(position, _)| self.maps[position].clone())
+            .filter(|map| is_map_allowed(&map.id))
+            .collect()
+    }
+}
+
+/// Short words are compared exactly, typos are only forgiven in longer ones
+fn typos_allowed(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Rebuild the index from a freshly downloaded catalog
+pub fn update_index(maps: &[Map]) {
+    let path = get_index_path();
+    let written = serde_json::to_vec(&CatalogIndex::build(maps))
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+            // Written aside first, a background refresh may race with a foreground one
+            let partial = path.with_extension(format!("json.{}", std::process::id()));
+            fs::write(&partial, content).map_err(|e| e.to_string())?;
+            fs::rename(&partial, &path).map_err(|e| e.to_string())
+        });
+    if let Err(e) = written {
+        eprintln!("Warning: Failed to update the search index: {}", e);
+    }
+}
+
+/// Download the catalog again without holding up the command. The index is only updated
+/// if the command runs long enough for the download to finish.
+fn refresh_in_background() {
+    std::thread::spawn(|| {
+        if let Ok(maps) = download_catalog() {
+            update_index(&maps);
+        }
+    });
+}
+
+/// The index when it is recent enough, downloading the catalog first when there is none
+fn load_index() -> Result<CatalogIndex, Box<dyn Error>> {
+    match CatalogIndex::load() {
+        Some(index) if index.age() < MAX_AGE => {
+            if index.age() >= REFRESH_AFTER {
+                refresh_in_background();
+            }
+            Ok(index)
+        }
+        _ => Ok(CatalogIndex::build(&fetch_maps()?)),
+    }
+}
+
+/// The catalog from the local index, for lookups that should not wait for the network
+pub fn indexed_maps() -> Result<Vec<Map>, Box<dyn Error>> {
+    let maps = load_index()?.maps;
+    Ok(maps
+        .into_iter()
+        .filter(|map| is_map_allowed(&map.id))
+        .collect())
+}
+
+/// Maps matching a query by words, word starts or with typos, best first
+pub fn search_maps(query: &str) -> Result<Vec<Map>, Box<dyn Error>> {
+    Ok(load_index()?.search(query))
+}
+
+/// Suggests map names from the index while a query is typed
+#[derive(Clone)]
+struct MapCompleter {
+    names: Vec<String>,
+}
+
+impl inquire::Autocomplete for MapCompleter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        let input = input.to_lowercase();
+        Ok(self
+            .names
+            .iter()
+            .filter(|name| name.to_lowercase().contains(&input))
+            .take(MAX_SUGGESTIONS)
+            .cloned()
+            .collect())
+    }
+
+    fn get_completion(
+        &mut self,
+        _input: &str,
+        highlighted_suggestion: Option<String>,
+    ) -> Result<Replacement, CustomUserError> {
+        Ok(highlighted_suggestion)
+    }
+}
+
+/// Ask which map to play, suggesting names of the catalog as it is typed
+pub fn ask_map_query() -> Option<String> {
+    let names = indexed_maps()
+        .ok()?
+        .into_iter()
+        .map(|map| map.name)
+        .collect();
+    Text::new("Map to play:")
+        .with_autocomplete(MapCompleter { names })
+        .prompt()
+        .ok()
+        .filter(|query| !query.trim().is_empty())
+}
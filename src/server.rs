@@ -0,0 +1,590 @@
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use chrono::{Local, NaiveTime};
+use colored::Colorize;
+use qrcode::{QrCode, render::unicode};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::{
+    backup::backup_world,
+    cmd::{ask_yes_no, ask_yes_no_attended},
+    config::load_config,
+    console::ConsoleHub,
+    dir::get_app_support_dir,
+    java::get_java_path,
+    join::{HostInfo, serve_host_info},
+    net,
+    profile::{dashed_uuid, lookup_profile},
+    rcon::Rcon,
+    releases::get_pack_release,
+    scan::scan_download,
+    session::parse_duration,
+    theme::Themed,
+    tunnel::Tunnel,
+    versions::fetch_version_json,
+};
+
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct ServerRunOptions {
+    #[arg(long, help = "Forward the server port on your router with UPnP")]
+    pub upnp: bool,
+    #[arg(
+        long,
+        help = "Share the server through the tunnel set as `tunnel_command` in config.toml"
+    )]
+    pub tunnel: bool,
+    #[arg(long, value_parser = parse_duration, help = "Back up the world at this interval (e.g. 30m, 2h)")]
+    pub backup_every: Option<Duration>,
+    #[arg(long, value_parser = parse_time, help = "Restart the server every day at this time (e.g. 04:00)")]
+    pub restart_at: Option<NaiveTime>,
+    #[arg(
+        long,
+        help = "Show the join addresses as QR codes, for friends in the same room"
+    )]
+    pub qr: bool,
+    /// Catalog map the server hosts, shared in the invite links
+    #[arg(skip)]
+    pub invite_map: Option<String>,
+    /// Print who is online whenever a player joins or leaves
+    #[arg(skip)]
+    pub track_players: bool,
+}
+
+impl ServerRunOptions {
+    fn is_scheduled(&self) -> bool {
+        self.backup_every.is_some() || self.restart_at.is_some()
+    }
+}
+
+fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time, expected HH:MM: {}", input))
+}
+
+/// Folder of the locally hosted server, unless `--dir` points somewhere else
+pub fn get_server_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join("server")
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WhitelistEntry {
+    pub uuid: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpEntry {
+    pub uuid: String,
+    pub name: String,
+    pub level: u8,
+    pub bypasses_player_limit: bool,
+}
+
+fn read_list<T: for<'de> Deserialize<'de>>(
+    path: &Path,
+) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if !content.trim().is_empty() => Ok(serde_json::from_str(&content)?),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn write_list<T: Serialize>(path: &Path, list: &[T]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent_dir) = path.parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(list)?)?;
+    Ok(())
+}
+
+/// Resolve a player name to its proper casing and dashed UUID
+async fn resolve_player(name: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    match lookup_profile(name).await? {
+        Some(profile) => Ok((profile.name, dashed_uuid(&profile.uuid))),
+        None => Err(format!("No Minecraft player is named {}", name).into()),
+    }
+}
+
+pub async fn whitelist_add(dir: &Path, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let path = dir.join("whitelist.json");
+    let mut list: Vec<WhitelistEntry> = read_list(&path)?;
+    let (name, uuid) = resolve_player(name).await?;
+
+    if !list.iter().any(|e| e.uuid == uuid) {
+        list.push(WhitelistEntry {
+            uuid,
+            name: name.clone(),
+        });
+        write_list(&path, &list)?;
+    }
+
+    Ok(name)
+}
+
+pub fn whitelist_remove(dir: &Path, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let path = dir.join("whitelist.json");
+    let mut list: Vec<WhitelistEntry> = read_list(&path)?;
+    let before = list.len();
+    list.retain(|e| !e.name.eq_ignore_ascii_case(name));
+
+    write_list(&path, &list)?;
+    Ok(list.len() != before)
+}
+
+pub fn print_whitelist(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let whitelist: Vec<WhitelistEntry> = read_list(&dir.join("whitelist.json"))?;
+    let ops: Vec<OpEntry> = read_list(&dir.join("ops.json"))?;
+
+    if whitelist.is_empty() {
+        println!("The whitelist is empty.");
+    }
+    for entry in &whitelist {
+        let op = ops.iter().any(|o| o.uuid == entry.uuid);
+        println!(
+            "{} ({}){}",
+            entry.name.bold(),
+            entry.uuid,
+            if op {
+                " [op]".warning().to_string()
+            } else {
+                String::new()
+            }
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn op(dir: &Path, name: &str, level: u8) -> Result<String, Box<dyn std::error::Error>> {
+    let path = dir.join("ops.json");
+    let mut list: Vec<OpEntry> = read_list(&path)?;
+    let (name, uuid) = resolve_player(name).await?;
+
+    list.retain(|e| e.uuid != uuid);
+    list.push(OpEntry {
+        uuid,
+        name: name.clone(),
+        level,
+        bypasses_player_limit: false,
+    });
+    write_list(&path, &list)?;
+
+    Ok(name)
+}
+
+pub fn deop(dir: &Path, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let path = dir.join("ops.json");
+    let mut list: Vec<OpEntry> = read_list(&path)?;
+    let before = list.len();
+    list.retain(|e| !e.name.eq_ignore_ascii_case(name));
+
+    write_list(&path, &list)?;
+    Ok(list.len() != before)
+}
+
+/// Read a value from server.properties
+pub fn get_server_property(dir: &Path, key: &str) -> Option<String> {
+    let content = std::fs::read_to_string(dir.join("server.properties")).ok()?;
+    content.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key).then(|| v.trim().to_string())
+    })
+}
+
+/// Set a value in server.properties, keeping every other line as is
+pub fn set_server_property(
+    dir: &Path,
+    key: &str,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join("server.properties");
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((k, _)) if k.trim() == key => {
+                found = true;
+                format!("{}={}", key, value)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{}={}", key, value));
+    }
+
+    std::fs::write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+pub fn get_server_port(dir: &Path) -> u16 {
+    get_server_property(dir, "server-port")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(25565)
+}
+
+/// Address of this machine on the local network
+pub fn get_local_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    // Nothing is sent, this only selects the outgoing interface
+    socket.connect("8.8.8.8:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// Download the vanilla server jar for `version` unless it is already there
+async fn install_server(dir: &Path, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let version_file = dir.join(".ovl-version");
+    let jar = dir.join("server.jar");
+
+    if jar.exists() && std::fs::read_to_string(&version_file).is_ok_and(|v| v.trim() == version) {
+        return Ok(());
+    }
+
+    println!("Downloading Minecraft server {}...", version);
+    let version_json = fetch_version_json(version).await?;
+    let url = version_json["downloads"]["server"]["url"]
+        .as_str()
+        .ok_or_else(|| format!("Minecraft {} has no server download", version))?;
+
+    let bytes = net::get(url).await?.error_for_status()?.bytes().await?;
+    scan_download(&format!("server-{}.jar", version), &bytes)?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&jar, bytes)?;
+    std::fs::write(version_file, version)?;
+
+    Ok(())
+}
+
+fn accept_eula(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let eula = dir.join("eula.txt");
+    if std::fs::read_to_string(&eula).is_ok_and(|c| c.contains("eula=true")) {
+        return Ok(true);
+    }
+
+    if !ask_yes_no_attended("Do you accept the Minecraft EULA (https://aka.ms/MinecraftEULA)?") {
+        return Ok(false);
+    }
+
+    std::fs::write(eula, "eula=true\n")?;
+    Ok(true)
+}
+
+/// Forwards the server port on the router for as long as it lives
+pub struct PortMapping {
+    gateway: igd_next::Gateway,
+    port: u16,
+}
+
+impl PortMapping {
+    pub fn open(port: u16) -> Result<(PortMapping, IpAddr), Box<dyn std::error::Error>> {
+        let local_ip = get_local_ip().ok_or("Could not find the local IP address")?;
+        let gateway = igd_next::search_gateway(Default::default())?;
+
+        gateway.add_port(
+            igd_next::PortMappingProtocol::TCP,
+            port,
+            SocketAddr::new(local_ip, port),
+            0,
+            "OpenVoxel server",
+        )?;
+        let public_ip = gateway.get_external_ip()?;
+
+        Ok((PortMapping { gateway, port }, public_ip))
+    }
+}
+
+impl Drop for PortMapping {
+    fn drop(&mut self) {
+        match self
+            .gateway
+            .remove_port(igd_next::PortMappingProtocol::TCP, self.port)
+        {
+            Ok(()) => println!("Removed the port forwarding for {}.", self.port),
+            Err(e) => eprintln!("Failed to remove the port forwarding: {}", e),
+        }
+    }
+}
+
+/// Render `text` as a QR code with half-block characters, two modules per line
+fn print_qr(text: &str) {
+    match QrCode::new(text) {
+        Ok(code) => println!(
+            "{}",
+            code.render::<unicode::Dense1x2>()
+                .dark_color(unicode::Dense1x2::Light)
+                .light_color(unicode::Dense1x2::Dark)
+                .build()
+        ),
+        Err(e) => eprintln!("Failed to render the QR code: {}", e),
+    }
+}
+
+/// `ovl://join` link to a server hosting a catalog map
+pub fn invite_link(address: &str, map_id: &str) -> String {
+    format!("ovl://join/{}?map={}", address, map_id)
+}
+
+fn share_address(address: &str, options: &ServerRunOptions) {
+    println!("\nFriends can join at: {}", address.bold().success());
+    if let Some(map) = &options.invite_map {
+        println!(
+            "Invite for friends using ovl: {}",
+            invite_link(address, map).bold()
+        );
+    }
+    if options.qr {
+        print_qr(address);
+    }
+    if ask_yes_no("Copy the address to the clipboard?") {
+        match arboard::Clipboard::new().and_then(|mut c| c.set_text(address)) {
+            Ok(()) => println!("Copied!"),
+            Err(e) => eprintln!("Failed to copy to the clipboard: {}", e),
+        }
+    }
+}
+
+pub async fn run_server(
+    dir: &Path,
+    version: Option<&String>,
+    options: &ServerRunOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let version = match version {
+        Some(version) => version.clone(),
+        None => std::fs::read_to_string(dir.join(".ovl-version"))
+            .map(|v| v.trim().to_string())
+            .map_err(|_| "No server installed yet, pass the Minecraft version to run")?,
+    };
+
+    install_server(dir, &version).await?;
+    if !accept_eula(dir)? {
+        return Err("The EULA must be accepted to run a server".into());
+    }
+
+    let port = get_server_port(dir);
+    serve_host_info(
+        port,
+        &HostInfo {
+            minecraft: version.clone(),
+            map: options.invite_map.clone(),
+            mods: load_config().extra_mods,
+            resource_pack: get_pack_release().ok().map(|r| r.tag_name),
+        },
+    );
+    let _mapping = if options.upnp {
+        match PortMapping::open(port) {
+            Ok((mapping, public_ip)) => {
+                share_address(&format!("{}:{}", public_ip, port), options);
+                Some(mapping)
+            }
+            Err(e) => {
+                eprintln!(
+                    "UPnP port forwarding failed, only LAN players can join: {}",
+                    e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let _tunnel = if options.tunnel {
+        let command = load_config()
+            .tunnel_command
+            .ok_or("No tunnel configured, set `tunnel_command` in config.toml (e.g. \"playit\")")?;
+
+        println!("Starting the tunnel...");
+        let (tunnel, address) = Tunnel::start(&command, port)?;
+        match address {
+            Some(address) => share_address(&address, options),
+            None => println!("The tunnel is running, check its output above for the address."),
+        }
+        Some(tunnel)
+    } else {
+        None
+    };
+
+    if let Some(local_ip) = get_local_ip() {
+        let address = format!("{}:{}", local_ip, port);
+        println!("Players on your network can join at: {}", address);
+        if let Some(map) = &options.invite_map {
+            println!("LAN invite: {}", invite_link(&address, map));
+        }
+        if options.qr {
+            print_qr(&address);
+        }
+    }
+
+    let rcon = if options.is_scheduled() {
+        Some(enable_rcon(dir)?)
+    } else {
+        None
+    };
+
+    let mut console = ConsoleHub::start(dir)?;
+    if options.track_players {
+        console.track_players();
+    }
+
+    loop {
+        println!("Starting Minecraft server {}...\n", version);
+        let mut process = Command::new(get_java_path(&version))
+            .arg("-Xmx2G")
+            .arg("-jar")
+            .arg("server.jar")
+            .arg("nogui")
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        console.attach(&mut process);
+
+        let restart = match &rcon {
+            Some((port, password)) => supervise_server(&mut process, dir, options, *port, password),
+            None => false,
+        };
+        let status = process.wait()?;
+
+        if restart {
+            println!("\nRestarting the server...");
+            continue;
+        }
+        if !status.success() {
+            return Err(format!("The server exited with {}", status).into());
+        }
+        return Ok(());
+    }
+}
+
+/// Turn on RCON so scheduled tasks can talk to the server, returns its port and password
+fn enable_rcon(dir: &Path) -> Result<(u16, String), Box<dyn std::error::Error>> {
+    let port = get_server_property(dir, "rcon.port")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(25575);
+
+    let password = match get_server_property(dir, "rcon.password") {
+        Some(password) if !password.is_empty() => password,
+        _ => {
+            let mut bytes = [0u8; 16];
+            OsRng.fill_bytes(&mut bytes);
+            let password: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            set_server_property(dir, "rcon.password", &password)?;
+            password
+        }
+    };
+
+    if get_server_property(dir, "enable-rcon").as_deref() != Some("true") {
+        set_server_property(dir, "enable-rcon", "true")?;
+        set_server_property(dir, "rcon.port", &port.to_string())?;
+    }
+
+    Ok((port, password))
+}
+
+/// Minutes before a scheduled restart at which players are warned
+const RESTART_WARNINGS: &[i64] = &[10, 5, 1];
+
+/// Run the scheduled backups and restarts until the server exits.
+/// Returns whether the server was stopped for a scheduled restart.
+fn supervise_server(
+    process: &mut Child,
+    dir: &Path,
+    options: &ServerRunOptions,
+    rcon_port: u16,
+    rcon_password: &str,
+) -> bool {
+    let world_dir = dir.join(get_server_property(dir, "level-name").unwrap_or("world".to_string()));
+    let mut rcon: Option<Rcon> = None;
+    let mut next_backup = options.backup_every.map(|every| Instant::now() + every);
+    let restart_at = options.restart_at.and_then(|time| {
+        let now = Local::now();
+        let today = now
+            .date_naive()
+            .and_time(time)
+            .and_local_timezone(Local)
+            .earliest()?;
+        Some(if today > now {
+            today
+        } else {
+            today + chrono::Duration::days(1)
+        })
+    });
+    let mut warned: Vec<i64> = Vec::new();
+
+    loop {
+        if !matches!(process.try_wait(), Ok(None)) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_secs(1));
+
+        // The server only opens RCON once it is done loading
+        if rcon.is_none() {
+            rcon = Rcon::connect(rcon_port, rcon_password).ok();
+            if rcon.is_none() {
+                continue;
+            }
+        }
+        let Some(console) = rcon.as_mut() else {
+            continue;
+        };
+
+        if let (Some(due), Some(every)) = (next_backup, options.backup_every)
+            && Instant::now() >= due
+        {
+            next_backup = Some(Instant::now() + every);
+            if let Err(e) = run_backup(console, dir, &world_dir) {
+                eprintln!("Scheduled backup failed: {}", e);
+                rcon = None;
+                continue;
+            }
+        }
+
+        if let Some(restart_at) = restart_at {
+            let seconds_left = (restart_at - Local::now()).num_seconds();
+
+            for minutes in RESTART_WARNINGS {
+                if seconds_left <= minutes * 60 && seconds_left > 0 && !warned.contains(minutes) {
+                    warned.push(*minutes);
+                    let _ = console.command(&format!(
+                        "say The server will restart in {} minute(s).",
+                        minutes
+                    ));
+                }
+            }
+
+            if seconds_left <= 0 {
+                println!("\nScheduled restart, stopping the server...");
+                let _ = console.command("say The server is restarting now!");
+                return console.command("stop").is_ok();
+            }
+        }
+    }
+}
+
+fn run_backup(
+    console: &mut Rcon,
+    dir: &Path,
+    world_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    console.command("say Backing up the world...")?;
+    console.command("save-off")?;
+    console.command("save-all flush")?;
+
+    let result = backup_world(dir, world_dir);
+    console.command("save-on")?;
+
+    match result {
+        Ok(path) => {
+            println!("\nWorld backed up to {}", path.display());
+            console.command("say Backup complete.")?;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,387 @@
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::dir::get_app_support_dir;
+use crate::theme::Themed;
+
+/// Log lines worth pointing out after a session, with what the player can do about them
+const LOG_HINTS: &[(&str, &str)] = &[
+    (
+        "Missing resource pack",
+        "A resource pack could not be found, check options.txt or re-run the map install",
+    ),
+    (
+        "Failed to load resource pack",
+        "A resource pack failed to load, it may be corrupted or for another game version",
+    ),
+    (
+        "OpenGL debug message",
+        "The graphics driver reported OpenGL errors, try updating your GPU driver",
+    ),
+    (
+        "Unsupported graphics card",
+        "Your graphics card or driver is not supported, try updating your GPU driver",
+    ),
+    (
+        "GLFW error",
+        "The window could not be created properly, try updating your GPU driver",
+    ),
+    (
+        "OutOfMemoryError",
+        "The game ran out of memory, close other programs or give Java more memory",
+    ),
+];
+
+/// Logged once the game has picked up the account, before any window exists
+const STARTED_MARKER: &str = "Setting user:";
+/// Logged once the window and the rendering backend are up
+const WINDOW_MARKERS: &[&str] = &["Backend library:", "Created: "];
+
+#[derive(Debug)]
+pub struct SessionReport {
+    pub duration: Duration,
+    pub exit_code: Option<i32>,
+    pub crash_report: Option<PathBuf>,
+    pub hints: Vec<&'static str>,
+    /// Set when the watchdog killed a launch that never opened a window
+    pub hung: Option<&'static str>,
+}
+
+impl SessionReport {
+    pub fn crashed(&self) -> bool {
+        self.hung.is_some()
+            || self.crash_report.is_some()
+            || self.exit_code.is_some_and(|code| code != 0)
+    }
+
+    pub fn print(&self, version: &str) {
+        let minutes = self.duration.as_secs() / 60;
+        let seconds = self.duration.as_secs() % 60;
+
+        println!("\n{}", "Session report".bold());
+        println!("  Played for: {}m {:02}s", minutes, seconds);
+        println!(
+            "  Exit code: {}",
+            self.exit_code
+                .map(|code| code.to_string())
+                .unwrap_or("unknown".to_string())
+        );
+
+        match &self.crash_report {
+            Some(path) => println!("  Crash report: {}", path.display().to_string().error()),
+            None => println!("  Crash report: none"),
+        }
+
+        if let Some(cause) = self.hung {
+            println!(
+                "  {} The game never opened a window and was stopped.",
+                "!".error()
+            );
+            println!("  {} {}", "!".error(), cause);
+        }
+
+        for hint in &self.hints {
+            println!("  {} {}", "!".warning(), hint);
+        }
+
+        if self.crashed() {
+            println!("\nNext steps:");
+            println!(
+                "  ovl run {} --no-mods    check whether the crash is caused by a mod",
+                version
+            );
+            println!(
+                "  ovl mods update {}    reinstall the mods for this version",
+                version
+            );
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SupervisorOptions {
+    /// Kill the game when no window shows up within this time
+    pub window_timeout: Option<Duration>,
+    /// Stop the game once it has been running for this long
+    pub time_limit: Option<Duration>,
+}
+
+/// Minutes before the time limit at which the player is warned
+const TIME_LIMIT_WARNINGS: &[u64] = &[10, 1];
+/// How long a game asked to stop gets before it is killed
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Wait for the game to exit and collect what happened during the session
+pub fn supervise(
+    process: &mut Child,
+    game_dir: &Path,
+    options: SupervisorOptions,
+) -> SessionReport {
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    let log_path = game_dir.join("logs").join("latest.log");
+
+    let mut window_opened = false;
+    let mut hung = None;
+    let mut warned: Vec<u64> = Vec::new();
+    let mut stop_requested: Option<Instant> = None;
+
+    let exit_code = loop {
+        match process.try_wait() {
+            Ok(Some(status)) => break status.code(),
+            Ok(None) => {}
+            Err(_) => break None,
+        }
+
+        if let Some(limit) = options.time_limit {
+            let remaining = limit.saturating_sub(started.elapsed());
+
+            for minutes in TIME_LIMIT_WARNINGS {
+                if remaining <= Duration::from_secs(minutes * 60) && !warned.contains(minutes) {
+                    warned.push(*minutes);
+                    notify(&format!(
+                        "{} minute(s) of play time left, the game will close soon.",
+                        minutes
+                    ));
+                }
+            }
+
+            match stop_requested {
+                None if remaining.is_zero() => {
+                    notify("Time is up! Closing Minecraft...");
+                    request_stop(process);
+                    stop_requested = Some(Instant::now());
+                }
+                Some(requested) if requested.elapsed() > STOP_GRACE_PERIOD => {
+                    let _ = process.kill();
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(timeout) = options.window_timeout
+            && !window_opened
+        {
+            let log = read_session_log(&log_path, started_at);
+            window_opened = WINDOW_MARKERS.iter().any(|m| log.contains(m));
+
+            if !window_opened && started.elapsed() > timeout {
+                hung = Some(if log.contains(STARTED_MARKER) {
+                    "The game started but could not create its window. This usually means broken natives or an outdated GPU driver."
+                } else {
+                    "The game did not get past startup. Check your Java installation and try `--no-mods`."
+                });
+
+                let _ = process.kill();
+                break process.wait().ok().and_then(|status| status.code());
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(500));
+    };
+
+    SessionReport {
+        duration: started.elapsed(),
+        exit_code,
+        crash_report: find_crash_report(game_dir, started_at),
+        hints: scan_log(&log_path),
+        hung,
+    }
+}
+
+/// Tell the player something, in the terminal and as a desktop notification when possible
+pub fn notify(message: &str) {
+    println!("{} {}", "!".warning(), message);
+
+    let result = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "display notification \"{}\" with title \"OpenVoxel\"",
+                message.replace('"', "'")
+            ))
+            .output()
+    } else if cfg!(target_os = "windows") {
+        Command::new("msg").arg("*").arg(message).output()
+    } else {
+        Command::new("notify-send")
+            .arg("OpenVoxel")
+            .arg(message)
+            .output()
+    };
+    let _ = result;
+}
+
+/// Ask the game to close so it can save, instead of killing it right away
+fn request_stop(process: &mut Child) {
+    let pid = process.id().to_string();
+    let result = if cfg!(target_os = "windows") {
+        Command::new("taskkill").arg("/PID").arg(&pid).output()
+    } else {
+        Command::new("kill").arg("-TERM").arg(&pid).output()
+    };
+
+    if !result.is_ok_and(|output| output.status.success()) {
+        let _ = process.kill();
+    }
+}
+
+/// Parse durations like `90m`, `1h30m` or `45s`, a bare number is a number of minutes
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid duration: {}", input);
+    if let Ok(minutes) = input.parse::<u64>() {
+        return match minutes.checked_mul(60) {
+            Some(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+            _ => Err(invalid()),
+        };
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+
+        let unit = match c {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("Invalid duration unit '{}' in {}", c, input)),
+        };
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Playtime {
+    /// Days since the Unix epoch
+    day: u64,
+    seconds: u64,
+}
+
+fn get_playtime_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join("settings")
+        .join("playtime.json")
+}
+
+/// Days since the Unix epoch in local time, the limit starts again at local midnight
+fn today() -> u64 {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    (Local::now().date_naive() - epoch).num_days().max(0) as u64
+}
+
+/// Time played today, across every session
+pub fn played_today() -> Duration {
+    let playtime: Playtime = fs::read_to_string(get_playtime_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    if playtime.day == today() {
+        Duration::from_secs(playtime.seconds)
+    } else {
+        Duration::ZERO
+    }
+}
+
+pub fn record_playtime(duration: Duration) {
+    let playtime = Playtime {
+        day: today(),
+        seconds: (played_today() + duration).as_secs(),
+    };
+
+    let path = get_playtime_path();
+    if let Some(parent_dir) = path.parent() {
+        let _ = fs::create_dir_all(parent_dir);
+    }
+    if let Ok(content) = serde_json::to_string(&playtime) {
+        let _ = fs::write(path, content);
+    }
+}
+
+/// The current log, ignoring the one left over from a previous session
+fn read_session_log(log_path: &Path, since: SystemTime) -> String {
+    let fresh = fs::metadata(log_path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified >= since);
+
+    if fresh {
+        fs::read_to_string(log_path).unwrap_or_default()
+    } else {
+        String::new()
+    }
+}
+
+fn find_crash_report(game_dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(game_dir.join("crash-reports"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= since)
+        })
+        .max()
+}
+
+fn scan_log(log_path: &Path) -> Vec<&'static str> {
+    let Ok(log) = fs::read_to_string(log_path) else {
+        return Vec::new();
+    };
+
+    LOG_HINTS
+        .iter()
+        .filter(|(needle, _)| log.contains(needle))
+        .map(|(_, hint)| *hint)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration(" 1h30m "), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 60 * 60)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for input in ["", "0", "0m", "h", "1d", "1h30", "-5m", "1.5h"] {
+            assert!(parse_duration(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration("18446744073709551615").is_err());
+        assert!(parse_duration("18446744073709551615h").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Config, Context, Editor, Helper};
+use std::path::PathBuf;
+
+use crate::{
+    auth::{fetch_file, get_selected_account_name},
+    dir::get_app_support_dir,
+    searchindex::indexed_maps,
+};
+
+/// Commands kept in the history file
+const HISTORY_SIZE: usize = 1000;
+
+fn get_history_path() -> PathBuf {
+    get_app_support_dir().unwrap().join(".shell_history")
+}
+
+/// Completes command names, then map ids, and account names after `--account`
+struct ShellHelper {
+    commands: Vec<String>,
+    maps: Vec<String>,
+    accounts: Vec<String>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = before[start..].to_lowercase();
+
+        let candidates = match before[..start].split_whitespace().last() {
+            None => &self.commands,
+            Some("--account") => &self.accounts,
+            Some(_) if word.starts_with('-') => return Ok((start, Vec::new())),
+            Some(_) => &self.maps,
+        };
+        let pairs = candidates
+            .iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate.clone(),
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Split a line into arguments as a shell would, quotes keep spaces in one argument
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None | Some('"'), '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or("Nothing to escape at the end of the line")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("Unclosed quote".into());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The prompt of `ovl shell`
+pub struct Shell {
+    editor: Editor<ShellHelper, DefaultHistory>,
+}
+
+impl Shell {
+    /// Open the prompt, completing the given command names and the maps and accounts known now
+    pub fn start(commands: Vec<String>) -> Result<Shell, Box<dyn std::error::Error>> {
+        let config = Config::builder().max_history_size(HISTORY_SIZE)?.build();
+        let mut editor = Editor::with_config(config)?;
+
+        let maps = indexed_maps()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|map| map.id)
+            .collect();
+        let accounts = match get_selected_account_name() {
+            Some(_) => fetch_file(false)
+                .accounts
+                .into_iter()
+                .map(|account| account.name)
+                .collect(),
+            None => Vec::new(),
+        };
+        editor.set_helper(Some(ShellHelper {
+            commands,
+            maps,
+            accounts,
+        }));
+        // There is no history the first time
+        let _ = editor.load_history(&get_history_path());
+
+        Ok(Shell { editor })
+    }
+
+    /// The arguments of the next command typed, `None` once the user leaves
+    pub fn next_command(&mut self) -> Option<Vec<String>> {
+        loop {
+            let line = match self.editor.readline("ovl> ") {
+                Ok(line) => line,
+                // Ctrl+C only clears the line, as in a shell
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => return None,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return None;
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if matches!(line, "exit" | "quit") {
+                return None;
+            }
+
+            let _ = self.editor.add_history_entry(line);
+            let _ = self.editor.append_history(&get_history_path());
+            match split_line(line) {
+                Ok(words) => return Some(words),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+}
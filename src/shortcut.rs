@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::map::{extract_map_icon, fetch_maps, install_map, is_safe_map_id};
+
+fn get_shortcut_dir() -> PathBuf {
+    dirs::desktop_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &PathBuf) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// The map name without control characters, for one-line fields
+fn single_line(name: &str) -> String {
+    name.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// The map name as a file name in the shortcut folder, falling back to the id when nothing
+/// usable is left
+fn shortcut_file_name(name: &str, id: &str) -> String {
+    let name: String = single_line(name)
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    match name.trim().trim_matches('.') {
+        "" => id.replace(':', "-"),
+        name => name.to_string(),
+    }
+}
+
+/// A POSIX shell word
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// An argument of the `Exec` key of a `.desktop` file. Its backslashes are unescaped once
+/// as a string value before the quoting is read, so they are doubled twice.
+fn desktop_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in single_line(value).chars() {
+        match c {
+            '"' | '`' | '$' => {
+                quoted.push_str(r"\\");
+                quoted.push(c);
+            }
+            '\\' => quoted.push_str(r"\\\\"),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Create a desktop shortcut that runs `ovl play <map> --yes`, returning where it was written
+pub fn create_shortcut(query: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let map = fetch_maps()?
+        .into_iter()
+        .find(|m| m.id.eq_ignore_ascii_case(query) || m.name.eq_ignore_ascii_case(query))
+        .ok_or_else(|| format!("Unknown map: {}", query))?;
+    // The id ends up in scripts, the name in file names and one-line fields
+    if !is_safe_map_id(&map.id) {
+        return Err(format!("{:?} is not a valid map id", map.id).into());
+    }
+    let name = single_line(&map.name);
+    let file_name = shortcut_file_name(&map.name, &map.id);
+
+    let exe = std::env::current_exe()?;
+    let args = format!("play {} --yes", map.id);
+    let dir = get_shortcut_dir();
+
+    if cfg!(windows) {
+        let path = dir.join(format!("{}.lnk", file_name));
+        // Writing .lnk files by hand is not worth it when Windows can do it
+        let script = format!(
+            "$s = (New-Object -ComObject WScript.Shell).CreateShortcut('{}'); $s.TargetPath = '{}'; $s.Arguments = '{}'; $s.Description = 'Play {} with OpenVoxel'; $s.Save()",
+            path.display().to_string().replace('\'', "''"),
+            exe.display().to_string().replace('\'', "''"),
+            args.replace('\'', "''"),
+            name.replace('\'', "''"),
+        );
+        let status = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .status()?;
+        if !status.success() {
+            return Err("PowerShell could not create the shortcut".into());
+        }
+        return Ok(path);
+    }
+
+    let path = if cfg!(target_os = "macos") {
+        let path = dir.join(format!("{}.command", file_name));
+        fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\nexec {} play {} --yes\n",
+                sh_quote(&exe.display().to_string()),
+                sh_quote(&map.id)
+            ),
+        )?;
+        path
+    } else {
+        // The icon comes from the world, so the map has to be downloaded once
+        install_map(map.id.clone())?;
+        let icon = extract_map_icon(&map.id)
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+
+        let path = dir.join(format!("ovl-{}.desktop", map.id.replace(':', "-")));
+        fs::write(
+            &path,
+            format!(
+                "[Desktop Entry]\nType=Application\nName={}\nComment=Play {} with OpenVoxel\nExec={} play {} --yes\nIcon={}\nTerminal=true\nCategories=Game;\n",
+                name,
+                name,
+                desktop_quote(&exe.display().to_string()),
+                desktop_quote(&map.id),
+                single_line(&icon)
+            ),
+        )?;
+        path
+    };
+
+    #[cfg(unix)]
+    make_executable(&path)?;
+    Ok(path)
+}
+
+/// Whether a file of the desktop is a shortcut written by `create_shortcut`
+fn is_ovl_shortcut(path: &Path) -> bool {
+    let Ok(content) = fs::read(path) else {
+        return false;
+    };
+    let contains = |needle: &[u8]| content.windows(needle.len()).any(|w| w == needle);
+
+    match path.extension().and_then(|e| e.to_str()) {
+        // Windows keeps the description of a .lnk as UTF-16
+        Some("lnk") => {
+            let needle: Vec<u8> = " with OpenVoxel"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            contains(&needle)
+        }
+        Some("command") => content.starts_with(b"#!/bin/sh\nexec ") && contains(b" --yes\n"),
+        Some("desktop") => {
+            path.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("ovl-"))
+                && contains(b" with OpenVoxel\n")
+        }
+        _ => false,
+    }
+}
+
+/// Desktop shortcuts created by `ovl shortcut`
+pub fn find_shortcuts() -> Vec<PathBuf> {
+    fs::read_dir(get_shortcut_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && is_ovl_shortcut(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcut_file_names_stay_in_the_folder() {
+        assert_eq!(
+            shortcut_file_name("Escape Room", "escape-room"),
+            "Escape Room"
+        );
+        assert_eq!(shortcut_file_name("../../.bashrc", "escape-room"), "bashrc");
+        assert_eq!(
+            shortcut_file_name("..", "school:escape-room"),
+            "school-escape-room"
+        );
+        assert_eq!(shortcut_file_name("a\nb/c", "x"), "abc");
+    }
+
+    #[test]
+    fn quotes_for_the_shell() {
+        assert_eq!(sh_quote("/Apps/o'vl"), r"'/Apps/o'\''vl'");
+    }
+
+    #[test]
+    fn quotes_for_desktop_files() {
+        assert_eq!(desktop_quote("/opt/ovl"), "\"/opt/ovl\"");
+        assert_eq!(desktop_quote("a\"$`%\nb"), r#""a\\"\\$\\`%%b""#);
+        assert_eq!(desktop_quote(r"C:\ovl"), r#""C:\\\\ovl""#);
+    }
+}
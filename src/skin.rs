@@ -0,0 +1,72 @@
+use image::{GenericImageView, ImageFormat};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    auth::fetch_file,
+    config::{load_config, save_config},
+    dir::get_game_dir,
+};
+
+/// Loads skins from local files for offline accounts
+const SKIN_LOADER_MOD: &str = "customskinloader";
+
+pub fn get_local_skin_path(name: &str) -> PathBuf {
+    get_game_dir()
+        .join("CustomSkinLoader")
+        .join("LocalSkin")
+        .join("skins")
+        .join(format!("{}.png", name))
+}
+
+/// Use a local PNG as the skin of the selected offline account, or remove it with `None`
+pub fn set_offline_skin(png: Option<&Path>) -> Result<String, Box<dyn std::error::Error>> {
+    let accounts = fetch_file(false);
+    let account = accounts
+        .accounts
+        .iter()
+        .find(|a| a.name == accounts.selected)
+        .ok_or("No account selected")?;
+
+    if !account.offline {
+        return Err(
+            "Skins can only be set for offline accounts, online accounts use their Minecraft skin"
+                .into(),
+        );
+    }
+
+    let target = get_local_skin_path(&account.name);
+
+    let Some(png) = png else {
+        if target.exists() {
+            std::fs::remove_file(&target)?;
+        }
+        return Ok(account.name.clone());
+    };
+
+    let bytes = std::fs::read(png)?;
+    let image = image::load_from_memory_with_format(&bytes, ImageFormat::Png)
+        .map_err(|e| format!("The skin must be a PNG file: {}", e))?;
+    // 64x32 is the layout of skins from before 1.8
+    if !matches!(image.dimensions(), (64, 64) | (64, 32)) {
+        return Err(format!(
+            "The skin must be 64x64 or 64x32 pixels, this one is {}x{}",
+            image.width(),
+            image.height()
+        )
+        .into());
+    }
+
+    std::fs::create_dir_all(target.parent().unwrap())?;
+    std::fs::write(&target, bytes)?;
+
+    let mut config = load_config();
+    if !config.extra_mods.iter().any(|m| m == SKIN_LOADER_MOD) {
+        config.extra_mods.push(SKIN_LOADER_MOD.to_string());
+        save_config(&config)?;
+        eprintln!(
+            "Added CustomSkinLoader to the extra mods, it will be installed on the next launch."
+        );
+    }
+
+    Ok(account.name.clone())
+}
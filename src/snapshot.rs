@@ -0,0 +1,312 @@
+use chrono::Local;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::{
+    audit::audit,
+    dir::{get_app_support_dir, get_game_dir},
+    filesys::get_used_version_save,
+    manifest::{forget_file, refresh_file},
+    theme::Themed,
+};
+
+/// Snapshots kept, older ones are dropped with the files only they used
+const MAX_SNAPSHOTS: usize = 10;
+
+/// The managed files of the game folder at one point in time, stored by content hash
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub reason: String,
+    /// Set once a game session ended without crashing with this state
+    pub good: bool,
+    pub version: Option<String>,
+    /// sha256 of each file, by path relative to the data directory
+    pub files: BTreeMap<String, String>,
+}
+
+/// Size, modification time and hash of a managed file when it was last captured, so that
+/// unchanged files are not hashed again
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct HashedFile {
+    size: u64,
+    /// Nanoseconds since the Unix epoch
+    modified: u64,
+    sha256: String,
+}
+
+fn get_snapshots_dir() -> PathBuf {
+    get_app_support_dir().unwrap().join("snapshots")
+}
+
+fn get_objects_dir() -> PathBuf {
+    get_snapshots_dir().join("objects")
+}
+
+fn get_index_path() -> PathBuf {
+    get_snapshots_dir().join("index.json")
+}
+
+fn get_hashes_path() -> PathBuf {
+    get_snapshots_dir().join("hashes.json")
+}
+
+fn read_hashes() -> BTreeMap<String, HashedFile> {
+    fs::read_to_string(get_hashes_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn read_snapshots() -> Vec<Snapshot> {
+    fs::read_to_string(get_index_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_snapshots(snapshots: &[Snapshot]) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(get_snapshots_dir())?;
+    fs::write(get_index_path(), serde_json::to_string_pretty(snapshots)?)?;
+    Ok(())
+}
+
+/// The files a snapshot covers: mod jars, options.txt, the OpenVoxel pack and mods.lock
+fn managed_files() -> Vec<PathBuf> {
+    let app_dir = get_app_support_dir().unwrap();
+    let game_dir = get_game_dir();
+
+    let mut files: Vec<PathBuf> = fs::read_dir(game_dir.join("mods"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "jar"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.push(game_dir.join("options.txt"));
+    files.push(game_dir.join("resourcepacks").join("OVP.zip"));
+    files.push(app_dir.join("mods.lock"));
+
+    files
+        .into_iter()
+        .filter(|p| p.is_file())
+        .filter_map(|p| Some(p.strip_prefix(&app_dir).ok()?.to_path_buf()))
+        .collect()
+}
+
+/// Hash the current managed files, copying any new content to the object store. Files with
+/// the same size and modification time as at the last capture keep their hash.
+fn capture() -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let app_dir = get_app_support_dir().unwrap();
+    fs::create_dir_all(get_objects_dir())?;
+
+    let known = read_hashes();
+    let mut hashes = BTreeMap::new();
+    let mut files = BTreeMap::new();
+    for relative in managed_files() {
+        let path = app_dir.join(&relative);
+        let key = relative.to_string_lossy().replace('\\', "/");
+        let metadata = fs::metadata(&path)?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_nanos() as u64)
+            .unwrap_or_default();
+
+        let unchanged = known
+            .get(&key)
+            .filter(|h| h.size == size && h.modified == modified && modified != 0)
+            .filter(|h| get_objects_dir().join(&h.sha256).exists());
+        let hash = match unchanged {
+            Some(hashed) => hashed.sha256.clone(),
+            None => {
+                let content = fs::read(&path)?;
+                let hash = sha256::digest(&content);
+                let object = get_objects_dir().join(&hash);
+                if !object.exists() {
+                    fs::write(object, &content)?;
+                }
+                hash
+            }
+        };
+        hashes.insert(
+            key.clone(),
+            HashedFile {
+                size,
+                modified,
+                sha256: hash.clone(),
+            },
+        );
+        files.insert(key, hash);
+    }
+    fs::write(get_hashes_path(), serde_json::to_string_pretty(&hashes)?)?;
+    Ok(files)
+}
+
+/// Drop the oldest snapshots, except `keep`, and the objects no remaining snapshot uses
+fn prune(snapshots: &mut Vec<Snapshot>, keep: Option<&str>) {
+    while snapshots.len() > MAX_SNAPSHOTS {
+        let Some(oldest) = snapshots.iter().position(|s| Some(s.id.as_str()) != keep) else {
+            break;
+        };
+        snapshots.remove(oldest);
+    }
+
+    let used: HashSet<&String> = snapshots.iter().flat_map(|s| s.files.values()).collect();
+    if let Ok(entries) = fs::read_dir(get_objects_dir()) {
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if !used.contains(&name) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+fn record(reason: &str, good: bool, keep: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let files = capture()?;
+    let mut snapshots = read_snapshots();
+
+    match snapshots.last_mut() {
+        // Nothing changed since the last snapshot
+        Some(last) if last.files == files => last.good |= good,
+        _ => snapshots.push(Snapshot {
+            id: Local::now().format("%Y%m%d-%H%M%S").to_string(),
+            reason: reason.to_string(),
+            good,
+            version: get_used_version_save(),
+            files,
+        }),
+    }
+
+    prune(&mut snapshots, keep);
+    write_snapshots(&snapshots)
+}
+
+/// Record the managed state before something changes it, so `ovl rollback` can bring it back
+pub fn take_snapshot(reason: &str) {
+    if let Err(e) = record(reason, false, None) {
+        eprintln!("Warning: Failed to snapshot the game setup: {}", e);
+    }
+}
+
+/// Remember the current state as known-good, after a session that did not crash
+pub fn mark_good() {
+    if let Err(e) = record("clean game session", true, None) {
+        eprintln!("Warning: Failed to snapshot the game setup: {}", e);
+    }
+}
+
+pub fn print_snapshots(snapshots: &[Snapshot]) {
+    if snapshots.is_empty() {
+        println!("No snapshots yet, one is taken before the mods, version or pack change.");
+        return;
+    }
+
+    for snapshot in snapshots.iter().rev() {
+        println!(
+            "{}  {}{}  {} files{}",
+            snapshot.id.bold(),
+            snapshot.reason,
+            snapshot
+                .version
+                .as_ref()
+                .map(|v| format!(" ({})", v))
+                .unwrap_or_default(),
+            snapshot.files.len(),
+            if snapshot.good {
+                format!("  {}", "known-good".success())
+            } else {
+                String::new()
+            }
+        );
+    }
+}
+
+fn restore(snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+    let app_dir = get_app_support_dir().unwrap();
+
+    // Nothing is removed unless every file can be brought back
+    let missing: Vec<&String> = snapshot
+        .files
+        .iter()
+        .filter(|(_, hash)| !get_objects_dir().join(hash).is_file())
+        .map(|(relative, _)| relative)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "The snapshot {} cannot be restored, the content of {} is gone",
+            snapshot.id,
+            missing
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into());
+    }
+
+    for relative in managed_files() {
+        let key = relative.to_string_lossy().replace('\\', "/");
+        if !snapshot.files.contains_key(&key) {
+            let path = app_dir.join(&relative);
+            fs::remove_file(&path)?;
+            forget_file(&path);
+        }
+    }
+
+    for (relative, hash) in &snapshot.files {
+        let path = app_dir.join(Path::new(relative));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // The file may be a link to a shared object, copying into it would change the object
+        let _ = fs::remove_file(&path);
+        fs::copy(get_objects_dir().join(hash), &path)?;
+        refresh_file(&path);
+    }
+
+    if let Some(version) = &snapshot.version {
+        fs::write(get_game_dir().join("mods").join(".ovl"), version)?;
+    }
+    Ok(())
+}
+
+/// Restore a snapshot, by default the latest known-good one that differs from the current state
+pub fn rollback(id: Option<&String>) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let current = capture()?;
+    let snapshots = read_snapshots();
+
+    let target = match id {
+        Some(id) => snapshots.iter().rev().find(|s| &s.id == id),
+        None => snapshots
+            .iter()
+            .rev()
+            .filter(|s| s.files != current)
+            .find(|s| s.good)
+            .or_else(|| snapshots.iter().rev().find(|s| s.files != current)),
+    }
+    .cloned()
+    .ok_or("No snapshot to roll back to, see `ovl rollback --list`")?;
+
+    // The current state becomes a snapshot too, so the rollback can be undone, without
+    // pruning the one being restored
+    if let Err(e) = record(
+        &format!("before rolling back to {}", target.id),
+        false,
+        Some(&target.id),
+    ) {
+        eprintln!("Warning: Failed to snapshot the game setup: {}", e);
+    }
+    restore(&target)?;
+    audit("rollback", &format!("{} ({})", target.id, target.reason));
+    Ok(target)
+}
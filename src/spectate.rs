@@ -0,0 +1,85 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::{
+    auth::fetch_file,
+    console::connect_console,
+    mc::{LaunchOptions, launch},
+    server::get_local_ip,
+    theme::Themed,
+    versions::resolve_version,
+};
+
+/// Whether `host` points at this computer
+fn is_local_host(host: &str) -> bool {
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    if name.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    match name.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback() || get_local_ip() == Some(ip),
+        Err(_) => false,
+    }
+}
+
+/// Watch the console of the local server and switch `player` to spectator once they join
+fn spectate_on_join(server_dir: &Path, player: String) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = connect_console(server_dir)?;
+    let output = BufReader::new(stream.try_clone()?);
+
+    std::thread::spawn(move || {
+        let joined = format!(" {} joined the game", player);
+        let switched = format!("Set {}'s game mode to Spectator Mode", player);
+        let mut sent = false;
+
+        for line in output.lines().map_while(Result::ok) {
+            // The backlog may replay an older join, the command then fails and the next join retries
+            if line.ends_with(&joined) {
+                sent = writeln!(stream, "gamemode spectator {}", player).is_ok();
+            } else if sent && line.contains(&switched) {
+                println!("\n{}", "Switched to spectator mode.".success());
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Join a server as a spectator, switching game mode through the console when we host it
+pub async fn spectate(
+    host: &str,
+    version: Option<&String>,
+    server_dir: &Path,
+    options: &LaunchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hosted = is_local_host(host) && server_dir.join(".ovl-console").exists();
+
+    let version = match version {
+        Some(version) => resolve_version(version).await?.id,
+        None if hosted => std::fs::read_to_string(server_dir.join(".ovl-version"))?
+            .trim()
+            .to_string(),
+        None => return Err("Pass the Minecraft version of the server with --version".into()),
+    };
+
+    let player = fetch_file(false).selected;
+    let mut options = options.clone();
+    if hosted {
+        spectate_on_join(server_dir, player)?;
+        println!("You will be switched to spectator mode once you join.");
+        // The console has to be watched until the player joins
+        options.detach = false;
+    } else {
+        println!(
+            "{} Ask a server operator to run `/gamemode spectator {}` once you join.",
+            "Note:".warning(),
+            player
+        );
+    }
+
+    println!("Launching Minecraft {}...\n", version);
+    launch(version, None, Some(&host.to_string()), &options).await;
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    auth::fetch_file,
+    dir::{get_app_support_dir, get_game_dir},
+    profile::{dashed_uuid, lookup_profile},
+};
+
+/// What a tester can send back to a map creator after playing
+#[derive(Debug, Serialize)]
+pub struct PlayerStats {
+    pub uuid: String,
+    pub name: Option<String>,
+    pub play_time_minutes: u64,
+    pub deaths: u64,
+    pub distance_blocks: u64,
+    pub jumps: u64,
+    pub mob_kills: u64,
+    pub damage_taken: u64,
+    pub advancements: Vec<String>,
+}
+
+/// Custom statistics summed up as travelled distance, in centimeters
+const DISTANCE_STATS: &[&str] = &[
+    "walk_one_cm",
+    "sprint_one_cm",
+    "crouch_one_cm",
+    "swim_one_cm",
+    "walk_under_water_one_cm",
+    "walk_on_water_one_cm",
+    "fly_one_cm",
+    "climb_one_cm",
+    "fall_one_cm",
+    "aviate_one_cm",
+    "boat_one_cm",
+    "horse_one_cm",
+    "minecart_one_cm",
+];
+
+/// Names of the players known to this launcher, by dashed UUID
+fn known_players() -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let minecraft_dir = get_game_dir();
+
+    if let Ok(content) = fs::read_to_string(minecraft_dir.join("usercache.json"))
+        && let Ok(Value::Array(entries)) = serde_json::from_str(&content)
+    {
+        for entry in entries {
+            if let (Some(uuid), Some(name)) = (entry["uuid"].as_str(), entry["name"].as_str()) {
+                names.insert(dashed_uuid(uuid), name.to_string());
+            }
+        }
+    }
+
+    if get_app_support_dir().unwrap().join(".accounts").exists() {
+        for account in fetch_file(false).accounts {
+            if let Some(uuid) = &account.uuid {
+                names.insert(dashed_uuid(uuid), account.name);
+            }
+        }
+    }
+
+    names
+}
+
+fn read_player_stats(world_dir: &Path, uuid: &str, name: Option<String>) -> PlayerStats {
+    let stats: Value = fs::read_to_string(world_dir.join("stats").join(format!("{}.json", uuid)))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let custom = &stats["stats"]["minecraft:custom"];
+    let stat = |name: &str| custom[format!("minecraft:{}", name)].as_u64().unwrap_or(0);
+
+    // Renamed from play_one_minute in 1.17, both count ticks
+    let ticks = stat("play_time").max(stat("play_one_minute"));
+
+    let advancements: Value = fs::read_to_string(
+        world_dir
+            .join("advancements")
+            .join(format!("{}.json", uuid)),
+    )
+    .ok()
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+    let mut done: Vec<String> = advancements
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(id, advancement)| {
+            !id.contains(":recipes/") && advancement["done"].as_bool() == Some(true)
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    done.sort();
+
+    PlayerStats {
+        uuid: uuid.to_string(),
+        name,
+        play_time_minutes: ticks / 20 / 60,
+        deaths: stat("deaths"),
+        distance_blocks: DISTANCE_STATS.iter().map(|s| stat(s)).sum::<u64>() / 100,
+        jumps: stat("jump"),
+        mob_kills: stat("mob_kills"),
+        // Stored in tenths of hearts
+        damage_taken: stat("damage_taken") / 10,
+        advancements: done,
+    }
+}
+
+/// Statistics of every player of a world, or only of `player` (name or UUID)
+pub async fn collect_world_stats(
+    world_dir: &Path,
+    player: Option<&String>,
+) -> Result<Vec<PlayerStats>, Box<dyn std::error::Error>> {
+    let known = known_players();
+
+    let mut uuids: Vec<String> = fs::read_dir(world_dir.join("stats"))
+        .into_iter()
+        .chain(fs::read_dir(world_dir.join("advancements")))
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            (path.extension()? == "json").then(|| path.file_stem()?.to_str().map(str::to_string))?
+        })
+        .collect();
+    uuids.sort();
+    uuids.dedup();
+
+    if let Some(player) = player {
+        let uuid = match known
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(player))
+        {
+            Some((uuid, _)) => uuid.clone(),
+            None if player.len() >= 32 => dashed_uuid(player),
+            None => match lookup_profile(player).await? {
+                Some(profile) => dashed_uuid(&profile.uuid),
+                None => return Err(format!("Unknown player: {}", player).into()),
+            },
+        };
+
+        if !uuids.contains(&uuid) {
+            return Err(format!("{} has not played in this world", player).into());
+        }
+        uuids = vec![uuid];
+    }
+
+    Ok(uuids
+        .into_iter()
+        .map(|uuid| {
+            let name = known.get(&uuid).cloned();
+            read_player_stats(world_dir, &uuid, name)
+        })
+        .collect())
+}
+
+pub fn print_world_stats(stats: &[PlayerStats]) {
+    if stats.is_empty() {
+        println!("Nobody has played in this world yet.");
+        return;
+    }
+
+    for player in stats {
+        println!("{}", player.name.as_deref().unwrap_or(&player.uuid).bold());
+        println!(
+            "  Play time: {}h {:02}m",
+            player.play_time_minutes / 60,
+            player.play_time_minutes % 60
+        );
+        println!("  Deaths: {}", player.deaths);
+        println!("  Distance: {} blocks", player.distance_blocks);
+        println!("  Jumps: {}", player.jumps);
+        println!("  Mobs killed: {}", player.mob_kills);
+        println!("  Damage taken: {} hearts", player.damage_taken / 2);
+        println!("  Advancements: {}", player.advancements.len());
+        for advancement in &player.advancements {
+            println!("    {}", advancement);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use flate2::Crc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cmd::ask_yes_no_attended,
+    map::{extract_map_icon, fetch_maps, install_map},
+    versions::resolve_version,
+};
+
+/// A node of Steam's binary VDF format, as used by `shortcuts.vdf`. Keys and strings are
+/// kept as raw bytes, they are not always UTF-8 and the shortcuts of other launchers are
+/// written back untouched.
+#[derive(Debug, Clone)]
+enum Vdf {
+    Map(Vec<(Vec<u8>, Vdf)>),
+    Str(Vec<u8>),
+    Int(u32),
+    Long(u64),
+}
+
+const MAP: u8 = 0x00;
+const STR: u8 = 0x01;
+const INT: u8 = 0x02;
+const LONG: u8 = 0x07;
+const END: u8 = 0x08;
+
+fn read_cstring(data: &[u8], pos: &mut usize) -> Result<Vec<u8>, String> {
+    let len = data[*pos..]
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or("unterminated string")?;
+    let value = data[*pos..*pos + len].to_vec();
+    *pos += len + 1;
+    Ok(value)
+}
+
+fn read_map(data: &[u8], pos: &mut usize) -> Result<Vec<(Vec<u8>, Vdf)>, String> {
+    let mut entries = Vec::new();
+    loop {
+        let kind = *data.get(*pos).ok_or("unexpected end of file")?;
+        *pos += 1;
+        if kind == END {
+            return Ok(entries);
+        }
+
+        let key = read_cstring(data, pos)?;
+        let value = match kind {
+            MAP => Vdf::Map(read_map(data, pos)?),
+            STR => Vdf::Str(read_cstring(data, pos)?),
+            INT => {
+                let bytes = data.get(*pos..*pos + 4).ok_or("unexpected end of file")?;
+                *pos += 4;
+                Vdf::Int(u32::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            LONG => {
+                let bytes = data.get(*pos..*pos + 8).ok_or("unexpected end of file")?;
+                *pos += 8;
+                Vdf::Long(u64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            other => return Err(format!("unknown field type {:#04x}", other)),
+        };
+        entries.push((key, value));
+    }
+}
+
+fn write_map(entries: &[(Vec<u8>, Vdf)], out: &mut Vec<u8>) {
+    for (key, value) in entries {
+        let kind = match value {
+            Vdf::Map(_) => MAP,
+            Vdf::Str(_) => STR,
+            Vdf::Int(_) => INT,
+            Vdf::Long(_) => LONG,
+        };
+        out.push(kind);
+        out.extend_from_slice(key);
+        out.push(0);
+
+        match value {
+            Vdf::Map(entries) => write_map(entries, out),
+            Vdf::Str(s) => {
+                out.extend_from_slice(s);
+                out.push(0);
+            }
+            Vdf::Int(n) => out.extend_from_slice(&n.to_le_bytes()),
+            Vdf::Long(n) => out.extend_from_slice(&n.to_le_bytes()),
+        }
+    }
+    out.push(END);
+}
+
+fn get_steam_dir() -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if cfg!(windows) {
+        vec![PathBuf::from(r"C:\Program Files (x86)\Steam")]
+    } else if cfg!(target_os = "macos") {
+        vec![dirs::data_dir()?.join("Steam")]
+    } else {
+        let home = dirs::home_dir()?;
+        vec![
+            home.join(".steam").join("steam"),
+            home.join(".local").join("share").join("Steam"),
+            home.join(".var/app/com.valvesoftware.Steam/data/Steam"),
+        ]
+    };
+
+    candidates
+        .into_iter()
+        .find(|dir| dir.join("userdata").exists())
+}
+
+/// Steam's id for a non-Steam game, used to name its artwork
+fn shortcut_app_id(exe: &str, name: &str) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(exe.as_bytes());
+    crc.update(name.as_bytes());
+    crc.sum() | 0x8000_0000
+}
+
+/// The `userdata` folder of every Steam account that logged in on this computer
+fn user_dirs(steam_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(steam_dir.join("userdata"))? {
+        let user_dir = entry?.path();
+        // Only the numeric folders are accounts
+        let is_account = user_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n != "0" && n.chars().all(|c| c.is_ascii_digit()));
+        if is_account {
+            dirs.push(user_dir);
+        }
+    }
+    Ok(dirs)
+}
+
+fn get_shortcuts_path(user_dir: &Path) -> PathBuf {
+    user_dir.join("config").join("shortcuts.vdf")
+}
+
+fn read_shortcuts(path: &Path) -> Result<Vec<(Vec<u8>, Vdf)>, String> {
+    match fs::read(path) {
+        Ok(data) => {
+            let mut pos = 0;
+            match read_map(&data, &mut pos)?.into_iter().next() {
+                Some((_, Vdf::Map(entries))) => Ok(entries),
+                _ => Ok(Vec::new()),
+            }
+        }
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn save_shortcuts(
+    path: &Path,
+    mut shortcuts: Vec<(Vec<u8>, Vdf)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Entries are keyed by their position
+    for (index, (key, _)) in shortcuts.iter_mut().enumerate() {
+        *key = index.to_string().into_bytes();
+    }
+
+    let mut out = Vec::new();
+    write_map(&[("shortcuts".into(), Vdf::Map(shortcuts))], &mut out);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // The library of the player, a copy is kept in case Steam cannot read the new one
+    if path.exists() {
+        fs::copy(path, path.with_extension("vdf.bak"))?;
+    }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+/// Add a shortcut to one `shortcuts.vdf`, replacing the one `ovl steam` added with the same
+/// name. Shortcuts added by hand or by other launchers are never touched.
+fn write_shortcut(
+    path: &Path,
+    shortcut: Vec<(Vec<u8>, Vdf)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut shortcuts = read_shortcuts(path)?;
+
+    let name_of = |entries: &[(Vec<u8>, Vdf)]| {
+        entries.iter().find_map(|(key, value)| match value {
+            Vdf::Str(name) if key.eq_ignore_ascii_case(b"appname") => Some(name.clone()),
+            _ => None,
+        })
+    };
+    let name = name_of(&shortcut);
+    shortcuts.retain(|(_, entry)| {
+        !(is_ovl_shortcut(entry) && matches!(entry, Vdf::Map(e) if name_of(e) == name))
+    });
+    shortcuts.push((Vec::new(), Vdf::Map(shortcut)));
+    save_shortcuts(path, shortcuts)
+}
+
+/// Whether a shortcut was added by `add_steam_shortcut`, which tags them all
+fn is_ovl_shortcut(entry: &Vdf) -> bool {
+    let Vdf::Map(fields) = entry else {
+        return false;
+    };
+    fields.iter().any(|(key, value)| match value {
+        Vdf::Map(tags) if key.eq_ignore_ascii_case(b"tags") => tags
+            .iter()
+            .any(|(_, tag)| matches!(tag, Vdf::Str(tag) if tag == b"OpenVoxel")),
+        _ => false,
+    })
+}
+
+/// How many shortcuts of the Steam library were added by `ovl steam`, across accounts
+pub fn count_steam_shortcuts() -> usize {
+    let Some(user_dirs) = get_steam_dir().and_then(|dir| user_dirs(&dir).ok()) else {
+        return 0;
+    };
+    user_dirs
+        .iter()
+        .filter_map(|dir| read_shortcuts(&get_shortcuts_path(dir)).ok())
+        .flatten()
+        .filter(|(_, entry)| is_ovl_shortcut(entry))
+        .count()
+}
+
+/// Remove the shortcuts added by `ovl steam` from the Steam library. Steam has to be closed.
+pub fn remove_steam_shortcuts() -> Result<usize, Box<dyn std::error::Error>> {
+    let Some(steam_dir) = get_steam_dir() else {
+        return Ok(0);
+    };
+
+    let mut removed = 0;
+    for user_dir in user_dirs(&steam_dir)? {
+        let path = get_shortcuts_path(&user_dir);
+        let mut shortcuts = read_shortcuts(&path)?;
+        let before = shortcuts.len();
+        shortcuts.retain(|(_, entry)| !is_ovl_shortcut(entry));
+        if shortcuts.len() < before {
+            removed += before - shortcuts.len();
+            save_shortcuts(&path, shortcuts)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Add an OpenVoxel map or a Minecraft version to the Steam library as a non-Steam game
+pub async fn add_steam_shortcut(target: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let steam_dir = get_steam_dir().ok_or("Steam was not found on this computer")?;
+
+    let map = fetch_maps()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|m| m.id.eq_ignore_ascii_case(target) || m.name.eq_ignore_ascii_case(target));
+    let (name, launch_options, icon) = match map {
+        Some(map) => {
+            // The icon comes from the world, so the map has to be downloaded once
+            install_map(map.id.clone())?;
+            (
+                map.name.clone(),
+                // Nobody can answer questions in Big Picture
+                format!("play {} --non-interactive", map.id),
+                extract_map_icon(&map.id),
+            )
+        }
+        None => {
+            let version = resolve_version(target).await.map_err(|_| {
+                format!(
+                    "{} is neither an OpenVoxel map nor a Minecraft version",
+                    target
+                )
+            })?;
+            (
+                format!("Minecraft {}", version.id),
+                format!("run {} --non-interactive", version.id),
+                None,
+            )
+        }
+    };
+
+    let exe_path = std::env::current_exe()?;
+    let exe = format!("\"{}\"", exe_path.display());
+    let start_dir = format!(
+        "\"{}\"",
+        exe_path.parent().unwrap_or(Path::new(".")).display()
+    );
+    let icon = icon.map(|p| p.display().to_string()).unwrap_or_default();
+
+    let shortcut = vec![
+        ("appid".into(), Vdf::Int(shortcut_app_id(&exe, &name))),
+        ("AppName".into(), Vdf::Str(name.clone().into_bytes())),
+        ("Exe".into(), Vdf::Str(exe.into_bytes())),
+        ("StartDir".into(), Vdf::Str(start_dir.into_bytes())),
+        ("icon".into(), Vdf::Str(icon.into_bytes())),
+        ("ShortcutPath".into(), Vdf::Str(Vec::new())),
+        (
+            "LaunchOptions".into(),
+            Vdf::Str(launch_options.into_bytes()),
+        ),
+        ("IsHidden".into(), Vdf::Int(0)),
+        ("AllowDesktopConfig".into(), Vdf::Int(1)),
+        ("AllowOverlay".into(), Vdf::Int(1)),
+        ("OpenVR".into(), Vdf::Int(0)),
+        ("LastPlayTime".into(), Vdf::Int(0)),
+        (
+            "tags".into(),
+            Vdf::Map(vec![("0".into(), Vdf::Str("OpenVoxel".into()))]),
+        ),
+    ];
+
+    // Steam rewrites its shortcuts when it exits, dropping anything added meanwhile
+    if !ask_yes_no_attended("Steam has to be closed while the shortcut is added. Is it closed?") {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let mut users = 0;
+    for user_dir in user_dirs(&steam_dir)? {
+        write_shortcut(&get_shortcuts_path(&user_dir), shortcut.clone())?;
+        users += 1;
+    }
+
+    if users == 0 {
+        return Err("No Steam account has logged in on this computer yet".into());
+    }
+
+    println!(
+        "Added {} to the Steam library, it shows up the next time Steam starts.",
+        name
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(entries: &[(Vec<u8>, Vdf)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_map(entries, &mut out);
+        out
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let shortcut = vec![
+            ("appid".into(), Vdf::Int(0x8000_1234)),
+            ("AppName".into(), Vdf::Str("Parkour Paradise".into())),
+            ("LastPlayTime".into(), Vdf::Long(1_700_000_000)),
+            (
+                "tags".into(),
+                Vdf::Map(vec![("0".into(), Vdf::Str("OpenVoxel".into()))]),
+            ),
+        ];
+        let root = vec![(
+            "shortcuts".into(),
+            Vdf::Map(vec![("0".into(), Vdf::Map(shortcut))]),
+        )];
+        let data = encode(&root);
+
+        let mut pos = 0;
+        let read = read_map(&data, &mut pos).unwrap();
+        assert_eq!(pos, data.len());
+        assert_eq!(encode(&read), data);
+
+        let Some((_, Vdf::Map(shortcuts))) = read.first() else {
+            panic!("no shortcuts map");
+        };
+        assert!(is_ovl_shortcut(&shortcuts[0].1));
+    }
+
+    #[test]
+    fn keeps_strings_that_are_not_utf8() {
+        // "Café" in Windows-1252, as some launchers write it
+        let data = encode(&[("AppName".into(), Vdf::Str(b"Caf\xe9".to_vec()))]);
+        let read = read_map(&data, &mut 0).unwrap();
+        assert_eq!(encode(&read), data);
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data = encode(&[("AppName".into(), Vdf::Str("Minecraft".into()))]);
+        for end in 0..data.len() {
+            assert!(read_map(&data[..end], &mut 0).is_err(), "cut at {}", end);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        assert!(read_map(&[0x05, b'a', 0, END], &mut 0).is_err());
+    }
+}
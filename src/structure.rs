@@ -0,0 +1,311 @@
+use fastnbt::Value;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::world::{nbt_get, nbt_number, nbt_string, read_level_dat};
+
+/// Data version of 1.21, where the datapack folders lost their plural
+const SINGULAR_FOLDERS_DATA_VERSION: f64 = 3953.0;
+
+fn read_nbt_file(path: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let bytes = fs::read(path)?;
+    // Structure files are gzipped, but some tools write them raw
+    let mut decoded = Vec::new();
+    let bytes = match GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded) {
+        Ok(_) => decoded,
+        Err(_) => bytes,
+    };
+    Ok(fastnbt::from_bytes(&bytes)?)
+}
+
+/// Split `minecraft:oak_stairs[facing=east,half=bottom]` into the palette entry of a
+/// structure file
+fn block_state(state: &str) -> Value {
+    let (name, properties) = match state.split_once('[') {
+        Some((name, properties)) => (name, properties.trim_end_matches(']')),
+        None => (state, ""),
+    };
+    let mut entry = HashMap::from([("Name".to_string(), Value::String(name.to_string()))]);
+    let properties: HashMap<String, Value> = properties
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect();
+    if !properties.is_empty() {
+        entry.insert("Properties".to_string(), Value::Compound(properties));
+    }
+    Value::Compound(entry)
+}
+
+fn read_varints(data: impl Iterator<Item = i8>) -> Vec<usize> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0, 0);
+    for byte in data.map(|b| b as u8) {
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            (value, shift) = (0, 0);
+        } else {
+            shift += 7;
+        }
+    }
+    values
+}
+
+fn int_list(values: &[i32]) -> Value {
+    Value::List(values.iter().map(|v| Value::Int(*v)).collect())
+}
+
+/// Convert a Sponge schematic (version 2 or 3, the `.schem` files of WorldEdit) into the
+/// structure format of structure blocks. Entities are not carried over.
+fn convert_schematic(schematic: &Value) -> Result<Value, Box<dyn std::error::Error>> {
+    // Version 3 wraps everything in a `Schematic` compound
+    let root = nbt_get(schematic, &["Schematic"]).unwrap_or(schematic);
+    let dimension = |key: &str| {
+        nbt_number(nbt_get(root, &[key]))
+            .map(|n| (n as i32).max(0))
+            .ok_or(format!("The schematic has no {}", key))
+    };
+    let (width, height, length) = (
+        dimension("Width")?,
+        dimension("Height")?,
+        dimension("Length")?,
+    );
+    if width == 0 || height == 0 || length == 0 {
+        return Err("The schematic is empty".into());
+    }
+
+    let blocks = nbt_get(root, &["Blocks"]);
+    let (palette, data, block_entities) = match blocks {
+        Some(blocks) => (
+            nbt_get(blocks, &["Palette"]),
+            nbt_get(blocks, &["Data"]),
+            nbt_get(blocks, &["BlockEntities"]),
+        ),
+        None => (
+            nbt_get(root, &["Palette"]),
+            nbt_get(root, &["BlockData"]),
+            nbt_get(root, &["BlockEntities"]),
+        ),
+    };
+    let Some(Value::Compound(palette)) = palette else {
+        return Err("The schematic has no block palette".into());
+    };
+    let Some(Value::ByteArray(data)) = data else {
+        return Err("The schematic has no block data".into());
+    };
+
+    // Schematic palette ids are not contiguous, structure ones are list indexes
+    let mut states: Vec<(usize, &String)> = palette
+        .iter()
+        .filter_map(|(state, id)| Some((nbt_number(Some(id))? as usize, state)))
+        .collect();
+    states.sort();
+    let index_of: HashMap<usize, usize> = states
+        .iter()
+        .enumerate()
+        .map(|(index, (id, _))| (*id, index))
+        .collect();
+
+    let mut block_nbt: HashMap<(i32, i32, i32), Value> = HashMap::new();
+    if let Some(Value::List(entities)) = block_entities {
+        for entity in entities {
+            let (Some(Value::IntArray(pos)), Value::Compound(fields)) =
+                (nbt_get(entity, &["Pos"]), entity)
+            else {
+                continue;
+            };
+            let pos: Vec<i32> = pos.iter().copied().collect();
+            let [x, y, z] = pos[..] else {
+                continue;
+            };
+            // Version 3 moved the block entity data into `Data`
+            let mut nbt = match fields.get("Data") {
+                Some(Value::Compound(data)) => data.clone(),
+                _ => fields.clone(),
+            };
+            nbt.remove("Pos");
+            nbt.remove("Id");
+            if let Some(id) = nbt_string(fields.get("Id")) {
+                nbt.insert("id".to_string(), Value::String(id));
+            }
+            block_nbt.insert((x, y, z), Value::Compound(nbt));
+        }
+    }
+
+    let mut blocks = Vec::new();
+    // Blocks are ordered by y, then z, then x
+    for (i, id) in read_varints(data.iter().copied()).into_iter().enumerate() {
+        let i = i as i32;
+        let (x, z, y) = (i % width, i / width % length, i / (width * length));
+        let Some(&state) = index_of.get(&id) else {
+            continue;
+        };
+        // Structure void is what leaves the world untouched when loading
+        if states[state].1.starts_with("minecraft:structure_void") {
+            continue;
+        }
+        let mut block = HashMap::from([
+            ("pos".to_string(), int_list(&[x, y, z])),
+            ("state".to_string(), Value::Int(state as i32)),
+        ]);
+        if let Some(nbt) = block_nbt.remove(&(x, y, z)) {
+            block.insert("nbt".to_string(), nbt);
+        }
+        blocks.push(Value::Compound(block));
+    }
+
+    let mut structure = HashMap::from([
+        ("size".to_string(), int_list(&[width, height, length])),
+        (
+            "palette".to_string(),
+            Value::List(states.iter().map(|(_, state)| block_state(state)).collect()),
+        ),
+        ("blocks".to_string(), Value::List(blocks)),
+        ("entities".to_string(), Value::List(Vec::new())),
+    ]);
+    if let Some(version) = nbt_number(nbt_get(root, &["DataVersion"])) {
+        structure.insert("DataVersion".to_string(), Value::Int(version as i32));
+    }
+    Ok(Value::Compound(structure))
+}
+
+/// `namespace:name` from the `--name` option or the file name, as typed in a structure block
+fn structure_id(file: &Path, name: Option<&str>) -> Result<(String, String), String> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => file
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase().replace(' ', "_"))
+            .unwrap_or_default(),
+    };
+    let (namespace, path) = name.split_once(':').unwrap_or(("minecraft", &name));
+    let valid = |s: &str, extra: &str| {
+        !s.is_empty()
+            && s.chars().all(|c| {
+                c.is_ascii_lowercase()
+                    || c.is_ascii_digit()
+                    || "_-.".contains(c)
+                    || extra.contains(c)
+            })
+    };
+    // `..` would write outside of the structures folder
+    let traverses = std::iter::once(namespace)
+        .chain(path.split('/'))
+        .any(|part| part.is_empty() || part == "." || part == "..");
+    if !valid(namespace, "") || !valid(path, "/") || traverses {
+        return Err(format!(
+            "Invalid structure name {:?}, use lowercase letters, digits, _, - and . (e.g. my_map:arena)",
+            name
+        ));
+    }
+    Ok((namespace.to_string(), path.to_string()))
+}
+
+/// Copy a structure file (or convert a schematic) into a world, where structure blocks and
+/// `/place template` find it. Returns the id to load it with and the written file.
+pub fn import_structure(
+    world_dir: &Path,
+    file: &Path,
+    name: Option<&str>,
+    datapack: Option<&str>,
+) -> Result<(String, PathBuf), Box<dyn std::error::Error>> {
+    let (namespace, path) = structure_id(file, name)?;
+    let extension = file
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let nbt = read_nbt_file(file)?;
+    let structure = match extension.as_str() {
+        "nbt" => {
+            if nbt_get(&nbt, &["size"]).is_none() {
+                return Err(format!("{} is not a structure file", file.display()).into());
+            }
+            nbt
+        }
+        "schem" => convert_schematic(&nbt)?,
+        _ => return Err("Only .nbt structure files and .schem schematics are supported".into()),
+    };
+
+    let target_dir = match datapack {
+        Some(datapack) => {
+            let pack_dir = world_dir.join("datapacks").join(datapack);
+            if !pack_dir.join("pack.mcmeta").exists() {
+                return Err(format!(
+                    "The world has no datapack named {}, create it first (with its pack.mcmeta)",
+                    datapack
+                )
+                .into());
+            }
+            let data_version = read_level_dat(world_dir)
+                .ok()
+                .and_then(|level| nbt_number(nbt_get(&level, &["Data", "DataVersion"])))
+                .unwrap_or(0.0);
+            let folder = if data_version >= SINGULAR_FOLDERS_DATA_VERSION {
+                "structure"
+            } else {
+                "structures"
+            };
+            pack_dir.join("data").join(&namespace).join(folder)
+        }
+        None => world_dir
+            .join("generated")
+            .join(&namespace)
+            .join("structures"),
+    };
+    let target = target_dir.join(format!("{}.nbt", path));
+    fs::create_dir_all(target.parent().unwrap())?;
+
+    let mut encoder = GzEncoder::new(fs::File::create(&target)?, Compression::default());
+    encoder.write_all(&fastnbt::to_bytes(&structure)?)?;
+    encoder.finish()?;
+
+    Ok((format!("{}:{}", namespace, path), target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Result<(String, String), String> {
+        structure_id(Path::new("ignored.nbt"), Some(name))
+    }
+
+    #[test]
+    fn reads_structure_ids() {
+        assert_eq!(
+            id("my_map:arena"),
+            Ok(("my_map".to_string(), "arena".to_string()))
+        );
+        assert_eq!(
+            id("rooms/hall-1"),
+            Ok(("minecraft".to_string(), "rooms/hall-1".to_string()))
+        );
+        assert_eq!(
+            structure_id(Path::new("/tmp/Boss Room.schem"), None),
+            Ok(("minecraft".to_string(), "boss_room".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_ids_outside_of_the_structures_folder() {
+        for name in [
+            "..:arena",
+            ".:arena",
+            "map:../arena",
+            "map:a//b",
+            "map:",
+            ":arena",
+            "Map:Arena",
+            "a:b:c",
+        ] {
+            assert!(id(name).is_err(), "{:?}", name);
+        }
+    }
+}
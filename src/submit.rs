@@ -0,0 +1,105 @@
+use fastnbt::Value;
+use flate2::read::GzDecoder;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use crate::{
+    auth::prove_online_session,
+    cmd::ask_yes_no_attended,
+    map::{cached_map_path, fetch_maps},
+    net,
+    world::{get_saves_dir, nbt_get, nbt_number, nbt_string},
+    zipper::get_root_folder_name,
+};
+
+const LEADERBOARD_API: &str = "https://openvoxel.studio/api/leaderboards";
+
+/// Scores of every player in a scoreboard objective of a world
+pub fn read_scores(
+    world_dir: &Path,
+    objective: &str,
+) -> Result<HashMap<String, i64>, Box<dyn std::error::Error>> {
+    let file = File::open(world_dir.join("data").join("scoreboard.dat"))
+        .map_err(|_| "The world has no scoreboard yet, play the map first")?;
+    let scoreboard: Value = fastnbt::from_reader(GzDecoder::new(file))?;
+
+    let mut scores = HashMap::new();
+    if let Some(Value::List(entries)) = nbt_get(&scoreboard, &["data", "PlayerScores"]) {
+        for entry in entries {
+            if nbt_string(nbt_get(entry, &["Objective"])).as_deref() != Some(objective) {
+                continue;
+            }
+            if let (Some(name), Some(score)) = (
+                nbt_string(nbt_get(entry, &["Name"])),
+                nbt_number(nbt_get(entry, &["Score"])),
+            ) {
+                scores.insert(name, score as i64);
+            }
+        }
+    }
+    Ok(scores)
+}
+
+/// Send the score of the selected account on a competitive map to the OpenVoxel leaderboard
+pub async fn submit_score(map_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let map = fetch_maps()?
+        .into_iter()
+        .find(|m| m.id.eq_ignore_ascii_case(map_id))
+        .ok_or_else(|| format!("Unknown map: {}", map_id))?;
+    if map.source.is_some() {
+        return Err(format!(
+            "{} is not an OpenVoxel map, it has no leaderboard here",
+            map.name
+        )
+        .into());
+    }
+    let leaderboard = map
+        .leaderboard
+        .as_ref()
+        .ok_or_else(|| format!("{} does not have a leaderboard", map.name))?;
+
+    let cached_zip = cached_map_path(&map.id);
+    let world_name = get_root_folder_name(&cached_zip)
+        .map_err(|_| format!("{} was not played yet, run `ovl play {}`", map.name, map.id))?;
+    let world_dir = get_saves_dir().join(world_name);
+
+    let scores = read_scores(&world_dir, &leaderboard.objective)?;
+    let (account, session) = prove_online_session().await?;
+    let score = *scores.get(&account.name).ok_or_else(|| {
+        format!(
+            "{} has no score in {} yet",
+            account.name, leaderboard.objective
+        )
+    })?;
+
+    println!(
+        "{} scored {} on {} ({}).",
+        account.name, score, map.name, leaderboard.objective
+    );
+    if !ask_yes_no_attended("Submit this score to the OpenVoxel leaderboard?") {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let response = net::send(
+        net::client()
+            .post(format!("{}/{}", LEADERBOARD_API, map.id))
+            .json(&json!({
+                "session": session,
+                "objective": leaderboard.objective,
+                "score": score,
+                "version": map.version,
+            })),
+    )
+    .await?
+    .error_for_status()?;
+
+    let result: serde_json::Value = response.json().await.unwrap_or_default();
+    match result["rank"].as_u64() {
+        Some(rank) => println!("Score submitted! You are #{} on {}.", rank, map.name),
+        None => println!("Score submitted!"),
+    }
+    Ok(())
+}
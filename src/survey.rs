@@ -0,0 +1,179 @@
+use inquire::{Confirm, Select, Text};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value, json};
+
+use crate::{
+    auth::get_selected_account_name,
+    cmd::{ask_yes_no, is_non_interactive},
+    config::{load_config, save_config},
+    map::{Map, fetch_maps},
+    net,
+    session::SessionReport,
+    theme::Themed,
+};
+
+/// Catalog channel of the maps still being tested
+const BETA_CHANNEL: &str = "beta";
+
+/// Questionnaire the team of a map in testing asks after each session, from the catalog
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Survey {
+    /// Where the answers are posted as JSON
+    pub endpoint: String,
+    pub questions: Vec<SurveyQuestion>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SurveyQuestion {
+    /// Key of the answer in what is sent
+    pub id: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub kind: QuestionKind,
+    /// Answers to pick from, for `choice` questions
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuestionKind {
+    #[default]
+    Text,
+    /// From 1 to 5
+    Rating,
+    YesNo,
+    Choice,
+}
+
+/// The survey of a map when it is in beta and has one
+fn beta_survey(map: &Map) -> Option<&Survey> {
+    (map.channel.as_deref() == Some(BETA_CHANNEL))
+        .then_some(map.survey.as_ref())
+        .flatten()
+}
+
+fn opted_in(map: &Map) -> bool {
+    load_config()
+        .survey_maps
+        .iter()
+        .any(|id| id.eq_ignore_ascii_case(&map.id))
+}
+
+/// Answer the survey of a beta map after each session, or stop with `off`
+pub fn set_survey(map_id: &str, off: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let map = fetch_maps()?
+        .into_iter()
+        .find(|m| m.id.eq_ignore_ascii_case(map_id))
+        .ok_or_else(|| format!("Unknown map: {}", map_id))?;
+
+    let mut config = load_config();
+    config
+        .survey_maps
+        .retain(|id| !id.eq_ignore_ascii_case(&map.id));
+    if off {
+        save_config(&config)?;
+        println!("No more survey after playing {}.", map.name);
+        return Ok(());
+    }
+
+    if beta_survey(&map).is_none() {
+        return Err(format!("{} is not in testing, it has no survey", map.name).into());
+    }
+    config.survey_maps.push(map.id.clone());
+    save_config(&config)?;
+    println!(
+        "A short survey now follows each session of {}, its team gets the answers.",
+        map.name
+    );
+    Ok(())
+}
+
+/// Point testers of a beta map with a survey at `ovl survey`
+pub fn print_survey_hint(map: &Map) {
+    if beta_survey(map).is_some() && !opted_in(map) {
+        println!(
+            "{} {} is in testing, run `ovl survey {}` to tell its team how your sessions went.",
+            "Beta:".warning(),
+            map.name,
+            map.id
+        );
+    }
+}
+
+/// Ask one question, `None` when it is skipped with Esc
+fn ask_question(question: &SurveyQuestion) -> Option<Value> {
+    let answer = match question.kind {
+        QuestionKind::Text => Text::new(&question.prompt).prompt_skippable().ok()??.into(),
+        QuestionKind::Rating => {
+            let ratings: Vec<u8> = (1..=5).collect();
+            Select::new(&question.prompt, ratings)
+                .with_starting_cursor(2)
+                .prompt_skippable()
+                .ok()??
+                .into()
+        }
+        QuestionKind::YesNo => Confirm::new(&question.prompt)
+            .prompt_skippable()
+            .ok()??
+            .into(),
+        QuestionKind::Choice => Select::new(&question.prompt, question.choices.clone())
+            .prompt_skippable()
+            .ok()??
+            .into(),
+    };
+    Some(answer)
+}
+
+async fn send_answers(endpoint: &str, body: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    net::send(net::client().post(endpoint).json(body))
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// After a session of a beta map the player opted in for, ask its survey and send the answers
+/// with what happened in the session
+pub async fn run_exit_survey(map: &Map, report: &SessionReport) {
+    let Some(survey) = beta_survey(map) else {
+        return;
+    };
+    // Nobody is there to answer it
+    if !opted_in(map) || survey.questions.is_empty() || is_non_interactive() {
+        return;
+    }
+    if !ask_yes_no(&format!(
+        "Answer the {} question survey of {}?",
+        survey.questions.len(),
+        map.name
+    )) {
+        return;
+    }
+    println!("{}", "Press Esc to skip a question.".muted());
+
+    let answers: JsonMap<String, Value> = survey
+        .questions
+        .iter()
+        .map(|q| (q.id.clone(), ask_question(q).unwrap_or(Value::Null)))
+        .collect();
+
+    let body = json!({
+        "map": map.id,
+        "version": map.version,
+        "player": get_selected_account_name(),
+        "answers": answers,
+        "session": {
+            "duration_secs": report.duration.as_secs(),
+            "exit_code": report.exit_code,
+            "crashed": report.crashed(),
+            "hung": report.hung,
+            "hints": report.hints,
+        },
+        "launcher": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+    });
+    match send_answers(&survey.endpoint, &body).await {
+        Ok(()) => println!("Thanks, the answers were sent to the {} team.", map.name),
+        Err(e) => eprintln!("Failed to send the survey: {}", e),
+    }
+}
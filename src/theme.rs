@@ -0,0 +1,279 @@
+use colored::{Color, ColoredString, Colorize};
+use inquire::ui::{
+    Color as PromptColor, ErrorMessageRenderConfig, RenderConfig, StyleSheet, Styled,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::config::load_config;
+
+/// Colors of the output and the prompts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// `dark` or `light` from the background the terminal reports, `default` when it does not
+    #[default]
+    Auto,
+    Default,
+    /// Brighter colors for dark backgrounds
+    Dark,
+    /// No yellow or light cyan, they are unreadable on white
+    Light,
+    /// Bold and bright, nothing dimmed
+    HighContrast,
+}
+
+/// Colors replacing those of the theme for each kind of output: a color name (`red`,
+/// `bright blue`), `#rrggbb`, `dimmed` or `none`
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ThemeColors {
+    pub success: Option<String>,
+    pub warning: Option<String>,
+    pub error: Option<String>,
+    /// Highlights, and the answers and selection of prompts
+    pub accent: Option<String>,
+    /// Secondary details
+    pub muted: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Role {
+    color: Option<Color>,
+    dimmed: bool,
+}
+
+impl Role {
+    const DIMMED: Role = Role {
+        color: None,
+        dimmed: true,
+    };
+
+    fn color(color: Color) -> Self {
+        Role {
+            color: Some(color),
+            dimmed: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Palette {
+    success: Role,
+    warning: Role,
+    error: Role,
+    accent: Role,
+    muted: Role,
+    bold: bool,
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+/// Whether the terminal background is dark, from `COLORFGBG` (`<fg>;<bg>`) when it is set
+fn dark_background() -> Option<bool> {
+    let colors = std::env::var("COLORFGBG").ok()?;
+    let background: u8 = colors.rsplit(';').next()?.parse().ok()?;
+    // 7 is light grey and 9 to 15 are the bright colors
+    Some(!matches!(background, 7 | 9..=15))
+}
+
+impl Palette {
+    fn of(theme: Theme) -> Self {
+        match theme {
+            Theme::Auto => match dark_background() {
+                Some(true) => Palette::of(Theme::Dark),
+                Some(false) => Palette::of(Theme::Light),
+                None => Palette::of(Theme::Default),
+            },
+            Theme::Default => Palette {
+                success: Role::color(Color::Green),
+                warning: Role::color(Color::Yellow),
+                error: Role::color(Color::Red),
+                accent: Role::color(Color::Cyan),
+                muted: Role::DIMMED,
+                bold: false,
+            },
+            Theme::Dark => Palette {
+                success: Role::color(Color::BrightGreen),
+                warning: Role::color(Color::BrightYellow),
+                error: Role::color(Color::BrightRed),
+                accent: Role::color(Color::BrightCyan),
+                muted: Role::DIMMED,
+                bold: false,
+            },
+            Theme::Light => Palette {
+                success: Role::color(Color::Green),
+                warning: Role::color(Color::Magenta),
+                error: Role::color(Color::Red),
+                accent: Role::color(Color::Blue),
+                muted: Role::DIMMED,
+                bold: false,
+            },
+            Theme::HighContrast => Palette {
+                success: Role::color(Color::BrightGreen),
+                warning: Role::color(Color::BrightYellow),
+                error: Role::color(Color::BrightRed),
+                accent: Role::color(Color::BrightCyan),
+                muted: Role {
+                    color: None,
+                    dimmed: false,
+                },
+                bold: true,
+            },
+        }
+    }
+
+    fn with_colors(mut self, colors: &ThemeColors) -> Self {
+        let roles = [
+            (&mut self.success, &colors.success, "success"),
+            (&mut self.warning, &colors.warning, "warning"),
+            (&mut self.error, &colors.error, "error"),
+            (&mut self.accent, &colors.accent, "accent"),
+            (&mut self.muted, &colors.muted, "muted"),
+        ];
+        for (role, color, name) in roles {
+            let Some(color) = color else {
+                continue;
+            };
+            match parse_role(color) {
+                Some(parsed) => *role = parsed,
+                None => eprintln!(
+                    "Warning: Unknown color for theme_colors.{}: {}",
+                    name, color
+                ),
+            }
+        }
+        self
+    }
+}
+
+/// A color of `theme_colors`
+fn parse_role(input: &str) -> Option<Role> {
+    let input = input.trim().to_lowercase().replace(['_', '-'], " ");
+    match input.as_str() {
+        "dimmed" => return Some(Role::DIMMED),
+        "none" => {
+            return Some(Role {
+                color: None,
+                dimmed: false,
+            });
+        }
+        _ => {}
+    }
+    if let Some(hex) = input.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        let [_, r, g, b] = value.to_be_bytes();
+        return Some(Role::color(Color::TrueColor { r, g, b }));
+    }
+    input.parse().ok().map(Role::color)
+}
+
+fn palette() -> Palette {
+    *PALETTE.get_or_init(|| {
+        let config = load_config();
+        Palette::of(config.theme).with_colors(&config.theme_colors)
+    })
+}
+
+fn paint(text: impl Colorize, role: Role, bold: bool) -> ColoredString {
+    let mut painted = match role.color {
+        Some(color) => text.color(color),
+        None => text.normal(),
+    };
+    if role.dimmed {
+        painted = painted.dimmed();
+    }
+    if bold {
+        painted = painted.bold();
+    }
+    painted
+}
+
+/// Colors by what the text means rather than by name, so that the theme picks them
+pub trait Themed: Colorize + Sized {
+    fn success(self) -> ColoredString {
+        let palette = palette();
+        paint(self, palette.success, palette.bold)
+    }
+
+    fn warning(self) -> ColoredString {
+        let palette = palette();
+        paint(self, palette.warning, palette.bold)
+    }
+
+    fn error(self) -> ColoredString {
+        let palette = palette();
+        paint(self, palette.error, palette.bold)
+    }
+
+    fn accent(self) -> ColoredString {
+        let palette = palette();
+        paint(self, palette.accent, palette.bold)
+    }
+
+    fn muted(self) -> ColoredString {
+        paint(self, palette().muted, false)
+    }
+}
+
+impl<T: Colorize> Themed for T {}
+
+fn prompt_color(color: Color) -> PromptColor {
+    match color {
+        Color::Black => PromptColor::Black,
+        Color::Red => PromptColor::DarkRed,
+        Color::Green => PromptColor::DarkGreen,
+        Color::Yellow => PromptColor::DarkYellow,
+        Color::Blue => PromptColor::DarkBlue,
+        Color::Magenta => PromptColor::DarkMagenta,
+        Color::Cyan => PromptColor::DarkCyan,
+        Color::White => PromptColor::Grey,
+        Color::BrightBlack => PromptColor::DarkGrey,
+        Color::BrightRed => PromptColor::LightRed,
+        Color::BrightGreen => PromptColor::LightGreen,
+        Color::BrightYellow => PromptColor::LightYellow,
+        Color::BrightBlue => PromptColor::LightBlue,
+        Color::BrightMagenta => PromptColor::LightMagenta,
+        Color::BrightCyan => PromptColor::LightCyan,
+        Color::BrightWhite => PromptColor::White,
+        Color::TrueColor { r, g, b } => PromptColor::rgb(r, g, b),
+    }
+}
+
+fn sheet(role: Role) -> StyleSheet {
+    match role.color {
+        Some(color) => StyleSheet::new().with_fg(prompt_color(color)),
+        None => StyleSheet::new(),
+    }
+}
+
+fn styled(text: &'static str, role: Role) -> Styled<&'static str> {
+    Styled::new(text).with_style_sheet(sheet(role))
+}
+
+/// Give the prompts the colors of the theme, none when colors are turned off (`NO_COLOR`)
+pub fn use_prompt_theme() {
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        inquire::set_global_render_config(RenderConfig::empty());
+        return;
+    }
+
+    let palette = palette();
+    let config = RenderConfig::default_colored()
+        .with_prompt_prefix(styled("?", palette.success))
+        .with_answered_prompt_prefix(styled(">", palette.success))
+        .with_help_message(sheet(palette.accent))
+        .with_answer(sheet(palette.accent))
+        .with_highlighted_option_prefix(styled(">", palette.accent))
+        .with_selected_option(Some(sheet(palette.accent)))
+        .with_selected_checkbox(styled("[x]", palette.success))
+        .with_canceled_prompt_indicator(styled("<canceled>", palette.error))
+        .with_error_message(
+            ErrorMessageRenderConfig::default_colored()
+                .with_prefix(styled("#", palette.error))
+                .with_message(sheet(palette.error)),
+        );
+    inquire::set_global_render_config(config);
+}
@@ -0,0 +1,182 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use image::{RgbaImage, imageops::FilterType};
+use std::io::{Write, stdout};
+
+use crate::net;
+
+/// Width of a thumbnail in terminal columns
+const COLUMNS: u32 = 32;
+/// Width in pixels of a thumbnail drawn with sixels
+const SIXEL_WIDTH: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Graphics {
+    Kitty,
+    Iterm,
+    Sixel,
+    None,
+}
+
+/// Guess the inline image protocol of the terminal from its environment
+fn detect_graphics() -> Graphics {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    if !var("KITTY_WINDOW_ID").is_empty()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || program == "WezTerm"
+    {
+        Graphics::Kitty
+    } else if program == "iTerm.app" {
+        Graphics::Iterm
+    } else if term.contains("sixel") || term == "foot" || term.starts_with("mlterm") {
+        Graphics::Sixel
+    } else {
+        Graphics::None
+    }
+}
+
+/// Whether `print_thumbnail` can draw actual images in this terminal
+pub fn supports_graphics() -> bool {
+    detect_graphics() != Graphics::None
+}
+
+fn print_kitty(png: &[u8]) {
+    let encoded = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    let mut out = stdout().lock();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        let chunk = String::from_utf8_lossy(chunk);
+        // Only the first chunk carries the image settings
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Gf=100,a=T,c={},m={};{}\x1b\\",
+                COLUMNS, more, chunk
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    let _ = writeln!(out);
+}
+
+fn print_iterm(png: &[u8]) {
+    println!(
+        "\x1b]1337;File=inline=1;width={};preserveAspectRatio=1:{}\x07",
+        COLUMNS,
+        STANDARD.encode(png)
+    );
+}
+
+/// Draw with sixels, the colors are reduced to a 6x6x6 cube
+fn print_sixel(image: &RgbaImage) {
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let index = |p: &image::Rgba<u8>| {
+        if p[3] < 128 {
+            None
+        } else {
+            Some((level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize)
+        }
+    };
+
+    let mut out = String::from("\x1bPq");
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, i / 6 % 6, i % 6);
+        out += &format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20);
+    }
+
+    for band in (0..image.height()).step_by(6) {
+        let mut colors: Vec<usize> = Vec::new();
+        for y in band..(band + 6).min(image.height()) {
+            for x in 0..image.width() {
+                if let Some(c) = index(image.get_pixel(x, y))
+                    && !colors.contains(&c)
+                {
+                    colors.push(c);
+                }
+            }
+        }
+
+        for color in colors {
+            out += &format!("#{}", color);
+            for x in 0..image.width() {
+                let mut bits = 0u8;
+                for dy in 0..6 {
+                    let y = band + dy;
+                    if y < image.height() && index(image.get_pixel(x, y)) == Some(color) {
+                        bits |= 1 << dy;
+                    }
+                }
+                out.push((63 + bits) as char);
+            }
+            // Go back to the start of the band for the next color
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out += "\x1b\\";
+    println!("{}", out);
+}
+
+fn print_placeholder(name: &str) {
+    let width = COLUMNS as usize;
+    let label: String = name.chars().take(width - 4).collect();
+    println!("+{}+", "-".repeat(width - 2));
+    println!("| {:^w$} |", label, w = width - 4);
+    println!("| {:^w$} |", "(no preview)", w = width - 4);
+    println!("+{}+", "-".repeat(width - 2));
+}
+
+/// Show an image from the catalog inline, or a placeholder when the terminal cannot draw it
+pub fn print_thumbnail(url: Option<&str>, name: &str) {
+    let graphics = detect_graphics();
+    let Some(url) = url.filter(|_| graphics != Graphics::None) else {
+        print_placeholder(name);
+        return;
+    };
+
+    let bytes = match net::blocking_get(url).and_then(|r| r.error_for_status()?.bytes()) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            print_placeholder(name);
+            return;
+        }
+    };
+
+    match graphics {
+        Graphics::Kitty | Graphics::Iterm if bytes.starts_with(b"\x89PNG") => {
+            if graphics == Graphics::Kitty {
+                print_kitty(&bytes);
+            } else {
+                print_iterm(&bytes);
+            }
+        }
+        _ => match image::load_from_memory(&bytes) {
+            Ok(image) => {
+                let height = image.height() * SIXEL_WIDTH / image.width().max(1);
+                let image = image
+                    .resize(SIXEL_WIDTH, height, FilterType::Triangle)
+                    .to_rgba8();
+                match graphics {
+                    Graphics::Sixel => print_sixel(&image),
+                    _ => {
+                        let mut png = Vec::new();
+                        let _ = image
+                            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png);
+                        if graphics == Graphics::Kitty {
+                            print_kitty(&png);
+                        } else {
+                            print_iterm(&png);
+                        }
+                    }
+                }
+            }
+            Err(_) => print_placeholder(name),
+        },
+    }
+}
@@ -0,0 +1,69 @@
+use colored::Colorize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static START: OnceLock<Instant> = OnceLock::new();
+/// What was measured, in the order it finished
+static SPANS: Mutex<Vec<(String, Duration)>> = Mutex::new(Vec::new());
+
+/// Start measuring, as `--timings` does. Anything before this is counted as process startup.
+pub fn use_timings(start: Instant) {
+    let _ = START.set(start);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Run `f` and record how long it took under `label` when `--timings` is on
+pub fn timed<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(label, start.elapsed());
+    result
+}
+
+pub fn record(label: &str, elapsed: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        SPANS.lock().unwrap().push((label.to_string(), elapsed));
+    }
+}
+
+/// Printed when dropped, so commands that return early still get their report
+pub struct TimingsReport;
+
+impl Drop for TimingsReport {
+    fn drop(&mut self) {
+        print_report();
+    }
+}
+
+/// Exit the process, printing the report first since exiting skips the drop of
+/// [`TimingsReport`]
+pub fn exit(code: i32) -> ! {
+    if ENABLED.load(Ordering::Relaxed) {
+        print_report();
+    }
+    std::process::exit(code)
+}
+
+fn print_report() {
+    let Some(start) = START.get() else {
+        return;
+    };
+    let total = start.elapsed();
+    let spans = SPANS.lock().unwrap();
+
+    eprintln!();
+    eprintln!("{}", "Timings".bold());
+    for (label, elapsed) in spans.iter() {
+        eprintln!("  {:<24} {:>8.1} ms", label, elapsed.as_secs_f64() * 1000.0);
+    }
+    eprintln!(
+        "  {:<24} {:>8.1} ms",
+        "total".bold(),
+        total.as_secs_f64() * 1000.0
+    );
+}
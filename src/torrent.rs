@@ -0,0 +1,384 @@
+use reqwest::StatusCode;
+use reqwest::header::RANGE;
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write, stdout};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::net;
+
+/// Pieces downloaded at the same time
+const MAX_WORKERS: usize = 8;
+/// Failures after which a web seed is only used when every other one failed too
+const MAX_SEED_FAILURES: u32 = 3;
+/// Lists and dictionaries nested deeper than this are refused, real torrents use a handful
+const MAX_BENCODE_DEPTH: usize = 64;
+
+enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Bencode::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Bencode::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn string(&self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+}
+
+/// Decode the value at `pos`, `depth` being the number of lists and dictionaries it is in
+fn parse_bencode(data: &[u8], pos: &mut usize, depth: usize) -> Result<Bencode, String> {
+    let invalid = || "Invalid torrent file".to_string();
+    if depth > MAX_BENCODE_DEPTH {
+        return Err("The torrent file is nested too deeply".to_string());
+    }
+    let read_until = |pos: &mut usize, end: u8| -> Result<String, String> {
+        let start = *pos;
+        let length = data[start..]
+            .iter()
+            .position(|b| *b == end)
+            .ok_or_else(invalid)?;
+        *pos = start + length + 1;
+        String::from_utf8(data[start..start + length].to_vec()).map_err(|_| invalid())
+    };
+
+    match data.get(*pos).ok_or_else(invalid)? {
+        b'i' => {
+            *pos += 1;
+            Ok(Bencode::Int(
+                read_until(pos, b'e')?.parse().map_err(|_| invalid())?,
+            ))
+        }
+        b'l' => {
+            *pos += 1;
+            let mut list = Vec::new();
+            while data.get(*pos) != Some(&b'e') {
+                list.push(parse_bencode(data, pos, depth + 1)?);
+            }
+            *pos += 1;
+            Ok(Bencode::List(list))
+        }
+        b'd' => {
+            *pos += 1;
+            let mut dict = BTreeMap::new();
+            while data.get(*pos) != Some(&b'e') {
+                let Bencode::Bytes(key) = parse_bencode(data, pos, depth + 1)? else {
+                    return Err(invalid());
+                };
+                dict.insert(key, parse_bencode(data, pos, depth + 1)?);
+            }
+            *pos += 1;
+            Ok(Bencode::Dict(dict))
+        }
+        b'0'..=b'9' => {
+            let length: usize = read_until(pos, b':')?.parse().map_err(|_| invalid())?;
+            let end = pos.checked_add(length).ok_or_else(invalid)?;
+            let bytes = data.get(*pos..end).ok_or_else(invalid)?;
+            *pos = end;
+            Ok(Bencode::Bytes(bytes.to_vec()))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// The parts of a single-file `.torrent` needed to download it from its web seeds
+struct Torrent {
+    name: String,
+    length: u64,
+    piece_length: u64,
+    pieces: Vec<[u8; 20]>,
+    /// HTTP servers holding the whole file (BEP 19 `url-list`)
+    web_seeds: Vec<String>,
+}
+
+impl Torrent {
+    fn parse(data: &[u8]) -> Result<Torrent, String> {
+        let root = parse_bencode(data, &mut 0, 0)?;
+        let info = root.get("info").ok_or("The torrent has no info")?;
+        if info.get("files").is_some() {
+            return Err("Torrents with several files are not supported".into());
+        }
+
+        let name = info
+            .get("name")
+            .and_then(Bencode::string)
+            .unwrap_or_default();
+        let length = info
+            .get("length")
+            .and_then(Bencode::int)
+            .filter(|n| *n >= 0)
+            .ok_or("The torrent has no length")? as u64;
+        let piece_length = info
+            .get("piece length")
+            .and_then(Bencode::int)
+            .filter(|n| *n > 0)
+            .ok_or("The torrent has no piece length")? as u64;
+        let pieces: Vec<[u8; 20]> = info
+            .get("pieces")
+            .and_then(Bencode::bytes)
+            .ok_or("The torrent has no pieces")?
+            .chunks_exact(20)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect();
+        if pieces.len() as u64 != length.div_ceil(piece_length) {
+            return Err("The torrent pieces do not cover the file".into());
+        }
+
+        // A single seed can be written as a string instead of a list
+        let web_seeds = match root.get("url-list") {
+            Some(Bencode::List(urls)) => urls.iter().filter_map(Bencode::string).collect(),
+            Some(url) => url.string().into_iter().collect(),
+            None => Vec::new(),
+        };
+        let web_seeds = web_seeds
+            .into_iter()
+            .filter(|url: &String| url.starts_with("https://") || url.starts_with("http://"))
+            .map(|url| match url.ends_with('/') {
+                true => format!("{}{}", url, name),
+                false => url,
+            })
+            .collect();
+
+        Ok(Torrent {
+            name,
+            length,
+            piece_length,
+            pieces,
+            web_seeds,
+        })
+    }
+
+    fn piece_range(&self, index: usize) -> (u64, u64) {
+        let start = index as u64 * self.piece_length;
+        (start, (start + self.piece_length).min(self.length))
+    }
+}
+
+fn fetch_piece(url: &str, start: u64, end: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let response = net::blocking_client()
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", start, end - 1))
+        .send()?
+        .error_for_status()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err("the server does not support range requests".into());
+    }
+    let piece = response.bytes()?;
+    if piece.len() as u64 != end - start {
+        return Err("the server sent a piece of the wrong size".into());
+    }
+    Ok(piece.to_vec())
+}
+
+/// Pieces of a previous attempt that are already there and intact
+fn verified_pieces(torrent: &Torrent, file: &mut File) -> Vec<bool> {
+    let mut done = vec![false; torrent.pieces.len()];
+    if file.metadata().map(|m| m.len()).unwrap_or(0) != torrent.length {
+        return done;
+    }
+    for (index, expected) in torrent.pieces.iter().enumerate() {
+        let (start, end) = torrent.piece_range(index);
+        let mut piece = vec![0; (end - start) as usize];
+        if file.seek(SeekFrom::Start(start)).is_ok() && file.read_exact(&mut piece).is_ok() {
+            done[index] = Sha1::digest(&piece).as_slice() == expected;
+        }
+    }
+    done
+}
+
+/// Download the file of a torrent from its web seeds, several pieces at a time and spread
+/// over the seeds. Every piece is checked against the torrent before it is kept, and a
+/// download that is interrupted resumes from the pieces already there.
+pub fn download_torrent(torrent_url: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    let data = net::blocking_get(torrent_url)?
+        .error_for_status()?
+        .bytes()?;
+    let torrent = Torrent::parse(&data)?;
+    if torrent.web_seeds.is_empty() {
+        return Err(format!("The torrent of {} has no web seeds", torrent.name).into());
+    }
+
+    let partial = path.with_extension("part");
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&partial)?;
+    let done = verified_pieces(&torrent, &mut file);
+    file.set_len(torrent.length)?;
+
+    let todo: Vec<usize> = (0..torrent.pieces.len()).filter(|i| !done[*i]).collect();
+    let already = torrent.pieces.len() - todo.len();
+    if already > 0 {
+        println!("Resuming, {} pieces were already downloaded.", already);
+    }
+    println!(
+        "Downloading {} pieces from {} web seeds...",
+        todo.len(),
+        torrent.web_seeds.len()
+    );
+
+    let file = Mutex::new(file);
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failures: Vec<AtomicU32> = torrent
+        .web_seeds
+        .iter()
+        .map(|_| AtomicU32::new(0))
+        .collect();
+    let error: Mutex<Option<String>> = Mutex::new(None);
+    let workers = MAX_WORKERS.min(todo.len().max(1));
+
+    // Each piece starts on its own seed and moves on to the next one when it fails
+    let download = |index: usize| -> Result<(), String> {
+        let (start, end) = torrent.piece_range(index);
+        let seeds = torrent.web_seeds.len();
+        let mut order: Vec<usize> = (0..seeds).map(|s| (index + s) % seeds).collect();
+        order.sort_by_key(|s| failures[*s].load(Ordering::Relaxed) >= MAX_SEED_FAILURES);
+
+        let mut last_error = String::new();
+        for seed in order {
+            let url = &torrent.web_seeds[seed];
+            match fetch_piece(url, start, end) {
+                Ok(piece) if Sha1::digest(&piece).as_slice() == torrent.pieces[index] => {
+                    let mut file = file.lock().unwrap();
+                    file.seek(SeekFrom::Start(start))
+                        .and_then(|_| file.write_all(&piece))
+                        .map_err(|e| e.to_string())?;
+                    failures[seed].store(0, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Ok(_) => last_error = format!("{} sent a corrupted piece", url),
+                Err(e) => last_error = format!("{}: {}", url, e),
+            }
+            failures[seed].fetch_add(1, Ordering::Relaxed);
+        }
+        Err(format!(
+            "Piece {} failed on every seed, {}",
+            index, last_error
+        ))
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= todo.len() || error.lock().unwrap().is_some() {
+                        return;
+                    }
+                    match download(todo[i]) {
+                        Ok(()) => {
+                            finished.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            error.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                }
+            });
+        }
+
+        loop {
+            let count = finished.load(Ordering::Relaxed);
+            print!("\rPieces: {}/{}", count, todo.len());
+            let _ = stdout().flush();
+            if count >= todo.len() || error.lock().unwrap().is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    });
+    println!();
+
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e.into());
+    }
+    file.into_inner().unwrap().sync_all()?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &[u8]) -> Result<Bencode, String> {
+        parse_bencode(data, &mut 0, 0)
+    }
+
+    #[test]
+    fn parses_nested_values() {
+        let root = parse(b"d4:listli1ei-2ee4:name3:mape").unwrap();
+        assert_eq!(
+            root.get("name").and_then(Bencode::string).as_deref(),
+            Some("map")
+        );
+        let Some(Bencode::List(list)) = root.get("list") else {
+            panic!("not a list");
+        };
+        let numbers: Vec<i64> = list.iter().filter_map(Bencode::int).collect();
+        assert_eq!(numbers, [1, -2]);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        for data in [&b"d4:name"[..], b"l", b"i12", b"5:ab", b""] {
+            assert!(parse(data).is_err(), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn rejects_huge_string_lengths() {
+        assert!(parse(b"18446744073709551615:a").is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let deep = [vec![b'l'; 100_000], vec![b'e'; 100_000]].concat();
+        assert!(parse(&deep).is_err());
+
+        let shallow = [vec![b'l'; MAX_BENCODE_DEPTH], vec![b'e'; MAX_BENCODE_DEPTH]].concat();
+        assert!(parse(&shallow).is_ok());
+    }
+
+    #[test]
+    fn parses_a_torrent() {
+        let mut data = b"d8:url-list18:https://seed.test/4:infod6:lengthi5e4:name5:a.zip12:piece lengthi4e6:pieces40:".to_vec();
+        data.extend([0; 40]);
+        data.extend(b"ee");
+        let torrent = Torrent::parse(&data).unwrap();
+        assert_eq!(torrent.name, "a.zip");
+        assert_eq!(torrent.pieces.len(), 2);
+        assert_eq!(torrent.piece_range(1), (4, 5));
+        assert_eq!(torrent.web_seeds, ["https://seed.test/a.zip"]);
+    }
+}
@@ -0,0 +1,132 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use inquire::Password;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::auth::{Accounts, fetch_file, save_account, write_accounts};
+use crate::cmd::check_interactive;
+use crate::dir::get_app_support_dir;
+
+const MAGIC: &[u8] = b"OVLENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Everything needed to recreate the accounts on another machine
+#[derive(Debug, Deserialize, Serialize)]
+struct AccountsBundle {
+    accounts: Accounts,
+    /// Account name -> access token
+    tokens: HashMap<String, String>,
+    /// Account name -> refresh token of Microsoft accounts, so they keep working once the
+    /// access token expires (missing in older exports)
+    #[serde(default)]
+    refresh_tokens: HashMap<String, String>,
+}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<Key, Box<dyn std::error::Error>> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(Key::from(key))
+}
+
+pub fn export_accounts(out: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let accounts = fetch_file(false);
+    let tokens: HashMap<String, String> = accounts
+        .accounts
+        .iter()
+        .filter_map(|a| a.get_access_token().map(|token| (a.name.clone(), token)))
+        .collect();
+    let refresh_tokens: HashMap<String, String> = accounts
+        .accounts
+        .iter()
+        .filter_map(|a| a.get_refresh_token().map(|token| (a.name.clone(), token)))
+        .collect();
+    let count = accounts.accounts.len();
+
+    check_interactive("Password to encrypt the export with:")?;
+    let password = Password::new("Password to encrypt the export with:").prompt()?;
+    if password.is_empty() {
+        return Err("The password cannot be empty".into());
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let plaintext = serde_json::to_vec(&AccountsBundle {
+        accounts,
+        tokens,
+        refresh_tokens,
+    })?;
+    let cipher = ChaCha20Poly1305::new(&derive_key(&password, &salt)?);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt the accounts")?;
+
+    let mut content = MAGIC.to_vec();
+    content.extend_from_slice(&salt);
+    content.extend_from_slice(&nonce);
+    content.extend_from_slice(&ciphertext);
+    std::fs::write(out, content)?;
+
+    Ok(count)
+}
+
+pub fn import_accounts(input: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let content = std::fs::read(input)?;
+    if !content.starts_with(MAGIC) || content.len() < MAGIC.len() + SALT_LEN + NONCE_LEN {
+        return Err("This is not an OpenVoxel accounts export".into());
+    }
+
+    let (salt, rest) = content[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    check_interactive("Password of the export:")?;
+    let password = Password::new("Password of the export:")
+        .without_confirmation()
+        .prompt()?;
+    let cipher = ChaCha20Poly1305::new(&derive_key(&password, salt)?);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong password or corrupted export")?;
+
+    let bundle: AccountsBundle = serde_json::from_slice(&plaintext)?;
+    let previously_selected = get_app_support_dir()
+        .unwrap()
+        .join(".accounts")
+        .exists()
+        .then(|| fetch_file(false).selected);
+
+    for account in &bundle.accounts.accounts {
+        if let Some(token) = bundle.tokens.get(&account.name)
+            && let Err(e) = account.store_access_token(token)
+        {
+            eprintln!("Failed to store the token of {}: {}", account.name, e);
+        }
+        if let Some(token) = bundle.refresh_tokens.get(&account.name)
+            && let Err(e) = account.store_refresh_token(token)
+        {
+            eprintln!(
+                "Failed to store the refresh token of {}: {}",
+                account.name, e
+            );
+        }
+        save_account(account);
+    }
+
+    // save_account selects every account it saves, put back a sensible selection
+    let mut accounts = fetch_file(false);
+    accounts.selected = previously_selected
+        .filter(|s| !s.is_empty())
+        .unwrap_or(bundle.accounts.selected);
+    write_accounts(&accounts)?;
+
+    Ok(bundle.accounts.accounts.len())
+}
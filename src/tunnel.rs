@@ -0,0 +1,100 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How long the tunnel gets to print its public address
+const ADDRESS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A tunnel program (playit, bore, ngrok...) running next to the server
+pub struct Tunnel {
+    process: Child,
+}
+
+impl Tunnel {
+    /// Start `command` through the shell, `{port}` is replaced with the server port.
+    /// Returns the first public address the tunnel prints, if any.
+    pub fn start(
+        command: &str,
+        port: u16,
+    ) -> Result<(Tunnel, Option<String>), Box<dyn std::error::Error>> {
+        let command = command.replace("{port}", &port.to_string());
+        let mut shell = if cfg!(target_os = "windows") {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let mut process = shell
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start the tunnel `{}`: {}", command, e))?;
+
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stdout) = process.stdout.take() {
+            forward_lines(BufReader::new(stdout), sender.clone());
+        }
+        if let Some(stderr) = process.stderr.take() {
+            forward_lines(BufReader::new(stderr), sender);
+        }
+
+        let started = Instant::now();
+        let mut address = None;
+        while address.is_none() {
+            let Some(remaining) = ADDRESS_TIMEOUT.checked_sub(started.elapsed()) else {
+                break;
+            };
+            match receiver.recv_timeout(remaining) {
+                Ok(line) => address = find_address(&line),
+                Err(_) => break,
+            }
+        }
+
+        if let Ok(Some(status)) = process.try_wait() {
+            return Err(format!("The tunnel exited right away ({})", status).into());
+        }
+
+        Ok((Tunnel { process }, address))
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Echo the tunnel output and hand each line over for address detection
+fn forward_lines<R: BufRead + Send + 'static>(reader: R, sender: mpsc::Sender<String>) {
+    std::thread::spawn(move || {
+        for line in reader.lines().map_while(Result::ok) {
+            println!("[tunnel] {}", line);
+            let _ = sender.send(line);
+        }
+    });
+}
+
+/// Pick something that looks like `host.name:port` out of a line of output
+fn find_address(line: &str) -> Option<String> {
+    line.split_whitespace()
+        .map(|word| {
+            let word = word.trim_matches(|c: char| matches!(c, '"' | '\'' | ',' | '(' | ')'));
+            word.split_once("://").map_or(word, |(_, rest)| rest)
+        })
+        .find(|word| {
+            word.rsplit_once(':').is_some_and(|(host, port)| {
+                host.contains('.')
+                    && !host.starts_with("127.")
+                    && !host.starts_with("0.")
+                    && port.parse::<u16>().is_ok()
+            })
+        })
+        .map(str::to_string)
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::{
+    cmd::ask_yes_no,
+    join::{Invite, join, parse_invite},
+    map::{MAIN_CATALOG, select_map},
+    mc::{LaunchOptions, run_map},
+};
+
+const SCHEME: &str = "ovl";
+
+/// Actions the website can trigger through an `ovl://` link
+#[derive(Debug, PartialEq)]
+pub enum UriAction {
+    Play(String),
+    /// Invite shared by `ovl coop`
+    Join(Invite),
+}
+
+pub fn parse_uri(uri: &str) -> Result<UriAction, String> {
+    let rest = uri
+        .strip_prefix(&format!("{}://", SCHEME))
+        .ok_or_else(|| format!("Not an {}:// link: {}", SCHEME, uri))?;
+    let mut parts = rest.trim_end_matches('/').splitn(2, '/');
+
+    match (parts.next(), parts.next()) {
+        (Some("play"), Some(id))
+            if !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            Ok(UriAction::Play(id.to_string()))
+        }
+        (Some("join"), Some(_)) => Ok(UriAction::Join(parse_invite(uri)?)),
+        _ => Err(format!("Unsupported link: {}", uri)),
+    }
+}
+
+/// Entry point of the links registered by `ovl register-uri`
+pub async fn handle_uri(uri: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match parse_uri(uri)? {
+        // Links come from web pages, so playing and joining still ask for confirmation. The play
+        // buttons of the website are for OpenVoxel maps.
+        UriAction::Play(id) => {
+            if let Some(map) = select_map(id, Some(MAIN_CATALOG), false) {
+                run_map(map, &LaunchOptions::default()).await;
+            }
+        }
+        UriAction::Join(invite) => {
+            if !ask_yes_no(&format!("Join the server {}?", invite.address())) {
+                println!("Cancelled.");
+                return Ok(());
+            }
+            join(&invite, &LaunchOptions::default()).await?;
+        }
+    }
+    Ok(())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new(program).args(args).status()?;
+    if !status.success() {
+        return Err(format!("{} failed ({})", program, status).into());
+    }
+    Ok(())
+}
+
+fn get_linux_desktop_file() -> PathBuf {
+    dirs::data_dir()
+        .unwrap()
+        .join("applications")
+        .join("ovl-uri-handler.desktop")
+}
+
+fn get_macos_app() -> PathBuf {
+    dirs::home_dir()
+        .unwrap()
+        .join("Applications")
+        .join("OpenVoxel Link Handler.app")
+}
+
+/// Make the system open `ovl://` links with this executable
+pub fn register_uri_handler() -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?.display().to_string();
+
+    if cfg!(windows) {
+        let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+        let command = format!("\"{}\" handle-uri \"%1\"", exe);
+        run("reg", &["add", &key, "/ve", "/d", "URL:OpenVoxel", "/f"])?;
+        run("reg", &["add", &key, "/v", "URL Protocol", "/d", "", "/f"])?;
+        run(
+            "reg",
+            &[
+                "add",
+                &format!(r"{}\shell\open\command", key),
+                "/ve",
+                "/d",
+                &command,
+                "/f",
+            ],
+        )?;
+    } else if cfg!(target_os = "macos") {
+        // Links reach apps as Apple Events, an AppleScript applet turns them back into a command
+        let app = get_macos_app();
+        let app_path = app.display().to_string();
+        run(
+            "osacompile",
+            &[
+                "-o",
+                &app_path,
+                "-e",
+                "on open location link",
+                "-e",
+                &format!(
+                    "tell application \"Terminal\" to do script quoted form of \"{}\" & \" handle-uri \" & quoted form of link",
+                    exe
+                ),
+                "-e",
+                "end open location",
+            ],
+        )?;
+
+        let plist = app
+            .join("Contents")
+            .join("Info.plist")
+            .display()
+            .to_string();
+        for entry in [
+            "Add :CFBundleURLTypes array",
+            "Add :CFBundleURLTypes:0 dict",
+            "Add :CFBundleURLTypes:0:CFBundleURLName string OpenVoxel",
+            "Add :CFBundleURLTypes:0:CFBundleURLSchemes array",
+            &format!(
+                "Add :CFBundleURLTypes:0:CFBundleURLSchemes:0 string {}",
+                SCHEME
+            ),
+        ] {
+            run("/usr/libexec/PlistBuddy", &["-c", entry, &plist])?;
+        }
+        run(
+            "/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister",
+            &["-f", &app_path],
+        )?;
+    } else {
+        let desktop_file = get_linux_desktop_file();
+        fs::create_dir_all(desktop_file.parent().unwrap())?;
+        fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=OpenVoxel\nExec=\"{}\" handle-uri %u\nTerminal=true\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+                exe, SCHEME
+            ),
+        )?;
+        run(
+            "xdg-mime",
+            &[
+                "default",
+                "ovl-uri-handler.desktop",
+                &format!("x-scheme-handler/{}", SCHEME),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Where the `ovl://` handler is registered, `None` when it is not
+pub fn uri_handler_location() -> Option<String> {
+    if cfg!(windows) {
+        let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+        let registered = Command::new("reg")
+            .args(["query", &key])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        return registered.then_some(key);
+    }
+
+    let path = if cfg!(target_os = "macos") {
+        get_macos_app()
+    } else {
+        get_linux_desktop_file()
+    };
+    path.exists().then(|| path.display().to_string())
+}
+
+pub fn unregister_uri_handler() -> Result<(), Box<dyn std::error::Error>> {
+    if cfg!(windows) {
+        run(
+            "reg",
+            &[
+                "delete",
+                &format!(r"HKCU\Software\Classes\{}", SCHEME),
+                "/f",
+            ],
+        )?;
+    } else if cfg!(target_os = "macos") {
+        let app = get_macos_app();
+        if app.exists() {
+            fs::remove_dir_all(app)?;
+        }
+    } else {
+        let desktop_file = get_linux_desktop_file();
+        if desktop_file.exists() {
+            fs::remove_file(desktop_file)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_play_links() {
+        assert_eq!(
+            parse_uri("ovl://play/escape-room/"),
+            Ok(UriAction::Play("escape-room".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_join_links() {
+        let Ok(UriAction::Join(invite)) = parse_uri("ovl://join/example.com:25570?map=escape-room")
+        else {
+            panic!("not a join link");
+        };
+        assert_eq!(invite.address(), "example.com:25570");
+        assert_eq!(invite.map.as_deref(), Some("escape-room"));
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        for uri in [
+            "https://openvoxel.studio/play/escape-room",
+            "ovl://play/",
+            "ovl://play/../escape-room",
+            "ovl://play/a b",
+            "ovl://install/escape-room",
+            "ovl://join/",
+        ] {
+            assert!(parse_uri(uri).is_err(), "{:?}", uri);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    dir::{get_game_dir, get_minecraft_support_dir},
+    filesys::{get_used_fabric_save, get_used_version_save},
+    map::{extract_map_icon, install_map, select_map},
+    mc::fetch_fabric,
+    net,
+    versions::resolve_version,
+};
+
+/// The official launcher keeps the profiles of the Microsoft Store install in another file
+const PROFILE_FILES: &[&str] = &[
+    "launcher_profiles.json",
+    "launcher_profiles_microsoft_store.json",
+];
+/// Profiles exported by ovl are keyed with this prefix
+const PROFILE_PREFIX: &str = "ovl-";
+/// Memory and garbage collector settings of new profiles in the official launcher
+const DEFAULT_JVM_ARGS: &str = "-Xmx2G -XX:+UnlockExperimentalVMOptions -XX:+UseG1GC -XX:G1NewSizePercent=20 -XX:G1ReservePercent=20 -XX:MaxGCPauseMillis=50 -XX:G1HeapRegionSize=32M";
+
+/// What a profile is created for: a Minecraft version, or a map of the catalog
+struct Target {
+    key: String,
+    name: String,
+    version: String,
+    icon: Option<String>,
+}
+
+async fn resolve_target(input: &str) -> Result<Target, Box<dyn Error>> {
+    if let Ok(version) = resolve_version(input).await {
+        return Ok(Target {
+            key: version.id.clone(),
+            name: format!("OpenVoxel {}", version.id),
+            version: version.id,
+            icon: None,
+        });
+    }
+
+    let map = select_map(input.to_lowercase(), None, false)
+        .ok_or_else(|| format!("{} is neither a Minecraft version nor a map", input))?;
+    // The world has to be there for the official launcher to find it
+    install_map(map.id.clone())?;
+    let icon = extract_map_icon(&map.id)
+        .and_then(|path| fs::read(path).ok())
+        .map(|png| format!("data:image/png;base64,{}", STANDARD.encode(png)));
+    Ok(Target {
+        key: map.id,
+        name: format!("OpenVoxel - {}", map.name),
+        version: map.version,
+        icon,
+    })
+}
+
+/// The Fabric loader the managed installation uses for `version`: the one of the last
+/// launch when it was the same version, else the latest stable one
+async fn fabric_loader(version: &str) -> Option<String> {
+    if get_used_version_save().as_deref() == Some(version)
+        && let Some(loader) = get_used_fabric_save()
+    {
+        return Some(loader);
+    }
+    let versions = fetch_fabric(version.to_string()).await.ok()?;
+    Some(versions.first()?.loader.version.clone())
+}
+
+/// Install the Fabric profile of a version in the official launcher, as the Fabric installer
+/// does. Returns its version id.
+async fn install_fabric_version(version: &str, loader: &str) -> Result<String, Box<dyn Error>> {
+    let profile: Value = net::get(&format!(
+        "https://meta.fabricmc.net/v2/versions/loader/{}/{}/profile/json",
+        version, loader
+    ))
+    .await?
+    .error_for_status()?
+    .json()
+    .await?;
+    let id = profile["id"]
+        .as_str()
+        .ok_or("Invalid Fabric profile")?
+        .to_string();
+
+    let version_dir = get_minecraft_support_dir()
+        .ok_or("Could not find the official launcher folder")?
+        .join("versions")
+        .join(&id);
+    fs::create_dir_all(&version_dir)?;
+    fs::write(
+        version_dir.join(format!("{}.json", id)),
+        serde_json::to_string_pretty(&profile)?,
+    )?;
+    Ok(id)
+}
+
+/// The profile files of the official launcher that exist on this computer
+fn profile_files() -> Vec<PathBuf> {
+    let Some(launcher_dir) = get_minecraft_support_dir() else {
+        return Vec::new();
+    };
+    PROFILE_FILES
+        .iter()
+        .map(|name| launcher_dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn read_profiles(path: &Path) -> Result<Value, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Names of the profiles exported to the official launcher by `export_vanilla_profile`
+pub fn exported_profiles() -> Vec<String> {
+    let mut names: Vec<String> = profile_files()
+        .iter()
+        .filter_map(|path| read_profiles(path).ok())
+        .flat_map(|profiles| {
+            profiles["profiles"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| key.starts_with(PROFILE_PREFIX))
+                .map(|(key, profile)| profile["name"].as_str().unwrap_or(key).to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Remove the profiles exported by `export_vanilla_profile` from the official launcher
+pub fn remove_exported_profiles() -> Result<(), Box<dyn Error>> {
+    for path in profile_files() {
+        let mut profiles = read_profiles(&path)?;
+        let Some(entries) = profiles["profiles"].as_object_mut() else {
+            continue;
+        };
+        entries.retain(|key, _| !key.starts_with(PROFILE_PREFIX));
+        fs::write(&path, serde_json::to_string_pretty(&profiles)?)?;
+    }
+    Ok(())
+}
+
+/// Add a profile to the official launcher that plays a version or a map with the game folder
+/// and mods of this launcher. Returns the name of the profile.
+pub async fn export_vanilla_profile(
+    input: &str,
+    jvm_args: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    if get_minecraft_support_dir().is_none() {
+        return Err("Could not find the official launcher folder".into());
+    }
+    let profile_files = profile_files();
+    if profile_files.is_empty() {
+        return Err("The official launcher is not set up, open it once first".into());
+    }
+
+    let target = resolve_target(input).await?;
+    let version_id = match fabric_loader(&target.version).await {
+        Some(loader) => {
+            if get_used_version_save().as_deref() != Some(target.version.as_str()) {
+                println!(
+                    "The mods folder holds the mods of the last version played with ovl, play {} with ovl once so they match.",
+                    target.version
+                );
+            }
+            install_fabric_version(&target.version, &loader).await?
+        }
+        None => target.version.clone(),
+    };
+
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    for path in profile_files {
+        let mut profiles = read_profiles(&path)?;
+        let key = format!("{}{}", PROFILE_PREFIX, target.key);
+        let created = profiles["profiles"][&key]["created"]
+            .as_str()
+            .unwrap_or(&now)
+            .to_string();
+
+        // No `javaDir`, the Java runtime the official launcher bundles per version is the right one
+        let profile = json!({
+            "name": target.name,
+            "type": "custom",
+            "created": created,
+            "lastUsed": now,
+            "icon": target.icon.as_deref().unwrap_or("Grass"),
+            "lastVersionId": version_id,
+            "gameDir": get_game_dir().display().to_string(),
+            "javaArgs": jvm_args.unwrap_or(DEFAULT_JVM_ARGS),
+        });
+        if !profiles["profiles"].is_object() {
+            profiles["profiles"] = json!({});
+        }
+        profiles["profiles"][&key] = profile;
+        fs::write(&path, serde_json::to_string_pretty(&profiles)?)?;
+    }
+    Ok(target.name)
+}
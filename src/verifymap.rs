@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    doctor::print_check,
+    filesys::getsha256,
+    map::{download_map, fetch_maps, install_map_from_path},
+    mc::{LaunchOptions, fetch_fabric, get_version_name, launch},
+    mirrors::map_hash,
+    mods::{MODS, resolve_mods},
+    world::{read_level_dat, verify_world},
+    zipper::{extract_zip, get_root_folder_name},
+};
+
+/// Where a map under test comes from, with the version it declares
+struct MapSource {
+    zip: PathBuf,
+    declared_version: Option<String>,
+}
+
+/// Download a catalog map and check it against its published hash
+fn download_catalog_map(id: &str, failures: &mut usize) -> Option<MapSource> {
+    let map = fetch_maps()
+        .ok()
+        .and_then(|maps| maps.into_iter().find(|m| m.id.eq_ignore_ascii_case(id)));
+    let Some(map) = map else {
+        print_check(false, "Catalog", &format!("{} is not in the catalog", id));
+        *failures += 1;
+        return None;
+    };
+    print_check(true, "Catalog", &format!("{} ({})", map.name, map.version));
+
+    let expected_hash = match map_hash(&map.id) {
+        Ok(hash) => hash,
+        Err(e) => {
+            print_check(false, "Hash", &format!("Could not fetch the hash: {}", e));
+            *failures += 1;
+            return None;
+        }
+    };
+
+    match download_map(map.id.clone(), expected_hash.clone()) {
+        Ok(path) => {
+            print_check(
+                true,
+                "Download",
+                &format!("SHA256 {}", expected_hash.trim()),
+            );
+            Some(MapSource {
+                zip: PathBuf::from(path),
+                declared_version: Some(map.version),
+            })
+        }
+        Err(e) => {
+            print_check(false, "Download", &e.to_string());
+            *failures += 1;
+            None
+        }
+    }
+}
+
+/// Extract, parse and resolve everything a map needs, returning the detected version
+async fn check_map(source: &MapSource, work_dir: &Path, failures: &mut usize) -> Option<String> {
+    let mut check = |ok: bool, label: &str, detail: &str| {
+        print_check(ok, label, detail);
+        if !ok {
+            *failures += 1;
+        }
+    };
+
+    let world_dir = match get_root_folder_name(&source.zip) {
+        Ok(root) => work_dir.join(root),
+        Err(e) => {
+            check(false, "Archive", &e.to_string());
+            return None;
+        }
+    };
+    if let Err(e) = extract_zip(&source.zip, &world_dir) {
+        check(false, "Extract", &e.to_string());
+        return None;
+    }
+    check(true, "Extract", &world_dir.display().to_string());
+
+    if let Err(e) = read_level_dat(&world_dir) {
+        check(false, "level.dat", &e.to_string());
+        return None;
+    }
+    check(true, "level.dat", "parsed");
+
+    let detected = get_version_name(&world_dir.join("level.dat"));
+    if detected == "none" {
+        check(false, "Version", "level.dat has no version name");
+        return None;
+    }
+    match &source.declared_version {
+        Some(declared) if declared != &detected => {
+            check(
+                false,
+                "Version",
+                &format!(
+                    "{} is declared but the world was saved with {}",
+                    declared, detected
+                ),
+            );
+        }
+        _ => {
+            check(true, "Version", &detected);
+        }
+    }
+    let version = source.declared_version.clone().unwrap_or(detected);
+
+    let report = verify_world(&world_dir);
+    check(
+        report.is_ok(),
+        "Chunks",
+        &format!(
+            "{} chunks in {} region files, {} problems",
+            report.chunks,
+            report.regions,
+            report.issues.len()
+        ),
+    );
+    if !report.is_ok() {
+        report.print(&world_dir);
+    }
+
+    match fetch_fabric(version.clone()).await {
+        Ok(loaders) if !loaders.is_empty() => {
+            check(true, "Fabric", &format!("{} stable loaders", loaders.len()));
+        }
+        Ok(_) => {
+            check(
+                false,
+                "Fabric",
+                &format!("no stable loader for {}", version),
+            );
+        }
+        Err(e) => {
+            check(false, "Fabric", &e.to_string());
+        }
+    }
+
+    let roots: Vec<String> = MODS.iter().map(|s| s.to_string()).collect();
+    match resolve_mods(&version, &roots, &HashSet::new()).await {
+        Ok(plan) => {
+            check(
+                plan.issues.is_empty(),
+                "Mods",
+                &format!("{} resolved for {}", plan.mods.len(), version),
+            );
+            for issue in &plan.issues {
+                println!("    {}", issue);
+            }
+        }
+        Err(e) => {
+            check(false, "Mods", &e.to_string());
+        }
+    }
+
+    Some(version)
+}
+
+/// Run the install pipeline on a map zip or catalog id in a temporary folder, for CI.
+/// Returns the number of problems found; without `no_launch` a map that passes is installed
+/// and played.
+pub async fn verify_map(target: &str, version: Option<String>, no_launch: bool) -> usize {
+    let mut failures = 0;
+
+    let path = Path::new(target);
+    let source = if path.is_file() {
+        let hash = getsha256(&path.to_path_buf()).unwrap_or_default();
+        print_check(true, "Archive", &format!("SHA256 {}", hash));
+        Some(MapSource {
+            zip: path.to_path_buf(),
+            declared_version: version,
+        })
+    } else {
+        download_catalog_map(target, &mut failures)
+    };
+    let Some(source) = source else {
+        return failures;
+    };
+
+    let work_dir = std::env::temp_dir().join(format!("ovl-verify-{}", std::process::id()));
+    let version = check_map(&source, &work_dir, &mut failures).await;
+    let _ = fs::remove_dir_all(&work_dir);
+
+    if failures > 0 {
+        println!("\n{} problems found.", failures);
+        return failures;
+    }
+    println!("\nThe map passed every check.");
+
+    if !no_launch && let Some(version) = version {
+        match install_map_from_path(source.zip.clone(), true) {
+            Ok(world) => {
+                launch(version, Some(&world), None, &LaunchOptions::default()).await;
+            }
+            Err(e) => {
+                eprintln!("Failed to install the map: {}", e);
+                failures += 1;
+            }
+        }
+    }
+    failures
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::{dir::get_app_support_dir, net};
+
+const MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+const MANIFEST_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct VersionManifest {
+    pub latest: LatestVersions,
+    pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ManifestVersion {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: String,
+    pub url: String,
+}
+
+impl ManifestVersion {
+    pub fn is_snapshot(&self) -> bool {
+        self.version_type == "snapshot"
+    }
+}
+
+fn get_manifest_cache_path() -> PathBuf {
+    get_app_support_dir()
+        .unwrap()
+        .join(".cache")
+        .join("version_manifest_v2.json")
+}
+
+fn read_cached_manifest(max_age: Option<Duration>) -> Option<VersionManifest> {
+    let path = get_manifest_cache_path();
+
+    if let Some(max_age) = max_age {
+        let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+        if SystemTime::now().duration_since(modified).ok()? > max_age {
+            return None;
+        }
+    }
+
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// The Mojang version manifest, refreshed at most once an hour.
+/// An outdated copy is used when Mojang cannot be reached.
+pub async fn fetch_version_manifest() -> Result<VersionManifest, Box<dyn std::error::Error>> {
+    if let Some(manifest) = read_cached_manifest(Some(MANIFEST_TTL)) {
+        return Ok(manifest);
+    }
+
+    let fetched: Result<VersionManifest, Box<dyn std::error::Error>> = async {
+        Ok(net::get(MANIFEST_URL)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+    .await;
+
+    match fetched {
+        Ok(manifest) => {
+            let path = get_manifest_cache_path();
+            if let Some(parent_dir) = path.parent()
+                && std::fs::create_dir_all(parent_dir).is_ok()
+            {
+                let _ = std::fs::write(path, serde_json::to_string(&manifest)?);
+            }
+            Ok(manifest)
+        }
+        Err(e) => match read_cached_manifest(None) {
+            Some(manifest) => {
+                eprintln!(
+                    "Warning: Could not refresh the version list, using the cached one: {}",
+                    e
+                );
+                Ok(manifest)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Find a version by id, `latest` and `snapshot` stand for the newest release and snapshot
+pub async fn resolve_version(input: &str) -> Result<ManifestVersion, Box<dyn std::error::Error>> {
+    let manifest = fetch_version_manifest().await?;
+    let id = match input {
+        "latest" => &manifest.latest.release,
+        "snapshot" => &manifest.latest.snapshot,
+        id => id,
+    };
+
+    manifest
+        .versions
+        .iter()
+        .find(|v| v.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown Minecraft version: {}", input).into())
+}
+
+/// The full version JSON (downloads, libraries, arguments) of a Minecraft version
+pub async fn fetch_version_json(id: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let version = resolve_version(id).await?;
+
+    Ok(net::get(&version.url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
@@ -0,0 +1,395 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{
+    cmd::print_output,
+    dir::{get_app_support_dir, get_game_dir},
+    filesys::{get_used_version_save, getsha256},
+    mods::{MODS, read_lock, resolve_mods},
+    net,
+    releases::{get_map_release, get_pack_release},
+    theme::Themed,
+};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/OpenVoxelStudios/CLI/releases";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+/// The CLI version the user saw the changelog for last
+fn get_last_version_path() -> PathBuf {
+    get_app_support_dir().unwrap().join(".last-version")
+}
+
+/// The version used before the last update, kept until `whatsnew` shows what changed since
+fn get_upgraded_from_path() -> PathBuf {
+    get_app_support_dir().unwrap().join(".upgraded-from")
+}
+
+/// Remember the version of this run, on every command, so that an update is noticed even
+/// when `whatsnew` is not run in between
+pub fn record_version() {
+    let current = env!("CARGO_PKG_VERSION");
+    let last = fs::read_to_string(get_last_version_path()).map(|v| v.trim().to_string());
+    if last.as_deref().is_ok_and(|last| last == current) {
+        return;
+    }
+    if let Ok(last) = last
+        && version_key(&last) < version_key(current)
+    {
+        // The oldest version not looked at yet, when updating several times in a row
+        if !get_upgraded_from_path().exists() {
+            let _ = fs::write(get_upgraded_from_path(), &last);
+        }
+        eprintln!(
+            "{}",
+            format!(
+                "ovl was updated to {}, run `ovl whatsnew` to see what changed.",
+                current
+            )
+            .muted()
+        );
+    }
+    let _ = fs::write(get_last_version_path(), current);
+}
+
+/// Numeric parts of a version such as `v1.4.2`, for ordering releases
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+async fn fetch_releases() -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    let releases: Vec<Release> = net::get(RELEASES_URL)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(releases.into_iter().filter(|r| !r.draft).collect())
+}
+
+/// What changed in the CLI, `releases` are the notes of the versions since the last look
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CliChanges {
+    Checked {
+        version: String,
+        latest: Option<String>,
+        releases: Vec<ReleaseNotes>,
+    },
+    Failed {
+        version: String,
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct ReleaseNotes {
+    pub version: String,
+    pub notes: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum MapUpdates {
+    NoneDownloaded,
+    Checked { outdated: Vec<String> },
+    Failed { error: String },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ModUpdates {
+    NotLaunched,
+    NotLocked {
+        minecraft: String,
+    },
+    Checked {
+        minecraft: String,
+        outdated: Vec<String>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PackUpdate {
+    NotInstalled,
+    UpToDate,
+    Available,
+    Failed { error: String },
+}
+
+#[derive(Serialize)]
+pub struct WhatsNew {
+    pub cli: CliChanges,
+    pub maps: MapUpdates,
+    pub mods: ModUpdates,
+    pub pack: PackUpdate,
+}
+
+async fn check_cli_changes() -> CliChanges {
+    let current = env!("CARGO_PKG_VERSION");
+    let last_seen = fs::read_to_string(get_upgraded_from_path())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_else(|_| current.to_string());
+
+    let releases = match fetch_releases().await {
+        Ok(releases) => releases,
+        Err(e) => {
+            return CliChanges::Failed {
+                version: current.to_string(),
+                error: e.to_string(),
+            };
+        }
+    };
+
+    let current_key = version_key(current);
+    let last_key = version_key(&last_seen);
+
+    CliChanges::Checked {
+        version: current.to_string(),
+        latest: releases
+            .iter()
+            .find(|r| version_key(&r.tag_name) > current_key)
+            .map(|r| r.tag_name.clone()),
+        releases: releases
+            .iter()
+            .filter(|r| version_key(&r.tag_name) > last_key)
+            .map(|r| ReleaseNotes {
+                version: r.tag_name.clone(),
+                notes: r.body.clone().unwrap_or_default(),
+            })
+            .collect(),
+    }
+}
+
+fn print_cli_changes(changes: &CliChanges) {
+    println!("{}", "ovl".bold());
+    let (version, latest, releases) = match changes {
+        CliChanges::Checked {
+            version,
+            latest,
+            releases,
+        } => (version, latest, releases),
+        CliChanges::Failed { error, .. } => {
+            eprintln!("  Could not fetch the release notes: {}", error);
+            return;
+        }
+    };
+
+    match latest {
+        Some(latest) => println!(
+            "  {} is available (you have {}), see the changes below.",
+            latest.success(),
+            version
+        ),
+        None => println!("  {} is the latest version.", version),
+    }
+
+    for release in releases {
+        println!("\n  {}", release.version.bold());
+        for line in release.notes.lines() {
+            println!("    {}", line);
+        }
+    }
+    if releases.is_empty() && latest.is_none() {
+        println!("  Nothing new since you last looked.");
+    }
+}
+
+fn check_map_updates() -> MapUpdates {
+    let games_dir = get_app_support_dir().unwrap().join(".cache").join("games");
+    let Ok(entries) = fs::read_dir(&games_dir) else {
+        return MapUpdates::NoneDownloaded;
+    };
+
+    let release = match get_map_release() {
+        Ok(release) => release,
+        Err(e) => {
+            return MapUpdates::Failed {
+                error: e.to_string(),
+            };
+        }
+    };
+
+    let mut outdated = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        let Some(id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".zip"))
+        else {
+            continue;
+        };
+
+        let Ok(expected) = release.sha256(&format!("{}.zip", id)) else {
+            continue;
+        };
+
+        if getsha256(&path).is_ok_and(|hash| hash.trim() != expected.trim()) {
+            outdated.push(id.to_string());
+        }
+    }
+    MapUpdates::Checked { outdated }
+}
+
+fn print_map_updates(updates: &MapUpdates) {
+    println!("\n{}", "Maps".bold());
+    match updates {
+        MapUpdates::NoneDownloaded => println!("  No maps downloaded yet."),
+        MapUpdates::Failed { error } => eprintln!("  Could not check the maps: {}", error),
+        MapUpdates::Checked { outdated } if outdated.is_empty() => {
+            println!("  Every downloaded map is up to date.")
+        }
+        MapUpdates::Checked { outdated } => {
+            for id in outdated {
+                println!(
+                    "  {} has an update, it is downloaded the next time you play it.",
+                    id.success()
+                );
+            }
+        }
+    }
+}
+
+async fn check_mod_updates() -> ModUpdates {
+    let Some(version) = get_used_version_save() else {
+        return ModUpdates::NotLaunched;
+    };
+    let Some(locked) = read_lock().versions.get(&version).cloned() else {
+        return ModUpdates::NotLocked { minecraft: version };
+    };
+
+    let roots: Vec<String> = MODS.iter().map(|s| s.to_string()).collect();
+    let plan = match resolve_mods(&version, &roots, &HashSet::new()).await {
+        Ok(plan) => plan,
+        Err(e) => {
+            return ModUpdates::Failed {
+                error: e.to_string(),
+            };
+        }
+    };
+
+    let outdated = plan
+        .downloads()
+        .into_iter()
+        .filter(|download| {
+            locked
+                .iter()
+                .any(|m| m.name == download.name && m.version_id != download.version_id)
+        })
+        .map(|download| download.name)
+        .collect();
+    ModUpdates::Checked {
+        minecraft: version,
+        outdated,
+    }
+}
+
+fn print_mod_updates(updates: &ModUpdates) {
+    println!("\n{}", "Mods".bold());
+    match updates {
+        ModUpdates::NotLaunched => println!("  No game launched yet."),
+        ModUpdates::NotLocked { minecraft } => {
+            println!("  No mods locked for {} yet.", minecraft)
+        }
+        ModUpdates::Failed { error } => eprintln!("  Could not check the mods: {}", error),
+        ModUpdates::Checked {
+            minecraft,
+            outdated,
+        } => {
+            for name in outdated {
+                println!("  {} has a new version for {}", name.success(), minecraft);
+            }
+            if outdated.is_empty() {
+                println!("  The mods for {} are up to date.", minecraft);
+            } else {
+                println!("  Run `ovl mods update {}` to install them.", minecraft);
+            }
+        }
+    }
+}
+
+fn check_pack_update() -> PackUpdate {
+    let pack = get_game_dir().join("resourcepacks").join("OVP.zip");
+    if !pack.exists() {
+        return PackUpdate::NotInstalled;
+    }
+
+    let expected = match get_pack_release().and_then(|r| r.sha256("OVP.zip")) {
+        Ok(hash) => hash,
+        Err(e) => {
+            return PackUpdate::Failed {
+                error: e.to_string(),
+            };
+        }
+    };
+
+    match getsha256(&pack).is_ok_and(|hash| hash.trim() == expected.trim()) {
+        true => PackUpdate::UpToDate,
+        false => PackUpdate::Available,
+    }
+}
+
+fn print_pack_update(update: &PackUpdate) {
+    println!("\n{}", "OpenVoxel resource pack".bold());
+    match update {
+        PackUpdate::NotInstalled => println!("  Not installed yet."),
+        PackUpdate::Failed { error } => {
+            eprintln!("  Could not check the resource pack: {}", error)
+        }
+        PackUpdate::UpToDate => println!("  Up to date."),
+        PackUpdate::Available => println!(
+            "  {}, it is downloaded on the next launch.",
+            "An update is available".success()
+        ),
+    }
+}
+
+/// Changes since the last version of the CLI the user ran, and what has updates waiting
+pub async fn print_whats_new() {
+    let whats_new = WhatsNew {
+        cli: check_cli_changes().await,
+        maps: check_map_updates(),
+        mods: check_mod_updates().await,
+        pack: check_pack_update(),
+    };
+    print_output(&whats_new, |whats_new| {
+        print_cli_changes(&whats_new.cli);
+        print_map_updates(&whats_new.maps);
+        print_mod_updates(&whats_new.mods);
+        print_pack_update(&whats_new.pack);
+    });
+
+    let _ = fs::remove_file(get_upgraded_from_path());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_versions_numerically() {
+        assert_eq!(version_key("v1.4.2"), [1, 4, 2]);
+        assert!(version_key("0.10.0") > version_key("0.9.3"));
+        assert!(version_key("v1.0.0") > version_key("0.99"));
+        // Prerelease suffixes are ignored
+        assert_eq!(version_key("1.2.0-beta"), [1, 2, 0]);
+        assert!(version_key("nightly").is_empty());
+    }
+}
@@ -0,0 +1,669 @@
+use colored::Colorize;
+use fastnbt::Value;
+use flate2::Compression;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use inquire::Select;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write, stdout};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cmd::{ask_yes_no, check_interactive},
+    config::load_config,
+    dir::get_game_dir,
+    theme::Themed,
+};
+
+/// Region files are split in sectors of this size, the first two hold the header
+const SECTOR: usize = 4096;
+const CHUNKS_PER_REGION: usize = 1024;
+
+pub fn get_saves_dir() -> PathBuf {
+    get_game_dir().join("saves")
+}
+
+/// A world given by path, or by folder name in the saves
+pub fn resolve_world(world: &str) -> Result<PathBuf, String> {
+    let path = Path::new(world);
+    if path.join("level.dat").exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    let saved = get_saves_dir().join(world);
+    if saved.join("level.dat").exists() {
+        return Ok(saved);
+    }
+
+    Err(format!("No world found at {} or in the saves", world))
+}
+
+/// How the world to quick play is found when no saves folder has the requested name, as
+/// happens once a world is renamed in game (only its `LevelName` changes)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuickPlayLookup {
+    /// Play the world whose display name matches
+    #[default]
+    DisplayName,
+    /// Ask before playing a world found by its display name
+    Ask,
+    /// Only match folder names, the world list opens when none does
+    Folder,
+}
+
+/// Folders of `saves_dir` whose world is displayed as `name`
+fn folders_named(saves_dir: &Path, name: &str) -> Vec<String> {
+    let mut folders: Vec<String> = fs::read_dir(saves_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            read_level_dat(&entry.path())
+                .ok()
+                .and_then(|level| nbt_string(nbt_get(&level, &["Data", "LevelName"])))
+                .is_some_and(|level_name| level_name.trim().eq_ignore_ascii_case(name.trim()))
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    folders.sort();
+    folders
+}
+
+/// The folder of `saves_dir` Quick Play needs for `name`, which can be a folder name or the
+/// name the world is displayed with, following `quick_play_lookup`
+pub fn quick_play_folder(saves_dir: &Path, name: &str) -> Option<String> {
+    if saves_dir.join(name).join("level.dat").exists() {
+        return Some(name.to_string());
+    }
+    let lookup = load_config().quick_play_lookup;
+    if lookup == QuickPlayLookup::Folder {
+        return None;
+    }
+
+    let mut folders = folders_named(saves_dir, name);
+    match folders.len() {
+        0 => None,
+        1 => {
+            let folder = folders.remove(0);
+            let confirmed = lookup != QuickPlayLookup::Ask
+                || ask_yes_no(&format!(
+                    "No saves folder is named {}, play the world {} displayed with that name?",
+                    name, folder
+                ));
+            confirmed.then_some(folder)
+        }
+        _ => {
+            let question = format!("Several worlds are named {}, which one?", name);
+            check_interactive(&question)
+                .map_err(|e| eprintln!("{}", e))
+                .ok()?;
+            Select::new(&question, folders).prompt().ok()
+        }
+    }
+}
+
+pub fn read_level_dat(world_dir: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let file = fs::File::open(world_dir.join("level.dat"))?;
+    Ok(fastnbt::from_reader(GzDecoder::new(file))?)
+}
+
+/// Save an edited level.dat the way Minecraft does: the previous one is kept as
+/// `level.dat_old` and the new one is written next to it before replacing it
+pub fn write_level_dat(world_dir: &Path, level: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    let path = world_dir.join("level.dat");
+    let temp = world_dir.join("level.dat.tmp");
+
+    let mut encoder = GzEncoder::new(fs::File::create(&temp)?, Compression::default());
+    encoder.write_all(&fastnbt::to_bytes(level)?)?;
+    encoder.finish()?.sync_all()?;
+
+    fs::copy(&path, world_dir.join("level.dat_old"))?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+/// Every region file of a world, including the entities and POI ones of each dimension
+pub fn find_region_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            files.extend(find_region_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "mca") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Region coordinates from a file name like `r.-1.2.mca`
+fn region_coords(path: &Path) -> Option<(i32, i32)> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// Chunk coordinates of the chunk at `index` in a region
+pub fn chunk_coords(region: (i32, i32), index: usize) -> (i32, i32) {
+    (
+        region.0 * 32 + (index % 32) as i32,
+        region.1 * 32 + (index / 32) as i32,
+    )
+}
+
+/// Location of a chunk in a region file: first sector and sector count
+pub fn chunk_location(data: &[u8], index: usize) -> (usize, usize) {
+    let entry = &data[index * 4..index * 4 + 4];
+    let offset = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize;
+    (offset, entry[3] as usize)
+}
+
+/// Decompress the payload of a chunk. `Ok(None)` for LZ4, which is only checked for presence.
+pub fn decode_chunk(compression: u8, payload: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let mut raw = Vec::new();
+    let result = match compression {
+        1 => GzDecoder::new(payload).read_to_end(&mut raw),
+        2 => ZlibDecoder::new(payload).read_to_end(&mut raw),
+        3 => {
+            raw.extend_from_slice(payload);
+            Ok(raw.len())
+        }
+        4 => return Ok(None),
+        other => return Err(format!("unknown compression type {}", other)),
+    };
+
+    result.map_err(|e| format!("could not be decompressed: {}", e))?;
+    Ok(Some(raw))
+}
+
+/// Compression type and payload of a chunk, read from the external `.mcc` file when needed
+pub fn read_chunk(
+    region_path: &Path,
+    data: &[u8],
+    index: usize,
+    coords: (i32, i32),
+) -> Result<(u8, Vec<u8>), String> {
+    let (offset, sectors) = chunk_location(data, index);
+    if offset < 2 {
+        return Err("points into the region header".to_string());
+    }
+    if sectors == 0 {
+        return Err("takes no sectors".to_string());
+    }
+    let start = offset * SECTOR;
+    if (offset + sectors) * SECTOR > data.len() || start + 5 > data.len() {
+        return Err("extends past the end of the region file".to_string());
+    }
+
+    let length = u32::from_be_bytes(data[start..start + 4].try_into().unwrap()) as usize;
+    if length == 0 || length + 4 > sectors * SECTOR {
+        return Err(format!("has an invalid length ({} bytes)", length));
+    }
+
+    let compression = data[start + 4];
+    if compression & 0x80 != 0 {
+        let external = region_path.with_file_name(format!("c.{}.{}.mcc", coords.0, coords.1));
+        let payload = fs::read(&external)
+            .map_err(|_| format!("is missing its external file {}", external.display()))?;
+        return Ok((compression & 0x7f, payload));
+    }
+
+    Ok((compression, data[start + 5..start + 4 + length].to_vec()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkIssue {
+    pub region: PathBuf,
+    /// `None` when the whole region file is unusable
+    pub chunk: Option<(i32, i32)>,
+    pub problem: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub level_dat: Option<String>,
+    pub regions: usize,
+    pub chunks: usize,
+    pub issues: Vec<ChunkIssue>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.level_dat.is_none() && self.issues.is_empty()
+    }
+
+    pub fn print(&self, world_dir: &Path) {
+        match &self.level_dat {
+            Some(e) => println!("  {} level.dat: {}", "✗".error(), e),
+            None => println!("  {} level.dat", "✓".success()),
+        }
+        println!(
+            "  {} {} chunks in {} region files",
+            if self.issues.is_empty() {
+                "✓".success()
+            } else {
+                "✗".error()
+            },
+            self.chunks,
+            self.regions
+        );
+
+        for issue in &self.issues {
+            let region = issue
+                .region
+                .strip_prefix(world_dir)
+                .unwrap_or(&issue.region)
+                .display();
+            match issue.chunk {
+                Some((x, z)) => println!(
+                    "    {} chunk {}, {} (blocks {}, {}): {}",
+                    region,
+                    x,
+                    z,
+                    x * 16,
+                    z * 16,
+                    issue.problem
+                ),
+                None => println!("    {}: {}", region, issue.problem),
+            }
+        }
+    }
+}
+
+fn verify_region(path: &Path, report: &mut VerifyReport) {
+    let issue = |chunk, problem: String| ChunkIssue {
+        region: path.to_path_buf(),
+        chunk,
+        problem,
+    };
+
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            report.issues.push(issue(None, e.to_string()));
+            return;
+        }
+    };
+    report.regions += 1;
+
+    // The game leaves empty region files around, they are valid
+    if data.is_empty() {
+        return;
+    }
+    if data.len() < 2 * SECTOR {
+        report
+            .issues
+            .push(issue(None, "the region header is truncated".to_string()));
+        return;
+    }
+    let region = region_coords(path).unwrap_or((0, 0));
+
+    for index in 0..CHUNKS_PER_REGION {
+        if chunk_location(&data, index) == (0, 0) {
+            continue;
+        }
+        report.chunks += 1;
+
+        let coords = chunk_coords(region, index);
+        let problem = read_chunk(path, &data, index, coords).and_then(|(compression, payload)| {
+            match decode_chunk(compression, &payload)? {
+                Some(raw) => fastnbt::from_bytes::<Value>(&raw)
+                    .map(|_| ())
+                    .map_err(|e| format!("has corrupted NBT data: {}", e)),
+                None => Ok(()),
+            }
+        });
+
+        if let Err(problem) = problem {
+            report.issues.push(issue(Some(coords), problem));
+        }
+    }
+}
+
+/// Check level.dat and every chunk of every region file of a world
+pub fn verify_world(world_dir: &Path) -> VerifyReport {
+    let mut report = VerifyReport {
+        level_dat: read_level_dat(world_dir).err().map(|e| e.to_string()),
+        ..Default::default()
+    };
+
+    for region in find_region_files(world_dir) {
+        verify_region(&region, &mut report);
+    }
+    report
+}
+
+/// Chunks bigger than this many sectors are stored in an external `.mcc` file
+const MAX_CHUNK_SECTORS: usize = 255;
+
+/// Rewrite a region file with every chunk packed back to back and compressed again with
+/// zlib at `level`. Returns the size of the file before and after.
+fn optimize_region(path: &Path, level: u32) -> Result<(u64, u64), String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    if data.len() < 2 * SECTOR {
+        return Ok((data.len() as u64, data.len() as u64));
+    }
+    let region = region_coords(path).unwrap_or((0, 0));
+
+    let mut header = vec![0u8; 2 * SECTOR];
+    // Keep the last modification times of the chunks
+    header[SECTOR..].copy_from_slice(&data[SECTOR..2 * SECTOR]);
+    let mut body: Vec<u8> = Vec::new();
+    let mut stale_externals = Vec::new();
+
+    for index in 0..CHUNKS_PER_REGION {
+        if chunk_location(&data, index) == (0, 0) {
+            continue;
+        }
+
+        let coords = chunk_coords(region, index);
+        let (compression, payload) = read_chunk(path, &data, index, coords)
+            .map_err(|problem| format!("chunk {}, {} {}", coords.0, coords.1, problem))?;
+
+        let (compression, payload) = match decode_chunk(compression, &payload)
+            .map_err(|problem| format!("chunk {}, {} {}", coords.0, coords.1, problem))?
+        {
+            Some(raw) => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::new(level));
+                encoder.write_all(&raw).map_err(|e| e.to_string())?;
+                (2, encoder.finish().map_err(|e| e.to_string())?)
+            }
+            // LZ4 chunks are kept as they are
+            None => (compression, payload),
+        };
+
+        let mut chunk = Vec::with_capacity(payload.len() + 5);
+        let sectors = (payload.len() + 5).div_ceil(SECTOR);
+        let external = path.with_file_name(format!("c.{}.{}.mcc", coords.0, coords.1));
+        if sectors > MAX_CHUNK_SECTORS {
+            fs::write(&external, &payload).map_err(|e| e.to_string())?;
+            chunk.extend_from_slice(&1u32.to_be_bytes());
+            chunk.push(compression | 0x80);
+        } else {
+            // The chunk may have been external before it was compressed again
+            stale_externals.push(external);
+            chunk.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+            chunk.push(compression);
+            chunk.extend_from_slice(&payload);
+        }
+        chunk.resize(chunk.len().div_ceil(SECTOR) * SECTOR, 0);
+
+        let offset = 2 + body.len() / SECTOR;
+        let location = ((offset as u32) << 8) | (chunk.len() / SECTOR) as u32;
+        header[index * 4..index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+        body.extend_from_slice(&chunk);
+    }
+
+    header.extend_from_slice(&body);
+
+    // Write next to the original first so a failure never leaves a half written region
+    let temp = path.with_extension("mca.tmp");
+    fs::write(&temp, &header).map_err(|e| e.to_string())?;
+    fs::rename(&temp, path).map_err(|e| e.to_string())?;
+    for external in stale_externals {
+        let _ = fs::remove_file(external);
+    }
+
+    Ok((data.len() as u64, header.len() as u64))
+}
+
+/// Compact every region file of a world. Returns the total size before and after.
+pub fn optimize_world(world_dir: &Path, level: u32) -> (u64, u64) {
+    let regions = find_region_files(world_dir);
+    let (mut before, mut after) = (0, 0);
+
+    for (i, region) in regions.iter().enumerate() {
+        print!("\rOptimizing region files: {}/{}", i + 1, regions.len());
+        let _ = stdout().flush();
+
+        match optimize_region(region, level) {
+            Ok((old, new)) => {
+                before += old;
+                after += new;
+            }
+            Err(e) => {
+                let size = fs::metadata(region).map(|m| m.len()).unwrap_or(0);
+                before += size;
+                after += size;
+                eprintln!(
+                    "\nSkipped {}, {}. Run `ovl world verify` for details.",
+                    region.display(),
+                    e
+                );
+            }
+        }
+    }
+    println!();
+
+    (before, after)
+}
+
+/// Follow a path of compound keys in an NBT value
+pub fn nbt_get<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Compound(compound) => compound.get(*key),
+        _ => None,
+    })
+}
+
+pub fn nbt_number(value: Option<&Value>) -> Option<f64> {
+    match value? {
+        Value::Byte(n) => Some(*n as f64),
+        Value::Short(n) => Some(*n as f64),
+        Value::Int(n) => Some(*n as f64),
+        Value::Long(n) => Some(*n as f64),
+        Value::Float(n) => Some(*n as f64),
+        Value::Double(n) => Some(*n),
+        _ => None,
+    }
+}
+
+pub fn nbt_string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        other => nbt_number(Some(other)).map(|n| n.to_string()),
+    }
+}
+
+fn nbt_strings(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::List(list)) => list.iter().filter_map(|v| nbt_string(Some(v))).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct WorldBorder {
+    pub size: f64,
+    pub center: [f64; 2],
+}
+
+/// The level.dat summary of `ovl world info`, `None` for what the world does not record
+#[derive(Serialize)]
+pub struct WorldInfo {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub seed: Option<String>,
+    pub spawn: Option<[f64; 3]>,
+    pub game_mode: &'static str,
+    pub hardcore: bool,
+    pub border: Option<WorldBorder>,
+    pub enabled_datapacks: Vec<String>,
+    pub disabled_datapacks: Vec<String>,
+    pub game_rules: BTreeMap<String, String>,
+}
+
+pub fn read_world_info(world_dir: &Path) -> Result<WorldInfo, Box<dyn std::error::Error>> {
+    let level = read_level_dat(world_dir)?;
+    let data = nbt_get(&level, &["Data"]).ok_or("level.dat has no Data")?;
+    let field = |path: &[&str]| nbt_string(nbt_get(data, path));
+
+    // Moved to WorldGenSettings in 1.16
+    let seed = nbt_get(data, &["WorldGenSettings", "seed"])
+        .or(nbt_get(data, &["RandomSeed"]))
+        .and_then(|seed| match seed {
+            Value::Long(seed) => Some(seed.to_string()),
+            _ => None,
+        });
+
+    let spawn = [&["SpawnX"][..], &["SpawnY"], &["SpawnZ"]]
+        .iter()
+        .map(|path| nbt_number(nbt_get(data, path)))
+        .collect::<Option<Vec<f64>>>()
+        .or_else(|| {
+            // 1.21.5 and newer store the spawn as a position array
+            match nbt_get(data, &["spawn", "pos"])? {
+                Value::IntArray(pos) => Some(pos.iter().map(|n| *n as f64).collect()),
+                _ => None,
+            }
+        })
+        .and_then(|spawn| spawn.try_into().ok());
+
+    let game_mode = match nbt_number(nbt_get(data, &["GameType"])).map(|n| n as i64) {
+        Some(0) => "survival",
+        Some(1) => "creative",
+        Some(2) => "adventure",
+        Some(3) => "spectator",
+        _ => "unknown",
+    };
+
+    let border = match (
+        nbt_number(nbt_get(data, &["BorderCenterX"])),
+        nbt_number(nbt_get(data, &["BorderCenterZ"])),
+        nbt_number(nbt_get(data, &["BorderSize"])),
+    ) {
+        (Some(x), Some(z), Some(size)) => Some(WorldBorder {
+            size,
+            center: [x, z],
+        }),
+        _ => None,
+    };
+
+    let game_rules = match nbt_get(data, &["GameRules"]) {
+        Some(Value::Compound(rules)) => rules
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), nbt_string(Some(value))?)))
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+
+    Ok(WorldInfo {
+        name: field(&["LevelName"]),
+        version: field(&["Version", "Name"]),
+        seed,
+        spawn,
+        game_mode,
+        hardcore: nbt_number(nbt_get(data, &["hardcore"])) == Some(1.0),
+        border,
+        enabled_datapacks: nbt_strings(nbt_get(data, &["DataPacks", "Enabled"])),
+        disabled_datapacks: nbt_strings(nbt_get(data, &["DataPacks", "Disabled"])),
+        game_rules,
+    })
+}
+
+pub fn print_world_info(info: &WorldInfo) {
+    println!("{}", info.name.as_deref().unwrap_or("Unnamed world").bold());
+    println!(
+        "  Version: {}",
+        info.version.as_deref().unwrap_or("unknown")
+    );
+    println!("  Seed: {}", info.seed.as_deref().unwrap_or("unknown"));
+    match info.spawn {
+        Some([x, y, z]) => println!("  Spawn: {} {} {}", x, y, z),
+        None => println!("  Spawn: unknown"),
+    }
+    println!(
+        "  Game mode: {}{}",
+        info.game_mode,
+        if info.hardcore { " (hardcore)" } else { "" }
+    );
+    match &info.border {
+        Some(border) => println!(
+            "  World border: {} blocks wide, centered on {} {}",
+            border.size, border.center[0], border.center[1]
+        ),
+        None => println!("  World border: unknown"),
+    }
+
+    println!("\n{}", "Datapacks".bold());
+    for pack in &info.enabled_datapacks {
+        println!("  {} {}", "✓".success(), pack);
+    }
+    for pack in &info.disabled_datapacks {
+        println!("  {} {}", "✗".error(), pack);
+    }
+    if info.enabled_datapacks.is_empty() && info.disabled_datapacks.is_empty() {
+        println!("  none");
+    }
+
+    println!("\n{}", "Game rules".bold());
+    for (name, value) in &info.game_rules {
+        println!("  {} = {}", name, value);
+    }
+    if info.game_rules.is_empty() {
+        println!("  none");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A region file with one chunk at `offset`, taking `sectors` sectors
+    fn region(offset: usize, sectors: u8, length: u32, total_sectors: usize) -> Vec<u8> {
+        let mut data = vec![0; total_sectors * SECTOR];
+        data[0..3].copy_from_slice(&(offset as u32).to_be_bytes()[1..]);
+        data[3] = sectors;
+        if offset * SECTOR + 5 <= data.len() {
+            let start = offset * SECTOR;
+            data[start..start + 4].copy_from_slice(&length.to_be_bytes());
+            data[start + 4] = 2;
+        }
+        data
+    }
+
+    #[test]
+    fn reads_a_chunk() {
+        let data = region(2, 1, 11, 3);
+        let (compression, payload) = read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).unwrap();
+        assert_eq!(compression, 2);
+        assert_eq!(payload.len(), 10);
+    }
+
+    #[test]
+    fn rejects_chunks_in_the_header() {
+        let data = region(1, 1, 11, 3);
+        assert!(read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).is_err());
+    }
+
+    #[test]
+    fn rejects_chunks_without_sectors() {
+        // The offset is the end of the file, reading the length would go past it
+        let data = region(3, 0, 0, 3);
+        assert!(read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).is_err());
+    }
+
+    #[test]
+    fn rejects_chunks_past_the_end() {
+        let data = region(2, 2, 11, 3);
+        assert!(read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        let data = region(2, 1, 0, 3);
+        assert!(read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).is_err());
+        let data = region(2, 1, SECTOR as u32, 3);
+        assert!(read_chunk(Path::new("r.0.0.mca"), &data, 0, (0, 0)).is_err());
+    }
+}
@@ -1,9 +1,12 @@
 use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
-use std::io::{self};
+use std::io::{self, Write, stderr};
 use std::path::Path;
-use zip::ZipArchive;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use zip::{CompressionMethod, ZipArchive, result::ZipError};
 
 pub fn get_root_folder_name(zip_path: &Path) -> std::io::Result<String> {
     let file = File::open(zip_path)?;
@@ -13,7 +16,12 @@ pub fn get_root_folder_name(zip_path: &Path) -> std::io::Result<String> {
 
     for i in 0..archive.len() {
         let entry = archive.by_index(i)?;
-        let path = entry.enclosed_name().unwrap();
+        let Some(path) = entry.enclosed_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} points outside of the archive folder", entry.name()),
+            ));
+        };
 
         if let Some(component) = path.components().next() {
             top_dirs.insert(component.as_os_str().to_string_lossy().to_string());
@@ -33,8 +41,15 @@ pub fn extract_zip(zip_path: &Path, extract_to: &Path) -> zip::result::ZipResult
 
     let mut top_dirs = vec![];
     for i in 0..archive.len() {
-        let name = archive.by_index(i)?.name().to_string();
-        if let Some(first) = Path::new(&name).components().next() {
+        let entry = archive.by_index(i)?;
+        // Absolute paths and `..` would be written outside of the folder
+        let Some(name) = entry.enclosed_name() else {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} points outside of the archive folder", entry.name()),
+            )));
+        };
+        if let Some(first) = name.components().next() {
             let dir = first.as_os_str().to_string_lossy().to_string();
             if !top_dirs.contains(&dir) {
                 top_dirs.push(dir);
@@ -48,29 +63,133 @@ pub fn extract_zip(zip_path: &Path, extract_to: &Path) -> zip::result::ZipResult
         None
     };
 
+    let mut total_bytes = 0;
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let out_path = match strip_prefix {
-            Some(prefix) => {
-                let path = Path::new(file.name());
-                match path.strip_prefix(prefix) {
-                    Ok(stripped) => extract_to.join(stripped),
-                    Err(_) => extract_to.join(path),
+        let file = archive.by_index(i)?;
+        // Fail before extracting anything rather than halfway through
+        let method = file.compression();
+        if !matches!(
+            method,
+            CompressionMethod::Stored
+                | CompressionMethod::Deflated
+                | CompressionMethod::Bzip2
+                | CompressionMethod::Zstd
+        ) {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{} uses an unsupported compression method ({:?})",
+                    file.name(),
+                    method
+                ),
+            )));
+        }
+        total_bytes += file.size();
+    }
+
+    let entries = archive.len();
+    let next_entry = AtomicUsize::new(0);
+    let extracted_entries = AtomicUsize::new(0);
+    let extracted_bytes = AtomicU64::new(0);
+    let error: Mutex<Option<ZipError>> = Mutex::new(None);
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(entries.max(1));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                // Each worker reads through its own handle, a zip reader cannot be shared
+                let result = File::open(zip_path)
+                    .map_err(ZipError::from)
+                    .and_then(ZipArchive::new)
+                    .and_then(|mut archive| {
+                        loop {
+                            let i = next_entry.fetch_add(1, Ordering::Relaxed);
+                            if i >= entries || error.lock().unwrap().is_some() {
+                                return Ok(());
+                            }
+
+                            let size = extract_entry(&mut archive, i, extract_to, strip_prefix)?;
+                            extracted_bytes.fetch_add(size, Ordering::Relaxed);
+                            extracted_entries.fetch_add(1, Ordering::Relaxed);
+                        }
+                    });
+
+                if let Err(e) = result {
+                    error.lock().unwrap().get_or_insert(e);
                 }
-            }
-            None => extract_to.join(file.name()),
-        };
+            });
+        }
 
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&out_path)?;
-        } else {
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)?;
+        loop {
+            let done = extracted_entries.load(Ordering::Relaxed);
+            print_progress(
+                done,
+                entries,
+                extracted_bytes.load(Ordering::Relaxed),
+                total_bytes,
+            );
+            if done >= entries || error.lock().unwrap().is_some() {
+                break;
             }
-            let mut out_file = File::create(&out_path)?;
-            io::copy(&mut file, &mut out_file)?;
+            std::thread::sleep(Duration::from_millis(100));
         }
+    });
+    eprintln!();
+
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Extract one entry, returning its uncompressed size
+fn extract_entry(
+    archive: &mut ZipArchive<File>,
+    index: usize,
+    extract_to: &Path,
+    strip_prefix: Option<&Path>,
+) -> zip::result::ZipResult<u64> {
+    let mut file = archive.by_index(index)?;
+    let path = file.enclosed_name().ok_or_else(|| {
+        ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} points outside of the archive folder", file.name()),
+        ))
+    })?;
+    let out_path = match strip_prefix.and_then(|prefix| path.strip_prefix(prefix).ok()) {
+        Some(stripped) => extract_to.join(stripped),
+        None => extract_to.join(path),
+    };
+
+    if file.is_dir() {
+        fs::create_dir_all(&out_path)?;
+    } else {
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out_file = File::create(&out_path)?;
+        io::copy(&mut file, &mut out_file)?;
     }
 
-    Ok(())
+    Ok(file.size())
+}
+
+fn print_progress(entries: usize, total_entries: usize, bytes: u64, total_bytes: u64) {
+    let percent = match total_bytes {
+        0 => 100.0,
+        _ => bytes as f64 / total_bytes as f64 * 100.0,
+    };
+
+    eprint!(
+        "\rExtracting: {}/{} files, {:.1}/{:.1} MB ({:.1}%)",
+        entries,
+        total_entries,
+        bytes as f64 / 1024.0 / 1024.0,
+        total_bytes as f64 / 1024.0 / 1024.0,
+        percent
+    );
+    let _ = stderr().flush();
 }